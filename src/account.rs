@@ -2,39 +2,56 @@ use std::{
     collections::BTreeMap,
     str::FromStr,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         mpsc, Arc, Mutex,
     },
     thread::{self, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use joinstr::{
     electrum::{CoinRequest, CoinResponse},
     miniscript::{
-        bitcoin::{self, absolute, Amount, EcdsaSighashType, OutPoint, ScriptBuf, TxOut},
+        bitcoin::{
+            self, absolute, bip32::DerivationPath, Amount, CompressedPublicKey, EcdsaSighashType,
+            OutPoint, ScriptBuf, TxOut,
+        },
         psbt::PsbtExt,
+        Descriptor, DescriptorPublicKey,
     },
     nostr::{self, error, sync::NostrClient, Pool},
     simple_nostr_client::nostr::key::Keys,
 };
 
 use crate::{
-    address_store::{AddressEntry, AddressTip},
+    address_store::AddressTip,
+    backup::Backup,
     coin::Coin,
+    coin_selection::{select_coins, Candidate},
     coin_store::{CoinEntry, CoinStore},
-    config::Tip,
+    config::{
+        descriptor_contains_fingerprint, is_electrum_url_valid, is_pool_author_allowed,
+        parse_electrum_config, parse_min_relay_fee, parse_nostr_back, ConfigError, Tip,
+        MAX_NOSTR_BACK, RECONNECT_INITIAL_DELAY_MS, RECONNECT_MAX_DELAY_MS, RECONNECT_MULTIPLIER,
+    },
     cpp_joinstr::{
-        AddrAccount, AddressStatus, CoinState, PoolStatus, RustAddress, RustPool, SignalFlag,
-        TransactionSimulation, TransactionTemplate,
+        AddrAccount, AddressValidation, CoinSort, CoinState, CoinStatus, Output, PoolRole,
+        PoolStatus, PsbtOutput, PsbtOutputKind, PsbtVerification, RustAddress, RustCoin, RustLabel,
+        RustPool, RustTransaction, SignalFlag, TransactionDetails, TransactionSimulation,
+        TransactionTemplate, TxInputDetails, UtxoBucket, WalletSummary,
     },
     derivator::Derivator,
     label_store::{LabelKey, LabelStore},
-    pool_store::PoolStore,
+    pool_store::{
+        check_coinjoin_excess, coinjoin_excess, contribution_fee, is_pool_fee_valid,
+        joinable_pools, PoolStore, PoolsSnapshot,
+    },
     result,
-    signing_manager::SigningManager,
-    tx_store::TxStore,
-    Config, PoolsResult, PsbtResult,
+    signer::HotSigner,
+    signing_manager::{self, SigningManager},
+    tx_store::{TxEntry, TxStore},
+    ChangeAddresses, CoinjoinExcess, Config, ConfirmationTarget, ImportedTxs, PoolsResult,
+    PsbtResult, SatsAmount, SignedMessage,
 };
 
 /// The factor that non-witness serialization data is multiplied by during weight calculation.
@@ -42,6 +59,18 @@ const WITNESS_SCALE_FACTOR: u64 = 4;
 
 const DUST_AMOUNT: u64 = 5_000;
 
+/// The maximum weight of a transaction relayed by the network.
+const MAX_STANDARD_TX_WEIGHT: u64 = 400_000;
+
+/// The fee rate in sats/vb coins are expected to be spent at in the long
+/// run, consolidating below it saves fees (the default
+/// `-consolidatefeerate` of Bitcoin Core).
+const LONG_TERM_FEE_RATE: f64 = 10.0;
+
+/// The size in vbytes of the version, locktime and inputs/outputs counts of
+/// a transaction.
+const TX_OVERHEAD_VBYTES: u64 = 11;
+
 result!(Poll, Signal);
 
 impl Poll {
@@ -56,6 +85,7 @@ pub struct Signal {
     flag: Option<SignalFlag>,
     payload: Option<String>,
     error: Option<String>,
+    account: Option<String>,
 }
 impl Signal {
     /// Creates a new `Signal` instance with no inner value or error.
@@ -64,6 +94,7 @@ impl Signal {
             flag: None,
             payload: None,
             error: None,
+            account: None,
         }
     }
     /// Sets the inner value of the signal and clears any existing error.
@@ -92,9 +123,32 @@ impl Signal {
     pub fn unwrap(&self) -> SignalFlag {
         self.flag.unwrap()
     }
+    /// Returns the payload of the signal, if any.
+    ///
+    /// # Returns
+    ///
+    /// A string containing the payload (e.g. a signed PSBT or a pool id),
+    /// or an empty string if no payload is present.
     pub fn payload(&self) -> String {
         self.payload.clone().unwrap_or_default()
     }
+    /// Returns the name of the account the signal comes from.
+    ///
+    /// # Returns
+    ///
+    /// The account name if the signal has been received through an
+    /// `AccountManager`, or an empty string.
+    pub fn account(&self) -> String {
+        self.account.clone().unwrap_or_default()
+    }
+    /// Tags the signal with the name of the account it comes from.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The name of the account.
+    pub fn set_account(&mut self, account: String) {
+        self.account = Some(account);
+    }
     /// Returns a boxed version of the inner value of the signal.
     ///
     /// # Panics
@@ -142,14 +196,159 @@ pub enum Notification {
     Electrum(TxListenerNotif),
     Joinstr(JoinstrNotif),
     AddressTipChanged,
+    /// The number of handed out receive addresses that are still unused
+    /// reached the look-ahead.
+    AddressGapWarning(u32),
+    /// Stored addresses and coins are not derived anymore by the
+    /// descriptors, the payload is the number of mismatched entries.
+    AddressMismatch(u32),
     CoinUpdate,
     InvalidElectrumConfig,
     InvalidNostrConfig,
+    /// The nostr lookback is out of the accepted range.
+    InvalidNostrBack,
     InvalidLookAhead,
     Stopped,
     Error(Error),
 }
 
+/// Number of notification kinds that are coalesced.
+const COALESCED_KINDS: usize = 3;
+
+/// Maximum number of queued notifications that can be dropped, see
+/// [`notification_channel`].
+const NOTIFICATION_CAPACITY: usize = 1_000;
+
+/// Creates the notification channel of an [`Account`].
+///
+/// Notifications that only ask the consumer to refresh its state
+/// (`CoinUpdate`, `AddressTipChanged` & `PoolUpdate`) are coalesced: one of
+/// them is not queued while another of the same kind is still pending.
+/// Other non critical notifications are queued in a bounded channel and
+/// dropped when it is full, so a slow consumer cannot make the channel grow
+/// unbounded during a large sync. Errors, stops and pool outcomes are never
+/// dropped, they are queued apart and received in sending order with the
+/// others.
+///
+/// NOTE: a blocking send is never used as the consumer thread also sends
+/// notifications and would deadlock on a full channel.
+pub fn notification_channel() -> (NotificationSender, NotificationReceiver) {
+    let (sender, receiver) = mpsc::sync_channel(NOTIFICATION_CAPACITY);
+    let (critical_sender, critical_receiver) = mpsc::channel();
+    let pending: Arc<[AtomicBool; COALESCED_KINDS]> = Default::default();
+    (
+        NotificationSender {
+            sender,
+            critical: critical_sender,
+            sequence: Default::default(),
+            pending: pending.clone(),
+        },
+        NotificationReceiver {
+            receiver,
+            critical: critical_receiver,
+            heads: Default::default(),
+            pending,
+        },
+    )
+}
+
+/// A notification with its sending order.
+type Sequenced = (u64, Notification);
+
+/// Sending half of the notification channel, see [`notification_channel`].
+#[derive(Debug, Clone)]
+pub struct NotificationSender {
+    sender: mpsc::SyncSender<Sequenced>,
+    critical: mpsc::Sender<Sequenced>,
+    sequence: Arc<AtomicU64>,
+    pending: Arc<[AtomicBool; COALESCED_KINDS]>,
+}
+
+impl NotificationSender {
+    /// Sends a notification, coalescing it if one of the same kind is still
+    /// pending and dropping it if the channel is full and it is not
+    /// critical.
+    pub fn send(&self, notification: Notification) -> Result<(), mpsc::SendError<Notification>> {
+        if notification.is_critical() {
+            let sequence = self.sequence.fetch_add(1, Ordering::AcqRel);
+            return self
+                .critical
+                .send((sequence, notification))
+                .map_err(|mpsc::SendError((_, n))| mpsc::SendError(n));
+        }
+        let kind = notification.coalesced_kind();
+        if let Some(kind) = kind {
+            if self.pending[kind].swap(true, Ordering::AcqRel) {
+                return Ok(());
+            }
+        }
+        let sequence = self.sequence.fetch_add(1, Ordering::AcqRel);
+        match self.sender.try_send((sequence, notification)) {
+            Ok(()) => Ok(()),
+            Err(mpsc::TrySendError::Full((_, notification))) => {
+                log::warn!("NotificationSender::send() channel full, drop {notification:?}");
+                // NOTE: the kind is not pending anymore, the next
+                // notification of this kind is queued once drained
+                if let Some(kind) = kind {
+                    self.pending[kind].store(false, Ordering::Release);
+                }
+                Ok(())
+            }
+            Err(mpsc::TrySendError::Disconnected((_, notification))) => {
+                Err(mpsc::SendError(notification))
+            }
+        }
+    }
+}
+
+/// Receiving half of the notification channel, see [`notification_channel`].
+#[derive(Debug)]
+pub struct NotificationReceiver {
+    receiver: mpsc::Receiver<Sequenced>,
+    critical: mpsc::Receiver<Sequenced>,
+    /// The next notification of each channel, received but not returned
+    /// yet as the other channel may hold an older one.
+    heads: Mutex<(Option<Sequenced>, Option<Sequenced>)>,
+    pending: Arc<[AtomicBool; COALESCED_KINDS]>,
+}
+
+impl NotificationReceiver {
+    /// Attempts to receive a notification without blocking.
+    pub fn try_recv(&self) -> Result<Notification, mpsc::TryRecvError> {
+        let mut heads = self.heads.lock().expect("poisoned");
+        let (head, critical_head) = &mut *heads;
+        let mut disconnected = true;
+        for (head, receiver) in [
+            (&mut *head, &self.receiver),
+            (critical_head, &self.critical),
+        ] {
+            if head.is_none() {
+                match receiver.try_recv() {
+                    Ok(sequenced) => *head = Some(sequenced),
+                    Err(mpsc::TryRecvError::Empty) => disconnected = false,
+                    Err(mpsc::TryRecvError::Disconnected) => {}
+                }
+            }
+            disconnected &= head.is_none();
+        }
+        // the oldest of both heads is returned first
+        let (_, notification) = match (&heads.0, &heads.1) {
+            (Some((a, _)), Some((b, _))) if b < a => heads.1.take(),
+            (Some(_), _) => heads.0.take(),
+            (None, Some(_)) => heads.1.take(),
+            (None, None) if disconnected => return Err(mpsc::TryRecvError::Disconnected),
+            (None, None) => return Err(mpsc::TryRecvError::Empty),
+        }
+        .expect("checked");
+        if let Some(kind) = notification.coalesced_kind() {
+            // NOTE: the flag is released before the consumer handles the
+            // notification, so it cannot miss a later state change.
+            self.pending[kind].store(false, Ordering::Release);
+        }
+        Ok(notification)
+    }
+}
+
 impl From<TxListenerNotif> for Notification {
     fn from(value: TxListenerNotif) -> Self {
         Notification::Electrum(value)
@@ -187,6 +386,38 @@ impl From<Error> for Notification {
 }
 
 impl Notification {
+    /// Returns the coalescing slot of the notification if it only asks the
+    /// consumer to refresh its state.
+    fn coalesced_kind(&self) -> Option<usize> {
+        match self {
+            Notification::CoinUpdate => Some(0),
+            Notification::AddressTipChanged => Some(1),
+            Notification::Joinstr(JoinstrNotif::PoolUpdate) => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the notification must never be dropped, i.e. it
+    /// reports an error, a stop or the outcome of a pool.
+    fn is_critical(&self) -> bool {
+        matches!(
+            self,
+            Notification::Electrum(TxListenerNotif::Error(_) | TxListenerNotif::Stopped)
+                | Notification::Joinstr(
+                    JoinstrNotif::Error(_)
+                        | JoinstrNotif::Stopped
+                        | JoinstrNotif::PoolMined(_)
+                        | JoinstrNotif::PoolFailed(_)
+                )
+                | Notification::InvalidElectrumConfig
+                | Notification::InvalidNostrConfig
+                | Notification::InvalidNostrBack
+                | Notification::InvalidLookAhead
+                | Notification::Stopped
+                | Notification::Error(_)
+        )
+    }
+
     /// Converts a `Notification` into a `Signal`.
     ///
     /// # Returns
@@ -206,6 +437,22 @@ impl Notification {
             Notification::Joinstr(notif) => match notif {
                 JoinstrNotif::Started => signal.set(SignalFlag::PoolListenerStarted),
                 JoinstrNotif::PoolUpdate => signal.set(SignalFlag::PoolUpdate),
+                JoinstrNotif::PoolStepChanged(id) => {
+                    signal.set(SignalFlag::PoolStepChanged);
+                    signal.payload = Some(id);
+                }
+                JoinstrNotif::PoolMined(id) => {
+                    signal.set(SignalFlag::PoolMined);
+                    signal.payload = Some(id);
+                }
+                JoinstrNotif::PoolFailed(id) => {
+                    signal.set(SignalFlag::PoolFailed);
+                    signal.payload = Some(id);
+                }
+                JoinstrNotif::PeerJoined(id) => {
+                    signal.set(SignalFlag::PeerJoined);
+                    signal.payload = Some(id);
+                }
                 JoinstrNotif::Stopped => signal.set(SignalFlag::PoolListenerStopped),
                 JoinstrNotif::Error(e) => {
                     signal.set(SignalFlag::PoolListenerError);
@@ -214,6 +461,14 @@ impl Notification {
                 JoinstrNotif::Stop => unreachable!(),
             },
             Notification::AddressTipChanged => signal.set(SignalFlag::AddressTipChanged),
+            Notification::AddressGapWarning(gap) => {
+                signal.set(SignalFlag::AddressGapWarning);
+                signal.payload = Some(gap.to_string());
+            }
+            Notification::AddressMismatch(count) => {
+                signal.set(SignalFlag::AddressMismatch);
+                signal.payload = Some(count.to_string());
+            }
             Notification::CoinUpdate => signal.set(SignalFlag::CoinUpdate),
             Notification::Stopped => signal.set(SignalFlag::Stopped),
             Notification::InvalidElectrumConfig => {
@@ -224,6 +479,12 @@ impl Notification {
                 signal.set(SignalFlag::AccountError);
                 signal.set_error("Invalid nostr config".to_string());
             }
+            Notification::InvalidNostrBack => {
+                signal.set(SignalFlag::AccountError);
+                signal.set_error(format!(
+                    "Invalid nostr lookback, it must be between 1 and {MAX_NOSTR_BACK} seconds"
+                ));
+            }
             Notification::InvalidLookAhead => {
                 signal.set(SignalFlag::AccountError);
                 signal.set_error("Invalid look_ahead value".to_string());
@@ -252,6 +513,19 @@ pub enum Error {
     PoolMissing,
     WrongKeyType,
     Satisfaction,
+    CoinjoinDisabled,
+    Config(ConfigError),
+    InvalidPoolFee,
+    InvalidAddress,
+    Transaction(String),
+    /// Joining the pool would pay this amount in sats of the coin to the
+    /// miners on top of the fees, above the configured maximum.
+    CoinjoinExcess(u64),
+    /// The coin is frozen and cannot be spent.
+    CoinFrozen,
+    /// The coinjoin signer cannot sign the coin or does not own the
+    /// coinjoin output.
+    CoinjoinSigner,
 }
 
 impl From<nostr::error::Error> for Error {
@@ -268,10 +542,27 @@ pub enum TxListenerNotif {
     Stopped,
 }
 
+/// Represents control requests sent to the transaction listener.
+#[derive(Debug, Clone, Copy)]
+pub enum ListenerControl {
+    /// Re-subscribe all the watched scripts in order to re-check their
+    /// statuses.
+    Refresh,
+}
+
+/// Represents notifications related to pools.
+///
+/// `PoolStepChanged`, `PoolMined`, `PoolFailed` & `PeerJoined` carry the id
+/// of the pool.
 #[derive(Debug)]
 pub enum JoinstrNotif {
     Started,
     PoolUpdate,
+    PoolStepChanged(String),
+    PoolMined(String),
+    PoolFailed(String),
+    /// A peer registered its input to a pool initiated by the wallet.
+    PeerJoined(String),
     Stopped,
     Stop,
     Error(Error),
@@ -287,15 +578,23 @@ impl From<nostr::error::Error> for JoinstrNotif {
 pub struct Account {
     coin_store: Arc<Mutex<CoinStore>>,
     pool_store: Arc<Mutex<PoolStore>>,
+    /// The available pools, readable while the pool store is locked.
+    pools: PoolsSnapshot,
     label_store: Arc<Mutex<LabelStore>>,
-    receiver: mpsc::Receiver<Notification>,
-    sender: mpsc::Sender<Notification>,
+    receiver: NotificationReceiver,
+    sender: NotificationSender,
     tx_listener: Option<JoinHandle<()>>,
     pool_listener: Option<JoinHandle<()>>,
     config: Config,
     electrum_stop: Option<Arc<AtomicBool>>,
+    electrum_control: Option<mpsc::Sender<ListenerControl>>,
     nostr_stop: Option<Arc<AtomicBool>>,
+    shutdown: Option<JoinHandle<()>>,
     signing_manager: SigningManager,
+    /// Whether the network listeners are never started.
+    offline: bool,
+    /// Fee estimates (in sats/vb) by confirmation target (in blocks).
+    fee_estimates: BTreeMap<u16, f64>,
 }
 
 impl Drop for Account {
@@ -306,6 +605,8 @@ impl Drop for Account {
         if let Some(stop) = self.nostr_stop.as_mut() {
             stop.store(true, Ordering::Relaxed);
         }
+        // NOTE: the workers are detached, the drop must not block
+        PoolStore::shutdown(&self.pool_store);
     }
 }
 
@@ -321,8 +622,37 @@ impl Account {
     ///
     /// A new `Account` instance.
     pub fn new(config: Config) -> Self {
+        let mut account = Self::load(config, false);
+        account.start_electrum();
+        account.start_nostr();
+        account
+    }
+
+    /// Creates a new `Account` instance that never starts its network
+    /// listeners, e.g. for an offline signing device. Coins and addresses
+    /// are loaded from the persisted state.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration for the account.
+    ///
+    /// # Returns
+    ///
+    /// A new offline `Account` instance.
+    pub fn new_offline(config: Config) -> Self {
+        Self::load(config, true)
+    }
+
+    /// Loads an account from its configuration and persisted state without
+    /// starting the network listeners.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration for the account.
+    /// * `offline` - Whether the network listeners must never be started.
+    fn load(config: Config, offline: bool) -> Self {
         assert!(!config.account.is_empty());
-        let (sender, receiver) = mpsc::channel();
+        let (sender, receiver) = notification_channel();
         let tx_data = TxStore::store_from_file(config.transactions_path());
         let tx_store = TxStore::new(tx_data, Some(config.transactions_path()));
         let Tip { receive, change } = config.tip_from_file();
@@ -330,34 +660,47 @@ impl Account {
         let coin_store = Arc::new(Mutex::new(CoinStore::new(
             config.network,
             config.descriptor.clone(),
+            config.secondary_descriptor.clone(),
             sender.clone(),
             receive,
             change,
-            config.look_ahead,
+            config.recv_look_ahead.unwrap_or(config.look_ahead),
+            config.change_look_ahead.unwrap_or(config.look_ahead),
+            config.scan_from,
             tx_store,
             label_store.clone(),
             Some(config.clone()),
         )));
         coin_store.lock().expect("poisoned").generate();
-        let pool_store = Arc::new(Mutex::new(PoolStore::new()));
+        let pool_store = PoolStore::new(Some(config.clone()));
+        let pools = pool_store.snapshot();
+        let pool_store = Arc::new(Mutex::new(pool_store));
         let mut signing_manager = SigningManager::default();
-        signing_manager.new_hot_signer_from_mnemonic(config.network(), config.mnemonic.to_string());
-        let mut account = Account {
+        // NOTE: without mnemonic the account is watch-only until unlocked
+        if !config.mnemonic.is_empty() {
+            signing_manager
+                .new_hot_signer_from_mnemonic(config.network(), config.mnemonic.to_string());
+            // NOTE: only the account descriptor is signed for
+            signing_manager.register_descriptor(config.descriptor.to_string());
+        }
+        Account {
             coin_store,
             pool_store,
+            pools,
             label_store,
             tx_listener: None,
             pool_listener: None,
             electrum_stop: None,
+            electrum_control: None,
             nostr_stop: None,
+            shutdown: None,
             receiver,
             sender,
             config,
             signing_manager,
-        };
-        account.start_electrum();
-        account.start_nostr();
-        account
+            offline,
+            fee_estimates: BTreeMap::new(),
+        }
     }
 
     /// Returns a boxed version of the account.
@@ -378,46 +721,65 @@ impl Account {
     ///
     /// # Returns
     ///
-    /// A tuple containing a sender for address tips and a stop flag.
+    /// A tuple containing a sender for address tips, a stop flag and a
+    /// sender for control requests.
     fn start_listen_txs(
         &mut self,
         addr: String,
         port: u16,
         config: Config,
-    ) -> (mpsc::Sender<AddressTip>, Arc<AtomicBool>) {
+    ) -> (
+        mpsc::Sender<AddressTip>,
+        Arc<AtomicBool>,
+        mpsc::Sender<ListenerControl>,
+    ) {
         log::debug!("Account::start_poll_txs()");
         let (sender, address_tip) = mpsc::channel();
+        let (control_sender, control) = mpsc::channel();
         let coin_store = self.coin_store.clone();
         let notification = self.sender.clone();
-        let derivator = self.derivator();
+        let derivators = self.coin_store.lock().expect("poisoned").derivators();
         let stop = Arc::new(AtomicBool::new(false));
         let stop_request = stop.clone();
 
         let poller = thread::spawn(move || {
-            let client = match joinstr::electrum::Client::new(&addr, port) {
-                Ok(c) => c,
+            let connect = move || match joinstr::electrum::Client::new(&addr, port) {
+                Ok(client) => Ok(client.listen::<CoinRequest, CoinResponse>()),
                 Err(e) => {
                     log::error!("start_listen_txs(): fail to create electrum client {}", e);
-                    let _ = notification.send(TxListenerNotif::Error(e.to_string()).into());
-                    return;
+                    Err(e.to_string())
                 }
             };
 
-            let (request, response) = client.listen::<CoinRequest, CoinResponse>();
+            let (request, response) = match connect() {
+                Ok(channels) => channels,
+                Err(e) => {
+                    let _ = notification.send(TxListenerNotif::Error(e).into());
+                    return;
+                }
+            };
 
+            let timeout = Duration::from_secs(config.electrum_timeout);
+            let batch_size = config.electrum_batch_size as usize;
+            let backoff = Backoff::from_config(&config);
             listen_txs(
                 coin_store,
-                derivator,
+                derivators,
                 notification,
                 address_tip,
+                control,
                 stop_request,
                 request,
                 response,
                 Some(config),
+                timeout,
+                batch_size,
+                backoff,
+                connect,
             );
         });
         self.tx_listener = Some(poller);
-        (sender, stop)
+        (sender, stop, control_sender)
     }
 
     /// Starts polling pools with the specified parameters.
@@ -435,10 +797,21 @@ impl Account {
         let pool_store = self.pool_store.clone();
         let sender = self.sender.clone();
 
+        let authors = self.config.nostr_pool_authors.clone();
+        let backoff = Backoff::from_config(&self.config);
+
         let stop = Arc::new(AtomicBool::new(false));
         let cloned_stop = stop.clone();
         let poller = thread::spawn(move || {
-            pool_listener(relay, pool_store, sender, back, cloned_stop);
+            pool_listener(
+                relay,
+                pool_store,
+                sender,
+                back,
+                authors,
+                cloned_stop,
+                backoff,
+            );
         });
         self.pool_listener = Some(poller);
         stop
@@ -512,6 +885,85 @@ impl Account {
     pub fn new_change_addr(&mut self) -> bitcoin::Address {
         self.coin_store.lock().expect("poisoned").new_change_addr()
     }
+    /// Generates a new address for a coinjoin output, derived from the
+    /// keychain configured for coinjoin outputs.
+    ///
+    /// The output follows the descriptor of the contributed coin, so a
+    /// coin of the secondary descriptor is mixed into an output of the
+    /// same script type.
+    ///
+    /// # Arguments
+    ///
+    /// * `secondary` - Whether the contributed coin is derived from the
+    ///   secondary descriptor.
+    ///
+    /// # Returns
+    ///
+    /// A `bitcoin::Address` instance.
+    pub fn coinjoin_output_addr(&mut self, secondary: bool) -> bitcoin::Address {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .new_coinjoin_addr(self.config.coinjoin_keychain, secondary)
+    }
+
+    /// Edits the labels of several coins at once, the coins are only
+    /// regenerated once all the labels have been edited.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The `(outpoint, label)` pairs to edit, an empty label
+    ///   removes the label of the coin. Unparsable outpoints are skipped.
+    pub fn edit_coin_labels(&self, entries: Vec<(String, String)>) {
+        {
+            let mut store = self.label_store.lock().expect("poisoned");
+            for (outpoint, label) in entries {
+                if let Ok(outpoint) = bitcoin::OutPoint::from_str(&outpoint) {
+                    store.edit(
+                        LabelKey::OutPoint(outpoint),
+                        (!label.is_empty()).then_some(label),
+                    );
+                }
+            }
+        } // <- release label_store lock
+        if let Ok(mut store) = self.coin_store.try_lock() {
+            store.generate();
+        }
+    }
+
+    /// Returns the unconfirmed chain a coin is part of.
+    ///
+    /// # Arguments
+    ///
+    /// * `outpoint` - The outpoint of the coin.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the number of unconfirmed ancestors of the coin,
+    /// the transaction creating it included, and the sum of their known
+    /// fees in sats. Zeros if the coin is confirmed, unknown or if the
+    /// outpoint cannot be parsed.
+    pub fn unconfirmed_chain(&self, outpoint: String) -> (usize, u64) {
+        match OutPoint::from_str(&outpoint) {
+            Ok(op) => self
+                .coin_store
+                .lock()
+                .expect("poisoned")
+                .unconfirmed_chain(&op),
+            Err(_) => (0, 0),
+        }
+    }
+
+    /// Returns whether the script pubkey is derived from the secondary
+    /// descriptor.
+    fn is_secondary_spk(&self, spk: &ScriptBuf) -> bool {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .address_info(spk)
+            .map(|e| e.is_secondary())
+            .unwrap_or(false)
+    }
 
     /// Returns the current receiving watch tip index.
     ///
@@ -530,6 +982,24 @@ impl Account {
     pub fn change_watch_tip(&self) -> u32 {
         self.coin_store.lock().expect("poisoned").change_watch_tip()
     }
+
+    /// Returns the index of the last generated receiving address.
+    ///
+    /// # Returns
+    ///
+    /// The receiving tip index as a `u32`.
+    pub fn recv_tip(&self) -> u32 {
+        self.coin_store.lock().expect("poisoned").recv_tip()
+    }
+
+    /// Returns the index of the last generated change address.
+    ///
+    /// # Returns
+    ///
+    /// The change tip index as a `u32`.
+    pub fn change_tip(&self) -> u32 {
+        self.coin_store.lock().expect("poisoned").change_tip()
+    }
 }
 
 // C++ shared interface
@@ -543,139 +1013,440 @@ impl Account {
         self.coin_store.lock().expect("poisoned").spendable_coins()
     }
 
-    /// Calculates the satisfaction size for an input, returning the result
-    /// in weight units (WU).
+    /// Returns the spendable coins of the account in the given order.
     ///
-    /// This function estimates the size required to satisfy the inputs of a
-    /// transaction based on the
-    /// account's descriptor.
+    /// # Arguments
+    ///
+    /// * `sort` - The order of the coins.
     ///
     /// # Returns
     ///
-    /// A `Result<usize, Error>` where:
-    /// - `Ok(usize)` indicates the estimated satisfaction size in weight units (WU).
-    /// - `Err(Error)` indicates an error occurred during the calculation.
+    /// A `CoinState` instance.
+    pub fn spendable_coins_sorted(&self, sort: CoinSort) -> CoinState {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .spendable_coins_sorted(sort)
+    }
+
+    /// Returns the state of the wallet displayed on the dashboard in a
+    /// single call.
     ///
-    /// # Errors
+    /// # Returns
     ///
-    /// This function can return an error in the following cases:
-    /// - If the descriptor is invalid or cannot be parsed, leading to an
-    ///   inability to determine the satisfaction size for the inputs.
-    pub fn input_satisfaction_size(&self) -> Result<usize, Error> {
-        self.config
-            .descriptor
-            .clone()
-            .into_single_descriptors()
-            .expect("multikey")
-            .first()
-            .expect("multikey")
-            .clone()
-            .max_weight_to_satisfy()
-            .map_err(|_| Error::Satisfaction)
-            .map(|w| w.to_wu() as usize)
+    /// A `WalletSummary` with the spendable balances and coin count, the
+    /// pools counts, the listeners status and whether the coins are synced.
+    pub fn summary(&self) -> WalletSummary {
+        let (state, synced) = {
+            let store = self.coin_store.lock().expect("poisoned");
+            (store.spendable_coins(), store.is_synced())
+        }; // <- release coin_store lock
+        let pools = self.pools();
+        let pools = if pools.is_ok() { pools.value() } else { vec![] };
+        let running = |stop: &Option<Arc<AtomicBool>>| {
+            stop.as_ref().is_some_and(|s| !s.load(Ordering::Relaxed))
+        };
+        WalletSummary {
+            confirmed_balance: state.confirmed_balance,
+            unconfirmed_balance: state.unconfirmed_balance,
+            coins: state.coins.len(),
+            available_pools: pools
+                .iter()
+                .filter(|p| p.status == PoolStatus::Available)
+                .count(),
+            active_coinjoins: pools
+                .iter()
+                .filter(|p| {
+                    p.role != PoolRole::None
+                        && !matches!(
+                            p.status,
+                            PoolStatus::Mined | PoolStatus::Closed | PoolStatus::Error
+                        )
+                })
+                .count(),
+            electrum_running: running(&self.electrum_stop),
+            nostr_running: running(&self.nostr_stop),
+            synced,
+        }
     }
 
-    /// Estimates the maximum possible weight in weight units of an unsigned
-    /// transaction, `tx`, after satisfaction, assuming all inputs of `tx` are
-    /// from this descriptor.
+    /// Freezes or unfreezes a coin, a frozen coin is excluded from the
+    /// spendable coins and is never spent. The frozen coins are persisted.
     ///
     /// # Arguments
     ///
-    /// * `tx` - A reference to the `bitcoin::Transaction` for which the weight
-    ///   is to be estimated.
+    /// * `outpoint` - The outpoint of the coin.
+    /// * `frozen` - Whether the coin must be frozen.
     ///
     /// # Returns
     ///
-    /// A `Result<u64, Error>` containing:
-    /// - `Ok(u64)` - The estimated weight of the transaction in weight units (WU).
-    /// - `Err(Error)` - An error if the estimation fails.
-    ///
-    /// # Errors
-    ///
-    /// This function can return an error in the following cases:
-    /// - If the satisfaction size for the inputs cannot be calculated due to an
-    ///   invalid descriptor.
-    /// - If the input satisfaction size exceeds the maximum allowable weight,
-    ///   leading to an overflow.
-    /// - If the transaction contains no inputs, which would make weight estimation
-    ///   impossible.
-    /// - If there is an issue with the underlying logic of the weight calculation,
-    ///   such as unexpected behavior from the `miniscript` library.
-    ///
-    /// # Note
-    ///
-    /// The weight is calculated based on the number of inputs and their respective
-    /// satisfaction sizes.
-    /// The function takes into account the Segwit marker and flag, ensuring that
-    /// the weight is accurately
-    /// represented according to Bitcoin's transaction weight rules. This logic have
-    /// been borrowed from Liana wallet.
-    pub fn tx_estimated_weight(&self, tx: &bitcoin::Transaction) -> Result<u64, Error> {
-        let num_inputs: u64 = tx.input.len().try_into().unwrap();
-        let max_sat_weight: u64 = self.input_satisfaction_size()?.try_into().unwrap();
-        // Add weights together before converting to vbytes to avoid rounding up multiple times.
-        let size = tx
-            .weight()
-            .to_wu()
-            .checked_add(max_sat_weight.checked_mul(num_inputs).unwrap())
-            .and_then(|weight| {
-                weight.checked_add(
-                    // Make sure the Segwit marker and flag are included:
-                    // https://docs.rs/bitcoin/0.31.0/src/bitcoin/blockdata/transaction.rs.html#752-753
-                    // https://docs.rs/bitcoin/0.31.0/src/bitcoin/blockdata/transaction.rs.html#968-979
-                    if num_inputs > 0 && tx.input.iter().all(|txin| txin.witness.is_empty()) {
-                        2
-                    } else {
-                        0
-                    },
-                )
-            })
-            .unwrap();
-        let size = size
-            .checked_add(WITNESS_SCALE_FACTOR.checked_sub(1).unwrap())
-            .unwrap()
-            .checked_div(WITNESS_SCALE_FACTOR)
-            .unwrap();
-        Ok(size)
+    /// `false` if the outpoint cannot be parsed or is not a wallet coin.
+    pub fn freeze_coin(&mut self, outpoint: String, frozen: bool) -> bool {
+        let Ok(outpoint) = OutPoint::from_str(&outpoint) else {
+            return false;
+        };
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .freeze_coin(outpoint, frozen)
     }
 
-    /// Returns the coin matching the given outpoint if found, else None.
-    pub fn get_coin(&self, outpoint: &OutPoint) -> Option<Coin> {
+    /// Returns the outpoints of the frozen coins.
+    pub fn frozen_coins(&self) -> Vec<String> {
         self.coin_store
             .lock()
             .expect("poisoned")
-            .get(outpoint)
-            .map(|e| e.coin)
+            .frozen_coins()
+            .into_iter()
+            .map(|op| op.to_string())
+            .collect()
     }
 
-    /// Generates a static dummy script public key (SPK) for change outputs.
-    ///
-    /// This function always returns the same dummy spk,
-    /// It is useful for simulating change outputs in a non-final transaction
-    /// while allowing for easy replacement of this SPK later during the final
-    /// crafting of the transaction.
+    /// Registers a transaction as prepared by this wallet, e.g. once signed
+    /// by a hardware or an offline signer, the coins it spends are then not
+    /// flagged as externally spent. The transactions signed by the hot
+    /// signer are registered automatically.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// A `ScriptBuf` representing the dummy script public key for the change address.
+    /// * `txid` - The txid of the transaction.
     ///
-    /// # Note
+    /// # Returns
     ///
-    /// The SPK returned by this function is not intended for actual use in
-    /// transactions until it is replaced with a valid SPK at the time of final
-    /// transaction crafting.
-    pub fn dummy_spk(&self) -> ScriptBuf {
-        ScriptBuf::from_bytes(vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])
+    /// `false` if the txid cannot be parsed.
+    pub fn register_prepared_tx(&mut self, txid: String) -> bool {
+        let Ok(txid) = bitcoin::Txid::from_str(&txid) else {
+            return false;
+        };
+        let mut store = self.coin_store.lock().expect("poisoned");
+        store.register_prepared_tx(txid);
+        store.generate();
+        true
     }
 
-    /// Assembles a Bitcoin transaction from the provided inputs and outputs.
+    /// Re-derives the stored addresses and coins from the current
+    /// descriptors, e.g. after the descriptor has been edited.
     ///
-    /// This function creates a new `bitcoin::Transaction` by populating its
-    /// input and output fields based on the provided vectors of coin entries
-    /// and transaction outputs. The transaction is initialized with a version
-    /// and a lock time of zero.
+    /// An `AddressMismatch` notification is sent if some entries are not
+    /// derived anymore at their recorded derivation.
     ///
-    /// # Arguments
+    /// # Returns
+    ///
+    /// An `AddressValidation` listing the mismatched addresses and coins.
+    pub fn revalidate_addresses(&self) -> AddressValidation {
+        let (addresses, coins) = self
+            .coin_store
+            .lock()
+            .expect("poisoned")
+            .revalidate_addresses();
+        for entry in &addresses {
+            log::warn!(
+                "Account::revalidate_addresses() address {} is not derived at {:?}/{}",
+                entry.value(),
+                entry.account,
+                entry.index
+            );
+        }
+        for op in &coins {
+            log::warn!("Account::revalidate_addresses() coin {op} is not derived anymore");
+        }
+        let count = (addresses.len() + coins.len()) as u32;
+        if count > 0 {
+            let _ = self.sender.send(Notification::AddressMismatch(count));
+        }
+        AddressValidation {
+            addresses: addresses.iter().map(|e| e.value()).collect(),
+            coins: coins.iter().map(|op| op.to_string()).collect(),
+        }
+    }
+
+    /// Returns the spendable coins worth less than the fee to spend them.
+    ///
+    /// # Arguments
+    ///
+    /// * `fee_rate` - The fee rate in sats/vb.
+    ///
+    /// # Returns
+    ///
+    /// The coins whose value is below the fee of the input spending them at
+    /// `fee_rate`.
+    pub fn uneconomical_coins(&self, fee_rate: f64) -> Vec<RustCoin> {
+        let satisfaction_weight = match self.input_satisfaction_size() {
+            Ok(w) => w as u64,
+            Err(e) => {
+                log::error!("Account::uneconomical_coins() fail to estimate input size: {e:?}");
+                return vec![];
+            }
+        };
+        let input_fee = (fee_rate * input_vbytes(satisfaction_weight) as f64).ceil() as u64;
+        self.spendable_coins()
+            .coins
+            .into_iter()
+            .filter(|c| c.value < input_fee)
+            .collect()
+    }
+
+    /// Counts the spendable coins per value bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `buckets` - The lower bounds of the buckets in sats, a bucket
+    ///   spans up to the next bound, the last one is unbounded.
+    ///
+    /// # Returns
+    ///
+    /// The buckets sorted by ascending bound, each with its number of
+    /// coins. Coins worth less than the lowest bound are not counted.
+    pub fn utxo_histogram(&self, buckets: Vec<u64>) -> Vec<UtxoBucket> {
+        let values = self.spendable_coins().coins.into_iter().map(|c| c.value);
+        histogram(values, buckets)
+            .into_iter()
+            .map(|(lower_bound, coins)| UtxoBucket { lower_bound, coins })
+            .collect()
+    }
+
+    /// Returns whether consolidating the spendable coins now is advised by
+    /// the consolidation policy of the config.
+    ///
+    /// Consolidating saves the fee of spending all but one input at the
+    /// long term fee rate, at the cost of the consolidation transaction at
+    /// `fee_rate`. Coins not worth spending at `fee_rate` are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `fee_rate` - The current fee rate in sats/vb.
+    ///
+    /// # Returns
+    ///
+    /// `true` if consolidating saves fees and either the number of coins is
+    /// above `consolidation_max_utxos` or the savings reach
+    /// `consolidation_min_savings`.
+    pub fn should_consolidate(&self, fee_rate: f64) -> bool {
+        if !fee_rate.is_finite() || fee_rate <= 0.0 {
+            return false;
+        }
+        let satisfaction_weight = match self.input_satisfaction_size() {
+            Ok(w) => w as u64,
+            Err(e) => {
+                log::error!("Account::should_consolidate() fail to estimate input size: {e:?}");
+                return false;
+            }
+        };
+        let input_vbytes = input_vbytes(satisfaction_weight) as f64;
+        let uneconomical: BTreeSet<_> = self
+            .uneconomical_coins(fee_rate)
+            .into_iter()
+            .map(|c| c.outpoint)
+            .collect();
+        let coins = self
+            .spendable_coins()
+            .coins
+            .into_iter()
+            .filter(|c| !uneconomical.contains(&c.outpoint))
+            .count();
+        if coins < 2 {
+            return false;
+        }
+
+        let output_vbytes =
+            (TX_OVERHEAD_VBYTES + txout_vbytes(self.recv_at(0).script_pubkey().len())) as f64;
+        let consolidation_fee = (coins as f64 * input_vbytes + output_vbytes) * fee_rate;
+        let savings = (coins - 1) as f64 * input_vbytes * LONG_TERM_FEE_RATE - consolidation_fee;
+        if savings <= 0.0 {
+            return false;
+        }
+        coins > self.config.consolidation_max_utxos as usize
+            || savings >= self.config.consolidation_min_savings as f64
+    }
+
+    /// Returns the balance of the account as of a past block height.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The block height at which the balance is computed.
+    ///
+    /// # Returns
+    ///
+    /// The sum in sats of the coins confirmed at or before `height` and not
+    /// spent by that height, unconfirmed coins are excluded.
+    pub fn balance_at_height(&self, height: u64) -> u64 {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .balance_at_height(height)
+    }
+
+    /// Returns the unix timestamp (in seconds) of the last coins change.
+    ///
+    /// # Returns
+    ///
+    /// The timestamp of the last time a coin has been received, spent or
+    /// confirmed, 0 if unknown.
+    pub fn last_activity(&self) -> u64 {
+        self.coin_store.lock().expect("poisoned").last_activity()
+    }
+
+    /// Calculates the satisfaction size for an input, returning the result
+    /// in weight units (WU).
+    ///
+    /// This function estimates the size required to satisfy the inputs of a
+    /// transaction based on the
+    /// account's descriptor.
+    ///
+    /// # Returns
+    ///
+    /// A `Result<usize, Error>` where:
+    /// - `Ok(usize)` indicates the estimated satisfaction size in weight units (WU).
+    /// - `Err(Error)` indicates an error occurred during the calculation.
+    ///
+    /// # Errors
+    ///
+    /// This function can return an error in the following cases:
+    /// - If the descriptor is invalid or cannot be parsed, leading to an
+    ///   inability to determine the satisfaction size for the inputs.
+    /// - If the descriptor does not yield any single descriptor or cannot be
+    ///   satisfied at all.
+    pub fn input_satisfaction_size(&self) -> Result<usize, Error> {
+        // NOTE: if a secondary descriptor is tracked, the largest
+        // satisfaction is used in order to never underestimate fees
+        let mut size = 0;
+        for descriptor in Some(&self.config.descriptor)
+            .into_iter()
+            .chain(self.config.secondary_descriptor.as_ref())
+        {
+            let weight = descriptor
+                .clone()
+                .into_single_descriptors()
+                .map_err(|_| Error::Satisfaction)?
+                .into_iter()
+                .next()
+                .ok_or(Error::Satisfaction)?
+                .max_weight_to_satisfy()
+                .map_err(|_| Error::Satisfaction)?;
+            size = size.max(weight.to_wu() as usize);
+        }
+        Ok(size)
+    }
+
+    /// Estimates the maximum possible weight in weight units of an unsigned
+    /// transaction, `tx`, after satisfaction, assuming all inputs of `tx` are
+    /// from this descriptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - A reference to the `bitcoin::Transaction` for which the weight
+    ///   is to be estimated.
+    ///
+    /// # Returns
+    ///
+    /// A `Result<u64, Error>` containing:
+    /// - `Ok(u64)` - The estimated weight of the transaction in weight units (WU).
+    /// - `Err(Error)` - An error if the estimation fails.
+    ///
+    /// # Errors
+    ///
+    /// This function can return an error in the following cases:
+    /// - If the satisfaction size for the inputs cannot be calculated due to an
+    ///   invalid descriptor.
+    /// - If the input satisfaction size exceeds the maximum allowable weight,
+    ///   leading to an overflow.
+    /// - If the transaction contains no inputs, which would make weight estimation
+    ///   impossible.
+    /// - If there is an issue with the underlying logic of the weight calculation,
+    ///   such as unexpected behavior from the `miniscript` library.
+    ///
+    /// # Note
+    ///
+    /// The weight is calculated based on the number of inputs and their respective
+    /// satisfaction sizes.
+    /// The function takes into account the Segwit marker and flag, ensuring that
+    /// the weight is accurately
+    /// represented according to Bitcoin's transaction weight rules. This logic have
+    /// been borrowed from Liana wallet.
+    pub fn tx_estimated_weight(&self, tx: &bitcoin::Transaction) -> Result<u64, Error> {
+        let num_inputs: u64 = tx.input.len().try_into().map_err(|_| Error::Satisfaction)?;
+        let max_sat_weight: u64 = self
+            .input_satisfaction_size()?
+            .try_into()
+            .map_err(|_| Error::Satisfaction)?;
+        // Add weights together before converting to vbytes to avoid rounding up multiple times.
+        let size = tx
+            .weight()
+            .to_wu()
+            .checked_add(
+                max_sat_weight
+                    .checked_mul(num_inputs)
+                    .ok_or(Error::Satisfaction)?,
+            )
+            .and_then(|weight| {
+                weight.checked_add(
+                    // Make sure the Segwit marker and flag are included:
+                    // https://docs.rs/bitcoin/0.31.0/src/bitcoin/blockdata/transaction.rs.html#752-753
+                    // https://docs.rs/bitcoin/0.31.0/src/bitcoin/blockdata/transaction.rs.html#968-979
+                    if num_inputs > 0 && tx.input.iter().all(|txin| txin.witness.is_empty()) {
+                        2
+                    } else {
+                        0
+                    },
+                )
+            })
+            .ok_or(Error::Satisfaction)?;
+        let size = size
+            .checked_add(WITNESS_SCALE_FACTOR - 1)
+            .ok_or(Error::Satisfaction)?
+            / WITNESS_SCALE_FACTOR;
+        Ok(size)
+    }
+
+    /// Returns the coin matching the given outpoint if found, else None.
+    pub fn get_coin(&self, outpoint: &OutPoint) -> Option<Coin> {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .get(outpoint)
+            .map(|e| e.coin)
+    }
+
+    /// Generates a static dummy script public key (SPK) for change outputs.
+    ///
+    /// This function always returns the same dummy spk,
+    /// It is useful for simulating change outputs in a non-final transaction,
+    /// the change output is tracked by its index and its SPK is replaced
+    /// during the final crafting of the transaction.
+    ///
+    /// The dummy spk has the length of a change spk, so the weight (and thus
+    /// the fee) of a simulated transaction is the weight of the prepared one.
+    ///
+    /// # Returns
+    ///
+    /// A `ScriptBuf` representing the dummy script public key for the change address.
+    ///
+    /// # Note
+    ///
+    /// The SPK returned by this function is not intended for actual use in
+    /// transactions until it is replaced with a valid SPK at the time of final
+    /// transaction crafting.
+    pub fn dummy_spk(&self) -> ScriptBuf {
+        let len = self
+            .coin_store
+            .lock()
+            .expect("poisoned")
+            .derivator_ref()
+            .change_at(0)
+            .script_pubkey()
+            .len();
+        // NOTE: an all zeros script can never match a wallet or a payee spk
+        ScriptBuf::from_bytes(vec![0x00; len])
+    }
+
+    /// Assembles a Bitcoin transaction from the provided inputs and outputs.
+    ///
+    /// This function creates a new `bitcoin::Transaction` by populating its
+    /// input and output fields based on the provided vectors of coin entries
+    /// and transaction outputs. The transaction is initialized with a version
+    /// and a lock time of zero.
+    ///
+    /// # Arguments
     ///
     /// * `inputs` - A reference to a vector of `CoinEntry` representing the
     ///   inputs for the transaction.
@@ -708,11 +1479,122 @@ impl Account {
         tx
     }
 
+    /// Resolves the inputs of a transaction template given by address and/or
+    /// by minimum value: all the spendable coins matching every given
+    /// criterion are spent.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_template` - The template of the transaction.
+    ///
+    /// # Returns
+    ///
+    /// The outpoints of the matching coins, or an error if an address is
+    /// invalid or if no coin matches.
+    fn resolve_inputs(&self, tx_template: &TransactionTemplate) -> Result<Vec<OutPoint>, String> {
+        let mut spks = vec![];
+        for address in &tx_template.inputs_by_address {
+            let addr = match bitcoin::Address::from_str(address) {
+                Ok(a) => a,
+                Err(_) => return Err("Fail to parse input address".to_string()),
+            };
+            if !addr.is_valid_for_network(self.config.network) {
+                return Err("Provided address is not valid for the current network".to_string());
+            }
+            spks.push(addr.assume_checked().script_pubkey());
+        }
+
+        let store = self.coin_store.lock().expect("poisoned");
+        let outpoints: Vec<_> = store
+            .coins()
+            .into_values()
+            .filter(|c| matches!(c.status(), CoinStatus::Confirmed | CoinStatus::Unconfirmed))
+            .filter(|c| !tx_template.confirmed_only || c.status() == CoinStatus::Confirmed)
+            .filter(|c| !store.is_frozen(c.outpoint()))
+            .filter(|c| spks.is_empty() || spks.contains(&c.spk()))
+            .filter(|c| c.amount_sat() >= tx_template.min_input_value)
+            .map(|c| *c.outpoint())
+            .collect();
+
+        if outpoints.is_empty() {
+            return Err("No coin matches the inputs selection!".to_string());
+        }
+        Ok(outpoints)
+    }
+
+    /// Selects the coins to spend in order to fund the given outputs.
+    ///
+    /// Confirmed and unconfirmed coins are candidates, unless the template
+    /// is `confirmed_only`, a combination that do not need a change output
+    /// is preferred, see [`select_coins`].
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs` - The outputs of the transaction, without change output.
+    /// * `outputs_total` - The sum of the outputs amounts in sats.
+    /// * `tx_template` - The transaction template, used for the fee and the
+    ///   confirmed only flag.
+    ///
+    /// # Returns
+    ///
+    /// The outpoints of the selected coins, or an error message if the
+    /// available coins cannot fund the outputs.
+    fn select_inputs(
+        &self,
+        outputs: &Vec<(TxOut, Option<(AddrAccount, u32)>)>,
+        outputs_total: u64,
+        tx_template: &TransactionTemplate,
+    ) -> Result<Vec<OutPoint>, String> {
+        let fee_rate = tx_template.fee_sats_vb;
+        let fee = |vbytes: u64| (fee_rate * vbytes as f64).ceil() as u64;
+
+        let satisfaction_weight = match self.input_satisfaction_size() {
+            Ok(w) => w as u64,
+            Err(e) => return Err(format!("Failed to estimate tx weight: {e:?}")),
+        };
+        let input_vbytes = input_vbytes(satisfaction_weight);
+
+        let (base_fee, cost_of_change) = if fee_rate > 0.0 {
+            let base_vbytes = match self.tx_estimated_weight(&Self::assembly_tx(&vec![], outputs)) {
+                // NOTE: +1 vbyte for the segwit marker & flag
+                Ok(w) => w + 1,
+                Err(e) => return Err(format!("Failed to estimate tx weight: {e:?}")),
+            };
+            let change_vbytes = txout_vbytes(self.coinjoin_output_spk_len());
+            (fee(base_vbytes), self.min_change() + fee(change_vbytes))
+        } else {
+            (tx_template.fee_sats, self.min_change())
+        };
+
+        let store = self.coin_store.lock().expect("poisoned");
+        let candidates: Vec<_> = store
+            .coins()
+            .into_values()
+            .filter(|c| matches!(c.status(), CoinStatus::Confirmed | CoinStatus::Unconfirmed))
+            .filter(|c| !tx_template.confirmed_only || c.status() == CoinStatus::Confirmed)
+            .filter(|c| !store.is_frozen(c.outpoint()))
+            .filter_map(|c| {
+                let effective_value = c.amount_sat().checked_sub(fee(input_vbytes))?;
+                Some(Candidate {
+                    outpoint: *c.outpoint(),
+                    effective_value,
+                })
+            })
+            .collect();
+        drop(store);
+
+        match select_coins(&candidates, outputs_total + base_fee, cost_of_change) {
+            Some(selection) => Ok(selection.outpoints),
+            None => Err("Not enough funds to pay outputs and fees!".to_string()),
+        }
+    }
+
     /// Preprocesses a transaction based on the provided `TransactionTemplate`.
     ///
     /// This function processes the transaction template to estimate whether the
     /// transaction can be successfully executed, including whether it is spendable
-    /// and if it requires change.
+    /// and if it requires change. If no inputs are provided, the coins to spend
+    /// are selected from the wallet, see [`Account::select_inputs`].
     ///
     /// # Arguments
     ///
@@ -726,17 +1608,22 @@ impl Account {
     ///   - A `Vec<CoinEntry>` representing the inputs for the transaction.
     ///   - A `Vec<(TxOut, Option<(AddrAccount, u32)>)>` representing the outputs
     ///     for the transaction.
-    ///   - A boolean indicating if the transaction contains a change output.
+    ///   - The index of the change output if any, its script pubkey being
+    ///     a placeholder, see [`Account::dummy_spk`].
     ///
     /// # Errors
     ///
     /// This function can return an error in the following cases:
     /// - If both `fee_sats` and `fee_sats_vb` are filled, which is not allowed.
     /// - If neither `fee_sats` nor `fee_sats_vb` is filled, which is required.
-    /// - If `fee_sats_vb` is filled with a value less than 1.0, which is below
-    ///   the minimum allowed fee rate.
+    /// - If `fee_sats_vb` is filled with a value below the configured minimum
+    ///   relay fee rate.
+    /// - If the fee of the final transaction is below the minimum relay fee.
     /// - If the provided outpoints do not match any available coins in the coin
     ///   store. (external inputs are not allowed for now)
+    /// - If the same outpoint is provided several times.
+    /// - If no inputs are provided while an output is maxed.
+    /// - If no inputs are provided and the wallet coins cannot fund the outputs.
     /// - If the total inputs amount is less than the total outputs amount, making
     ///   the transaction invalid.
     /// - If the maximum output is selected but there are not enough reserves to fill it.
@@ -757,114 +1644,31 @@ impl Account {
         (
             Vec<CoinEntry>,                           /* inputs */
             Vec<(TxOut, Option<(AddrAccount, u32)>)>, /* outputs */
-            bool,                                     /* change */
+            Option<usize>,                            /* change index */
         ),
         String,
     > {
-        // TODO: implement coin selection if no or not enough input provided
-
         if (tx_template.fee_sats > 0) && (tx_template.fee_sats_vb > 0.0) {
             return Err("Only one of fee_sats or fee_sats_vb must be filled!".to_string());
         } else if (tx_template.fee_sats == 0) && (tx_template.fee_sats_vb == 0.0) {
             return Err("One of fee_sats or fee_sats_vb must be filled!".to_string());
-        } else if (tx_template.fee_sats == 0) && (tx_template.fee_sats_vb < 1.0) {
-            return Err("Minimum allowed fee rate is 1 sat/vb!".to_string());
-        }
-
-        if tx_template.outputs.is_empty() {
-            return Err("No outputs!".to_string());
-        } else if tx_template.inputs.is_empty() {
-            return Err("No inputs!".to_string());
+        } else if (tx_template.fee_sats == 0)
+            && (tx_template.fee_sats_vb < self.config.min_relay_fee)
+        {
+            return Err(format!(
+                "Fee rate is below the minimum relay fee of {} sat/vb!",
+                self.config.min_relay_fee
+            ));
         }
 
-        let mut inputs_total = 0;
-        let mut outputs_total = 0;
-
-        // count maxed outputs
-        let mut maxed_outputs = 0;
-        let mut maxed_output = None;
+        let (inputs, mut outputs, maxed_output, fee_reserve) = self.fund_template(tx_template)?;
 
-        for o in &tx_template.outputs {
-            if o.max {
-                if maxed_outputs > 0 {
-                    return Err("A single output can have the MAX field selected".to_string());
-                } else {
-                    maxed_outputs += 1;
-                }
-            }
+        if maxed_output.is_some() && fee_reserve < DUST_AMOUNT {
+            return Err("Not enough reserve to fill maxed output!".to_string());
         }
 
-        // parse outpoints
-        let mut outpoints = vec![];
-        for inp in &tx_template.inputs {
-            let parsed = OutPoint::from_str(&inp.outpoint);
-            match parsed {
-                Ok(op) => outpoints.push(op),
-                Err(_) => return Err("Fail to parse Outpoint".to_string()),
-            }
-        }
-
-        // craft outputs
-        let mut outputs = vec![];
-        {
-            for out in &tx_template.outputs {
-                if !out.max {
-                    outputs_total += out.amount;
-                }
-
-                // parse address & sanitize address
-                let addr = match bitcoin::Address::from_str(&out.address) {
-                    Ok(a) => a,
-                    Err(_) => return Err("Fail to parse address".to_string()),
-                };
-                if !addr.is_valid_for_network(self.config.network) {
-                    return Err("Provided address is not valid for the current network".to_string());
-                }
-                let addr = addr.assume_checked();
-                let amount = if out.max {
-                    bitcoin::Amount::ZERO
-                } else {
-                    bitcoin::Amount::from_sat(out.amount)
-                };
-                let txout = bitcoin::TxOut {
-                    value: amount,
-                    script_pubkey: addr.script_pubkey(),
-                };
-                if out.max {
-                    maxed_output = Some(addr.script_pubkey());
-                }
-
-                outputs.push((txout, None));
-            }
-        }
-
-        // get informations about coins to spend
-        let inputs = {
-            let store = self.coin_store.lock().expect("poisoned");
-            let mut inputs = Vec::<CoinEntry>::new();
-            for op in outpoints {
-                match store.get(&op) {
-                    Some(coin) => {
-                        inputs_total += coin.amount_sat();
-                        inputs.push(coin);
-                    }
-                    // TODO: maybe support external inputs?
-                    None => {
-                        return Err("Provided outpoint do not match an available coin".to_string())
-                    }
-                }
-            }
-            inputs
-        }; // <- release coin_store lock
-
-        let fee_reserve = inputs_total - outputs_total;
-
-        if maxed_output.is_some() && fee_reserve < DUST_AMOUNT {
-            return Err("Not enough reserve to fill maxed output!".to_string());
-        }
-
-        if tx_template.fee_sats > fee_reserve {
-            return Err("Not enough reserve to pay fees!".to_string());
+        if tx_template.fee_sats > fee_reserve {
+            return Err("Not enough reserve to pay fees!".to_string());
         }
 
         // estimate the fee value if change is not needed
@@ -884,7 +1688,8 @@ impl Account {
             tx_template.fee_sats
         };
 
-        let mut change = maxed_output.is_none() && (fee_reserve > DUST_AMOUNT);
+        let min_change = self.min_change();
+        let mut change = maxed_output.is_none() && (fee_reserve > min_change);
 
         let fees = if change {
             // if a change output is expected we add a dummy output
@@ -893,8 +1698,8 @@ impl Account {
                 // NOTE: putting a dummy 0 amount, will be adjusted
                 // after processing fees
                 value: bitcoin::Amount::from_sat(0),
-                // NOTE: we use here the dummy spk in order to make it easy to
-                // find which output we need to substract fees from in a later step.
+                // NOTE: the dummy spk only weighs as much as the change spk,
+                // the change output is tracked by its index
                 script_pubkey: dummy_spk,
             };
             outputs.push((txout, None));
@@ -921,7 +1726,8 @@ impl Account {
                 }
             } else {
                 // fee amount have been selected
-                let min_fee = estimated_weight_with_change;
+                let min_fee =
+                    (self.config.min_relay_fee * estimated_weight_with_change as f64).ceil() as u64;
                 if fee_reserve < min_fee {
                     outputs.pop();
                     change = false;
@@ -930,10 +1736,10 @@ impl Account {
             };
 
             if change {
-                // if the resulting change amount < DUST amount
-                // we drop the change output
+                // if the resulting change amount is not worth an output
+                // we drop it, its amount is added to the fees
                 let change_amount = fee_reserve - fees;
-                if change_amount < DUST_AMOUNT {
+                if change_amount < min_change {
                     outputs.pop();
                     change = false;
                     fees = fee_reserve;
@@ -949,44 +1755,193 @@ impl Account {
             return Err("Not enough reserve to pay fees!".to_string());
         }
 
+        // enforce the minimum relay fee on the final transaction
+        let final_weight = match self.tx_estimated_weight(&Self::assembly_tx(&inputs, &outputs)) {
+            Ok(w) => w,
+            Err(e) => return Err(format!("Failed to estimate tx weight: {e:?}")),
+        };
+        if fees < (self.config.min_relay_fee * final_weight as f64).ceil() as u64 {
+            return Err(format!(
+                "Fee is below the minimum relay fee of {} sat/vb!",
+                self.config.min_relay_fee
+            ));
+        }
+
         // fill amount for maxed or change output
         let change_or_max = bitcoin::Amount::from_sat(fee_reserve - fees);
-        if change {
-            outputs.last_mut().expect("as a last output").0.value = change_or_max;
-        } else {
+        let change_index = change.then(|| outputs.len() - 1);
+        if let Some(index) = change_index {
+            outputs[index].0.value = change_or_max;
+        } else if let Some(index) = maxed_output {
             if change_or_max.to_sat() < DUST_AMOUNT {
                 return Err("Maxed output amount is lower than the dust limit".to_string());
             }
-            let dummy_spk = self.dummy_spk();
-            for (txout, _) in &mut outputs {
-                if txout.script_pubkey == dummy_spk {
-                    txout.value = change_or_max;
-                }
-            }
+            outputs[index].0.value = change_or_max;
         }
 
         // populate addresses indexes
         {
             let store = self.coin_store.lock().expect("poisoned");
-            for out in &mut outputs {
+            for (index, out) in outputs.iter_mut().enumerate() {
+                if Some(index) == change_index {
+                    // NOTE: the change spk is not yet generated
+                    continue;
+                }
                 let spk = &out.0.script_pubkey;
                 // get derivation index of this address
-                // NOTE: this is needed by the signer to know it's a change/send-to-self
-                out.1 = store.address_info(spk).map(|e| (e.account(), e.index()));
+                // NOTE: this is needed by the signer to know it's a change/send-to-self,
+                // only addresses of the main descriptor are populated
+                out.1 = store
+                    .address_info(spk)
+                    .filter(|e| !e.is_secondary())
+                    .map(|e| (e.account(), e.index()));
             }
         } // <- release coin_store lock here
 
-        Ok((inputs, outputs, change))
+        Ok((inputs, outputs, change_index))
     }
 
-    pub fn change_index(&self, tx: &bitcoin::Transaction) -> Option<usize> {
-        let dummy_spk = self.dummy_spk();
-        for (index, TxOut { script_pubkey, .. }) in tx.output.iter().enumerate() {
-            if *script_pubkey == dummy_spk {
-                return Some(index);
+    /// Resolves the inputs and outputs of a transaction template, selecting
+    /// the coins to spend if no input is given.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_template` - The transaction template.
+    ///
+    /// # Returns
+    ///
+    /// The coins to spend, the outputs (a maxed output having a zero
+    /// amount), the index of the maxed output if any and the fee reserve,
+    /// i.e. the inputs total minus the outputs total.
+    #[allow(clippy::type_complexity)]
+    fn fund_template(
+        &self,
+        tx_template: &TransactionTemplate,
+    ) -> Result<
+        (
+            Vec<CoinEntry>,                           /* inputs */
+            Vec<(TxOut, Option<(AddrAccount, u32)>)>, /* outputs */
+            Option<usize>,                            /* maxed output index */
+            u64,                                      /* fee reserve */
+        ),
+        String,
+    > {
+        let by_spec = !tx_template.inputs_by_address.is_empty() || tx_template.min_input_value > 0;
+
+        if tx_template.outputs.is_empty() {
+            return Err("No outputs!".to_string());
+        } else if tx_template.inputs.is_empty()
+            && !by_spec
+            && tx_template.outputs.iter().any(|o| o.max)
+        {
+            // NOTE: coins are only selected automatically when no output is maxed
+            return Err("No inputs!".to_string());
+        }
+
+        let mut inputs_total = 0;
+        let mut outputs_total = 0;
+
+        // count maxed outputs
+        let mut maxed_outputs = 0;
+        let mut maxed_output = None;
+
+        for o in &tx_template.outputs {
+            if o.max {
+                if maxed_outputs > 0 {
+                    return Err("A single output can have the MAX field selected".to_string());
+                } else {
+                    maxed_outputs += 1;
+                }
+            }
+        }
+
+        // parse outpoints
+        let mut outpoints = vec![];
+        for inp in &tx_template.inputs {
+            let parsed = OutPoint::from_str(&inp.outpoint);
+            match parsed {
+                Ok(op) if outpoints.contains(&op) => {
+                    return Err(format!("Duplicate input {op}!"));
+                }
+                Ok(op) => outpoints.push(op),
+                Err(_) => return Err("Fail to parse Outpoint".to_string()),
+            }
+        }
+
+        // craft outputs
+        let mut outputs = vec![];
+        {
+            for out in &tx_template.outputs {
+                if !out.max {
+                    outputs_total += out.amount;
+                }
+
+                // parse address & sanitize address
+                let addr = match bitcoin::Address::from_str(&out.address) {
+                    Ok(a) => a,
+                    Err(_) => return Err("Fail to parse address".to_string()),
+                };
+                if !addr.is_valid_for_network(self.config.network) {
+                    return Err("Provided address is not valid for the current network".to_string());
+                }
+                let addr = addr.assume_checked();
+                let amount = if out.max {
+                    bitcoin::Amount::ZERO
+                } else {
+                    bitcoin::Amount::from_sat(out.amount)
+                };
+                let txout = bitcoin::TxOut {
+                    value: amount,
+                    script_pubkey: addr.script_pubkey(),
+                };
+                if out.max {
+                    maxed_output = Some(outputs.len());
+                }
+
+                outputs.push((txout, None));
             }
         }
-        None
+
+        // select coins to spend if none provided
+        if outpoints.is_empty() && by_spec {
+            outpoints = self.resolve_inputs(tx_template)?;
+        } else if outpoints.is_empty() {
+            outpoints = self.select_inputs(&outputs, outputs_total, tx_template)?;
+        }
+
+        // get informations about coins to spend
+        let inputs = {
+            let store = self.coin_store.lock().expect("poisoned");
+            let mut inputs = Vec::<CoinEntry>::new();
+            for op in outpoints {
+                match store.get(&op) {
+                    Some(_) if store.is_frozen(&op) => {
+                        return Err(format!("Coin {op} is frozen"));
+                    }
+                    Some(coin)
+                        if tx_template.confirmed_only
+                            && coin.status() == CoinStatus::Unconfirmed =>
+                    {
+                        return Err(format!("Coin {op} is not confirmed"));
+                    }
+                    Some(coin) => {
+                        inputs_total += coin.amount_sat();
+                        inputs.push(coin);
+                    }
+                    // TODO: maybe support external inputs?
+                    None => {
+                        return Err("Provided outpoint do not match an available coin".to_string())
+                    }
+                }
+            }
+            inputs
+        }; // <- release coin_store lock
+
+        let fee_reserve = inputs_total
+            .checked_sub(outputs_total)
+            .ok_or("Inputs amount do not cover the outputs amount!".to_string())?;
+
+        Ok((inputs, outputs, maxed_output, fee_reserve))
     }
 
     /// Simulates a transaction based on the provided `TransactionTemplate`
@@ -1004,9 +1959,11 @@ impl Account {
     /// # Returns
     ///
     /// A `Result` containing:
-    /// - A tuple with three elements:
+    /// - A tuple with four elements:
     ///   - A boolean indicating if the transaction is spendable.
     ///   - A boolean indicating if the transaction has change.
+    ///   - A boolean indicating if an unconfirmed coin is spent, the UI
+    ///     should warn the user as it can be replaced or never confirm.
     ///   - An estimated weight of the transaction in weight units (WU).
     ///
     /// # Errors
@@ -1029,12 +1986,16 @@ impl Account {
     /// information about the transaction before finalizing it, allowing for
     /// adjustments based on the simulation results.
     pub fn simulate_transaction(&self, tx_template: TransactionTemplate) -> TransactionSimulation {
-        let (inputs, outputs, has_change) = match self.process_transaction(&tx_template) {
+        let (inputs, outputs, change_index) = match self.process_transaction(&tx_template) {
             Ok(r) => r,
             Err(e) => {
                 return TransactionSimulation {
                     spendable: false,
                     has_change: false,
+                    change_amount: 0,
+                    change_index: -1,
+                    change_larger_than_payments: false,
+                    has_unconfirmed_inputs: false,
                     estimated_weight: 0,
                     error: e,
                 }
@@ -1047,19 +2008,203 @@ impl Account {
                 return TransactionSimulation {
                     spendable: false,
                     has_change: false,
+                    change_amount: 0,
+                    change_index: -1,
+                    change_larger_than_payments: false,
+                    has_unconfirmed_inputs: false,
                     estimated_weight: 0,
                     error: format!("{e:?}"),
                 }
             }
         };
+        let has_unconfirmed_inputs = inputs.iter().any(|c| c.status() == CoinStatus::Unconfirmed);
+        let has_change = change_index.is_some();
+        let change_amount = change_index
+            .map(|i| tx.output[i].value.to_sat())
+            .unwrap_or_default();
+        let max_payment = tx
+            .output
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != change_index)
+            .map(|(_, o)| o.value.to_sat())
+            .max();
         TransactionSimulation {
             spendable: true,
             has_change,
+            change_amount,
+            change_index: change_index.map(|i| i as i64).unwrap_or(-1),
+            change_larger_than_payments: change_index.is_some()
+                && max_payment.is_some_and(|max| change_amount > max),
+            has_unconfirmed_inputs,
             estimated_weight,
             error: String::new(),
         }
     }
 
+    /// Returns the minimum amount of a change output, a lower change is
+    /// added to the fees. It is never lower than the dust limit.
+    fn min_change(&self) -> u64 {
+        self.config
+            .min_change_sats
+            .unwrap_or(DUST_AMOUNT)
+            .max(DUST_AMOUNT)
+    }
+
+    /// Computes the maximum amount that can be sent to an address, all the
+    /// spendable coins being swept to a single output.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The destination address.
+    /// * `fee_rate` - The fee rate in sats/vb.
+    ///
+    /// # Returns
+    ///
+    /// The amount in sats delivered to `address`, the same amount a
+    /// transaction with a maxed output to `address` would pay.
+    ///
+    /// # Errors
+    ///
+    /// - `Error::InvalidAddress` if the address cannot be parsed or is not
+    ///   valid for the current network.
+    /// - `Error::Transaction` if the coins cannot fund the sweep fee or if
+    ///   the deliverable amount is below the dust limit.
+    pub fn max_spendable(&self, address: String, fee_rate: f64) -> Result<u64, Error> {
+        let addr = bitcoin::Address::from_str(&address).map_err(|_| Error::InvalidAddress)?;
+        if !addr.is_valid_for_network(self.config.network) {
+            return Err(Error::InvalidAddress);
+        }
+        let tx_template = TransactionTemplate {
+            inputs: self.spendable_coins().coins,
+            inputs_by_address: vec![],
+            min_input_value: 0,
+            confirmed_only: false,
+            outputs: vec![Output {
+                address,
+                amount: 0,
+                label: String::new(),
+                max: true,
+            }],
+            fee_sats: 0,
+            fee_sats_vb: fee_rate,
+        };
+        let (_, outputs, _) = self
+            .process_transaction(&tx_template)
+            .map_err(Error::Transaction)?;
+        Ok(outputs
+            .first()
+            .map(|(txout, _)| txout.value.to_sat())
+            .unwrap_or_default())
+    }
+
+    /// Computes the maximum amount that can be sent to an address, see
+    /// [`Account::max_spendable`].
+    ///
+    /// # Returns
+    ///
+    /// A `Box<SatsAmount>` containing the amount in sats, or an error.
+    pub fn max_spendable_result(&self, address: String, fee_rate: f64) -> Box<SatsAmount> {
+        match self.max_spendable(address, fee_rate) {
+            Ok(amount) => SatsAmount::ok(amount).boxed(),
+            Err(e) => SatsAmount::err(&format!("{e:?}")).boxed(),
+        }
+    }
+
+    /// Prepares a transaction sending the whole value of a single coin,
+    /// minus the fee, to an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `outpoint` - The outpoint of the coin to sweep.
+    /// * `address` - The destination address.
+    /// * `fee_rate` - The fee rate in sats/vb.
+    ///
+    /// # Returns
+    ///
+    /// A `Box<PsbtResult>` containing the PSBT of the 1-input 1-output
+    /// transaction, or an error if the coin is not spendable or if its value
+    /// do not cover the fee above the dust limit.
+    pub fn sweep_coin(
+        &mut self,
+        outpoint: String,
+        address: String,
+        fee_rate: f64,
+    ) -> Box<PsbtResult> {
+        let Some(coin) = self
+            .spendable_coins()
+            .coins
+            .into_iter()
+            .find(|c| c.outpoint == outpoint)
+        else {
+            return "Provided outpoint do not match an available coin".into();
+        };
+        let tx_template = TransactionTemplate {
+            inputs: vec![coin],
+            inputs_by_address: vec![],
+            min_input_value: 0,
+            confirmed_only: false,
+            outputs: vec![Output {
+                address,
+                amount: 0,
+                label: String::new(),
+                max: true,
+            }],
+            fee_sats: 0,
+            fee_sats_vb: fee_rate,
+        };
+        match self.process_transaction(&tx_template) {
+            Ok((_, outputs, _)) => {
+                let amount = outputs
+                    .first()
+                    .map(|(txout, _)| txout.value.to_sat())
+                    .unwrap_or_default();
+                if amount < DUST_AMOUNT {
+                    return "Coin value do not cover the fee above the dust limit".into();
+                }
+            }
+            Err(e) => return e.as_str().into(),
+        }
+        self.prepare_transaction(tx_template)
+    }
+
+    /// Computes the amount available to pay the fees of a transaction
+    /// template, without building the transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_template` - The transaction template, coins are selected if no
+    ///   input is given.
+    ///
+    /// # Returns
+    ///
+    /// The inputs total minus the outputs total in sats, a maxed output
+    /// counting as zero.
+    ///
+    /// # Errors
+    ///
+    /// - `Error::Transaction` if the inputs or outputs are invalid or if the
+    ///   inputs do not cover the outputs.
+    pub fn fee_headroom(&self, tx_template: &TransactionTemplate) -> Result<u64, Error> {
+        let (_, _, _, fee_reserve) = self
+            .fund_template(tx_template)
+            .map_err(Error::Transaction)?;
+        Ok(fee_reserve)
+    }
+
+    /// Computes the amount available to pay the fees of a transaction
+    /// template, see [`Account::fee_headroom`].
+    ///
+    /// # Returns
+    ///
+    /// A `Box<SatsAmount>` containing the amount in sats, or an error.
+    pub fn fee_headroom_result(&self, tx_template: TransactionTemplate) -> Box<SatsAmount> {
+        match self.fee_headroom(&tx_template) {
+            Ok(amount) => SatsAmount::ok(amount).boxed(),
+            Err(e) => SatsAmount::err(&format!("{e:?}")).boxed(),
+        }
+    }
+
     /// Prepares a PSBT from a given `TransactionTemplate`.
     ///
     /// This function processes the provided transaction template to create a
@@ -1103,7 +2248,7 @@ impl Account {
     /// a transaction for signing, allowing for adjustments based on the provided
     /// transaction template before finalizing the transaction.
     pub fn prepare_transaction(&mut self, tx_template: TransactionTemplate) -> Box<PsbtResult> {
-        let (inputs, mut outputs, change) = match self.process_transaction(&tx_template) {
+        let (inputs, mut outputs, change_index) = match self.process_transaction(&tx_template) {
             Ok(r) => r,
             Err(e) => {
                 return e.as_str().into();
@@ -1111,13 +2256,8 @@ impl Account {
         };
 
         // if there is a change, we replace the dummy spk by a freshly generated spk
-        if change {
-            let dummy_spk = self.dummy_spk();
-            for (txout, _) in &mut outputs {
-                if txout.script_pubkey == dummy_spk {
-                    txout.script_pubkey = self.new_change_addr().script_pubkey();
-                }
-            }
+        if let Some(index) = change_index {
+            outputs[index].0.script_pubkey = self.new_change_addr().script_pubkey();
         }
 
         let tx = Self::assembly_tx(&inputs, &outputs);
@@ -1127,21 +2267,45 @@ impl Account {
             Err(_) => return "Fail to generate PSBT from unsigned transaction".into(),
         };
 
-        let descriptor = self.config.descriptor.clone();
-        let mut descriptors = descriptor
-            .into_single_descriptors()
-            .expect("have a multipath")
-            .into_iter();
-        let receive = descriptors.next().expect("have a multipath");
-        let change = descriptors.next().expect("have a multipath");
+        let (receive, change) = match split_keychains(self.config.descriptor.clone()) {
+            Ok(keychains) => keychains,
+            Err(e) => return e.as_str().into(),
+        };
+        let secondary = match self
+            .config
+            .secondary_descriptor
+            .clone()
+            .map(split_keychains)
+            .transpose()
+        {
+            Ok(secondary) => secondary,
+            Err(e) => return e.as_str().into(),
+        };
+
+        // inputs derived from the secondary descriptor
+        let secondary_inputs: Vec<bool> = {
+            let store = self.coin_store.lock().expect("poisoned");
+            inputs
+                .iter()
+                .map(|coin| {
+                    store
+                        .address_info(&coin.spk())
+                        .map(|e| e.is_secondary())
+                        .unwrap_or(false)
+                })
+                .collect()
+        }; // <- release coin_store lock here
 
         // Populate PSBT inputs
         for (index, coin) in inputs.iter().enumerate() {
-            // NOTE: for now we consider all inputs to be owned by this descriptor
             let (account, addr_index) = coin.deriv();
+            let (receive, change) = match (&secondary, secondary_inputs[index]) {
+                (Some((receive, change)), true) => (receive, change),
+                _ => (&receive, &change),
+            };
             let descriptor = match account {
-                AddrAccount::Receive => &receive,
-                AddrAccount::Change => &change,
+                AddrAccount::Receive => receive,
+                AddrAccount::Change => change,
                 _ => unreachable!(),
             };
             let definite = descriptor
@@ -1177,36 +2341,603 @@ impl Account {
                 }
             }
         }
-        PsbtResult::ok(psbt.to_string()).boxed()
-    }
 
-    /// Returns the available pools for the account.
-    ///
-    /// # Returns
-    ///
-    /// A boxed `Pools` instance containing the available pools.
-    pub fn pools(&self) -> Box<PoolsResult> {
-        let mut pools = match self.pool_store.try_lock() {
-            Ok(lock) => {
-                let pools = lock.available_pools();
-                PoolsResult::ok(pools)
+        // Populate PSBT global xpubs
+        if self.config.psbt_global_xpubs {
+            let derivators = self.coin_store.lock().expect("poisoned").derivators();
+            for derivator in derivators {
+                psbt.xpub.extend(derivator.global_xpubs());
             }
-            Err(_) => PoolsResult::err("PoolStore locked"),
-        };
-        pools.relay = self.relay();
-        Box::new(pools)
+        }
+
+        PsbtResult::ok(psbt.to_string()).boxed()
     }
 
-    /// Creates a new pool with the specified parameters.
+    /// Returns the change addresses of a PSBT along with their derivation,
+    /// so the user can confirm they belong to the wallet.
+    ///
+    /// The derivation recorded in the PSBT outputs by
+    /// [`Account::prepare_transaction`] must match the one of the address
+    /// store.
     ///
     /// # Arguments
     ///
-    /// * `outpoint` - The outpoint for the pool.
-    /// * `denomination` - The denomination of the pool.
-    /// * `fee` - The fee for the pool.
-    /// * `max_duration` - The maximum duration of the pool.
+    /// * `psbt` - The PSBT, as a base64 string.
+    ///
+    /// # Returns
+    ///
+    /// A `Box<ChangeAddresses>` containing the change addresses, or an error
+    /// if the PSBT cannot be parsed or if a recorded derivation do not match.
+    pub fn verify_change_address(&self, psbt: String) -> Box<ChangeAddresses> {
+        let psbt = match bitcoin::Psbt::from_str(&psbt) {
+            Ok(psbt) => psbt,
+            Err(_) => return ChangeAddresses::err("Fail to parse PSBT").boxed(),
+        };
+        let store = self.coin_store.lock().expect("poisoned");
+        let derivators = store.derivators();
+        let mut addresses = vec![];
+        for (txout, output) in psbt.unsigned_tx.output.iter().zip(psbt.outputs.iter()) {
+            let entry = match store.address_info(&txout.script_pubkey) {
+                Some(e) if e.account() == AddrAccount::Change => e,
+                _ => continue,
+            };
+            // NOTE: the secondary derivator, if any, is the last one
+            let derivator = if entry.is_secondary() {
+                derivators.last()
+            } else {
+                derivators.first()
+            };
+            let derivator = derivator.expect("the main derivator");
+            let expected = Some((AddrAccount::Change, entry.index()));
+            // NOTE: taproot keys are recorded in `tap_key_origins`
+            let recorded: Vec<_> = output
+                .bip32_derivation
+                .values()
+                .chain(output.tap_key_origins.values().map(|(_, origin)| origin))
+                .collect();
+            // a change output w/o any derivation cannot be verified
+            let recorded_ok = !recorded.is_empty()
+                && recorded
+                    .iter()
+                    .all(|(fg, path)| derivator.address_index(*fg, path) == expected);
+            if !recorded_ok {
+                return ChangeAddresses::err("Change derivation do not match the address").boxed();
+            }
+            addresses.push(entry.into());
+        }
+        ChangeAddresses::ok(addresses).boxed()
+    }
+
+    /// Verifies that a PSBT belongs to this wallet before handing it to the
+    /// signer.
+    ///
+    /// # Arguments
+    ///
+    /// * `psbt` - The PSBT to verify, as a base64 string.
+    ///
+    /// # Returns
+    ///
+    /// A boxed `PsbtVerification` containing:
+    /// - `inputs_ours`: whether all inputs spend coins of this wallet.
+    /// - `outputs`: each output classified as receive, change or external.
+    /// - `fee`: the fee paid by the transaction in sats.
+    /// - `unexpected_spks`: the script pubkeys spent by inputs that are not
+    ///   ours.
+    /// - `error`: filled if the PSBT cannot be parsed, if its derivation
+    ///   paths are for another network or if the fee cannot be computed.
+    pub fn verify_psbt(&self, psbt: String) -> Box<PsbtVerification> {
+        let mut verification = PsbtVerification {
+            inputs_ours: false,
+            outputs: vec![],
+            fee: 0,
+            unexpected_spks: vec![],
+            error: String::new(),
+        };
+        let psbt = match bitcoin::Psbt::from_str(&psbt) {
+            Ok(psbt) => psbt,
+            Err(_) => {
+                verification.error = "Fail to parse PSBT".to_string();
+                return Box::new(verification);
+            }
+        };
+        let network = self.config.network;
+        if !signing_manager::matches_network(&psbt, network) {
+            verification.error = "PSBT do not match the account network".to_string();
+            return Box::new(verification);
+        }
+        let display = |spk: &ScriptBuf| display_spk(spk, network);
+
+        let store = self.coin_store.lock().expect("poisoned");
+
+        // Check inputs
+        let mut inputs_ours = !psbt.unsigned_tx.input.is_empty();
+        let mut inputs_total = Some(0u64);
+        for (txin, input) in psbt.unsigned_tx.input.iter().zip(psbt.inputs.iter()) {
+            let op = txin.previous_output;
+            // the spent output as claimed by the PSBT
+            let txout = input.witness_utxo.clone().or_else(|| {
+                input
+                    .non_witness_utxo
+                    .as_ref()
+                    .and_then(|tx| tx.output.get(op.vout as usize).cloned())
+            });
+            let ours = match (store.get(&op), &txout) {
+                (Some(coin), Some(txout)) => coin.coin.txout == *txout,
+                _ => false,
+            };
+            match txout {
+                Some(txout) => {
+                    if !ours {
+                        verification
+                            .unexpected_spks
+                            .push(display(&txout.script_pubkey));
+                    }
+                    inputs_total = inputs_total.map(|t| t + txout.value.to_sat());
+                }
+                None => inputs_total = None,
+            }
+            inputs_ours &= ours;
+        }
+        verification.inputs_ours = inputs_ours;
+
+        // Classify outputs
+        let mut outputs_total = 0;
+        for txout in &psbt.unsigned_tx.output {
+            outputs_total += txout.value.to_sat();
+            verification
+                .outputs
+                .push(classify_output(&store, txout, network));
+        }
+
+        match inputs_total.and_then(|total| total.checked_sub(outputs_total)) {
+            Some(fee) => verification.fee = fee,
+            None => verification.error = "Fail to compute the transaction fee".to_string(),
+        }
+
+        Box::new(verification)
+    }
+
+    /// Returns the raw hex of a stored transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `txid` - The txid of the transaction.
+    ///
+    /// # Returns
+    ///
+    /// The consensus encoded transaction as an hex string, empty if the
+    /// transaction is unknown.
+    pub fn raw_transaction(&self, txid: String) -> String {
+        let Ok(txid) = bitcoin::Txid::from_str(&txid) else {
+            return String::new();
+        };
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .transaction(&txid)
+            .map(|tx| bitcoin::consensus::encode::serialize_hex(&tx))
+            .unwrap_or_default()
+    }
+
+    /// Returns the inputs & outputs of a stored transaction, classified by
+    /// ownership.
+    ///
+    /// # Arguments
+    ///
+    /// * `txid` - The txid of the transaction.
+    ///
+    /// # Returns
+    ///
+    /// A boxed `TransactionDetails` containing:
+    /// - `inputs`: each input with the spent output if known and whether it
+    ///   spends a coin of this wallet.
+    /// - `outputs`: each output classified as receive, change or external.
+    /// - `fee`: the fee paid by the transaction in sats, if all the spent
+    ///   outputs are known.
+    /// - `error`: filled if the txid is invalid or the transaction unknown.
+    pub fn transaction_details(&self, txid: String) -> Box<TransactionDetails> {
+        let mut details = TransactionDetails {
+            txid: txid.clone(),
+            inputs: vec![],
+            outputs: vec![],
+            fee: 0,
+            error: String::new(),
+        };
+        let Ok(txid) = bitcoin::Txid::from_str(&txid) else {
+            details.error = "Invalid txid".to_string();
+            return Box::new(details);
+        };
+        let network = self.config.network;
+        let store = self.coin_store.lock().expect("poisoned");
+        let Some(tx) = store.transaction(&txid) else {
+            details.error = "Unknown transaction".to_string();
+            return Box::new(details);
+        };
+
+        let mut inputs_total = Some(0u64);
+        for txin in &tx.input {
+            let op = txin.previous_output;
+            let ours = store.get(&op).is_some();
+            // the spent output, known if the parent transaction is stored
+            let txout = store
+                .transaction(&op.txid)
+                .and_then(|parent| parent.output.get(op.vout as usize).cloned());
+            inputs_total = inputs_total
+                .zip(txout.as_ref())
+                .map(|(total, txout)| total + txout.value.to_sat());
+            details.inputs.push(TxInputDetails {
+                outpoint: op.to_string(),
+                address: txout
+                    .as_ref()
+                    .map(|txout| display_spk(&txout.script_pubkey, network))
+                    .unwrap_or_default(),
+                amount: txout.map(|txout| txout.value.to_sat()).unwrap_or(0),
+                ours,
+            });
+        }
+
+        let mut outputs_total = 0;
+        for txout in &tx.output {
+            outputs_total += txout.value.to_sat();
+            details
+                .outputs
+                .push(classify_output(&store, txout, network));
+        }
+        if let Some(fee) = inputs_total.and_then(|total| total.checked_sub(outputs_total)) {
+            details.fee = fee;
+        }
+
+        Box::new(details)
+    }
+
+    /// Returns the wallet transactions awaiting confirmation, incoming or
+    /// outgoing.
+    ///
+    /// # Returns
+    ///
+    /// The unconfirmed transactions with the amounts received and sent by
+    /// the wallet.
+    pub fn pending_transactions(&self) -> Vec<RustTransaction> {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .pending_transactions()
+    }
+
+    /// Returns the wallet transactions that have been waiting for a
+    /// confirmation for a long time, candidates for a fee bump.
+    ///
+    /// The age is counted from the time the transaction has been first
+    /// seen by this session, a block being expected every 10 minutes.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_age_blocks` - The minimum age of a transaction, in blocks.
+    ///
+    /// # Returns
+    ///
+    /// The unconfirmed transactions seen for at least `min_age_blocks`
+    /// blocks.
+    pub fn stuck_transactions(&self, min_age_blocks: u64) -> Vec<RustTransaction> {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .stuck_transactions(min_age_blocks)
+    }
+
+    /// Exports the wallet transactions as CSV for accounting, one row per
+    /// transaction, confirmed ones first by height then pending ones.
+    ///
+    /// The columns are `height` (empty if pending), `txid`, `type`
+    /// (`receive`, `send` or `self`), `amount` (the net change of the wallet
+    /// balance, in BTC), `fee` (in BTC, paid by the wallet) and `label`.
+    ///
+    /// # Returns
+    ///
+    /// The CSV content, starting with the header row.
+    pub fn export_csv(&self) -> String {
+        let txs = self.coin_store.lock().expect("poisoned").transactions();
+        let labels = self.label_store.lock().expect("poisoned");
+        let btc = |sats: i64| {
+            bitcoin::SignedAmount::from_sat(sats)
+                .display_in(bitcoin::Denomination::Bitcoin)
+                .to_string()
+        };
+        let mut csv = "height,txid,type,amount,fee,label\n".to_string();
+        for tx in txs {
+            let (received, sent) = (tx.received as i64, tx.sent as i64);
+            let fee = if sent > 0 { tx.fee as i64 } else { 0 };
+            let kind = if sent == 0 {
+                "receive"
+            } else if received + fee == sent {
+                "self"
+            } else {
+                "send"
+            };
+            let label = bitcoin::Txid::from_str(&tx.txid)
+                .ok()
+                .and_then(|txid| labels.transaction(txid))
+                .unwrap_or_default();
+            let height = if tx.height > 0 {
+                tx.height.to_string()
+            } else {
+                String::new()
+            };
+            csv.push_str(&format!(
+                "{height},{},{kind},{},{},{}\n",
+                tx.txid,
+                btc(received - sent),
+                btc(fee),
+                csv_field(&label)
+            ));
+        }
+        csv
+    }
+
+    /// Records the fee rate estimated to confirm a transaction within
+    /// `target` blocks, e.g. from the `blockchain.estimatefee` response of
+    /// the Electrum server.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The confirmation target, in blocks.
+    /// * `feerate` - The estimated fee rate, in sats/vb.
+    pub fn set_fee_estimate(&mut self, target: u16, feerate: f64) {
+        if target == 0 || !feerate.is_finite() || feerate <= 0.0 {
+            log::error!("Account::set_fee_estimate() invalid estimate {feerate} at {target}");
+            return;
+        }
+        self.fee_estimates.insert(target, feerate);
+    }
+
+    /// Estimates the number of blocks a transaction paying `feerate` takes
+    /// to confirm, this is the inverse of the fee estimation.
+    ///
+    /// # Arguments
+    ///
+    /// * `feerate` - The fee rate of the transaction, in sats/vb.
+    ///
+    /// # Returns
+    ///
+    /// A `Box<ConfirmationTarget>` containing the lowest confirmation target
+    /// whose estimate is lower or equal than `feerate`, or an error if no
+    /// estimate is known or if `feerate` is too low for all the known
+    /// targets.
+    pub fn estimate_confirmation_target(&self, feerate: f64) -> Box<ConfirmationTarget> {
+        if self.fee_estimates.is_empty() {
+            return ConfirmationTarget::err("No fee estimate known").boxed();
+        }
+        match confirmation_target(&self.fee_estimates, feerate) {
+            Some(target) => ConfirmationTarget::ok(target).boxed(),
+            None => ConfirmationTarget::err("Fee rate too low for the known estimates").boxed(),
+        }
+    }
+
+    /// Re-sends all the unconfirmed wallet transactions to the Electrum
+    /// server, useful after a reorg or a mempool eviction.
+    ///
+    /// # Returns
+    ///
+    /// The number of transactions successfully rebroadcast.
+    pub fn rebroadcast_unconfirmed(&self) -> u32 {
+        let (Some(url), Some(port)) = (self.config.electrum_url.clone(), self.config.electrum_port)
+        else {
+            let _ = self.sender.send(Notification::InvalidElectrumConfig);
+            return 0;
+        };
+        let txs = self
+            .coin_store
+            .lock()
+            .expect("poisoned")
+            .unconfirmed_transactions();
+        if txs.is_empty() {
+            return 0;
+        }
+        let mut client = match joinstr::electrum::Client::new(&url, port) {
+            Ok(c) => c,
+            Err(e) => {
+                log::error!(
+                    "Account::rebroadcast_unconfirmed() fail to create electrum client {e}"
+                );
+                let _ = self
+                    .sender
+                    .send(TxListenerNotif::Error(e.to_string()).into());
+                return 0;
+            }
+        };
+        let mut broadcast = 0;
+        for tx in txs {
+            match client.broadcast(&tx) {
+                Ok(_) => broadcast += 1,
+                Err(e) => log::warn!(
+                    "Account::rebroadcast_unconfirmed() fail to broadcast {}: {e:?}",
+                    tx.compute_txid()
+                ),
+            }
+        }
+        broadcast
+    }
+
+    /// Checks locally whether a transaction would be accepted by the
+    /// network, electrum servers do not expose `testmempoolaccept`.
+    ///
+    /// The transaction must be signed, standard, spend outputs known by the
+    /// wallet that are not already spent, and pay at least the minimum relay
+    /// fee.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_hex` - The serialized transaction as an hex string.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the transaction is acceptable, or the rejection reason.
+    pub fn test_accept(&self, tx_hex: String) -> Result<(), String> {
+        let tx: bitcoin::Transaction = bitcoin::consensus::encode::deserialize_hex(&tx_hex)
+            .map_err(|_| "Fail to parse transaction".to_string())?;
+
+        if tx.input.is_empty() {
+            return Err("No inputs!".to_string());
+        } else if tx.output.is_empty() {
+            return Err("No outputs!".to_string());
+        } else if !(1..=3).contains(&tx.version.0) {
+            return Err("Non standard transaction version".to_string());
+        } else if tx.weight().to_wu() > MAX_STANDARD_TX_WEIGHT {
+            return Err("Transaction weight exceeds the standard limit".to_string());
+        }
+
+        let mut outputs_total = 0;
+        for txout in &tx.output {
+            let spk = &txout.script_pubkey;
+            if spk.is_op_return() {
+                outputs_total += txout.value.to_sat();
+                continue;
+            }
+            if !(spk.is_p2pkh()
+                || spk.is_p2sh()
+                || spk.is_p2wpkh()
+                || spk.is_p2wsh()
+                || spk.is_p2tr())
+            {
+                return Err("Non standard output script".to_string());
+            }
+            if txout.value < spk.minimal_non_dust() {
+                return Err("Output amount is below the dust limit".to_string());
+            }
+            outputs_total += txout.value.to_sat();
+        }
+
+        let mut inputs_total = 0;
+        {
+            let store = self.coin_store.lock().expect("poisoned");
+            for txin in &tx.input {
+                if txin.witness.is_empty() && txin.script_sig.is_empty() {
+                    return Err("Transaction is not signed".to_string());
+                }
+                let op = txin.previous_output;
+                let value = match store.get(&op) {
+                    Some(coin) if coin.status() == CoinStatus::Spent => {
+                        return Err(format!("Input {op} is already spent"));
+                    }
+                    Some(coin) => coin.amount_sat(),
+                    None => store
+                        .transaction(&op.txid)
+                        .and_then(|prev| prev.output.get(op.vout as usize).cloned())
+                        .map(|txout| txout.value.to_sat())
+                        .ok_or(format!("Unknown spent output {op}"))?,
+                };
+                inputs_total += value;
+            }
+        } // <- release coin_store lock
+
+        let fee = inputs_total
+            .checked_sub(outputs_total)
+            .ok_or("Outputs amount exceeds inputs amount".to_string())?;
+        let fee_rate = fee as f64 / tx.vsize() as f64;
+        if fee_rate < self.config.min_relay_fee {
+            return Err(format!(
+                "Fee rate of {fee_rate:.2} sat/vb is below the minimum relay fee of {} sat/vb",
+                self.config.min_relay_fee
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks whether a transaction signed by another device is the
+    /// transaction prepared by the wallet.
+    ///
+    /// The unsigned skeletons are compared: version, locktime, spent
+    /// outpoints, sequences and outputs must be identical.
+    ///
+    /// # Arguments
+    ///
+    /// * `original_psbt` - The PSBT as prepared by the wallet, as a base64
+    ///   string.
+    /// * `signed_tx_hex` - The signed transaction as an hex string.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the signed transaction matches the prepared PSBT, `false`
+    /// if it doesn't or if any of them cannot be parsed.
+    pub fn matches_prepared(&self, original_psbt: String, signed_tx_hex: String) -> bool {
+        let psbt = match bitcoin::Psbt::from_str(&original_psbt) {
+            Ok(p) => p,
+            Err(_) => return false,
+        };
+        let mut tx: bitcoin::Transaction =
+            match bitcoin::consensus::encode::deserialize_hex(&signed_tx_hex) {
+                Ok(tx) => tx,
+                Err(_) => return false,
+            };
+        for txin in &mut tx.input {
+            txin.script_sig = ScriptBuf::new();
+            txin.witness = bitcoin::Witness::new();
+        }
+        tx == psbt.unsigned_tx
+    }
+
+    /// Returns the available pools for the account.
+    ///
+    /// The pools are read from a snapshot refreshed on every update of the
+    /// pool store, so it does not wait for the pool workers.
+    ///
+    /// # Returns
+    ///
+    /// A boxed `Pools` instance containing the available pools.
+    pub fn pools(&self) -> Box<PoolsResult> {
+        if !self.config.enable_coinjoin {
+            return PoolsResult::ok(vec![]).boxed();
+        }
+        let mut pools = PoolsResult::ok(self.pools.read().expect("poisoned").clone());
+        pools.relay = self.relay();
+        Box::new(pools)
+    }
+
+    /// Returns the available pools the account holds a spendable coin for.
+    ///
+    /// # Returns
+    ///
+    /// A boxed `Pools` instance containing the `Available` pools for which a
+    /// spendable coin covers the denomination plus the contribution fee.
+    pub fn joinable_pools(&self) -> Box<PoolsResult> {
+        let pools = self.pools();
+        if pools.is_err() {
+            return pools;
+        }
+        let satisfaction_weight = match self.input_satisfaction_size() {
+            Ok(weight) => weight as u64,
+            Err(_) => return PoolsResult::err("Fail to compute the input satisfaction").boxed(),
+        };
+        let coins: Vec<_> = self
+            .spendable_coins()
+            .coins
+            .into_iter()
+            .map(|c| c.value)
+            .collect();
+        let mut joinable = PoolsResult::ok(joinable_pools(
+            pools.value(),
+            &coins,
+            satisfaction_weight,
+            self.coinjoin_output_spk_len(),
+        ));
+        joinable.relay = pools.relay.clone();
+        Box::new(joinable)
+    }
+
+    /// Creates a new pool with the specified parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `outpoint` - The outpoint for the pool.
+    /// * `denomination` - The denomination of the pool.
+    /// * `fee` - The fee rate of the coinjoin transaction in sats/vB, must be
+    ///   in [`MIN_POOL_FEE_RATE`, `MAX_POOL_FEE_RATE`].
+    /// * `max_duration` - The maximum duration of the pool.
     /// * `peers` - The number of peers in the pool.
     /// * `coin` - the outpoint of the coin to coinjoin.
+    ///
+    /// [`MIN_POOL_FEE_RATE`]: crate::pool_store::MIN_POOL_FEE_RATE
+    /// [`MAX_POOL_FEE_RATE`]: crate::pool_store::MAX_POOL_FEE_RATE
     pub fn rust_create_pool(
         &mut self,
         outpoint: String,
@@ -1215,10 +2946,15 @@ impl Account {
         timeout: u64,
         peers: usize,
     ) -> Result<(), Error> {
-        let op = OutPoint::from_str(&outpoint).map_err(|_| Error::InvalidOutPoint)?;
-        let coin = self.get_coin(&op).ok_or(Error::CoinMissing)?;
+        if !self.config.enable_coinjoin {
+            return Err(Error::CoinjoinDisabled);
+        }
+        if !is_pool_fee_valid(fee) {
+            return Err(Error::InvalidPoolFee);
+        }
+        let coin = self.pool_coin(&outpoint)?;
+        let signer = self.coinjoin_signer(&coin)?;
         let denomination = Amount::from_sat(denomination).to_btc();
-        let address = self.new_recv_addr().as_unchecked().clone();
         let relay = self.config.nostr_relay.clone().ok_or(Error::RelayMissing)?;
         let electrum = if let (Some(url), Some(port)) =
             (self.config.electrum_url.clone(), self.config.electrum_port)
@@ -1227,6 +2963,14 @@ impl Account {
         } else {
             return Err(Error::WrongElectrumConfig);
         };
+        let secondary = self.is_secondary_spk(&coin.txout.script_pubkey);
+        let address = self.coinjoin_output_addr(secondary);
+        self.check_coinjoin_output(&signer, &address.script_pubkey())?;
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .register_coinjoin_output(address.script_pubkey());
+        let address = address.as_unchecked().clone();
         PoolStore::create_pool(
             denomination,
             fee,
@@ -1234,7 +2978,7 @@ impl Account {
             peers,
             coin,
             address,
-            self.config.mnemonic.clone(),
+            self.config.coinjoin_signer_mnemonic(),
             relay,
             electrum,
             self.config.network,
@@ -1259,13 +3003,20 @@ impl Account {
 
     /// Joins an existing pool with the specified outpoint and pool ID.
     ///
+    /// The coin is refused with `Error::CoinjoinExcess` if more than
+    /// [`Config::max_coinjoin_excess`] sats of it would be paid to the miners
+    /// on top of the fees, see [`Account::estimate_coinjoin_excess`].
+    ///
     /// # Arguments
     ///
     /// * `_outpoint` - The outpoint for the pool.
     /// * `_pool_id` - The ID of the pool to join.
     pub fn rust_join_pool(&mut self, outpoint: String, pool_id: String) -> Result<(), Error> {
-        let op = OutPoint::from_str(&outpoint).map_err(|_| Error::InvalidOutPoint)?;
-        let coin = self.get_coin(&op).ok_or(Error::CoinMissing)?;
+        if !self.config.enable_coinjoin {
+            return Err(Error::CoinjoinDisabled);
+        }
+        let coin = self.pool_coin(&outpoint)?;
+        let signer = self.coinjoin_signer(&coin)?;
         let relay = self.config.nostr_relay.clone().ok_or(Error::RelayMissing)?;
         let electrum = if let (Some(url), Some(port)) =
             (self.config.electrum_url.clone(), self.config.electrum_port)
@@ -1274,13 +3025,24 @@ impl Account {
         } else {
             return Err(Error::WrongElectrumConfig);
         };
-        let address = self.new_recv_addr().as_unchecked().clone();
-        let pool = self.rust_pool(pool_id).ok_or(Error::PoolMissing)?;
+        let pool = self.rust_pool(pool_id.clone()).ok_or(Error::PoolMissing)?;
+        // NOTE: there is no change output, the excess of the coin is paid
+        // to the miners
+        let excess = self.estimate_coinjoin_excess(pool_id, outpoint)?;
+        check_coinjoin_excess(excess, self.config.max_coinjoin_excess)?;
+        let secondary = self.is_secondary_spk(&coin.txout.script_pubkey);
+        let address = self.coinjoin_output_addr(secondary);
+        self.check_coinjoin_output(&signer, &address.script_pubkey())?;
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .register_coinjoin_output(address.script_pubkey());
+        let address = address.as_unchecked().clone();
         PoolStore::join_pool(
             relay,
             electrum,
             pool,
-            self.config.mnemonic.clone(),
+            self.config.coinjoin_signer_mnemonic(),
             self.config.network,
             self.pool_store.clone(),
             self.sender.clone(),
@@ -1290,52 +3052,244 @@ impl Account {
         Ok(())
     }
 
-    pub fn join_pool(&mut self, outpoint: String, pool_id: String) {
-        if let Err(e) = self.rust_join_pool(outpoint, pool_id) {
-            let _ = self.sender.send(e.into());
+    /// Returns the coin to contribute to a pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `outpoint` - The outpoint of the coin.
+    ///
+    /// # Errors
+    ///
+    /// `Error::InvalidOutPoint`, `Error::CoinMissing` or `Error::CoinFrozen`
+    /// if the coin cannot be spent.
+    fn pool_coin(&self, outpoint: &str) -> Result<Coin, Error> {
+        let op = OutPoint::from_str(outpoint).map_err(|_| Error::InvalidOutPoint)?;
+        let store = self.coin_store.lock().expect("poisoned");
+        if store.is_frozen(&op) {
+            return Err(Error::CoinFrozen);
         }
+        store.get(&op).map(|e| e.coin).ok_or(Error::CoinMissing)
     }
 
-    /// Retrieves a pool with the specified pool ID.
+    /// Returns the signer of the coinjoin input spending `coin`.
     ///
     /// # Arguments
     ///
-    /// * `_pool_id` - The ID of the pool to retrieve.
+    /// * `coin` - The coin to contribute.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// A boxed `Pool` instance.
-    pub fn pool(&mut self, pool_id: String) -> Box<RustPool> {
-        let entry = self
-            .pool_store
-            .lock()
-            .expect("poisoned")
-            .get(&pool_id)
-            .unwrap();
-        let pool = entry.into();
-
-        Box::new(pool)
+    /// `Error::CoinjoinSigner` if the coinjoin mnemonic is missing or if the
+    /// coin is not derived from its key, e.g. a coin of another mnemonic or
+    /// of a taproot descriptor.
+    fn coinjoin_signer(&self, coin: &Coin) -> Result<HotSigner, Error> {
+        let signer = HotSigner::new_from_mnemonics(
+            self.config.network,
+            &self.config.coinjoin_signer_mnemonic(),
+        )
+        .map_err(|_| Error::CoinjoinSigner)?;
+        check_coinjoin_signer(
+            &signer,
+            self.config.network,
+            &coin.txout.script_pubkey,
+            coin.coin_path,
+        )?;
+        Ok(signer)
     }
 
-    pub fn rust_pool(&mut self, pool_id: String) -> Option<Pool> {
-        self.pool_store
+    /// Checks that the coinjoin output `spk` is derived from the key of the
+    /// coinjoin signer.
+    ///
+    /// # Arguments
+    ///
+    /// * `signer` - The coinjoin signer.
+    /// * `spk` - The script pubkey of the coinjoin output.
+    fn check_coinjoin_output(&self, signer: &HotSigner, spk: &ScriptBuf) -> Result<(), Error> {
+        let entry = self
+            .coin_store
             .lock()
             .expect("poisoned")
-            .get(&pool_id)
-            .map(|e| e.pool())
+            .address_info(spk)
+            .ok_or(Error::CoinjoinSigner)?;
+        check_coinjoin_signer(
+            signer,
+            self.config.network,
+            spk,
+            (entry.account, entry.index),
+        )
     }
 
-    /// Sets the Electrum server URL and port for the account.
+    /// Estimates the fee paid for contributing the coin `outpoint` to the
+    /// pool `pool_id`.
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL of the Electrum server.
-    /// * `port` - The port of the Electrum server.
+    /// * `pool_id` - The ID of the pool to join.
+    /// * `outpoint` - The outpoint of the coin to contribute.
+    ///
+    /// # Returns
+    ///
+    /// A `Result<u64, Error>` containing the peer's share of the coinjoin
+    /// fees in sats.
+    pub fn estimate_coinjoin_cost(&self, pool_id: String, outpoint: String) -> Result<u64, Error> {
+        let op = OutPoint::from_str(&outpoint).map_err(|_| Error::InvalidOutPoint)?;
+        self.get_coin(&op).ok_or(Error::CoinMissing)?;
+        let pool = self
+            .pool_store
+            .lock()
+            .expect("poisoned")
+            .get(&pool_id)
+            .ok_or(Error::PoolMissing)?
+            .pool();
+        let payload = pool.payload.ok_or(Error::PoolMissing)?;
+        let fee_rate = match payload.fee {
+            nostr::Fee::Fixed(f) => f,
+            nostr::Fee::Provider(_) => return Err(Error::InvalidPoolFee),
+        };
+        let satisfaction_weight = self.input_satisfaction_size()? as u64;
+        Ok(contribution_fee(
+            fee_rate,
+            payload.peers,
+            satisfaction_weight,
+            self.coinjoin_output_spk_len(),
+        ))
+    }
+
+    /// Estimates the part of the coin `outpoint` that would be paid to the
+    /// miners on top of the fees if contributed to the pool `pool_id`.
+    ///
+    /// A joinstr transaction has no change output, the coinjoin output pays
+    /// exactly the denomination, so a coin larger than the denomination plus
+    /// the fee share loses the difference.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_id` - The ID of the pool to join.
+    /// * `outpoint` - The outpoint of the coin to contribute.
+    ///
+    /// # Returns
+    ///
+    /// A `Result<u64, Error>` containing the excess in sats, 0 if the coin
+    /// matches the denomination plus the fee share.
+    ///
+    /// # Errors
+    ///
+    /// - `Error::InvalidDenomination` if the coin does not cover the
+    ///   denomination plus the fee share.
+    /// - The errors of [`Account::estimate_coinjoin_cost`].
+    pub fn estimate_coinjoin_excess(
+        &self,
+        pool_id: String,
+        outpoint: String,
+    ) -> Result<u64, Error> {
+        let fee = self.estimate_coinjoin_cost(pool_id.clone(), outpoint.clone())?;
+        let op = OutPoint::from_str(&outpoint).map_err(|_| Error::InvalidOutPoint)?;
+        let coin = self.get_coin(&op).ok_or(Error::CoinMissing)?;
+        let denomination = self
+            .pool_store
+            .lock()
+            .expect("poisoned")
+            .get(&pool_id)
+            .and_then(|e| e.pool().payload)
+            .ok_or(Error::PoolMissing)?
+            .denomination
+            .to_sat();
+        coinjoin_excess(coin.txout.value.to_sat(), denomination, fee)
+            .ok_or(Error::InvalidDenomination)
+    }
+
+    /// Estimates the part of the coin `outpoint` that would be paid to the
+    /// miners on top of the fees if contributed to the pool `pool_id`, see
+    /// [`Account::estimate_coinjoin_excess`].
+    ///
+    /// # Returns
+    ///
+    /// A `Box<CoinjoinExcess>` containing the excess in sats, or an error.
+    pub fn coinjoin_excess_result(&self, pool_id: String, outpoint: String) -> Box<CoinjoinExcess> {
+        match self.estimate_coinjoin_excess(pool_id, outpoint) {
+            Ok(excess) => CoinjoinExcess::ok(excess).boxed(),
+            Err(e) => CoinjoinExcess::err(&format!("{e:?}")).boxed(),
+        }
+    }
+
+    /// Returns the length of the script pubkey of a coinjoin output.
+    fn coinjoin_output_spk_len(&self) -> usize {
+        // NOTE: the coinjoin output pays to one of our addresses
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .derivator_ref()
+            .receive_at(0)
+            .script_pubkey()
+            .len()
+    }
+
+    pub fn join_pool(&mut self, outpoint: String, pool_id: String) {
+        if let Err(e) = self.rust_join_pool(outpoint, pool_id) {
+            let _ = self.sender.send(e.into());
+        }
+    }
+
+    /// Retrieves a pool with the specified pool ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `_pool_id` - The ID of the pool to retrieve.
+    ///
+    /// # Returns
+    ///
+    /// A boxed `Pool` instance.
+    pub fn pool(&mut self, pool_id: String) -> Box<RustPool> {
+        let entry = self
+            .pool_store
+            .lock()
+            .expect("poisoned")
+            .get(&pool_id)
+            .unwrap();
+        let pool = entry.into();
+
+        Box::new(pool)
+    }
+
+    /// Returns the coins of the account created by a pool we took part in.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_id` - The ID of the pool.
+    ///
+    /// # Returns
+    ///
+    /// The coins created by the coinjoin of the pool, empty if we did not
+    /// take part in it or if the coinjoin is not seen yet.
+    pub fn coins_from_pool(&self, pool_id: String) -> Vec<RustCoin> {
+        let Some(output) = self.pool_store.lock().expect("poisoned").output(&pool_id) else {
+            return vec![];
+        };
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .coinjoin_coins(&output.assume_checked().script_pubkey())
+    }
+
+    pub fn rust_pool(&mut self, pool_id: String) -> Option<Pool> {
+        self.pool_store
+            .lock()
+            .expect("poisoned")
+            .get(&pool_id)
+            .map(|e| e.pool())
+    }
+
+    /// Sets the Electrum server URL and port for the account.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the Electrum server.
+    /// * `port` - The port of the Electrum server.
     pub fn set_electrum(&mut self, url: String, port: String) {
-        if let Ok(port) = port.parse::<u16>() {
+        if let Some((url, port)) = parse_electrum_config(&url, &port) {
             self.config.electrum_url = Some(url);
             self.config.electrum_port = Some(port);
-            self.config.to_file();
+            self.persist_config();
         } else {
             self.sender
                 .send(Notification::InvalidElectrumConfig)
@@ -1343,17 +3297,36 @@ impl Account {
         }
     }
 
+    /// Persists the config of the account, a failure is notified to the
+    /// consumer.
+    fn persist_config(&self) {
+        if let Err(e) = self.config.to_file() {
+            log::error!("Account::persist_config() {e}");
+            let _ = self.sender.send(Error::Config(e).into());
+        }
+    }
+
     /// Starts the Electrum listener for the account.
     pub fn start_electrum(&mut self) {
+        if self.offline {
+            log::info!("Account::start_electrum() offline account");
+            return;
+        }
         if let (None, Some(addr), Some(port)) = (
             &self.tx_listener,
             self.config.electrum_url.clone(),
             self.config.electrum_port,
         ) {
-            let (tx_listener, electrum_stop) =
+            if !is_electrum_url_valid(&addr) || port == 0 {
+                log::error!("Account::start_electrum() invalid electrum config");
+                let _ = self.sender.send(Notification::InvalidElectrumConfig);
+                return;
+            }
+            let (tx_listener, electrum_stop, electrum_control) =
                 self.start_listen_txs(addr, port, self.config.clone());
             self.coin_store.lock().expect("poisoned").init(tx_listener);
             self.electrum_stop = Some(electrum_stop);
+            self.electrum_control = Some(electrum_control);
         }
     }
 
@@ -1363,6 +3336,51 @@ impl Account {
             stop.store(true, Ordering::Relaxed);
         }
         self.electrum_stop = None;
+        self.electrum_control = None;
+    }
+
+    /// Requests the Electrum listener to re-check the statuses of all the
+    /// watched scripts, this helps to recover from missed notifications.
+    pub fn refresh(&mut self) {
+        if let Some(control) = self.electrum_control.as_ref() {
+            if control.send(ListenerControl::Refresh).is_err() {
+                log::error!("Account::refresh() electrum listener disconnected");
+                self.electrum_control = None;
+            }
+        } else {
+            log::warn!("Account::refresh() electrum listener not started");
+        }
+    }
+
+    /// Drops the cached transactions and coins and restarts the Electrum
+    /// listener, so everything is fetched again from the current watch
+    /// tips. Labels and tips are kept.
+    pub fn resync(&mut self) {
+        if let Some(stop) = self.electrum_stop.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+        self.electrum_control = None;
+        if let Some(handle) = self.tx_listener.take() {
+            if handle.join().is_err() {
+                log::error!("Account::resync() electrum listener panicked");
+            }
+            // NOTE: the `Stopped` notification of the old listener would
+            // be taken for the one of the new listener
+            let pending: Vec<_> = std::iter::from_fn(|| self.receiver.try_recv().ok()).collect();
+            for notif in pending {
+                if !matches!(notif, Notification::Electrum(TxListenerNotif::Stopped)) {
+                    let _ = self.sender.send(notif);
+                }
+            }
+        }
+
+        self.coin_store.lock().expect("poisoned").reset();
+        if let Err(e) = std::fs::remove_file(self.config.statuses_path()) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::error!("Account::resync() fail to delete statuses: {e}");
+            }
+        }
+        self.start_electrum();
     }
 
     /// Sets the Nostr relay URL and back value for the account.
@@ -1370,12 +3388,18 @@ impl Account {
     /// # Arguments
     ///
     /// * `url` - The URL of the Nostr relay.
-    /// * `back` - The back value for the Nostr relay.
+    /// * `back` - The lookback in seconds used to fetch pool events, between
+    ///   1 and [`MAX_NOSTR_BACK`].
     pub fn set_nostr(&mut self, url: String, back: String) {
-        if let Ok(back) = back.parse::<u64>() {
+        // NOTE: a single relay is supported for now, the lookback will be
+        // configured per relay when several relays are supported
+        if let Some(back) = parse_nostr_back(&back) {
             self.config.nostr_relay = Some(url);
             self.config.nostr_back = Some(back);
-            self.config.to_file();
+            self.persist_config();
+        } else if back.parse::<u64>().is_ok() {
+            log::error!("Account::set_nostr() lookback out of range: {back}");
+            let _ = self.sender.send(Notification::InvalidNostrBack);
         } else if !(url.is_empty() && back.is_empty()) {
             self.sender
                 .send(Notification::InvalidNostrConfig)
@@ -1383,14 +3407,21 @@ impl Account {
         }
     }
 
-    /// Starts the Nostr listener for the account.
+    /// Starts the Nostr listener for the account, if coinjoin is enabled.
     pub fn start_nostr(&mut self) {
-        if let (None, Some(relay), Some(back)) = (
-            &self.pool_listener,
-            self.config.nostr_relay.as_ref(),
-            self.config.nostr_back,
-        ) {
-            let stop = self.start_poll_pools(back, relay.clone());
+        if self.offline {
+            log::info!("Account::start_nostr() offline account");
+            return;
+        }
+        if !self.config.enable_coinjoin {
+            log::info!("Account::start_nostr() coinjoin disabled");
+            return;
+        }
+        if self.pool_listener.is_some() {
+            return;
+        }
+        if let Some((relay, back)) = nostr_subscription(&self.config) {
+            let stop = self.start_poll_pools(back, relay);
             self.nostr_stop = Some(stop);
         }
     }
@@ -1412,7 +3443,7 @@ impl Account {
         log::warn!("Account::set_look_ahead() {look_ahead}");
         if let Ok(la) = look_ahead.parse::<u32>() {
             self.config.look_ahead = la;
-            self.config.to_file();
+            self.persist_config();
         } else {
             self.sender
                 .send(Notification::InvalidNostrConfig)
@@ -1420,6 +3451,43 @@ impl Account {
         }
     }
 
+    /// Sets the minimum relay fee rate (sats/vb) for the account.
+    ///
+    /// # Arguments
+    ///
+    /// * `fee` - The minimum relay fee rate to set.
+    pub fn set_min_relay_fee(&mut self, fee: String) {
+        if let Some(fee) = parse_min_relay_fee(&fee) {
+            self.config.min_relay_fee = fee;
+            self.persist_config();
+        } else {
+            log::error!("Account::set_min_relay_fee() invalid fee rate: {fee}");
+        }
+    }
+
+    /// Sets whether the account xpubs are added to the global map of the
+    /// prepared PSBTs.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to add the xpubs.
+    pub fn set_psbt_global_xpubs(&mut self, enable: bool) {
+        self.config.psbt_global_xpubs = enable;
+        self.persist_config();
+    }
+
+    /// Sets the minimum amount of a change output, a lower change is added
+    /// to the fees.
+    ///
+    /// # Arguments
+    ///
+    /// * `sats` - The minimum change amount in sats, 0 to fallback to the
+    ///   dust limit.
+    pub fn set_min_change_sats(&mut self, sats: u64) {
+        self.config.set_min_change_sats(sats);
+        self.persist_config();
+    }
+
     /// Returns the configuration of the account.
     ///
     /// # Returns
@@ -1439,6 +3507,7 @@ impl Account {
             Ok(notif) => {
                 if let Notification::Electrum(TxListenerNotif::Stopped) = &notif {
                     self.electrum_stop = None;
+                    self.electrum_control = None;
                     self.tx_listener = None;
                 } else if let Notification::Joinstr(JoinstrNotif::Stopped) = &notif {
                     self.nostr_stop = None;
@@ -1457,11 +3526,24 @@ impl Account {
             match self.signing_manager.poll() {
                 Some(sn) => match sn {
                     crate::signer::SignerNotif::Signed(_, psbt) => {
+                        // NOTE: only signed transactions are registered, a
+                        // previewed one is never broadcast. Signing do not
+                        // change the (segwit) txid, so the coins spent by
+                        // this transaction will not be flagged as externally
+                        // spent. Transactions signed by another signer are
+                        // registered with `register_prepared_tx()`
+                        self.coin_store
+                            .lock()
+                            .expect("poisoned")
+                            .register_prepared_tx(psbt.unsigned_tx.compute_txid());
                         let mut signal = Signal::new();
                         signal.set(SignalFlag::SignedTx);
                         signal.payload = Some(psbt.to_string());
                         Poll::ok(signal).boxed()
                     }
+                    crate::signer::SignerNotif::DescriptorRegistered(_, _, true) => {
+                        Poll::new().boxed()
+                    }
                     sn => Poll::err(&format!("{sn:?}")).boxed(),
                 },
                 None => Poll::new().boxed(),
@@ -1505,21 +3587,126 @@ impl Account {
             .to_string()
     }
 
-    /// Generates a new receiving address entry for the account.
+    /// Returns a batch of consecutive receiving addresses as strings.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The index of the first receiving address.
+    /// * `count` - The number of addresses, the batch stops at the last
+    ///   non-hardened index.
     ///
     /// # Returns
     ///
-    /// A boxed `AddressEntry` instance.
-    pub fn new_addr(&mut self) -> RustAddress {
-        let addr = self.new_recv_addr();
-        let index = self.coin_store.lock().expect("poisoned").recv_tip();
-        AddressEntry {
-            status: AddressStatus::NotUsed,
-            address: addr.as_unchecked().clone(),
-            account: AddrAccount::Receive,
-            index,
+    /// The receiving addresses from `start` to `start + count - 1`.
+    pub fn recv_addrs(&self, start: u32, count: u32) -> Vec<String> {
+        let derivator = self.coin_store.lock().expect("poisoned").derivator();
+        batch_indexes(start, count)
+            .map(|i| derivator.receive_at(i).to_string())
+            .collect()
+    }
+
+    /// Returns a batch of consecutive change addresses as strings.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The index of the first change address.
+    /// * `count` - The number of addresses, the batch stops at the last
+    ///   non-hardened index.
+    ///
+    /// # Returns
+    ///
+    /// The change addresses from `start` to `start + count - 1`.
+    pub fn change_addrs(&self, start: u32, count: u32) -> Vec<String> {
+        let derivator = self.coin_store.lock().expect("poisoned").derivator();
+        batch_indexes(start, count)
+            .map(|i| derivator.change_at(i).to_string())
+            .collect()
+    }
+
+    /// Returns a BIP21 payment URI for the receiving address at the
+    /// specified index, ready to be encoded as a QR code.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the receiving address.
+    /// * `amount_sats` - The requested amount in sats, omitted if 0.
+    /// * `label` - The label of the request, omitted if empty.
+    ///
+    /// # Returns
+    ///
+    /// A `bitcoin:<address>?amount=<btc>&label=<label>` URI.
+    pub fn receive_uri(&self, index: u32, amount_sats: u64, label: String) -> String {
+        let mut uri = format!("bitcoin:{}", self.recv_addr_at(index));
+        let mut params = vec![];
+        if amount_sats > 0 {
+            let amount =
+                bitcoin::Amount::from_sat(amount_sats).to_string_in(bitcoin::Denomination::Bitcoin);
+            params.push(format!("amount={amount}"));
+        }
+        if !label.is_empty() {
+            params.push(format!("label={}", uri_encode(&label)));
         }
-        .into()
+        if !params.is_empty() {
+            uri.push('?');
+            uri.push_str(&params.join("&"));
+        }
+        uri
+    }
+
+    /// Returns the single descriptor of the receiving keychain as a string.
+    ///
+    /// # Returns
+    ///
+    /// The receiving descriptor (`.../0/*`) with its checksum.
+    pub fn receive_descriptor(&self) -> String {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .derivator_ref()
+            .receive_descriptor()
+            .to_string()
+    }
+
+    /// Returns the single descriptor of the change keychain as a string.
+    ///
+    /// # Returns
+    ///
+    /// The change descriptor (`.../1/*`) with its checksum.
+    pub fn change_descriptor(&self) -> String {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .derivator_ref()
+            .change_descriptor()
+            .to_string()
+    }
+
+    /// Returns the key expression (key origin and xpub) of the account,
+    /// to share with a multisig coordinator when this wallet is a cosigner.
+    ///
+    /// # Returns
+    ///
+    /// The key expression as a string, e.g. `[fingerprint/84'/0'/0']xpub...`.
+    pub fn cosigner_info(&self) -> String {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .derivator_ref()
+            .key_expression()
+    }
+
+    /// Generates a new receiving address entry for the account, or returns
+    /// the first unused one if the config asks to reuse unused addresses.
+    ///
+    /// # Returns
+    ///
+    /// A `RustAddress` instance.
+    pub fn new_addr(&mut self) -> RustAddress {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .next_recv_entry(self.config.reuse_unused_addresses)
+            .into()
     }
 
     /// Edits the label of a coin identified by the given outpoint.
@@ -1547,59 +3734,240 @@ impl Account {
         }
     }
 
+    /// Returns all the labels of the account.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `RustLabel`, the key of each label is prefixed by its
+    /// kind: `outpoint:`, `tx:` or `addr:`.
+    pub fn all_labels(&self) -> Vec<RustLabel> {
+        self.label_store
+            .lock()
+            .expect("poisoned")
+            .labels()
+            .into_iter()
+            .map(|(key, label)| RustLabel { key, label })
+            .collect()
+    }
+
     /// Returns the Nostr relay URL for the account.
     ///
     /// # Returns
     ///
-    /// The Nostr relay URL as a string.
+    /// The Nostr relay URL as a string, or an empty string if no relay is
+    /// configured or coinjoin is disabled.
     pub fn relay(&self) -> String {
-        self.config.nostr_relay.clone().unwrap()
+        if !self.config.enable_coinjoin {
+            return String::new();
+        }
+        self.config.nostr_relay.clone().unwrap_or_default()
     }
 
-    /// Stops all listeners and sends a stopped notification.
-    pub fn stop(&mut self) {
-        if self.electrum_stop.is_none() && self.nostr_stop.is_none() {
-            self.sender.send(Notification::Stopped).unwrap();
-            return;
-        }
+    /// Returns whether the pool listener thread is running.
+    pub fn is_pool_listener_running(&self) -> bool {
+        self.pool_listener
+            .as_ref()
+            .map(|h| !h.is_finished())
+            .unwrap_or(false)
+    }
 
-        if let Some(stopper) = &self.electrum_stop {
-            stopper.store(true, Ordering::Relaxed);
-        }
-        if let Some(stopper) = &self.nostr_stop {
-            stopper.store(true, Ordering::Relaxed);
+    /// Exports a backup bundle of the account: descriptors, network,
+    /// labels (BIP-329) and address tips.
+    ///
+    /// # Arguments
+    ///
+    /// * `include_mnemonic` - Whether the mnemonic is part of the backup,
+    ///   anyone having access to such a backup can spend the coins.
+    ///
+    /// # Returns
+    ///
+    /// The backup as a JSON string, see [`crate::backup::import_backup`], or
+    /// an error if the mnemonic is requested while the account is locked,
+    /// see [`Account::unlock`].
+    pub fn export_backup(&self, include_mnemonic: bool) -> Result<String, String> {
+        if include_mnemonic && self.config.mnemonic.is_empty() {
+            return Err("The account is locked, its mnemonic is unknown".to_string());
         }
-
-        let notification = self.sender.clone();
-        let tx_listener = self.tx_listener.take();
-        let pool_listener = self.pool_listener.take();
-
-        thread::spawn(move || loop {
-            let tx_stopped = if let Some(handle) = tx_listener.as_ref() {
-                handle.is_finished()
-            } else {
-                true
-            };
-            let pool_stopped = if let Some(handle) = pool_listener.as_ref() {
-                handle.is_finished()
-            } else {
-                true
-            };
-
-            if tx_stopped && pool_stopped {
-                notification.send(Notification::Stopped).unwrap();
-            }
-        });
+        let tip = Tip {
+            receive: self.recv_tip(),
+            change: self.change_tip(),
+        };
+        let secondary_tip = self.coin_store.lock().expect("poisoned").secondary_tip();
+        let labels = self.label_store.lock().expect("poisoned");
+        Ok(Backup::new(&self.config, &labels, tip, secondary_tip, include_mnemonic).to_json())
     }
 
-    pub fn sign(&self, psbt: String) {
-        self.signing_manager.sign(self.config.network(), psbt);
+    /// Unlocks an account whose mnemonic is not persisted, the account is
+    /// watch-only until then. The mnemonic is only held in memory and the
+    /// hot signer is registered for the account descriptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `mnemonic` - The mnemonic of the account.
+    ///
+    /// # Returns
+    ///
+    /// An error if the mnemonic cannot be parsed or if the account
+    /// descriptor has no key of its fingerprint.
+    pub fn unlock(&mut self, mnemonic: String) -> Result<(), String> {
+        let signer = HotSigner::new_from_mnemonics(self.config.network, &mnemonic)
+            .map_err(|_| "Invalid mnemonic".to_string())?;
+        if !descriptor_contains_fingerprint(
+            self.config.descriptor.to_string(),
+            signer.fingerprint().to_string(),
+        ) {
+            return Err("The mnemonic does not match the account descriptor".to_string());
+        }
+        self.signing_manager
+            .new_hot_signer_from_mnemonic(self.config.network(), mnemonic.clone());
+        self.signing_manager
+            .register_descriptor(self.config.descriptor.to_string());
+        self.config.mnemonic = mnemonic;
+        Ok(())
     }
-}
 
-/// Creates a new account with the specified account name.
-///
-/// # Arguments
+    /// Exports the transactions known by the account, e.g. in order to
+    /// sync an offline instance, see [`Account::import_transactions`].
+    ///
+    /// # Returns
+    ///
+    /// The transactions as a JSON string.
+    pub fn export_transactions(&self) -> String {
+        self.coin_store
+            .lock()
+            .expect("poisoned")
+            .dump_transactions()
+            .map(|v| v.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Imports transactions exported by another instance of the account,
+    /// the coins are then generated from them.
+    ///
+    /// # Arguments
+    ///
+    /// * `txs_json` - The transactions as exported by
+    ///   [`Account::export_transactions`].
+    ///
+    /// # Returns
+    ///
+    /// A `Box<ImportedTxs>` containing the number of imported transactions,
+    /// the ones not paying to or spending from the wallet being skipped, or
+    /// an error if the JSON is invalid.
+    pub fn import_transactions(&mut self, txs_json: String) -> Box<ImportedTxs> {
+        let entries: BTreeMap<bitcoin::Txid, TxEntry> = match serde_json::from_str(&txs_json) {
+            Ok(e) => e,
+            Err(e) => {
+                log::error!("Account::import_transactions() invalid JSON: {e}");
+                return ImportedTxs::err("Invalid transactions JSON").boxed();
+            }
+        };
+        let imported = self
+            .coin_store
+            .lock()
+            .expect("poisoned")
+            .import_transactions(entries);
+        ImportedTxs::ok(imported as u32).boxed()
+    }
+
+    /// Signs a message with the key of a wallet address (BIP322 simple).
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to prove the ownership of.
+    /// * `message` - The message to sign.
+    ///
+    /// # Returns
+    ///
+    /// A `Box<SignedMessage>` containing the base64 encoded signature, or an
+    /// error if the address does not belong to the wallet.
+    pub fn sign_message(&self, address: String, message: String) -> Box<SignedMessage> {
+        let addr = match bitcoin::Address::from_str(&address) {
+            Ok(a) if a.is_valid_for_network(self.config.network) => a.assume_checked(),
+            _ => return SignedMessage::err("Fail to parse address").boxed(),
+        };
+        let entry = self
+            .coin_store
+            .lock()
+            .expect("poisoned")
+            .address_info(&addr.script_pubkey());
+        let entry = match entry {
+            Some(e) if !e.is_secondary() => e,
+            Some(_) => {
+                return SignedMessage::err("Message signing is only supported for wpkh addresses")
+                    .boxed()
+            }
+            None => return SignedMessage::err("Address is not part of the wallet").boxed(),
+        };
+
+        let mut signer =
+            match HotSigner::new_from_mnemonics(self.config.network, &self.config.mnemonic) {
+                Ok(s) => s,
+                Err(e) => return SignedMessage::err(&e.to_string()).boxed(),
+            };
+        signer.inner_register_descriptor(self.config.descriptor.clone());
+        match signer.sign_message((entry.account, entry.index), &message) {
+            Ok(signature) => SignedMessage::ok(signature),
+            Err(e) => SignedMessage::err(&e.to_string()),
+        }
+        .boxed()
+    }
+
+    /// Returns whether the account is stopped: both listeners have
+    /// terminated, or have never been started.
+    ///
+    /// After [`Account::stop`] is called, this returns `true` once the
+    /// `Stopped` notification has been sent.
+    pub fn is_stopped(&self) -> bool {
+        [&self.tx_listener, &self.pool_listener, &self.shutdown]
+            .into_iter()
+            .all(|handle| handle.as_ref().map(|h| h.is_finished()).unwrap_or(true))
+    }
+
+    /// Stops all listeners and pool workers and sends a stopped notification.
+    ///
+    /// The listeners and the pool workers are stopped in the background,
+    /// see [`Account::is_stopped`] in order to track the shutdown progress.
+    pub fn stop(&mut self) {
+        let workers = PoolStore::shutdown(&self.pool_store);
+
+        if self.electrum_stop.is_none() && self.nostr_stop.is_none() && workers.is_empty() {
+            let _ = self.sender.send(Notification::Stopped);
+            return;
+        }
+
+        if let Some(stopper) = &self.electrum_stop {
+            stopper.store(true, Ordering::Relaxed);
+        }
+        if let Some(stopper) = &self.nostr_stop {
+            stopper.store(true, Ordering::Relaxed);
+        }
+
+        let notification = self.sender.clone();
+        let tx_listener = self.tx_listener.take();
+        let pool_listener = self.pool_listener.take();
+
+        self.shutdown = Some(thread::spawn(move || {
+            for handle in [tx_listener, pool_listener].into_iter().flatten() {
+                let _ = handle.join();
+            }
+            for handle in workers {
+                if handle.join().is_err() {
+                    log::error!("Account::stop() a pool worker panicked");
+                }
+            }
+            let _ = notification.send(Notification::Stopped);
+        }));
+    }
+
+    pub fn sign(&self, psbt: String) {
+        self.signing_manager.sign(self.config.network(), psbt);
+    }
+}
+
+/// Creates a new account with the specified account name.
+///
+/// # Arguments
 ///
 /// * `account` - The name of the account.
 ///
@@ -1613,6 +3981,23 @@ pub fn new_account(account: String) -> Box<Account> {
     account.boxed()
 }
 
+/// Creates a new offline account with the specified account name, the
+/// account never connects to electrum or nostr.
+///
+/// # Arguments
+///
+/// * `account` - The name of the account.
+///
+/// # Returns
+///
+/// A boxed `Account` instance.
+pub fn new_offline_account(account: String) -> Box<Account> {
+    let config = Config::from_file(account);
+
+    let account = Account::new_offline(config);
+    account.boxed()
+}
+
 macro_rules! send_notif {
     ($notification:expr, $request:expr, $msg:expr) => {
         let res = $notification.send($msg.into());
@@ -1625,14 +4010,409 @@ macro_rules! send_notif {
 }
 
 macro_rules! send_electrum {
-    ($request:expr, $notification:expr, $msg:expr) => {
-        if $request.send($msg).is_err() {
+    ($request:expr, $notification:expr, $pending:expr, $msg:expr) => {
+        let msg = $msg;
+        $pending.sent(&msg);
+        if $request.send(msg).is_err() {
             send_notif!($notification, $request, TxListenerNotif::Stopped);
             return;
         }
     };
 }
 
+/// Electrum requests of the transaction listener waiting for a response.
+#[derive(Debug, Default)]
+struct InFlight {
+    /// Number of unanswered subscriptions.
+    subscribe: usize,
+    /// Number of unanswered history and transactions requests.
+    fetch: usize,
+    /// Time of the last request sent or response received while some
+    /// requests are unanswered.
+    since: Option<Instant>,
+}
+
+impl InFlight {
+    /// Records a request sent to the electrum server.
+    fn sent(&mut self, request: &CoinRequest) {
+        match request {
+            CoinRequest::Subscribe(_) => self.subscribe += 1,
+            CoinRequest::History(_) | CoinRequest::Txs(_) => self.fetch += 1,
+            _ => return,
+        }
+        self.since.get_or_insert_with(Instant::now);
+    }
+
+    /// Records a response of the electrum server, the timeout is disarmed
+    /// only once all the requests are answered.
+    ///
+    /// NOTE: status notifications pushed by the server are counted as
+    /// subscription responses.
+    fn received(&mut self, response: &CoinResponse) {
+        match response {
+            CoinResponse::Status(_) => self.subscribe = self.subscribe.saturating_sub(1),
+            CoinResponse::History(_) | CoinResponse::Txs(_) => {
+                self.fetch = self.fetch.saturating_sub(1)
+            }
+            _ => {}
+        }
+        self.since = (self.subscribe + self.fetch > 0).then(Instant::now);
+    }
+
+    /// Returns whether the server did not answer for `timeout` while some
+    /// requests are unanswered.
+    fn timed_out(&self, timeout: Duration) -> bool {
+        self.since.is_some_and(|since| since.elapsed() >= timeout)
+    }
+
+    /// Returns whether all the requests are answered.
+    fn is_empty(&self) -> bool {
+        self.subscribe + self.fetch == 0
+    }
+}
+
+/// Returns the lowest confirmation target of a fee curve whose estimate is
+/// lower or equal than `feerate`.
+///
+/// # Arguments
+///
+/// * `estimates` - The fee estimates (in sats/vb) by target (in blocks).
+/// * `feerate` - The fee rate to look up, in sats/vb.
+fn confirmation_target(estimates: &BTreeMap<u16, f64>, feerate: f64) -> Option<u16> {
+    estimates
+        .iter()
+        .find(|(_, estimate)| **estimate <= feerate)
+        .map(|(target, _)| *target)
+}
+
+/// Returns the range of the non-hardened derivation indexes of a batch.
+///
+/// # Arguments
+///
+/// * `start` - The first index of the batch.
+/// * `count` - The number of indexes in the batch.
+fn batch_indexes(start: u32, count: u32) -> std::ops::Range<u32> {
+    const HARDENED: u32 = 1 << 31;
+    start.min(HARDENED)..start.saturating_add(count).min(HARDENED)
+}
+
+/// Counts values per bucket, see [`Account::utxo_histogram`].
+///
+/// # Arguments
+///
+/// * `values` - The values to count.
+/// * `buckets` - The lower bounds of the buckets.
+fn histogram(values: impl Iterator<Item = u64>, mut buckets: Vec<u64>) -> Vec<(u64, usize)> {
+    buckets.sort();
+    buckets.dedup();
+    let mut counts = vec![0; buckets.len()];
+    for value in values {
+        // index of the highest bound lower or equal than the value
+        if let Some(i) = buckets.partition_point(|b| *b <= value).checked_sub(1) {
+            counts[i] += 1;
+        }
+    }
+    buckets.into_iter().zip(counts).collect()
+}
+
+/// Returns the size in vbytes of a segwit input.
+///
+/// # Arguments
+///
+/// * `satisfaction_weight` - The max satisfaction weight of the input in WU.
+fn input_vbytes(satisfaction_weight: u64) -> u64 {
+    // outpoint + sequence + empty script_sig
+    ((32 + 4 + 4 + 1) * WITNESS_SCALE_FACTOR + satisfaction_weight).div_ceil(WITNESS_SCALE_FACTOR)
+}
+
+/// Returns the size in vbytes of a transaction output.
+///
+/// # Arguments
+///
+/// * `spk_len` - The length of the output script pubkey.
+fn txout_vbytes(spk_len: usize) -> u64 {
+    // amount + script_pubkey length + script_pubkey
+    8 + 1 + spk_len as u64
+}
+
+/// Returns the derivation path of the key the coinjoin signer signs a coin
+/// with, it only signs wpkh inputs of the first BIP84 account.
+///
+/// # Arguments
+///
+/// * `network` - The network of the account.
+/// * `coin_path` - The keychain and the index of the coin.
+fn coinjoin_signing_path(
+    network: bitcoin::Network,
+    coin_path: (AddrAccount, u32),
+) -> DerivationPath {
+    let coin_type = match network {
+        bitcoin::Network::Bitcoin => 0,
+        _ => 1,
+    };
+    let (keychain, index) = coin_path;
+    let keychain: u32 = keychain.into();
+    DerivationPath::from_str(&format!("m/84'/{coin_type}'/0'/{keychain}/{index}"))
+        .expect("valid path")
+}
+
+/// Checks that the coinjoin signer owns the script pubkey `spk`.
+///
+/// # Arguments
+///
+/// * `signer` - The coinjoin signer.
+/// * `network` - The network of the account.
+/// * `spk` - The script pubkey of the coin or of the output.
+/// * `coin_path` - The keychain and the index `spk` is derived at.
+///
+/// # Errors
+///
+/// `Error::CoinjoinSigner` if the wpkh script of the signer key at
+/// `coin_path` is not `spk`.
+fn check_coinjoin_signer(
+    signer: &HotSigner,
+    network: bitcoin::Network,
+    spk: &ScriptBuf,
+    coin_path: (AddrAccount, u32),
+) -> Result<(), Error> {
+    let key = CompressedPublicKey(signer.public_key_at(&coinjoin_signing_path(network, coin_path)));
+    if ScriptBuf::new_p2wpkh(&key.wpubkey_hash()) == *spk {
+        Ok(())
+    } else {
+        Err(Error::CoinjoinSigner)
+    }
+}
+
+/// Splits a multipath descriptor into its receive and change descriptors.
+///
+/// # Arguments
+///
+/// * `descriptor` - A multipath descriptor.
+///
+/// # Returns
+///
+/// The receive and change single path descriptors, or an error message if
+/// the descriptor do not have a receive and a change keychain.
+fn split_keychains(
+    descriptor: Descriptor<DescriptorPublicKey>,
+) -> Result<
+    (
+        Descriptor<DescriptorPublicKey>,
+        Descriptor<DescriptorPublicKey>,
+    ),
+    String,
+> {
+    let error = || "Descriptor do not have a receive and a change keychain".to_string();
+    let descriptors = descriptor.into_single_descriptors().map_err(|_| error())?;
+    match <[_; 2]>::try_from(descriptors) {
+        Ok([receive, change]) => Ok((receive, change)),
+        Err(_) => Err(error()),
+    }
+}
+
+/// Percent-encodes a BIP21 parameter value, only the RFC 3986 unreserved
+/// characters are kept as is.
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Quotes a CSV field if it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The request & response channels of an electrum client.
+type ElectrumChannels = (mpsc::Sender<CoinRequest>, mpsc::Receiver<CoinResponse>);
+
+/// Displays a script pubkey as an address, or as hex if it has no address
+/// form.
+fn display_spk(spk: &ScriptBuf, network: bitcoin::Network) -> String {
+    bitcoin::Address::from_script(spk, network)
+        .map(|a| a.to_string())
+        .unwrap_or_else(|_| spk.to_hex_string())
+}
+
+/// Classifies a transaction output as one of our receive or change
+/// outputs, or as an external one.
+fn classify_output(store: &CoinStore, txout: &TxOut, network: bitcoin::Network) -> PsbtOutput {
+    let kind = match store
+        .address_info(&txout.script_pubkey)
+        .map(|e| e.account())
+    {
+        Some(AddrAccount::Receive) => PsbtOutputKind::Receive,
+        Some(AddrAccount::Change) => PsbtOutputKind::Change,
+        _ => PsbtOutputKind::External,
+    };
+    PsbtOutput {
+        address: display_spk(&txout.script_pubkey, network),
+        amount: txout.value.to_sat(),
+        kind,
+    }
+}
+
+/// Inserts the scripts below the watch tips that are not yet tracked into
+/// `statuses`.
+///
+/// # Arguments
+///
+/// * `derivators` - The derivators of the descriptors to watch.
+/// * `statuses` - The tracked scripts statuses.
+/// * `tip` - The address tip to watch up to.
+/// * `tracked` - The receive and change indexes below which every script
+///   is already tracked, only the scripts above are derived. Updated to
+///   the new tips.
+///
+/// # Returns
+///
+/// A tuple of the newly tracked scripts from the scan checkpoint up to the
+/// watch tip, that should be subscribed first, and of the newly tracked
+/// scripts below the scan checkpoint.
+fn track_missing(
+    derivators: &[Derivator],
+    statuses: &mut BTreeMap<ScriptBuf, (Option<String>, u32, u32)>,
+    tip: &AddressTip,
+    tracked: &mut (u32 /* recv */, u32 /* change */),
+) -> (Vec<ScriptBuf>, Vec<ScriptBuf>) {
+    let AddressTip {
+        recv,
+        change,
+        recv_floor,
+        change_floor,
+    } = *tip;
+    let (recv_start, change_start) = *tracked;
+    let mut sub = vec![];
+    let mut lazy_sub = vec![];
+    for derivator in derivators {
+        for i in (recv_floor.max(recv_start)..recv).chain(recv_start..recv_floor) {
+            let spk = derivator.receive_at(i).script_pubkey();
+            if !statuses.contains_key(&spk) {
+                statuses.insert(spk.clone(), (None, 0, i));
+                if i < recv_floor {
+                    lazy_sub.push(spk);
+                } else {
+                    sub.push(spk);
+                }
+            }
+        }
+        for i in (change_floor.max(change_start)..change).chain(change_start..change_floor) {
+            let spk = derivator.change_at(i).script_pubkey();
+            if !statuses.contains_key(&spk) {
+                statuses.insert(spk.clone(), (None, 1, i));
+                if i < change_floor {
+                    lazy_sub.push(spk);
+                } else {
+                    sub.push(spk);
+                }
+            }
+        }
+    }
+    *tracked = (
+        recv_start.max(recv).max(recv_floor),
+        change_start.max(change).max(change_floor),
+    );
+    (sub, lazy_sub)
+}
+
+/// Exponential delay between the reconnection attempts of a listener.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    multiplier: f64,
+    max: Duration,
+    next: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_millis(RECONNECT_INITIAL_DELAY_MS),
+            RECONNECT_MULTIPLIER,
+            Duration::from_millis(RECONNECT_MAX_DELAY_MS),
+        )
+    }
+}
+
+impl Backoff {
+    /// Creates a new backoff.
+    ///
+    /// # Arguments
+    ///
+    /// * `initial` - The delay before the first attempt, at least 1 ms as a
+    ///   zero delay would never grow.
+    /// * `multiplier` - The factor applied to the delay after each attempt,
+    ///   values lower than 1 are treated as 1.
+    /// * `max` - The maximum delay.
+    pub fn new(initial: Duration, multiplier: f64, max: Duration) -> Self {
+        let min = Duration::from_millis(1);
+        let max = max.max(min);
+        let initial = initial.max(min).min(max);
+        Self {
+            initial,
+            multiplier: multiplier.max(1.0),
+            max,
+            next: initial,
+        }
+    }
+
+    /// Creates a new backoff from the reconnection parameters of a config.
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            Duration::from_millis(config.reconnect_initial_delay_ms),
+            config.reconnect_multiplier,
+            Duration::from_millis(config.reconnect_max_delay_ms),
+        )
+    }
+
+    /// Returns the delay before the next attempt, the following one grows
+    /// up to the maximum.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = Duration::try_from_secs_f64(delay.as_secs_f64() * self.multiplier)
+            .unwrap_or(self.max)
+            .min(self.max);
+        delay
+    }
+
+    /// Resets the delay to its initial value, after a successful connection.
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+
+    /// Waits the delay before the next attempt.
+    ///
+    /// # Arguments
+    ///
+    /// * `stop_request` - The stop flag of the listener, checked while
+    ///   waiting.
+    ///
+    /// # Returns
+    ///
+    /// `false` if a stop has been requested.
+    pub fn wait(&mut self, stop_request: &AtomicBool) -> bool {
+        let deadline = Instant::now() + self.next_delay();
+        while Instant::now() < deadline {
+            if stop_request.load(Ordering::Relaxed) {
+                return false;
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            thread::sleep(left.min(Duration::from_millis(50)));
+        }
+        !stop_request.load(Ordering::Relaxed)
+    }
+}
+
 /// Listens for transactions on the specified address and port.
 ///
 /// # Arguments
@@ -1640,24 +4420,42 @@ macro_rules! send_electrum {
 /// * `addr` - The address to listen on.
 /// * `port` - The port to listen on.
 /// * `coin_store` - The coin store to update with transaction data.
-/// * `signer` - The signer for the account.
+/// * `derivators` - The derivators of the descriptors to watch.
 /// * `notification` - The sender for notifications.
 /// * `address_tip` - The receiver for address tips.
+/// * `control` - The receiver for control requests.
 /// * `stop_request` - The stop flag for the listener.
+/// * `request` - The sender for electrum requests.
+/// * `response` - The receiver for electrum responses.
+/// * `config` - The config used to persist the scripts statuses.
+/// * `timeout` - The delay after which an unanswered request is considered
+///   failed.
+/// * `batch_size` - The maximum number of items in a single history or
+///   transactions request.
+/// * `backoff` - The delay between reconnection attempts.
+/// * `connect` - Connects a new electrum client, called on request timeout.
 #[allow(clippy::too_many_arguments)]
-fn listen_txs<T: From<TxListenerNotif>>(
+fn listen_txs<C: Fn() -> Result<ElectrumChannels, String>>(
     coin_store: Arc<Mutex<CoinStore>>,
-    derivator: Derivator,
-    notification: mpsc::Sender<T>,
+    derivators: Vec<Derivator>,
+    notification: NotificationSender,
     address_tip: mpsc::Receiver<AddressTip>,
+    control: mpsc::Receiver<ListenerControl>,
     stop_request: Arc<AtomicBool>,
-    request: mpsc::Sender<CoinRequest>,
-    response: mpsc::Receiver<CoinResponse>,
+    mut request: mpsc::Sender<CoinRequest>,
+    mut response: mpsc::Receiver<CoinResponse>,
     config: Option<Config>,
+    timeout: Duration,
+    batch_size: usize,
+    mut backoff: Backoff,
+    connect: C,
 ) {
     log::info!("listen_txs(): started");
     send_notif!(notification, request, TxListenerNotif::Started);
 
+    // requests not answered yet
+    let mut pending = InFlight::default();
+
     let mut statuses = if let Some(config) = &config {
         config.statuses_from_file()
     } else {
@@ -1666,7 +4464,7 @@ fn listen_txs<T: From<TxListenerNotif>>(
 
     if !statuses.is_empty() {
         let sub: Vec<_> = statuses.keys().cloned().collect();
-        send_electrum!(request, notification, CoinRequest::Subscribe(sub));
+        send_electrum!(request, notification, pending, CoinRequest::Subscribe(sub));
     }
 
     fn persist_status(
@@ -1678,6 +4476,36 @@ fn listen_txs<T: From<TxListenerNotif>>(
         }
     }
 
+    // NOTE: statuses.json can be missing or lag behind tip.json, so the
+    // watched scripts are reconciled against the actual watch tips before
+    // waiting for tip updates.
+    let tip = coin_store.lock().expect("poisoned").address_tip();
+    // NOTE: the first reconciliation derives every script below the tips,
+    // the next ones only the scripts above the previous tips
+    let mut tracked = (0, 0);
+    let (sub, lazy_sub) = track_missing(&derivators, &mut statuses, &tip, &mut tracked);
+    if !sub.is_empty() || !lazy_sub.is_empty() {
+        log::info!(
+            "listen_txs(): {} watched scripts missing from statuses",
+            sub.len() + lazy_sub.len()
+        );
+        persist_status(&config, &statuses);
+    }
+    if !sub.is_empty() {
+        send_electrum!(request, notification, pending, CoinRequest::Subscribe(sub));
+    }
+    if !lazy_sub.is_empty() {
+        send_electrum!(
+            request,
+            notification,
+            pending,
+            CoinRequest::Subscribe(lazy_sub)
+        );
+    }
+
+    // whether the coin store knows some requests are unanswered
+    let mut in_flight = None;
+
     loop {
         // stop request from consumer side
         if stop_request.load(Ordering::Relaxed) {
@@ -1686,41 +4514,37 @@ fn listen_txs<T: From<TxListenerNotif>>(
             return;
         }
 
+        // NOTE: the coins are synced only once every subscribed status has
+        // been received and its history and transactions fetched, the
+        // responses are processed after this point so the coin store is
+        // never seen synced while a request is unanswered
+        let busy = !pending.is_empty();
+        if in_flight != Some(busy) {
+            coin_store.lock().expect("poisoned").set_in_flight(busy);
+            in_flight = Some(busy);
+        }
+
         let mut received = false;
 
         // listen for AddressTip update
         match address_tip.try_recv() {
             Ok(tip) => {
                 log::debug!("listen_txs() receive {tip:?}");
-                let AddressTip { recv, change } = tip;
                 received = true;
-                let mut sub = vec![];
-                let r_spk = derivator.receive_at(recv).script_pubkey();
-                if !statuses.contains_key(&r_spk) {
-                    // FIXME: here we can be smart an not start at 0 but at `actual_tip`
-                    for i in 0..recv {
-                        let spk = derivator.receive_at(i).script_pubkey();
-                        if !statuses.contains_key(&spk) {
-                            statuses.insert(spk.clone(), (None, 0, i));
-                            persist_status(&config, &statuses);
-                            sub.push(spk);
-                        }
-                    }
-                }
-                let c_spk = derivator.change_at(recv).script_pubkey();
-                if !statuses.contains_key(&c_spk) {
-                    // FIXME: here we can be smart an not start at 0 but at `actual_tip`
-                    for i in 0..change {
-                        let spk = derivator.change_at(i).script_pubkey();
-                        if !statuses.contains_key(&spk) {
-                            statuses.insert(spk.clone(), (None, 1, i));
-                            persist_status(&config, &statuses);
-                            sub.push(spk);
-                        }
-                    }
+                let (sub, lazy_sub) = track_missing(&derivators, &mut statuses, &tip, &mut tracked);
+                if !sub.is_empty() || !lazy_sub.is_empty() {
+                    persist_status(&config, &statuses);
                 }
                 if !sub.is_empty() {
-                    send_electrum!(request, notification, CoinRequest::Subscribe(sub));
+                    send_electrum!(request, notification, pending, CoinRequest::Subscribe(sub));
+                }
+                if !lazy_sub.is_empty() {
+                    send_electrum!(
+                        request,
+                        notification,
+                        pending,
+                        CoinRequest::Subscribe(lazy_sub)
+                    );
                 }
             }
             Err(e) => match e {
@@ -1739,11 +4563,22 @@ fn listen_txs<T: From<TxListenerNotif>>(
             },
         }
 
+        // listen for control requests
+        if let Ok(ListenerControl::Refresh) = control.try_recv() {
+            log::debug!("listen_txs() refresh requested");
+            received = true;
+            if !statuses.is_empty() {
+                let sub: Vec<_> = statuses.keys().cloned().collect();
+                send_electrum!(request, notification, pending, CoinRequest::Subscribe(sub));
+            }
+        }
+
         // listen for response
         match response.try_recv() {
             Ok(rsp) => {
                 log::debug!("listen_txs() receive {rsp:#?}");
                 received = true;
+                pending.received(&rsp);
                 match rsp {
                     CoinResponse::Status(elct_status) => {
                         let mut history = vec![];
@@ -1786,20 +4621,26 @@ fn listen_txs<T: From<TxListenerNotif>>(
                                 let _ = store.handle_history_response(map);
                             }
                         }
-                        if !history.is_empty() {
-                            let hist = CoinRequest::History(history);
+                        // NOTE: large requests are split, some servers reject them
+                        for chunk in history.chunks(batch_size.max(1)) {
+                            let hist = CoinRequest::History(chunk.to_vec());
                             log::debug!("listen_txs() send {:#?}", hist);
-                            send_electrum!(request, notification, hist);
+                            send_electrum!(request, notification, pending, hist);
                         }
                         persist_status(&config, &statuses);
                     }
                     CoinResponse::History(map) => {
                         let mut store = coin_store.lock().expect("poisoned");
-                        let (height_updated, missing_txs) = store.handle_history_response(map);
-                        if !missing_txs.is_empty() {
-                            send_electrum!(request, notification, CoinRequest::Txs(missing_txs));
+                        let (updated, missing_txs) = store.handle_history_response(map);
+                        for chunk in missing_txs.chunks(batch_size.max(1)) {
+                            send_electrum!(
+                                request,
+                                notification,
+                                pending,
+                                CoinRequest::Txs(chunk.to_vec())
+                            );
                         }
-                        if height_updated {
+                        if updated {
                             store.generate();
                         }
                     }
@@ -1829,6 +4670,58 @@ fn listen_txs<T: From<TxListenerNotif>>(
             },
         }
 
+        // the electrum server did not answer in time
+        if pending.timed_out(timeout) {
+            log::error!("listen_txs() electrum request timed out, reconnecting");
+            send_notif!(
+                notification,
+                request,
+                TxListenerNotif::Error("Electrum request timed out".to_string())
+            );
+            let _ = request.send(CoinRequest::Stop);
+            if pending.fetch > 0 {
+                // NOTE: the statuses are recorded before their history is
+                // fetched, the lost histories and transactions are fetched
+                // again by invalidating the known statuses, an empty status
+                // never matches a server status
+                for (status, _, _) in statuses.values_mut() {
+                    if status.is_some() {
+                        *status = Some(String::new());
+                    }
+                }
+            }
+            pending = InFlight::default();
+            // NOTE: attempts are spaced by an exponential backoff in order
+            // to not hammer a flaky server
+            // NOTE: the consumer is notified once per outage, not on every
+            // failed attempt
+            let mut notified = false;
+            let (req, rsp) = loop {
+                if !backoff.wait(&stop_request) {
+                    send_notif!(notification, request, TxListenerNotif::Stopped);
+                    return;
+                }
+                match connect() {
+                    Ok(channels) => break channels,
+                    Err(e) => {
+                        log::error!("listen_txs() fail to reconnect: {e}");
+                        if !notified {
+                            notified = true;
+                            send_notif!(notification, request, TxListenerNotif::Error(e));
+                        }
+                    }
+                }
+            };
+            backoff.reset();
+            request = req;
+            response = rsp;
+            if !statuses.is_empty() {
+                let sub: Vec<_> = statuses.keys().cloned().collect();
+                send_electrum!(request, notification, pending, CoinRequest::Subscribe(sub));
+            }
+            continue;
+        }
+
         if received {
             continue;
         }
@@ -1846,12 +4739,15 @@ fn listen_txs<T: From<TxListenerNotif>>(
 /// * `sender` - The sender for notifications.
 /// * `back` - The number of past events to retrieve.
 /// * `stop_request` - The stop flag for the listener.
-fn pool_listener<N: From<JoinstrNotif> + Send + 'static>(
+/// * `backoff` - The delay between reconnection attempts.
+fn pool_listener(
     relay: String,
     pool_store: Arc<Mutex<PoolStore>>,
-    sender: mpsc::Sender<N>,
+    sender: NotificationSender,
     back: u64,
+    authors: Vec<String>,
     stop_request: Arc<AtomicBool>,
+    mut backoff: Backoff,
 ) {
     let mut pool_listener = NostrClient::new("pool_listener")
         .relay(relay.clone())
@@ -1889,32 +4785,46 @@ fn pool_listener<N: From<JoinstrNotif> + Send + 'static>(
             Err(e) => match e {
                 error::Error::Disconnected | error::Error::NotConnected => {
                     log::error!("pool_listener() connexion lost: {e:?}");
-                    // connexion lost try to reconnect
-                    pool_listener = NostrClient::new("pool_listener")
-                        .relay(relay.clone())
-                        .expect("not connected")
-                        .keys(Keys::generate())
-                        .expect("not connected");
-
-                    if let Err(e) = pool_listener.connect_nostr() {
-                        log::error!("pool_listener() fail to reconnect: {e:?}");
-                        let msg: JoinstrNotif = e.into();
-                        let _ = sender.send(msg.into());
-                        let msg = JoinstrNotif::Stopped;
-                        let _ = sender.send(msg.into());
-                        return;
-                    }
-                    if let Err(e) = pool_listener.subscribe_pools(back) {
-                        log::error!(
-                            "pool_listener() fail to subscribe to pool notifications: {e:?}"
-                        );
-                        let msg: JoinstrNotif = e.into();
-                        let _ = sender.send(msg.into());
-                        let msg = JoinstrNotif::Stopped;
-                        let _ = sender.send(msg.into());
-                        return;
+                    // connexion lost try to reconnect, attempts are spaced by
+                    // an exponential backoff, the consumer is notified once
+                    // per outage
+                    let mut notified = false;
+                    loop {
+                        if !backoff.wait(&stop_request) {
+                            log::error!("pool_listener() stop requested");
+                            let msg = JoinstrNotif::Stopped;
+                            let _ = sender.send(msg.into());
+                            return;
+                        }
+                        pool_listener = NostrClient::new("pool_listener")
+                            .relay(relay.clone())
+                            .expect("not connected")
+                            .keys(Keys::generate())
+                            .expect("not connected");
+
+                        if let Err(e) = pool_listener.connect_nostr() {
+                            log::error!("pool_listener() fail to reconnect: {e:?}");
+                            if !notified {
+                                notified = true;
+                                let msg: JoinstrNotif = e.into();
+                                let _ = sender.send(msg.into());
+                            }
+                            continue;
+                        }
+                        if let Err(e) = pool_listener.subscribe_pools(back) {
+                            log::error!(
+                                "pool_listener() fail to subscribe to pool notifications: {e:?}"
+                            );
+                            if !notified {
+                                notified = true;
+                                let msg: JoinstrNotif = e.into();
+                                let _ = sender.send(msg.into());
+                            }
+                            continue;
+                        }
+                        break;
                     }
-                    thread::sleep(Duration::from_millis(50));
+                    backoff.reset();
                     continue;
                 }
                 e => {
@@ -1927,14 +4837,62 @@ fn pool_listener<N: From<JoinstrNotif> + Send + 'static>(
                 }
             },
         };
-        {
-            let mut store = pool_store.lock().expect("poisoned");
-            store.update(pool, PoolStatus::Available);
-            if sender.send(JoinstrNotif::PoolUpdate.into()).is_err() {
-                return;
-            }
-        } // release store lock
+        if !store_pool(pool, &authors, &pool_store, &sender) {
+            return;
+        }
+    }
+}
+
+/// Returns the relay and the lookback the pool listener subscribes with.
+///
+/// # Arguments
+///
+/// * `config` - The config of the account.
+///
+/// # Returns
+///
+/// `None` if the relay or the lookback is not configured, or if the
+/// lookback is not between 1 and [`MAX_NOSTR_BACK`] seconds.
+fn nostr_subscription(config: &Config) -> Option<(String, u64)> {
+    let relay = config.nostr_relay.clone()?;
+    let back = config
+        .nostr_back
+        .filter(|b| (1..=MAX_NOSTR_BACK).contains(b))?;
+    Some((relay, back))
+}
+
+/// Stores a pool received by the pool listener, the pools of the publishers
+/// not allowed by `authors` are dropped.
+///
+/// # Arguments
+///
+/// * `pool` - The received pool.
+/// * `authors` - The allowed publishers, see [`is_pool_author_allowed`].
+/// * `pool_store` - The pool store to update.
+/// * `sender` - The sender for notifications.
+///
+/// # Returns
+///
+/// `false` if the notification receiver is gone.
+fn store_pool(
+    pool: Pool,
+    authors: &[String],
+    pool_store: &Mutex<PoolStore>,
+    sender: &NotificationSender,
+) -> bool {
+    // NOTE: the subscription filter (event kind, no authors) is defined by
+    // joinstr, pools of other publishers are dropped here
+    // TODO: move the authors and a configurable kind into the relay filter
+    // once `NostrClient::subscribe_pools()` accepts them, the relay would
+    // then not send the pools of other publishers at all
+    let author = pool.public_key.to_string();
+    if !is_pool_author_allowed(authors, &author) {
+        log::debug!("store_pool() drop pool {} of publisher {author}", pool.id);
+        return true;
     }
+    let mut store = pool_store.lock().expect("poisoned");
+    store.update(pool, PoolStatus::Available);
+    sender.send(JoinstrNotif::PoolUpdate.into()).is_ok()
 }
 
 #[cfg(test)]
@@ -1944,114 +4902,732 @@ mod tests {
     use joinstr::{bip39, miniscript::bitcoin::bip32::DerivationPath};
 
     use crate::{
+        config::ELECTRUM_BATCH_SIZE,
         cpp_joinstr::CoinStatus,
-        signer::{wpkh, HotSigner},
-        test_utils::{funding_tx, setup_logger, spending_tx},
+        signer::{tr, wpkh, HotSigner},
+        test_utils::{funding_tx, random_input, random_output, setup_logger, spending_tx, txid},
         tx_store::TxStore,
     };
 
-    use super::*;
+    use super::*;
+
+    struct CoinStoreMock {
+        pub store: Arc<Mutex<CoinStore>>,
+        pub notif: NotificationReceiver,
+        pub request: mpsc::Receiver<CoinRequest>,
+        pub response: mpsc::Sender<CoinResponse>,
+        pub control: mpsc::Sender<ListenerControl>,
+        pub listener: JoinHandle<()>,
+        pub stop: Arc<AtomicBool>,
+        pub derivator: Derivator,
+        pub secondary: Option<Derivator>,
+        /// The client side of the channels handed out on reconnection.
+        pub reconnect: mpsc::Receiver<(mpsc::Receiver<CoinRequest>, mpsc::Sender<CoinResponse>)>,
+    }
+
+    /// Timeout of the mocked electrum requests, long enough to never fire.
+    const MOCK_TIMEOUT: Duration = Duration::from_secs(3600);
+
+    impl Drop for CoinStoreMock {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    impl CoinStoreMock {
+        fn new(recv_tip: u32, change_tip: u32, look_ahead: u32) -> Self {
+            Self::new_with_checkpoint(recv_tip, change_tip, look_ahead, Tip::default())
+        }
+
+        fn new_with_checkpoint(
+            recv_tip: u32,
+            change_tip: u32,
+            look_ahead: u32,
+            scan_from: Tip,
+        ) -> Self {
+            Self::build(
+                recv_tip,
+                change_tip,
+                look_ahead,
+                look_ahead,
+                scan_from,
+                false,
+                MOCK_TIMEOUT,
+            )
+        }
+
+        fn new_with_look_aheads(recv_look_ahead: u32, change_look_ahead: u32) -> Self {
+            Self::build(
+                0,
+                0,
+                recv_look_ahead,
+                change_look_ahead,
+                Tip::default(),
+                false,
+                MOCK_TIMEOUT,
+            )
+        }
+
+        fn new_with_timeout(look_ahead: u32, timeout: Duration) -> Self {
+            Self::build(0, 0, look_ahead, look_ahead, Tip::default(), false, timeout)
+        }
+
+        fn new_with_secondary(recv_tip: u32, change_tip: u32, look_ahead: u32) -> Self {
+            Self::build(
+                recv_tip,
+                change_tip,
+                look_ahead,
+                look_ahead,
+                Tip::default(),
+                true,
+                MOCK_TIMEOUT,
+            )
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        fn build(
+            recv_tip: u32,
+            change_tip: u32,
+            recv_look_ahead: u32,
+            change_look_ahead: u32,
+            scan_from: Tip,
+            secondary: bool,
+            timeout: Duration,
+        ) -> Self {
+            let (notif_sender, notif_recv) = notification_channel();
+            let (reconnect_sender, reconnect_recv) = mpsc::channel();
+            let (tip_sender, tip_receiver) = mpsc::channel();
+            let (req_sender, req_receiver) = mpsc::channel();
+            let (resp_sender, resp_receiver) = mpsc::channel();
+            let (control_sender, control_receiver) = mpsc::channel();
+
+            let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+            let stop = Arc::new(AtomicBool::new(false));
+            let signer =
+                HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string())
+                    .unwrap();
+            let xpub = signer.xpub(&DerivationPath::from_str("m/84'/0'/0'/1").unwrap());
+            let descriptor = wpkh(xpub);
+            let derivator = Derivator::new(descriptor.clone(), bitcoin::Network::Regtest).unwrap();
+            let secondary_descriptor = secondary
+                .then(|| tr(signer.xpub(&DerivationPath::from_str("m/86'/0'/0'/1").unwrap())));
+            let secondary = secondary_descriptor
+                .clone()
+                .map(|d| Derivator::new(d, bitcoin::Network::Regtest).unwrap());
+            let derivators = [Some(derivator.clone()), secondary.clone()]
+                .into_iter()
+                .flatten()
+                .collect();
+
+            let tx_store = TxStore::new(Default::default(), None);
+            let label_store = Arc::new(Mutex::new(LabelStore::new()));
+            let coin_store = Arc::new(Mutex::new(CoinStore::new(
+                bitcoin::Network::Regtest,
+                descriptor.clone(),
+                secondary_descriptor,
+                notif_sender.clone(),
+                recv_tip,
+                change_tip,
+                recv_look_ahead,
+                change_look_ahead,
+                scan_from,
+                tx_store,
+                label_store,
+                None,
+            )));
+            coin_store.lock().expect("poisoned").init(tip_sender);
+            let store = coin_store.clone();
+            let cloned_stop = stop.clone();
+
+            let listener_handle = thread::spawn(move || {
+                listen_txs(
+                    coin_store,
+                    derivators,
+                    notif_sender,
+                    tip_receiver,
+                    control_receiver,
+                    stop,
+                    req_sender,
+                    resp_receiver,
+                    None,
+                    timeout,
+                    ELECTRUM_BATCH_SIZE as usize,
+                    Backoff::default(),
+                    move || {
+                        let (req_sender, req_receiver) = mpsc::channel();
+                        let (resp_sender, resp_receiver) = mpsc::channel();
+                        reconnect_sender
+                            .send((req_receiver, resp_sender))
+                            .map_err(|e| e.to_string())?;
+                        Ok((req_sender, resp_receiver))
+                    },
+                );
+            });
+
+            CoinStoreMock {
+                store,
+                notif: notif_recv,
+                request: req_receiver,
+                response: resp_sender,
+                control: control_sender,
+                listener: listener_handle,
+                stop: cloned_stop,
+                derivator,
+                secondary,
+                reconnect: reconnect_recv,
+            }
+        }
+
+        fn coins(&mut self) -> BTreeMap<OutPoint, CoinEntry> {
+            self.store.lock().expect("poisoned").coins()
+        }
+
+        fn stop(&self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn notifications_coalesce() {
+        let (sender, receiver) = notification_channel();
+
+        // a flood of refresh notifications from a producer thread while the
+        // consumer is not draining the channel
+        let producer = {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                for i in 0..10_000 {
+                    sender.send(Notification::CoinUpdate).unwrap();
+                    sender.send(Notification::AddressTipChanged).unwrap();
+                    sender.send(JoinstrNotif::PoolUpdate.into()).unwrap();
+                    if i % 1_000 == 0 {
+                        sender.send(Error::CoinMissing.into()).unwrap();
+                    }
+                }
+                sender.send(Notification::Stopped).unwrap();
+            })
+        };
+        producer.join().unwrap();
+
+        let received: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        // only one refresh of each kind is queued, errors & stopped are kept
+        assert_eq!(received.len(), 3 + 10 + 1);
+        let errors = received
+            .iter()
+            .filter(|n| matches!(n, Notification::Error(Error::CoinMissing)))
+            .count();
+        assert_eq!(errors, 10);
+        assert!(matches!(received.last(), Some(Notification::Stopped)));
+
+        // once drained, refresh notifications are queued again
+        sender.send(Notification::CoinUpdate).unwrap();
+        assert!(matches!(
+            receiver.try_recv().unwrap(),
+            Notification::CoinUpdate
+        ));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn notifications_bounded() {
+        let (sender, receiver) = notification_channel();
+
+        // a slow consumer do not drain the non coalesced notifications
+        for i in 0..NOTIFICATION_CAPACITY + 500 {
+            sender
+                .send(JoinstrNotif::PoolStepChanged("id".to_string()).into())
+                .unwrap();
+            if i == 10 {
+                sender.send(Error::CoinMissing.into()).unwrap();
+            }
+        }
+        sender.send(Notification::Stopped).unwrap();
+
+        let received: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        // the overflow is dropped but errors & stopped are kept, in order
+        assert_eq!(received.len(), NOTIFICATION_CAPACITY + 2);
+        assert!(matches!(
+            received[11],
+            Notification::Error(Error::CoinMissing)
+        ));
+        assert!(matches!(received.last(), Some(Notification::Stopped)));
+
+        // once drained, notifications are queued again
+        sender
+            .send(JoinstrNotif::PeerJoined("id".to_string()).into())
+            .unwrap();
+        assert!(matches!(
+            receiver.try_recv().unwrap(),
+            Notification::Joinstr(JoinstrNotif::PeerJoined(_))
+        ));
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+
+        drop(sender);
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(TryRecvError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn simple_start_stop() {
+        setup_logger();
+        let mock = CoinStoreMock::new(0, 0, 20);
+        thread::sleep(Duration::from_millis(10));
+        assert!(!mock.listener.is_finished());
+        assert!(matches!(
+            mock.notif.try_recv().unwrap(),
+            Notification::AddressTipChanged,
+        ));
+        assert!(matches!(
+            mock.notif.try_recv().unwrap(),
+            Notification::Electrum(TxListenerNotif::Started)
+        ));
+        mock.stop();
+        thread::sleep(Duration::from_secs(1));
+        assert!(mock.listener.is_finished());
+    }
+
+    #[test]
+    fn chunked_history_requests() {
+        setup_logger();
+        let mock = CoinStoreMock::new_with_timeout(500, Duration::from_millis(600));
+        thread::sleep(Duration::from_millis(100));
+        assert!(matches!(
+            mock.request.try_recv(),
+            Ok(CoinRequest::Subscribe(_))
+        ));
+
+        // electrum server send 500 non empty statuses
+        let spks: Vec<_> = (0..500).map(|i| mock.derivator.receive_spk_at(i)).collect();
+        let statuses: BTreeMap<_, _> = spks
+            .iter()
+            .map(|s| (s.clone(), Some("status".to_string())))
+            .collect();
+        mock.response
+            .send(CoinResponse::Status(statuses.clone()))
+            .unwrap();
+        thread::sleep(Duration::from_millis(200));
+
+        // histories are requested in several batches
+        let batches: Vec<_> = std::iter::from_fn(|| mock.request.try_recv().ok())
+            .filter_map(|req| match req {
+                CoinRequest::History(v) => Some(v),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(batches.len(), 500 / ELECTRUM_BATCH_SIZE as usize);
+        assert!(batches
+            .iter()
+            .all(|b| b.len() <= ELECTRUM_BATCH_SIZE as usize));
+        let requested: BTreeSet<_> = batches.iter().flatten().cloned().collect();
+        assert_eq!(requested, spks.iter().cloned().collect());
+
+        // all the batches but the last one are answered
+        for _ in 1..batches.len() {
+            mock.response
+                .send(CoinResponse::History(BTreeMap::new()))
+                .unwrap();
+        }
+        thread::sleep(Duration::from_millis(100));
+        assert!(mock.reconnect.try_recv().is_err());
+
+        // the timeout stays armed for the lost batch
+        thread::sleep(Duration::from_millis(800));
+        let (request, response) = mock.reconnect.try_recv().unwrap();
+        assert!(matches!(request.try_recv(), Ok(CoinRequest::Subscribe(_))));
+
+        // and its histories are requested again
+        response.send(CoinResponse::Status(statuses)).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let requested: BTreeSet<_> = std::iter::from_fn(|| request.try_recv().ok())
+            .filter_map(|req| match req {
+                CoinRequest::History(v) => Some(v),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        let lost = batches.last().unwrap();
+        assert!(lost.iter().all(|spk| requested.contains(spk)));
+    }
+
+    #[test]
+    fn reconnect_backoff() {
+        let mut backoff = Backoff::new(
+            Duration::from_millis(100),
+            2.0,
+            Duration::from_millis(1_000),
+        );
+
+        // the delay grows across consecutive failures up to the max
+        let delays: Vec<_> = (0..6).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1_000, 1_000]);
+
+        // and is reset after a successful connection
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+
+        // a multiplier lower than 1 keeps a constant delay
+        let mut constant = Backoff::new(Duration::from_millis(50), 0.5, Duration::from_secs(1));
+        assert_eq!(constant.next_delay(), Duration::from_millis(50));
+        assert_eq!(constant.next_delay(), Duration::from_millis(50));
+
+        // a zero delay is clamped, so it still grows
+        let mut zero = Backoff::new(Duration::ZERO, 2.0, Duration::ZERO);
+        assert_eq!(zero.next_delay(), Duration::from_millis(1));
+        let mut zero = Backoff::new(Duration::ZERO, 2.0, Duration::from_secs(1));
+        assert_eq!(zero.next_delay(), Duration::from_millis(1));
+        assert_eq!(zero.next_delay(), Duration::from_millis(2));
+
+        // a stop request interrupts the wait
+        let stop = AtomicBool::new(true);
+        let mut long = Backoff::new(Duration::from_secs(10), 2.0, Duration::from_secs(10));
+        let start = Instant::now();
+        assert!(!long.wait(&stop));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn request_timeout() {
+        setup_logger();
+        let mock = CoinStoreMock::new_with_timeout(5, Duration::from_millis(400));
+        thread::sleep(Duration::from_millis(100));
+
+        // the initial subscription is never answered
+        assert!(matches!(
+            mock.request.try_recv(),
+            Ok(CoinRequest::Subscribe(_))
+        ));
+        assert!(mock.reconnect.try_recv().is_err());
+
+        thread::sleep(Duration::from_millis(450));
+        let notifs: Vec<_> = std::iter::from_fn(|| mock.notif.try_recv().ok()).collect();
+        assert!(notifs
+            .iter()
+            .any(|n| matches!(n, Notification::Electrum(TxListenerNotif::Error(_)))));
+
+        // the hung client is stopped and a new one is connected
+        assert!(matches!(mock.request.try_recv(), Ok(CoinRequest::Stop)));
+        let (request, _response) = mock.reconnect.try_recv().unwrap();
+        assert!(!mock.listener.is_finished());
+
+        // the watched scripts are subscribed again
+        let subscribed = match request.try_recv() {
+            Ok(CoinRequest::Subscribe(v)) => v,
+            _ => panic!("expected a subscription"),
+        };
+        assert_eq!(subscribed.len(), 2 * (5 + 1));
+    }
+
+    #[test]
+    fn reconnect_error_notified_once() {
+        setup_logger();
+        let mut mock = CoinStoreMock::new_with_timeout(5, Duration::from_millis(400));
+        // every reconnection attempt fails
+        drop(std::mem::replace(&mut mock.reconnect, mpsc::channel().1));
+
+        // the request times out, then 4 attempts fail (100 + 200 + 400 + 800 ms)
+        thread::sleep(Duration::from_millis(100 + 450 + 1_700));
+        let errors = std::iter::from_fn(|| mock.notif.try_recv().ok())
+            .filter(|n| matches!(n, Notification::Electrum(TxListenerNotif::Error(_))))
+            .count();
+        // one for the timeout, one for the outage
+        assert_eq!(errors, 2);
+        assert!(!mock.listener.is_finished());
+    }
+
+    #[test]
+    fn in_flight_requests() {
+        let mut pending = InFlight::default();
+        assert!(!pending.timed_out(Duration::ZERO));
+
+        pending.sent(&CoinRequest::Subscribe(vec![]));
+        pending.sent(&CoinRequest::History(vec![]));
+        pending.sent(&CoinRequest::Stop);
+        assert_eq!((pending.subscribe, pending.fetch), (1, 1));
+
+        // a single response do not disarm the timeout of the others
+        pending.received(&CoinResponse::Status(BTreeMap::new()));
+        assert!(pending.timed_out(Duration::ZERO));
+        pending.received(&CoinResponse::History(BTreeMap::new()));
+        assert!(!pending.timed_out(Duration::ZERO));
+
+        // unexpected responses do not underflow
+        pending.received(&CoinResponse::Txs(vec![]));
+        assert_eq!((pending.subscribe, pending.fetch), (0, 0));
+    }
+
+    #[test]
+    fn synced_once_histories_processed() {
+        setup_logger();
+        let mock = CoinStoreMock::new(0, 0, 20);
+        thread::sleep(Duration::from_millis(100));
+        let subscriptions = std::iter::from_fn(|| mock.request.try_recv().ok())
+            .filter(|req| matches!(req, CoinRequest::Subscribe(_)))
+            .count();
+        assert!(subscriptions > 0);
+        // the statuses of the subscribed scripts are not received yet
+        assert!(!mock.store.lock().unwrap().is_synced());
+
+        // each subscription is answered, a single status is not empty
+        let spk = mock.derivator.receive_spk_at(0);
+        let mut statuses = BTreeMap::new();
+        statuses.insert(spk.clone(), Some("status".to_string()));
+        mock.response.send(CoinResponse::Status(statuses)).unwrap();
+        for _ in 1..subscriptions {
+            mock.response
+                .send(CoinResponse::Status(BTreeMap::new()))
+                .unwrap();
+        }
+        thread::sleep(Duration::from_millis(100));
+        assert!(matches!(
+            mock.request.try_recv(),
+            Ok(CoinRequest::History(_))
+        ));
+        // the history of the changed status is not received yet
+        assert!(!mock.store.lock().unwrap().is_synced());
+
+        let mut history = BTreeMap::new();
+        history.insert(spk, vec![]);
+        mock.response.send(CoinResponse::History(history)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(mock.store.lock().unwrap().is_synced());
+        mock.stop();
+    }
+
+    #[test]
+    fn lost_history_fetched_again() {
+        setup_logger();
+        let mock = CoinStoreMock::new_with_timeout(5, Duration::from_millis(400));
+        thread::sleep(Duration::from_millis(100));
+        assert!(matches!(
+            mock.request.try_recv(),
+            Ok(CoinRequest::Subscribe(_))
+        ));
+
+        // the status is answered but the history request is lost
+        let spk = mock.derivator.receive_spk_at(0);
+        let statuses: BTreeMap<_, _> = [(spk.clone(), Some("1_tx".to_string()))].into();
+        mock.response
+            .send(CoinResponse::Status(statuses.clone()))
+            .unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(matches!(
+            mock.request.try_recv(),
+            Ok(CoinRequest::History(v)) if v == vec![spk.clone()]
+        ));
+
+        // the listener reconnects on timeout
+        thread::sleep(Duration::from_millis(600));
+        let (request, response) = mock.reconnect.try_recv().unwrap();
+        assert!(matches!(request.try_recv(), Ok(CoinRequest::Subscribe(_))));
 
-    struct CoinStoreMock {
-        pub store: Arc<Mutex<CoinStore>>,
-        pub notif: mpsc::Receiver<Notification>,
-        pub request: mpsc::Receiver<CoinRequest>,
-        pub response: mpsc::Sender<CoinResponse>,
-        pub listener: JoinHandle<()>,
-        pub stop: Arc<AtomicBool>,
-        pub derivator: Derivator,
+        // the server answers the same status, the history is fetched again
+        response.send(CoinResponse::Status(statuses)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(matches!(
+            request.try_recv(),
+            Ok(CoinRequest::History(v)) if v == vec![spk]
+        ));
     }
 
-    impl Drop for CoinStoreMock {
-        fn drop(&mut self) {
-            self.stop.store(true, Ordering::Relaxed);
-        }
+    #[test]
+    fn track_missing_new_range() {
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let signer =
+            HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string())
+                .unwrap();
+        let xpub = signer.xpub(&DerivationPath::from_str("m/84'/0'/0'").unwrap());
+        let derivator = Derivator::new(wpkh(xpub), bitcoin::Network::Regtest).unwrap();
+        let derivators = [derivator.clone()];
+        let mut statuses = BTreeMap::new();
+        let mut tracked = (0, 0);
+
+        // the first reconciliation tracks every script below the tips
+        let tip = AddressTip {
+            recv: 5,
+            change: 3,
+            recv_floor: 2,
+            change_floor: 0,
+        };
+        let (sub, lazy_sub) = track_missing(&derivators, &mut statuses, &tip, &mut tracked);
+        assert_eq!(sub.len(), 3 + 3);
+        assert_eq!(
+            lazy_sub,
+            vec![derivator.receive_spk_at(0), derivator.receive_spk_at(1)]
+        );
+        assert_eq!(statuses.len(), 8);
+        assert_eq!(tracked, (5, 3));
+
+        // the next ones only derive the scripts above the tracked tips
+        statuses.remove(&derivator.receive_spk_at(0));
+        let tip = AddressTip { recv: 7, ..tip };
+        let (sub, lazy_sub) = track_missing(&derivators, &mut statuses, &tip, &mut tracked);
+        assert_eq!(
+            sub,
+            vec![derivator.receive_spk_at(5), derivator.receive_spk_at(6)]
+        );
+        assert!(lazy_sub.is_empty());
+        assert!(!statuses.contains_key(&derivator.receive_spk_at(0)));
+        assert_eq!(tracked, (7, 3));
+
+        // an unchanged tip derives nothing
+        let (sub, lazy_sub) = track_missing(&derivators, &mut statuses, &tip, &mut tracked);
+        assert!(sub.is_empty() && lazy_sub.is_empty());
     }
 
-    impl CoinStoreMock {
-        fn new(recv_tip: u32, change_tip: u32, look_ahead: u32) -> Self {
-            let (notif_sender, notif_recv) = mpsc::channel();
-            let (tip_sender, tip_receiver) = mpsc::channel();
-            let (req_sender, req_receiver) = mpsc::channel();
-            let (resp_sender, resp_receiver) = mpsc::channel();
+    #[test]
+    fn watch_tips_follow_look_ahead() {
+        setup_logger();
+        let look_ahead = 7;
+        let mock = CoinStoreMock::new(3, 2, look_ahead);
+        let store = mock.store.lock().expect("poisoned");
+        assert_eq!(store.recv_tip(), 3);
+        assert_eq!(store.change_tip(), 2);
+        assert_eq!(store.recv_watch_tip(), 3 + look_ahead + 1);
+        assert_eq!(store.change_watch_tip(), 2 + look_ahead + 1);
+    }
 
-            let mnemonic = bip39::Mnemonic::generate(12).unwrap();
-            let stop = Arc::new(AtomicBool::new(false));
-            let signer =
-                HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string())
-                    .unwrap();
-            let xpub = signer.xpub(&DerivationPath::from_str("m/84'/0'/0'/1").unwrap());
-            let descriptor = wpkh(xpub);
-            let derivator = Derivator::new(descriptor.clone(), bitcoin::Network::Regtest).unwrap();
+    #[test]
+    fn look_ahead_per_keychain() {
+        setup_logger();
+        let (recv_look_ahead, change_look_ahead) = (8, 3);
+        let mock = CoinStoreMock::new_with_look_aheads(recv_look_ahead, change_look_ahead);
+        {
+            let store = mock.store.lock().expect("poisoned");
+            assert_eq!(store.recv_watch_tip(), recv_look_ahead + 1);
+            assert_eq!(store.change_watch_tip(), change_look_ahead + 1);
+        }
+        thread::sleep(Duration::from_millis(500));
 
-            let tx_store = TxStore::new(Default::default(), None);
-            let label_store = Arc::new(Mutex::new(LabelStore::new()));
-            let coin_store = Arc::new(Mutex::new(CoinStore::new(
-                bitcoin::Network::Regtest,
-                descriptor.clone(),
-                notif_sender.clone(),
-                recv_tip,
-                change_tip,
-                look_ahead,
-                tx_store,
-                label_store,
-                None,
-            )));
-            coin_store.lock().expect("poisoned").init(tip_sender);
-            let store = coin_store.clone();
-            let cloned_stop = stop.clone();
-            let cloned_derivator = derivator.clone();
+        let subscribed = if let Ok(CoinRequest::Subscribe(v)) = mock.request.try_recv() {
+            v
+        } else {
+            panic!()
+        };
+        let count = |spk: &dyn Fn(u32) -> ScriptBuf| {
+            (0..20).filter(|i| subscribed.contains(&spk(*i))).count() as u32
+        };
+        let recv = count(&|i| mock.derivator.receive_spk_at(i));
+        let change = count(&|i| mock.derivator.change_spk_at(i));
+        assert_eq!(recv, recv_look_ahead + 1);
+        assert_eq!(change, change_look_ahead + 1);
+        assert_eq!(subscribed.len() as u32, recv + change);
+    }
 
-            let listener_handle = thread::spawn(move || {
-                listen_txs(
-                    coin_store,
-                    cloned_derivator,
-                    notif_sender,
-                    tip_receiver,
-                    stop,
-                    req_sender,
-                    resp_receiver,
-                    None,
-                );
-            });
+    #[test]
+    fn refresh() {
+        setup_logger();
+        let look_ahead = 5;
+        let mock = CoinStoreMock::new(0, 0, look_ahead);
+        thread::sleep(Duration::from_millis(500));
+        assert!(!mock.listener.is_finished());
 
-            CoinStoreMock {
-                store,
-                notif: notif_recv,
-                request: req_receiver,
-                response: resp_sender,
-                listener: listener_handle,
-                stop: cloned_stop,
-                derivator,
-            }
+        let subscribed = if let Ok(CoinRequest::Subscribe(v)) = mock.request.try_recv() {
+            v
+        } else {
+            panic!()
+        };
+        assert!(mock.request.try_recv().is_err());
+
+        // a refresh re-subscribe all the watched scripts
+        mock.control.send(ListenerControl::Refresh).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        if let Ok(CoinRequest::Subscribe(mut v)) = mock.request.try_recv() {
+            let mut subscribed = subscribed;
+            subscribed.sort();
+            v.sort();
+            assert_eq!(v, subscribed);
+        } else {
+            panic!()
         }
+    }
 
-        fn coins(&mut self) -> BTreeMap<OutPoint, CoinEntry> {
-            self.store.lock().expect("poisoned").coins()
+    #[test]
+    fn scan_from_checkpoint() {
+        setup_logger();
+        let look_ahead = 5;
+        let checkpoint = 1000;
+        let mock = CoinStoreMock::new_with_checkpoint(
+            0,
+            0,
+            look_ahead,
+            Tip {
+                receive: checkpoint,
+                change: 0,
+            },
+        );
+        thread::sleep(Duration::from_millis(500));
+        assert!(!mock.listener.is_finished());
+
+        // scripts around the checkpoint are subscribed first
+        if let Ok(CoinRequest::Subscribe(v)) = mock.request.try_recv() {
+            assert!(v.contains(&mock.derivator.receive_spk_at(checkpoint)));
+            assert!(v.contains(&mock.derivator.receive_spk_at(checkpoint + look_ahead)));
+            assert!(v.contains(&mock.derivator.change_spk_at(0)));
+            assert!(!v.contains(&mock.derivator.receive_spk_at(0)));
+            assert!(!v.contains(&mock.derivator.receive_spk_at(checkpoint - 1)));
+        } else {
+            panic!()
         }
 
-        fn stop(&self) {
-            self.stop.store(true, Ordering::Relaxed);
+        // then earlier scripts are lazily subscribed
+        if let Ok(CoinRequest::Subscribe(v)) = mock.request.try_recv() {
+            assert_eq!(v.len(), checkpoint as usize);
+            assert!(v.contains(&mock.derivator.receive_spk_at(0)));
+            assert!(v.contains(&mock.derivator.receive_spk_at(checkpoint - 1)));
+        } else {
+            panic!()
         }
     }
 
     #[test]
-    fn simple_start_stop() {
+    fn secondary_descriptor() {
         setup_logger();
-        let mock = CoinStoreMock::new(0, 0, 20);
-        thread::sleep(Duration::from_millis(10));
+        let mut mock = CoinStoreMock::new_with_secondary(0, 0, 5);
+        thread::sleep(Duration::from_millis(500));
         assert!(!mock.listener.is_finished());
-        assert!(matches!(
-            mock.notif.try_recv().unwrap(),
-            Notification::AddressTipChanged,
-        ));
-        assert!(matches!(
-            mock.notif.try_recv().unwrap(),
-            Notification::Electrum(TxListenerNotif::Started)
-        ));
-        mock.stop();
-        thread::sleep(Duration::from_secs(1));
-        assert!(mock.listener.is_finished());
+
+        let wpkh_spk = mock.derivator.receive_spk_at(0);
+        let tr_spk = mock.secondary.as_ref().unwrap().receive_spk_at(0);
+        assert_ne!(wpkh_spk, tr_spk);
+
+        // scripts of both descriptors are subscribed
+        if let Ok(CoinRequest::Subscribe(v)) = mock.request.try_recv() {
+            assert!(v.contains(&wpkh_spk));
+            assert!(v.contains(&tr_spk));
+        } else {
+            panic!()
+        }
+
+        let tx_0 = funding_tx(wpkh_spk.clone(), 0.1);
+        let tx_1 = funding_tx(tr_spk.clone(), 0.2);
+        {
+            let mut store = mock.store.lock().expect("poisoned");
+            let mut history = BTreeMap::new();
+            history.insert(wpkh_spk.clone(), vec![(tx_0.compute_txid(), Some(1))]);
+            history.insert(tr_spk.clone(), vec![(tx_1.compute_txid(), Some(1))]);
+            let (_, missing) = store.handle_history_response(history);
+            assert_eq!(missing.len(), 2);
+            store.handle_txs_response(vec![tx_0, tx_1]);
+
+            // coins are classified by the descriptor owning the spk
+            assert!(!store.address_info(&wpkh_spk).unwrap().is_secondary());
+            assert!(store.address_info(&tr_spk).unwrap().is_secondary());
+        }
+
+        let coins = mock.coins();
+        assert_eq!(coins.len(), 2);
+        assert!(coins.values().any(|c| c.spk() == wpkh_spk));
+        assert!(coins.values().any(|c| c.spk() == tr_spk));
+
+        // the address of a coin keeps the descriptor it is derived from
+        let store = mock.store.lock().expect("poisoned");
+        for coin in coins.values() {
+            let address = coin.rust_address(&store);
+            assert_eq!(address.secondary, coin.spk() == tr_spk);
+        }
     }
 
     fn simple_recv() -> (bitcoin::Transaction, CoinStoreMock) {
@@ -2243,8 +5819,9 @@ mod tests {
         assert_eq!(coins.len(), 1);
         let coin = coins.pop_first().unwrap().1;
 
-        // the coin is unconfirmed
+        // the coin is spent by tx_1
         assert_eq!(coin.status(), CoinStatus::Spent);
+        assert_eq!(coin.spent_by(), Some(tx_1.compute_txid()));
     }
 
     #[test]
@@ -2289,4 +5866,490 @@ mod tests {
         // the coin have a confirmation height of 2
         assert_eq!(coin.height(), Some(2));
     }
+
+    #[test]
+    fn edit_coin_labels() {
+        setup_logger();
+        let name = "edit_coin_labels".to_string();
+        let _ = std::fs::remove_dir_all(Config::path(name.clone()));
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let config = crate::config::new_config(
+            mnemonic.to_string(),
+            name,
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        let account = Account::new(*config);
+        let generations = || account.coin_store.lock().expect("poisoned").generations();
+        let before = generations();
+
+        let outpoints: Vec<_> = (0..5).map(|i| OutPoint::new(txid(i), i as u32)).collect();
+        account.edit_coin_labels(
+            outpoints
+                .iter()
+                .map(|op| (op.to_string(), format!("label {}", op.vout)))
+                .chain(Some(("not an outpoint".to_string(), "skipped".to_string())))
+                .collect(),
+        );
+
+        // all the labels are edited with a single regeneration
+        assert_eq!(generations(), before + 1);
+        {
+            let store = account.label_store.lock().expect("poisoned");
+            for op in &outpoints {
+                assert_eq!(store.outpoint(*op), Some(format!("label {}", op.vout)));
+            }
+            assert_eq!(store.labels().len(), 5);
+        }
+
+        // an empty label removes the label
+        account.edit_coin_labels(vec![(outpoints[0].to_string(), String::new())]);
+        assert_eq!(generations(), before + 2);
+        let store = account.label_store.lock().expect("poisoned");
+        assert_eq!(store.outpoint(outpoints[0]), None);
+        assert_eq!(store.labels().len(), 4);
+    }
+
+    #[test]
+    fn address_batches() {
+        let name = "address_batches".to_string();
+        let _ = std::fs::remove_dir_all(Config::path(name.clone()));
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let config = crate::config::new_config(
+            mnemonic.to_string(),
+            name,
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        let account = Account::new(*config);
+
+        let recv = account.recv_addrs(5, 10);
+        assert_eq!(recv.len(), 10);
+        let change = account.change_addrs(5, 10);
+        assert_eq!(change.len(), 10);
+        for (i, index) in (5..15).enumerate() {
+            assert_eq!(recv[i], account.recv_addr_at(index));
+            assert_eq!(change[i], account.change_addr_at(index));
+        }
+        assert!(account.recv_addrs(0, 0).is_empty());
+
+        // the batch stops at the last non-hardened index
+        let last = (1 << 31) - 1;
+        assert_eq!(account.recv_addrs(last - 1, 10).len(), 2);
+        assert!(account.change_addrs(u32::MAX, 10).is_empty());
+    }
+
+    #[test]
+    fn receive_uri() {
+        let name = "receive_uri".to_string();
+        let _ = std::fs::remove_dir_all(Config::path(name.clone()));
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let config = crate::config::new_config(
+            mnemonic.to_string(),
+            name,
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        let account = Account::new(*config);
+        let address = account.recv_addr_at(3);
+
+        assert_eq!(
+            account.receive_uri(3, 0, String::new()),
+            format!("bitcoin:{address}")
+        );
+
+        let uri = account.receive_uri(3, 150_000, "Coffee & cake".to_string());
+        let (addr, query) = uri
+            .strip_prefix("bitcoin:")
+            .unwrap()
+            .split_once('?')
+            .unwrap();
+        let params: BTreeMap<_, _> = query
+            .split('&')
+            .map(|p| p.split_once('=').unwrap())
+            .collect();
+        assert_eq!(addr, address);
+        let addr = bitcoin::Address::from_str(addr)
+            .unwrap()
+            .require_network(bitcoin::Network::Regtest)
+            .unwrap();
+        assert_eq!(addr, account.recv_at(3));
+        let amount =
+            bitcoin::Amount::from_str_in(params["amount"], bitcoin::Denomination::Bitcoin).unwrap();
+        assert_eq!(amount.to_sat(), 150_000);
+        assert_eq!(params["label"], "Coffee%20%26%20cake");
+    }
+
+    #[test]
+    fn degenerate_descriptor_satisfaction() {
+        let name = "degenerate_descriptor_satisfaction".to_string();
+        let _ = std::fs::remove_dir_all(Config::path(name.clone()));
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let mut config = crate::config::new_config(
+            mnemonic.to_string(),
+            name,
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        let signer =
+            HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string())
+                .unwrap();
+        let xpub = signer.xpub(&DerivationPath::from_str("m/84'/1'/0'").unwrap());
+        // the script can never be satisfied
+        let descriptor = format!(
+            "wsh(and_v(v:pk([{}/{}]{}/<0;1>/*),0))",
+            xpub.origin.0, xpub.origin.1, xpub.xkey
+        );
+        config.descriptor = Descriptor::<DescriptorPublicKey>::from_str(&descriptor).unwrap();
+        let account = Account::new(*config);
+
+        assert!(matches!(
+            account.input_satisfaction_size(),
+            Err(Error::Satisfaction)
+        ));
+        let tx = funding_tx(account.recv_at(0).script_pubkey(), 0.1);
+        assert!(matches!(
+            account.tx_estimated_weight(&tx),
+            Err(Error::Satisfaction)
+        ));
+        assert!(account.uneconomical_coins(10.0).is_empty());
+    }
+
+    #[test]
+    fn import_single_path_keychains() {
+        let name = "import_single_path_keychains".to_string();
+        let _ = std::fs::remove_dir_all(Config::path(name.clone()));
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let mut config = crate::config::new_config(
+            mnemonic.to_string(),
+            name,
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        let signer =
+            HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string())
+                .unwrap();
+        let xpub = signer.xpub(&DerivationPath::from_str("m/84'/1'/0'").unwrap());
+        let key = format!("[{}/{}]{}", xpub.origin.0, xpub.origin.1, xpub.xkey);
+        let receive = format!("wpkh({key}/0/*)");
+        let change = format!("wpkh({key}/1/*)");
+
+        let descriptor = crate::config::multipath_descriptor(receive.clone(), change.clone());
+        assert!(!descriptor.is_empty());
+        config.descriptor = Descriptor::<DescriptorPublicKey>::from_str(&descriptor).unwrap();
+        let account = Account::new(*config);
+
+        let address = |descr: &str, index: u32| {
+            Descriptor::<DescriptorPublicKey>::from_str(descr)
+                .unwrap()
+                .at_derivation_index(index)
+                .unwrap()
+                .address(bitcoin::Network::Regtest)
+                .unwrap()
+        };
+        for index in [0, 7] {
+            assert_eq!(account.recv_at(index), address(&receive, index));
+            assert_eq!(account.change_at(index), address(&change, index));
+        }
+
+        // descriptors of different keys are not merged
+        let other = signer.xpub(&DerivationPath::from_str("m/84'/1'/1'").unwrap());
+        assert!(
+            crate::config::multipath_descriptor(receive, format!("wpkh({}/1/*)", other.xkey))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn pools_while_store_locked() {
+        let name = "pools_while_store_locked".to_string();
+        let _ = std::fs::remove_dir_all(Config::path(name.clone()));
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let config = crate::config::new_config(
+            mnemonic.to_string(),
+            name,
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        let account = Account::new(*config);
+        account.pools.write().expect("poisoned").push(RustPool {
+            denomination: 100_000,
+            total_peers: 5,
+            current_peers: 0,
+            relay: "ws://127.0.0.1".to_string(),
+            fees: 10,
+            id: "pool".to_string(),
+            status: PoolStatus::Available,
+            role: crate::cpp_joinstr::PoolRole::None,
+            timeout: 0,
+        });
+
+        // a worker holds the store for an update
+        let store = account.pool_store.clone();
+        let (locked_tx, locked_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let worker = thread::spawn(move || {
+            let _lock = store.lock().expect("poisoned");
+            locked_tx.send(()).unwrap();
+            let _ = release_rx.recv();
+        });
+        locked_rx.recv().unwrap();
+
+        let pools = account.pools();
+        assert!(pools.is_ok());
+        let pools = pools.value();
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].id, "pool");
+
+        release_tx.send(()).unwrap();
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn split_keychains_single_path() {
+        let xpub = "tpubD6NzVbkrYhZ4WaWSyoBvQwbpLkojyoTZPRsgXELWz3Popb3qkjcJyJUGLnL4qHHoQvao8ESaAstxYSnhyswJ76uZPStJRJCTKvosUCJZL5B";
+        let multipath =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("wpkh({xpub}/<0;1>/*)")).unwrap();
+        let (receive, change) = split_keychains(multipath).unwrap();
+        assert!(receive
+            .to_string()
+            .starts_with(&format!("wpkh({xpub}/0/*)")));
+        assert!(change.to_string().starts_with(&format!("wpkh({xpub}/1/*)")));
+
+        // a single path descriptor is an error, not a panic
+        let single =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("wpkh({xpub}/0/*)")).unwrap();
+        assert_eq!(
+            split_keychains(single).unwrap_err(),
+            "Descriptor do not have a receive and a change keychain"
+        );
+
+        // as well as a multipath with extra keychains
+        let extra = Descriptor::<DescriptorPublicKey>::from_str(&format!("wpkh({xpub}/<0;1;2>/*)"))
+            .unwrap();
+        assert!(split_keychains(extra).is_err());
+    }
+
+    #[test]
+    fn utxo_histogram() {
+        let values = [500, 1_000, 5_000, 20_000, 99_999, 100_000, 3_000_000];
+        let buckets = vec![100_000, 1_000, 10_000];
+        assert_eq!(
+            histogram(values.into_iter(), buckets),
+            vec![(1_000, 2), (10_000, 2), (100_000, 2)]
+        );
+
+        // duplicated bounds are merged
+        assert_eq!(
+            histogram(values.into_iter(), vec![0, 0, 1_000_000]),
+            vec![(0, 6), (1_000_000, 1)]
+        );
+        assert!(histogram(values.into_iter(), vec![]).is_empty());
+        assert_eq!(histogram(std::iter::empty(), vec![1_000]), vec![(1_000, 0)]);
+    }
+
+    #[test]
+    fn csv_field_quoting() {
+        assert_eq!(csv_field("rent"), "rent");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("rent, march"), "\"rent, march\"");
+        assert_eq!(csv_field("the \"shop\""), "\"the \"\"shop\"\"\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+    }
+
+    #[test]
+    fn input_output_vbytes() {
+        // p2wpkh: 41 vB + 108 WU of witness rounded up
+        assert_eq!(input_vbytes(108), 68);
+        // p2tr key path
+        assert_eq!(input_vbytes(66), 58);
+        // p2wpkh & p2tr outputs
+        assert_eq!(txout_vbytes(22), 31);
+        assert_eq!(txout_vbytes(34), 43);
+    }
+
+    #[test]
+    fn confirmation_target_fee_curve() {
+        let curve: BTreeMap<u16, f64> = [(1, 50.0), (2, 30.0), (3, 20.0), (6, 10.0), (144, 2.0)]
+            .into_iter()
+            .collect();
+        assert_eq!(confirmation_target(&curve, 100.0), Some(1));
+        assert_eq!(confirmation_target(&curve, 30.0), Some(2));
+        assert_eq!(confirmation_target(&curve, 25.0), Some(3));
+        assert_eq!(confirmation_target(&curve, 10.0), Some(6));
+        assert_eq!(confirmation_target(&curve, 5.0), Some(144));
+        assert_eq!(confirmation_target(&curve, 1.0), None);
+        assert_eq!(confirmation_target(&BTreeMap::new(), 10.0), None);
+
+        // a higher fee rate never confirms slower
+        let mut previous = u16::MAX;
+        for feerate in (2..=60).map(f64::from) {
+            let target = confirmation_target(&curve, feerate).unwrap();
+            assert!(target <= previous);
+            previous = target;
+        }
+    }
+
+    #[test]
+    fn coinjoin_signer_signs_pool_input() {
+        let network = bitcoin::Network::Regtest;
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap().to_string();
+        let wallet = HotSigner::new_from_mnemonics(network, &mnemonic).unwrap();
+        let account_xpub = wallet.xpub(&DerivationPath::from_str("m/84'/1'/0'").unwrap());
+        let derivator = Derivator::new(wpkh(account_xpub.clone()), network).unwrap();
+
+        // a wallet coin spent by a pool transaction along with the coin of
+        // another peer
+        let coin = Coin {
+            txout: TxOut {
+                value: Amount::from_sat(100_500),
+                script_pubkey: derivator.receive_spk_at(3),
+            },
+            outpoint: OutPoint::new(txid(1), 0),
+            sequence: bitcoin::Sequence::MAX,
+            coin_path: (AddrAccount::Receive, 3),
+        };
+        let output = derivator.change_spk_at(5);
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![
+                bitcoin::TxIn {
+                    previous_output: coin.outpoint,
+                    sequence: coin.sequence,
+                    ..Default::default()
+                },
+                random_input(),
+            ],
+            output: vec![
+                TxOut {
+                    value: Amount::from_sat(100_000),
+                    script_pubkey: output.clone(),
+                },
+                random_output(),
+            ],
+        };
+        let sighash = bitcoin::sighash::SighashCache::new(&tx)
+            .p2wpkh_signature_hash(
+                0,
+                &coin.txout.script_pubkey,
+                coin.txout.value,
+                EcdsaSighashType::All,
+            )
+            .unwrap();
+        let msg = bitcoin::secp256k1::Message::from(sighash);
+        let sign = |signer: &HotSigner| {
+            let path = coinjoin_signing_path(network, coin.coin_path);
+            secp.sign_ecdsa(&msg, &signer.xpriv(&path).xkey.private_key)
+        };
+
+        // the key locking the coin, derived from the descriptor xpub
+        let expected = account_xpub
+            .xkey
+            .derive_pub(&secp, &DerivationPath::from_str("m/0/3").unwrap())
+            .unwrap()
+            .public_key;
+        assert_eq!(
+            ScriptBuf::new_p2wpkh(&CompressedPublicKey(expected).wpubkey_hash()),
+            coin.txout.script_pubkey
+        );
+
+        // a coinjoin signer of the wallet mnemonic signs the input with the
+        // key of the coin and owns the output
+        check_coinjoin_signer(&wallet, network, &coin.txout.script_pubkey, coin.coin_path).unwrap();
+        check_coinjoin_signer(&wallet, network, &output, (AddrAccount::Change, 5)).unwrap();
+        assert!(secp.verify_ecdsa(&msg, &sign(&wallet), &expected).is_ok());
+
+        // a coinjoin signer of another mnemonic is refused, its signature
+        // would not be valid
+        let other = HotSigner::new(network).unwrap();
+        assert!(matches!(
+            check_coinjoin_signer(&other, network, &coin.txout.script_pubkey, coin.coin_path),
+            Err(Error::CoinjoinSigner)
+        ));
+        assert!(matches!(
+            check_coinjoin_signer(&other, network, &output, (AddrAccount::Change, 5)),
+            Err(Error::CoinjoinSigner)
+        ));
+        assert!(secp.verify_ecdsa(&msg, &sign(&other), &expected).is_err());
+
+        // the coinjoin signer only signs wpkh inputs, a coin of a taproot
+        // descriptor of the same mnemonic is refused
+        let secondary = tr(wallet.xpub(&DerivationPath::from_str("m/86'/1'/0'").unwrap()));
+        let secondary = Derivator::new(secondary, network).unwrap();
+        assert!(matches!(
+            check_coinjoin_signer(
+                &wallet,
+                network,
+                &secondary.receive_spk_at(3),
+                (AddrAccount::Receive, 3)
+            ),
+            Err(Error::CoinjoinSigner)
+        ));
+    }
+
+    #[test]
+    fn nostr_subscription_lookback() {
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let mut config = *crate::config::new_config(
+            mnemonic.to_string(),
+            "nostr_subscription_lookback".to_string(),
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        assert_eq!(nostr_subscription(&config), None);
+        config.nostr_relay = Some("wss://relay.example".to_string());
+        assert_eq!(nostr_subscription(&config), None);
+
+        // a valid lookback persisted by the setter reaches the subscription
+        config.set_nostr_back("3600".to_string());
+        config.to_file().unwrap();
+        let loaded = Config::from_file(config.account.clone());
+        assert_eq!(
+            nostr_subscription(&loaded),
+            Some(("wss://relay.example".to_string(), 3600))
+        );
+
+        // an out of range lookback is clamped at load
+        config.nostr_back = Some(MAX_NOSTR_BACK + 1);
+        assert_eq!(nostr_subscription(&config), None);
+        config.to_file().unwrap();
+        let loaded = Config::from_file(config.account.clone());
+        assert_eq!(
+            nostr_subscription(&loaded),
+            Some(("wss://relay.example".to_string(), MAX_NOSTR_BACK))
+        );
+    }
+
+    #[test]
+    fn pool_listener_author_filter() {
+        let fixture: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/pool_entry.json")).unwrap();
+        let pool: Pool = serde_json::from_value(fixture["pool"].clone()).unwrap();
+        let author = pool.public_key.to_string();
+        let other = "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9".to_string();
+
+        let pool_store = Mutex::new(PoolStore::new(None));
+        let (sender, receiver) = notification_channel();
+
+        // a pool of another publisher is dropped
+        assert!(store_pool(
+            pool.clone(),
+            &[other.clone()],
+            &pool_store,
+            &sender
+        ));
+        assert!(pool_store.lock().unwrap().get(&pool.id).is_none());
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+
+        // a pool of an allowed publisher is stored, any publisher is
+        // allowed if none is configured
+        for authors in [vec![other, author.to_uppercase()], vec![]] {
+            let pool_store = Mutex::new(PoolStore::new(None));
+            assert!(store_pool(pool.clone(), &authors, &pool_store, &sender));
+            assert!(pool_store.lock().unwrap().get(&pool.id).is_some());
+            assert!(matches!(
+                receiver.try_recv(),
+                Ok(Notification::Joinstr(JoinstrNotif::PoolUpdate))
+            ));
+        }
+
+        // the listener stops once the receiver is gone
+        drop(receiver);
+        assert!(!store_pool(pool, &[], &pool_store, &sender));
+    }
 }
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    account::{new_account, Account, Poll},
+    config::config_exists,
+};
+
+/// Owns several accounts keyed by name and multiplexes their
+/// notifications.
+#[derive(Default)]
+pub struct AccountManager {
+    accounts: BTreeMap<String, Account>,
+    /// Name of the last account a notification has been received from,
+    /// accounts are polled in turn starting after it.
+    last_polled: Option<String>,
+}
+
+// Rust only interface
+impl AccountManager {
+    /// Adds an account to the manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account, keyed by the name of its config.
+    ///
+    /// # Returns
+    ///
+    /// `false` if an account with the same name is already managed, the
+    /// account is then dropped.
+    pub fn add_account(&mut self, account: Account) -> bool {
+        let name = account.get_config().account;
+        if self.accounts.contains_key(&name) {
+            return false;
+        }
+        self.accounts.insert(name, account);
+        true
+    }
+
+    /// Returns a reference to a managed account.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the account.
+    pub fn account(&self, name: &str) -> Option<&Account> {
+        self.accounts.get(name)
+    }
+
+    /// Returns a mutable reference to a managed account.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the account.
+    pub fn account_mut(&mut self, name: &str) -> Option<&mut Account> {
+        self.accounts.get_mut(name)
+    }
+}
+
+// C++ shared interface
+impl AccountManager {
+    /// Loads the account `name` from its config file and adds it to the
+    /// manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the account.
+    ///
+    /// # Returns
+    ///
+    /// `false` if there is no such account or if it is already managed.
+    pub fn open_account(&mut self, name: String) -> bool {
+        if self.accounts.contains_key(&name) || !config_exists(name.clone()) {
+            return false;
+        }
+        self.add_account(*new_account(name))
+    }
+
+    /// Stops and removes an account from the manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the account.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the account is not managed.
+    pub fn remove_account(&mut self, name: String) -> bool {
+        match self.accounts.remove(&name) {
+            Some(mut account) => {
+                account.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a managed account.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the account.
+    ///
+    /// # Errors
+    ///
+    /// An error message if the account is not managed.
+    pub fn managed_account(&mut self, name: String) -> Result<&mut Account, String> {
+        self.accounts
+            .get_mut(&name)
+            .ok_or_else(|| format!("Account {name} is not managed"))
+    }
+
+    /// Returns the names of the managed accounts.
+    pub fn accounts(&self) -> Vec<String> {
+        self.accounts.keys().cloned().collect()
+    }
+
+    /// Attempts to receive a notification from any of the managed accounts.
+    ///
+    /// Accounts are polled in turn, so a busy account cannot starve the
+    /// others.
+    ///
+    /// # Returns
+    ///
+    /// A boxed `Poll` instance, the signal is tagged with the name of the
+    /// account it comes from and errors are prefixed with it.
+    pub fn try_recv(&mut self) -> Box<Poll> {
+        let names = self.accounts();
+        let start = self
+            .last_polled
+            .as_ref()
+            .and_then(|last| names.iter().position(|n| n == last))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        for name in names.iter().cycle().skip(start).take(names.len()) {
+            let poll = self
+                .accounts
+                .get_mut(name)
+                .expect("managed account")
+                .try_recv();
+            if poll.is_ok() {
+                self.last_polled = Some(name.clone());
+                let mut signal = poll.value();
+                signal.set_account(name.clone());
+                return Poll::ok(signal).boxed();
+            } else if poll.is_err() {
+                self.last_polled = Some(name.clone());
+                return Poll::err(&format!("{name}: {}", poll.error())).boxed();
+            }
+        }
+        Poll::new().boxed()
+    }
+
+    /// Returns the balance of all the managed accounts.
+    ///
+    /// # Returns
+    ///
+    /// The sum in sats of the confirmed and unconfirmed spendable coins of
+    /// every account.
+    pub fn total_balance(&self) -> u64 {
+        self.accounts
+            .values()
+            .map(|a| {
+                let state = a.spendable_coins();
+                state.confirmed_balance + state.unconfirmed_balance
+            })
+            .sum()
+    }
+}
+
+/// Creates a new account manager without accounts.
+///
+/// # Returns
+///
+/// A boxed `AccountManager` instance.
+pub fn new_account_manager() -> Box<AccountManager> {
+    Box::new(AccountManager::default())
+}
@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, sync::mpsc};
 
 use crate::{
-    account::Notification,
+    account::{Notification, NotificationSender},
+    config::Tip,
     cpp_joinstr::{AddrAccount, AddressStatus, RustAddress},
     derivator::Derivator,
     Config,
@@ -15,9 +16,13 @@ use crate::{
 /// # Fields
 /// - `recv`: Last generated receiving address index.
 /// - `change`: Last generated change address index.
+/// - `recv_floor`: Receiving index the scan should start from.
+/// - `change_floor`: Change index the scan should start from.
 pub struct AddressTip {
     pub recv: u32,
     pub change: u32,
+    pub recv_floor: u32,
+    pub change_floor: u32,
 }
 
 #[derive(Debug)]
@@ -34,15 +39,28 @@ pub struct AddressTip {
 /// - `notification`: Channel for sending notifications about address
 ///   tips changes.
 /// - `tx_listener`: Optional channel for sending address tip changes.
-/// - `look_ahead`: Number of addresses to generate ahead of the current tip.
+/// - `recv_look_ahead`: Number of receiving addresses to generate ahead of
+///   the current tip.
+/// - `change_look_ahead`: Number of change addresses to generate ahead of
+///   the current tip.
+/// - `scan_from`: Scan checkpoint, addresses are watched at least up to
+///   this checkpoint plus the look-ahead.
+/// - `secondary`: Optional derivator of a secondary descriptor, its
+///   addresses are watched at the same indexes as the main ones.
+/// - `secondary_tip`: Last generated receiving and change indexes of the
+///   secondary descriptor, the watch tips cover both descriptors.
 pub struct AddressStore {
     store: BTreeMap<ScriptBuf, AddressEntry>,
     recv_generated_tip: u32,
     change_generated_tip: u32,
+    scan_from: Tip,
     derivator: Derivator,
-    notification: mpsc::Sender<Notification>,
+    secondary: Option<Derivator>,
+    secondary_tip: Tip,
+    notification: NotificationSender,
     tx_listener: Option<mpsc::Sender<AddressTip>>,
-    look_ahead: u32,
+    recv_look_ahead: u32,
+    change_look_ahead: u32,
     config: Option<Config>,
 }
 
@@ -51,31 +69,47 @@ impl AddressStore {
     ///
     /// # Parameters
     /// - `signer`: The signer used to generate new addresses.
+    /// - `secondary`: The optional derivator of a secondary descriptor.
     /// - `notification`: A channel for sending notifications about address
     ///   tip changes.
     /// - `recv_tip`: The initial index for receiving address generation.
     /// - `change_tip`: The initial index for change address generation.
-    /// - `look_ahead`: The number of addresses to generate ahead of the
-    ///   current tip.
+    /// - `recv_look_ahead`: The number of receiving addresses to generate
+    ///   ahead of the current tip.
+    /// - `change_look_ahead`: The number of change addresses to generate
+    ///   ahead of the current tip.
+    /// - `scan_from`: The scan checkpoint, it do not mark addresses as used.
     ///
     /// # Returns
     /// A new instance of `AddressStore`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         derivator: Derivator,
-        notification: mpsc::Sender<Notification>,
+        secondary: Option<Derivator>,
+        notification: NotificationSender,
         recv_tip: u32,
         change_tip: u32,
-        look_ahead: u32,
+        recv_look_ahead: u32,
+        change_look_ahead: u32,
+        scan_from: Tip,
         config: Option<Config>,
     ) -> Self {
+        let secondary_tip = match (&secondary, &config) {
+            (Some(_), Some(config)) => config.secondary_tip_from_file(),
+            _ => Tip::default(),
+        };
         let store = Self {
             derivator,
+            secondary,
+            secondary_tip,
             store: BTreeMap::new(),
             recv_generated_tip: recv_tip,
             change_generated_tip: change_tip,
+            scan_from,
             notification,
             tx_listener: None,
-            look_ahead,
+            recv_look_ahead,
+            change_look_ahead,
             config,
         };
         store.update_watch_tip();
@@ -98,14 +132,30 @@ impl AddressStore {
     /// This method sends the current address tips to the transaction listener.
     fn update_watch_tip(&self) {
         if let Some(tx_listener) = &self.tx_listener {
-            let recv = self.recv_watch_tip();
-            let change = self.change_watch_tip();
             // NOTE: tx_listener thread must send notification itself if
             // fail to connect to electrum
-            let _ = tx_listener.send(AddressTip { recv, change });
+            let _ = tx_listener.send(self.address_tip());
         }
         if let Some(config) = &self.config {
             config.persist_tip(self.recv_generated_tip, self.change_generated_tip);
+            if self.secondary.is_some() {
+                config.persist_secondary_tip(self.secondary_tip);
+            }
+        }
+    }
+
+    /// Returns the current watch tips along with the scan checkpoint floors.
+    ///
+    /// # Returns
+    /// An `AddressTip` describing the scripts to watch.
+    pub fn address_tip(&self) -> AddressTip {
+        let recv = self.recv_watch_tip();
+        let change = self.change_watch_tip();
+        AddressTip {
+            recv,
+            change,
+            recv_floor: self.scan_from.receive.min(recv),
+            change_floor: self.scan_from.change.min(change),
         }
     }
 
@@ -117,43 +167,74 @@ impl AddressStore {
     /// # Panics
     /// This function panics if the script public key is not found in the store.
     pub fn recv_coin_at(&mut self, spk: &ScriptBuf) {
-        let AddressEntry { account, index, .. } = self.store.get(spk).expect("must be there");
-        match *account {
-            AddrAccount::Receive => self.update_recv(*index),
-            AddrAccount::Change => self.update_change(*index),
+        let AddressEntry {
+            account,
+            index,
+            secondary,
+            ..
+        } = self.store.get(spk).expect("must be there");
+        let (account, index) = (*account, *index);
+        if *secondary {
+            self.update_secondary(account, index);
+            return;
+        }
+        match account {
+            AddrAccount::Receive => {
+                self.update_recv(index);
+                // NOTE: the last handed out address is used, the next one
+                // is handed out right away if configured so
+                let auto_new = self.config.as_ref().is_some_and(|c| c.auto_new_recv_addr);
+                if auto_new && index == self.recv_generated_tip {
+                    self.new_recv_addr();
+                }
+            }
+            AddrAccount::Change => self.update_change(index),
             _ => unreachable!(),
         }
     }
 
     /// Populates the address store with addresses up to the current watch tips.
     ///
-    /// This method generates receiving and change addresses and adds them if not present.
+    /// This method generates receiving and change addresses, for the main
+    /// descriptor and the secondary one if any, and adds them if not present.
     pub fn populate_maybe(&mut self) {
-        for i in 0..self.recv_watch_tip() + 1 {
-            let addr = self.derivator.receive_at(i);
-            let script = addr.script_pubkey();
-            self.store.entry(script).or_insert_with(|| {
-                let address = addr.as_unchecked().clone();
-                AddressEntry {
-                    status: AddressStatus::NotUsed,
-                    address,
-                    account: AddrAccount::Receive,
-                    index: i,
-                }
-            });
-        }
-        for i in 0..self.change_watch_tip() + 1 {
-            let addr = self.derivator.change_at(i);
-            let script = addr.script_pubkey();
-            self.store.entry(script).or_insert_with(|| {
-                let address = addr.as_unchecked().clone();
-                AddressEntry {
-                    status: AddressStatus::NotUsed,
-                    address,
-                    account: AddrAccount::Change,
-                    index: i,
-                }
-            });
+        let recv_tip = self.recv_watch_tip();
+        let change_tip = self.change_watch_tip();
+        let derivators = [
+            Some((&self.derivator, false)),
+            self.secondary.as_ref().map(|d| (d, true)),
+        ];
+        for (derivator, secondary) in derivators.into_iter().flatten() {
+            for i in 0..recv_tip + 1 {
+                let addr = derivator.receive_at(i);
+                let script = addr.script_pubkey();
+                self.store.entry(script).or_insert_with(|| {
+                    let address = addr.as_unchecked().clone();
+                    AddressEntry {
+                        status: AddressStatus::NotUsed,
+                        address,
+                        account: AddrAccount::Receive,
+                        index: i,
+                        secondary,
+                        derivation_path: derivator.derivation_path(AddrAccount::Receive, i),
+                    }
+                });
+            }
+            for i in 0..change_tip + 1 {
+                let addr = derivator.change_at(i);
+                let script = addr.script_pubkey();
+                self.store.entry(script).or_insert_with(|| {
+                    let address = addr.as_unchecked().clone();
+                    AddressEntry {
+                        status: AddressStatus::NotUsed,
+                        address,
+                        account: AddrAccount::Change,
+                        index: i,
+                        secondary,
+                        derivation_path: derivator.derivation_path(AddrAccount::Change, i),
+                    }
+                });
+            }
         }
     }
 
@@ -180,6 +261,22 @@ impl AddressStore {
         self.notify();
     }
 
+    /// Updates a tip of the secondary descriptor and populates addresses if
+    /// necessary, the tips of the main descriptor are left untouched.
+    ///
+    /// # Parameters
+    /// - `account`: The keychain of the tip.
+    /// - `index`: The new index for the tip.
+    fn update_secondary(&mut self, account: AddrAccount, index: u32) {
+        let tip = match account {
+            AddrAccount::Change => &mut self.secondary_tip.change,
+            _ => &mut self.secondary_tip.receive,
+        };
+        *tip = (*tip).max(index);
+        self.populate_maybe();
+        self.notify();
+    }
+
     /// Generates a new receiving address and updates the receiving address tip.
     ///
     /// # Returns
@@ -190,36 +287,191 @@ impl AddressStore {
         self.derivator.receive_at(self.recv_generated_tip)
     }
 
+    /// Returns a receive address to hand out to the user.
+    ///
+    /// An `AddressGapWarning` notification is sent if the handed out
+    /// addresses that are still unused reach the look-ahead, as funds
+    /// received past this gap could be missed by a wallet restoring from
+    /// the descriptor.
+    ///
+    /// # Parameters
+    /// - `reuse_unused`: Whether to return the first handed out receive
+    ///   address that is still unused instead of generating a new one.
+    ///
+    /// # Returns
+    /// The entry of the receive address.
+    pub fn next_recv_entry(&mut self, reuse_unused: bool) -> AddressEntry {
+        if reuse_unused {
+            if let Some(entry) = self.first_unused_recv() {
+                return entry;
+            }
+        }
+        let address = self.new_recv_addr();
+        let gap = self.recv_unused_gap();
+        if gap >= self.recv_look_ahead {
+            log::warn!("AddressStore::next_recv_entry() {gap} unused receive addresses");
+            if let Err(e) = self.notification.send(Notification::AddressGapWarning(gap)) {
+                log::error!("AddressStore::next_recv_entry() fail to send notification: {e:?}");
+            }
+        }
+        self.get_entry(&address.script_pubkey())
+            .expect("generated address is watched")
+    }
+
+    /// Returns the handed out receive address with the lowest index that
+    /// is still unused.
+    fn first_unused_recv(&self) -> Option<AddressEntry> {
+        self.store
+            .values()
+            .filter(|e| {
+                !e.secondary
+                    && e.account == AddrAccount::Receive
+                    && e.status == AddressStatus::NotUsed
+                    && e.index <= self.recv_generated_tip
+            })
+            .min_by_key(|e| e.index)
+            .cloned()
+    }
+
+    /// Returns the number of receive addresses handed out after the last
+    /// used one of the main descriptor.
+    ///
+    /// NOTE: the secondary descriptor is only watched, a payment to one of
+    /// its addresses do not consume the gap of the main one.
+    pub fn recv_unused_gap(&self) -> u32 {
+        let last_used = self
+            .store
+            .values()
+            .filter(|e| {
+                e.account == AddrAccount::Receive
+                    && !e.secondary
+                    && matches!(e.status, AddressStatus::Used | AddressStatus::Reused)
+            })
+            .map(|e| e.index)
+            .max();
+        match last_used {
+            Some(index) => self.recv_generated_tip.saturating_sub(index),
+            None => self.recv_generated_tip,
+        }
+    }
+
     /// Generates a new change address and updates the change address tip.
     ///
+    /// Used change addresses are skipped, so a change address is never
+    /// reused.
+    ///
     /// # Returns
     /// The newly generated change address.
     pub fn new_change_addr(&mut self) -> bitcoin::Address {
-        self.change_generated_tip += 1;
-        self.update_change(self.change_generated_tip);
-        self.derivator.change_at(self.change_generated_tip)
+        loop {
+            self.change_generated_tip += 1;
+            self.update_change(self.change_generated_tip);
+            let address = self.derivator.change_at(self.change_generated_tip);
+            // NOTE: the change tip is expected to be past every used change
+            // address, but a used address must never be handed out, even
+            // if the tip is lagging behind
+            match self.get_entry(&address.script_pubkey()) {
+                Some(entry) if entry.status != AddressStatus::NotUsed => {
+                    log::warn!(
+                        "AddressStore::new_change_addr() skip used change index {}",
+                        entry.index
+                    );
+                }
+                _ => return address,
+            }
+        }
+    }
+
+    /// Generates a new address for a coinjoin output.
+    ///
+    /// The output is derived from the secondary descriptor when the
+    /// contributed coin belongs to it, so the output script type matches
+    /// the script type of the input. Used addresses are skipped, so a
+    /// coinjoin output is never paid to a reused address.
+    ///
+    /// # Parameters
+    /// - `keychain`: The keychain the output is derived from.
+    /// - `secondary`: Whether the contributed coin is derived from the
+    ///   secondary descriptor.
+    ///
+    /// # Returns
+    /// The newly generated coinjoin output address.
+    pub fn new_coinjoin_addr(
+        &mut self,
+        keychain: AddrAccount,
+        secondary: bool,
+    ) -> bitcoin::Address {
+        let secondary = secondary && self.secondary.is_some();
+        loop {
+            let address = match (secondary, keychain) {
+                (true, _) => self.new_secondary_addr(keychain),
+                (false, AddrAccount::Change) => self.new_change_addr(),
+                (false, _) => self.new_recv_addr(),
+            };
+            match self.get_entry(&address.script_pubkey()) {
+                Some(entry) if entry.status != AddressStatus::NotUsed => {
+                    log::warn!(
+                        "AddressStore::new_coinjoin_addr() skip used {:?} index {}",
+                        entry.account,
+                        entry.index
+                    );
+                }
+                _ => return address,
+            }
+        }
+    }
+
+    /// Generates a new address of the secondary descriptor and updates its
+    /// tip, the tips of the main descriptor are left untouched.
+    ///
+    /// # Parameters
+    /// - `keychain`: The keychain the address is derived from.
+    ///
+    /// # Panics
+    /// This function panics if there is no secondary descriptor.
+    fn new_secondary_addr(&mut self, keychain: AddrAccount) -> bitcoin::Address {
+        let index = match keychain {
+            AddrAccount::Change => self.secondary_tip.change,
+            _ => self.secondary_tip.receive,
+        } + 1;
+        self.update_secondary(keychain, index);
+        let derivator = self.secondary.as_ref().expect("has a secondary descriptor");
+        match keychain {
+            AddrAccount::Change => derivator.change_at(index),
+            _ => derivator.receive_at(index),
+        }
     }
 
     /// Returns the current change watch tip index.
     ///
-    /// The watch tip is the index of the last generated change address plus
-    /// the look-ahead.
+    /// The watch tip is the index of the last generated change address of
+    /// either descriptor (or the scan checkpoint if greater) plus the change
+    /// look-ahead.
     ///
     /// # Returns
     /// The current change watch tip index.
     pub fn change_watch_tip(&self) -> u32 {
-        self.change_generated_tip + self.look_ahead + 1
+        self.change_generated_tip
+            .max(self.secondary_tip.change)
+            .max(self.scan_from.change)
+            + self.change_look_ahead
+            + 1
     }
 
     /// Returns the current receiving watch tip index.
     ///
     /// The watch tip is the index of the last generated receiving address
-    /// plus the look-ahead.
+    /// of either descriptor (or the scan checkpoint if greater) plus the
+    /// receive look-ahead.
     ///
     /// # Returns
     /// The current receiving watch tip index.
     pub fn recv_watch_tip(&self) -> u32 {
-        self.recv_generated_tip + self.look_ahead + 1
+        self.recv_generated_tip
+            .max(self.secondary_tip.receive)
+            .max(self.scan_from.receive)
+            + self.recv_look_ahead
+            + 1
     }
 
     /// Returns the current receiving address tip index.
@@ -230,6 +482,22 @@ impl AddressStore {
         self.recv_generated_tip
     }
 
+    /// Returns the last generated indexes of the secondary descriptor.
+    ///
+    /// # Returns
+    /// The receiving and change tips of the secondary descriptor.
+    pub fn secondary_tip(&self) -> Tip {
+        self.secondary_tip
+    }
+
+    /// Returns the current change address tip index.
+    ///
+    /// # Returns
+    /// The current change address tip index.
+    pub fn change_tip(&self) -> u32 {
+        self.change_generated_tip
+    }
+
     /// Initializes the address store with a transaction poller.
     ///
     /// This method populates the address store and sets the transaction
@@ -268,6 +536,27 @@ impl AddressStore {
         self.store.get_mut(spk)
     }
 
+    /// Returns the entries whose script public key is not derived by the
+    /// current descriptors at the entry derivation, e.g. entries restored
+    /// after the descriptor has been edited.
+    ///
+    /// # Returns
+    /// The mismatched address entries.
+    pub fn mismatched_entries(&self) -> Vec<AddressEntry> {
+        self.store
+            .iter()
+            .filter(|(spk, entry)| {
+                let derivator = match (entry.secondary, &self.secondary) {
+                    (false, _) => &self.derivator,
+                    (true, Some(secondary)) => secondary,
+                    (true, None) => return true,
+                };
+                derivator.spk_at(entry.account, entry.index) != **spk
+            })
+            .map(|(_, entry)| entry.clone())
+            .collect()
+    }
+
     /// Retrieves all unused receiving addresses.
     ///
     /// This method filters the address store for addresses that are not
@@ -343,6 +632,16 @@ impl AddressStore {
     /// A `Result` indicating success or failure of the restoration.
     pub fn restore(&mut self, value: serde_json::Value) -> Result<(), serde_json::Error> {
         self.store = serde_json::from_value(value)?;
+        // NOTE: entries dumped before the derivation path was recorded
+        for entry in self.store.values_mut() {
+            if entry.derivation_path.is_empty() {
+                let derivator = match (entry.secondary, &self.secondary) {
+                    (true, Some(secondary)) => secondary,
+                    _ => &self.derivator,
+                };
+                entry.derivation_path = derivator.derivation_path(entry.account, entry.index);
+            }
+        }
         Ok(())
     }
 }
@@ -357,12 +656,19 @@ impl AddressStore {
 /// - `address`: The Bitcoin address associated with this entry.
 /// - `account`: The account type (receiving or change).
 /// - `index`: The index of the address in the generation sequence.
+/// - `secondary`: Whether the address is derived from the secondary
+///   descriptor.
+/// - `derivation_path`: The full derivation path of the address.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddressEntry {
     pub status: AddressStatus,
     pub address: bitcoin::Address<NetworkUnchecked>,
     pub account: AddrAccount,
     pub index: u32,
+    #[serde(default)]
+    pub secondary: bool,
+    #[serde(default)]
+    pub derivation_path: String,
 }
 
 impl AddressEntry {
@@ -423,6 +729,13 @@ impl AddressEntry {
     pub fn index(&self) -> u32 {
         self.index
     }
+    /// Returns whether the address is derived from the secondary descriptor.
+    ///
+    /// # Returns
+    /// `true` if this address belongs to the secondary descriptor.
+    pub fn is_secondary(&self) -> bool {
+        self.secondary
+    }
     /// Returns the Bitcoin address associated with this entry.
     ///
     /// # Returns
@@ -447,6 +760,8 @@ impl From<AddressEntry> for RustAddress {
             status: value.status(),
             account: value.account(),
             index: value.index(),
+            secondary: value.secondary,
+            derivation_path: value.derivation_path,
         }
     }
 }
@@ -0,0 +1,416 @@
+use std::{fmt::Display, str::FromStr};
+
+use joinstr::bip39::Mnemonic;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{config_exists, descriptor_contains_fingerprint, ConfigError, Tip},
+    label_store::{Bip329Label, LabelStore},
+    signer::HotSigner,
+    Config,
+};
+
+/// Version of the backup format, bumped on breaking changes.
+pub const BACKUP_VERSION: u32 = 2;
+
+/// Errors that can occur while importing a backup.
+#[derive(Debug)]
+pub enum BackupError {
+    Parse(serde_json::Error),
+    Version(u32),
+    MissingMnemonic,
+    InvalidMnemonic,
+    MnemonicMismatch,
+    AccountExists,
+    Config(ConfigError),
+}
+
+impl Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupError::Parse(e) => write!(f, "Fail to parse backup: {e}"),
+            BackupError::Version(v) => write!(f, "Unsupported backup version: {v}"),
+            BackupError::MissingMnemonic => write!(f, "The backup do not contain the mnemonic"),
+            BackupError::InvalidMnemonic => write!(f, "Invalid mnemonic"),
+            BackupError::MnemonicMismatch => {
+                write!(f, "The mnemonic do not match the backup descriptor")
+            }
+            BackupError::AccountExists => write!(f, "An account with this name already exists"),
+            BackupError::Config(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for BackupError {
+    fn from(value: serde_json::Error) -> Self {
+        BackupError::Parse(value)
+    }
+}
+
+impl From<ConfigError> for BackupError {
+    fn from(value: ConfigError) -> Self {
+        BackupError::Config(value)
+    }
+}
+
+/// A portable wallet backup bundle.
+///
+/// The whole account config is part of the backup, flattened at its root
+/// so version 1 backups (network, descriptors and look-ahead only) still
+/// parse. The mnemonics are only included on explicit request, a backup
+/// without mnemonic must be imported along with the mnemonic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    #[serde(flatten)]
+    pub config: Config,
+    pub tip: Tip,
+    /// Tips of the secondary descriptor, if any.
+    #[serde(default)]
+    pub secondary_tip: Tip,
+    pub labels: Vec<Bip329Label>,
+}
+
+impl Backup {
+    /// Creates a backup of the given config and labels.
+    ///
+    /// # Parameters
+    /// - `config`: The config of the account.
+    /// - `labels`: The label store of the account.
+    /// - `tip`: The receive and change tips of the account.
+    /// - `secondary_tip`: The tips of the secondary descriptor.
+    /// - `include_mnemonic`: Whether the mnemonic and the coinjoin mnemonic
+    ///   are part of the backup.
+    pub fn new(
+        config: &Config,
+        labels: &LabelStore,
+        tip: Tip,
+        secondary_tip: Tip,
+        include_mnemonic: bool,
+    ) -> Self {
+        let mut config = config.clone();
+        if !include_mnemonic {
+            config.mnemonic = String::new();
+            config.coinjoin_mnemonic = None;
+        }
+        Backup {
+            version: BACKUP_VERSION,
+            config,
+            tip,
+            secondary_tip,
+            labels: labels.to_bip329(),
+        }
+    }
+
+    /// Serializes the backup to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("cannot fail")
+    }
+
+    /// Parses a backup from JSON.
+    ///
+    /// # Errors
+    /// - `BackupError::Parse` if `json` is not a valid backup.
+    /// - `BackupError::Version` if the backup has been created by a newer
+    ///   version.
+    pub fn from_json(json: &str) -> Result<Self, BackupError> {
+        let backup: Backup = serde_json::from_str(json)?;
+        if backup.version > BACKUP_VERSION {
+            return Err(BackupError::Version(backup.version));
+        }
+        Ok(backup)
+    }
+
+    /// Restores the backup as a new account: the config, the labels and
+    /// the tips are persisted in the account directory. The coinjoin
+    /// mnemonic is only restored if it is part of the backup.
+    ///
+    /// # Parameters
+    /// - `account`: The name of the new account.
+    /// - `mnemonic`: The mnemonic of the account, if `None` the mnemonic of
+    ///   the backup is used.
+    ///
+    /// # Returns
+    /// The config of the restored account.
+    ///
+    /// # Errors
+    /// `BackupError::MnemonicMismatch` if the descriptor do not contain a
+    /// key of the mnemonic.
+    pub fn restore(self, account: String, mnemonic: Option<String>) -> Result<Config, BackupError> {
+        if config_exists(account.clone()) {
+            return Err(BackupError::AccountExists);
+        }
+        let mut config = self.config;
+        let backup_mnemonic = (!config.mnemonic.is_empty()).then_some(config.mnemonic);
+        let mnemonic = mnemonic
+            .or(backup_mnemonic)
+            .ok_or(BackupError::MissingMnemonic)?;
+        Mnemonic::from_str(&mnemonic).map_err(|_| BackupError::InvalidMnemonic)?;
+        // NOTE: the account could not sign for a descriptor that do not
+        // contain a key of its mnemonic
+        let signer = HotSigner::new_from_mnemonics(config.network, &mnemonic)
+            .map_err(|_| BackupError::InvalidMnemonic)?;
+        if !descriptor_contains_fingerprint(
+            config.descriptor.to_string(),
+            signer.fingerprint().to_string(),
+        ) {
+            return Err(BackupError::MnemonicMismatch);
+        }
+
+        config.account = account;
+        config.mnemonic = mnemonic;
+        config.to_file()?;
+        config.persist_tip(self.tip.receive, self.tip.change);
+        if config.secondary_descriptor.is_some() {
+            config.persist_secondary_tip(self.secondary_tip);
+        }
+
+        let mut labels = LabelStore::from_file(config.clone());
+        labels.import_bip329(self.labels);
+        labels.persist();
+
+        Ok(config)
+    }
+}
+
+/// Imports a backup as a new account.
+///
+/// # Arguments
+///
+/// * `backup` - The backup as exported by `Account::export_backup()`.
+/// * `account` - The name of the new account.
+/// * `mnemonic` - The mnemonic of the account, if empty the mnemonic of the
+///   backup is used.
+///
+/// # Returns
+///
+/// The config of the restored account.
+pub fn import_backup(
+    backup: String,
+    account: String,
+    mnemonic: String,
+) -> Result<Box<Config>, BackupError> {
+    let mnemonic = (!mnemonic.is_empty()).then_some(mnemonic);
+    Backup::from_json(&backup)?
+        .restore(account, mnemonic)
+        .map(Box::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use joinstr::miniscript::bitcoin::{self, bip32::DerivationPath, OutPoint};
+
+    use super::*;
+    use crate::{
+        config::new_config,
+        cpp_joinstr::{AddrAccount, Network},
+        label_store::LabelKey,
+        signer::wpkh,
+        test_utils::txid,
+    };
+
+    const MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn fresh_account(name: &str) -> String {
+        let _ = std::fs::remove_dir_all(Config::path(name.to_string()));
+        name.to_string()
+    }
+
+    #[test]
+    fn backup_round_trip() {
+        let config = new_config(
+            MNEMONIC.to_string(),
+            "backup_export".to_string(),
+            Network::Regtest,
+        );
+        let mut labels = LabelStore::new();
+        let address =
+            bitcoin::Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080").unwrap();
+        labels.edit(LabelKey::Address(address), Some("addr".to_string()));
+        labels.edit(LabelKey::Transaction(txid(1)), Some("tx".to_string()));
+        labels.edit(
+            LabelKey::OutPoint(OutPoint::new(txid(2), 3)),
+            Some("output".to_string()),
+        );
+        let tip = Tip {
+            receive: 5,
+            change: 2,
+        };
+
+        // the mnemonic is not exported by default
+        let json = Backup::new(&config, &labels, tip, Tip::default(), false).to_json();
+        assert!(!json.contains(MNEMONIC));
+        let account = fresh_account("backup_import");
+        assert!(matches!(
+            Backup::from_json(&json)
+                .unwrap()
+                .restore(account.clone(), None),
+            Err(BackupError::MissingMnemonic)
+        ));
+        let restored = Backup::from_json(&json)
+            .unwrap()
+            .restore(account.clone(), Some(MNEMONIC.to_string()))
+            .unwrap();
+
+        let loaded = Config::from_file(account.clone());
+        assert_eq!(loaded.descriptor, config.descriptor);
+        assert_eq!(loaded.network, config.network);
+        assert_eq!(loaded.mnemonic, MNEMONIC);
+        assert_eq!(loaded.tip_from_file(), tip);
+        assert_eq!(
+            LabelStore::from_file(restored).to_bip329(),
+            labels.to_bip329()
+        );
+
+        // the mnemonic must match the descriptor
+        let other = Mnemonic::generate(12).unwrap().to_string();
+        assert!(matches!(
+            Backup::from_json(&json)
+                .unwrap()
+                .restore(fresh_account("backup_import_other"), Some(other.clone())),
+            Err(BackupError::MnemonicMismatch)
+        ));
+        assert!(import_backup(json.clone(), fresh_account("backup_import_other"), other).is_err());
+        assert!(!config_exists("backup_import_other".to_string()));
+
+        // an existing account is never overwritten
+        assert!(matches!(
+            Backup::from_json(&json)
+                .unwrap()
+                .restore(account, Some(MNEMONIC.to_string())),
+            Err(BackupError::AccountExists)
+        ));
+
+        // the mnemonic is exported on request
+        let json = Backup::new(&config, &labels, tip, Tip::default(), true).to_json();
+        let account = fresh_account("backup_import_mnemonic");
+        let restored = import_backup(json, account, String::new()).unwrap();
+        assert_eq!(restored.mnemonic, MNEMONIC);
+        assert_eq!(restored.descriptor, config.descriptor);
+    }
+
+    #[test]
+    fn backup_config_round_trip() {
+        let mut config = *new_config(
+            MNEMONIC.to_string(),
+            "backup_config_export".to_string(),
+            Network::Regtest,
+        );
+        let signer = HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, MNEMONIC).unwrap();
+        let secondary = wpkh(signer.xpub(&DerivationPath::from_str("m/84'/1'/1'").unwrap()));
+        // every field differs from its default value
+        config.electrum_url = Some("ssl://electrum.example".to_string());
+        config.electrum_port = Some(50002);
+        config.electrum_timeout += 1;
+        config.electrum_batch_size += 1;
+        config.reconnect_initial_delay_ms += 1;
+        config.reconnect_multiplier += 1.0;
+        config.reconnect_max_delay_ms += 1;
+        config.nostr_relay = Some("wss://relay.example".to_string());
+        config.nostr_back = Some(3600);
+        config.nostr_pool_authors = vec!["ab".repeat(32)];
+        config.enable_coinjoin = false;
+        config.look_ahead = 30;
+        config.recv_look_ahead = Some(40);
+        config.change_look_ahead = Some(10);
+        config.min_relay_fee += 1.0;
+        config.scan_from = Tip {
+            receive: 7,
+            change: 3,
+        };
+        config.persist_mnemonic = true;
+        config.coinjoin_mnemonic = Some(Mnemonic::generate(12).unwrap().to_string());
+        config.coinjoin_keychain = AddrAccount::Change;
+        config.reuse_unused_addresses = true;
+        config.consolidation_max_utxos += 1;
+        config.consolidation_min_savings += 1;
+        config.psbt_global_xpubs = true;
+        config.auto_new_recv_addr = true;
+        config.max_coinjoin_excess += 1;
+        config.min_change_sats = Some(5_000);
+        config.dust_attack_threshold += 1;
+        config.created_at = 1_700_000_000;
+        config.secondary_descriptor = Some(secondary);
+        let tip = Tip {
+            receive: 5,
+            change: 2,
+        };
+        let secondary_tip = Tip {
+            receive: 4,
+            change: 1,
+        };
+
+        let json = Backup::new(&config, &LabelStore::new(), tip, secondary_tip, true).to_json();
+        let account = fresh_account("backup_config_import");
+        import_backup(json, account.clone(), String::new()).unwrap();
+        let loaded = Config::from_file(account);
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&config).unwrap()
+        );
+        assert_eq!(loaded.tip_from_file(), tip);
+        assert_eq!(loaded.secondary_tip_from_file(), secondary_tip);
+
+        // the coinjoin mnemonic is not exported by default
+        let json = Backup::new(&config, &LabelStore::new(), tip, secondary_tip, false).to_json();
+        assert!(!json.contains(config.coinjoin_mnemonic.as_ref().unwrap()));
+        let account = fresh_account("backup_config_import_no_mnemonic");
+        import_backup(json, account.clone(), MNEMONIC.to_string()).unwrap();
+        let loaded = Config::from_file(account);
+        assert_eq!(loaded.coinjoin_mnemonic, None);
+        assert_eq!(loaded.scan_from, config.scan_from);
+        assert_eq!(loaded.recv_look_ahead, config.recv_look_ahead);
+        assert_eq!(loaded.change_look_ahead, config.change_look_ahead);
+    }
+
+    #[test]
+    fn backup_v1() {
+        // backups of version 1 only carry the descriptors and the look-ahead
+        let config = new_config(
+            MNEMONIC.to_string(),
+            "backup_v1".to_string(),
+            Network::Regtest,
+        );
+        let json = serde_json::json!({
+            "version": 1,
+            "network": "regtest",
+            "descriptor": config.descriptor.to_string(),
+            "look_ahead": 25,
+            "tip": { "receive": 3, "change": 1 },
+            "labels": [],
+        })
+        .to_string();
+        let account = fresh_account("backup_v1_import");
+        let restored = import_backup(json, account, MNEMONIC.to_string()).unwrap();
+        assert_eq!(restored.descriptor, config.descriptor);
+        assert_eq!(restored.look_ahead, 25);
+        assert_eq!(restored.scan_from, Tip::default());
+        assert_eq!(restored.coinjoin_keychain, config.coinjoin_keychain);
+    }
+
+    #[test]
+    fn backup_version() {
+        let config = new_config(
+            MNEMONIC.to_string(),
+            "backup_version".to_string(),
+            Network::Regtest,
+        );
+        let mut backup = Backup::new(
+            &config,
+            &LabelStore::new(),
+            Tip::default(),
+            Tip::default(),
+            false,
+        );
+        backup.version = BACKUP_VERSION + 1;
+        assert!(matches!(
+            Backup::from_json(&backup.to_json()),
+            Err(BackupError::Version(_))
+        ));
+        assert!(matches!(
+            Backup::from_json("{}"),
+            Err(BackupError::Parse(_))
+        ));
+    }
+}
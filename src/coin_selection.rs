@@ -0,0 +1,229 @@
+use joinstr::miniscript::bitcoin::OutPoint;
+
+/// Maximum number of branches explored by the branch and bound search.
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// A coin that can be selected.
+///
+/// # Fields
+/// - `outpoint`: The outpoint of the coin.
+/// - `effective_value`: The value of the coin minus the fee paid for
+///   spending it, in sats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    pub outpoint: OutPoint,
+    pub effective_value: u64,
+}
+
+/// The result of a coin selection.
+///
+/// # Fields
+/// - `outpoints`: The outpoints of the selected coins.
+/// - `excess`: The sum of the effective values of the selected coins
+///   minus the target, in sats.
+/// - `changeless`: Whether the excess is small enough to not create a
+///   change output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    pub outpoints: Vec<OutPoint>,
+    pub excess: u64,
+    pub changeless: bool,
+}
+
+/// Selects coins whose effective values cover `target`.
+///
+/// A branch and bound search first looks for a set of coins whose total
+/// lies in `[target, target + cost_of_change]`, the excess is then left to
+/// fees and no change output is created. If no such set exists, coins are
+/// selected largest first.
+///
+/// # Parameters
+/// - `candidates`: The coins that can be selected.
+/// - `target`: The amount to cover, outputs and fees not related to the
+///   inputs, in sats.
+/// - `cost_of_change`: The excess above which creating a change output is
+///   worth it, in sats.
+///
+/// # Returns
+/// The selection, or `None` if the candidates cannot cover `target`.
+pub fn select_coins(
+    candidates: &[Candidate],
+    target: u64,
+    cost_of_change: u64,
+) -> Option<Selection> {
+    let mut sorted: Vec<_> = candidates
+        .iter()
+        .filter(|c| c.effective_value > 0)
+        .copied()
+        .collect();
+    sorted.sort_by(|a, b| b.effective_value.cmp(&a.effective_value));
+
+    if let Some((excess, indexes)) = branch_and_bound(&sorted, target, cost_of_change) {
+        return Some(Selection {
+            outpoints: indexes.into_iter().map(|i| sorted[i].outpoint).collect(),
+            excess,
+            changeless: true,
+        });
+    }
+    largest_first(&sorted, target, cost_of_change)
+}
+
+/// Searches the set of coins with the smallest excess in
+/// `[target, target + cost_of_change]`, `sorted` must be sorted by
+/// decreasing effective value.
+fn branch_and_bound(
+    sorted: &[Candidate],
+    target: u64,
+    cost_of_change: u64,
+) -> Option<(u64, Vec<usize>)> {
+    // remaining[i] is the sum of the effective values of sorted[i..]
+    let mut remaining = vec![0; sorted.len() + 1];
+    for i in (0..sorted.len()).rev() {
+        remaining[i] = remaining[i + 1] + sorted[i].effective_value;
+    }
+    if remaining[0] < target {
+        return None;
+    }
+
+    let mut search = Search {
+        sorted,
+        remaining,
+        target,
+        upper: target + cost_of_change,
+        tries: 0,
+        selected: vec![],
+        best: None,
+    };
+    search.explore(0, 0);
+    search.best
+}
+
+struct Search<'a> {
+    sorted: &'a [Candidate],
+    remaining: Vec<u64>,
+    target: u64,
+    upper: u64,
+    tries: usize,
+    selected: Vec<usize>,
+    best: Option<(u64, Vec<usize>)>,
+}
+
+impl Search<'_> {
+    fn explore(&mut self, index: usize, total: u64) {
+        if self.tries >= BNB_MAX_TRIES || matches!(self.best, Some((0, _))) {
+            return;
+        }
+        self.tries += 1;
+        if total > self.upper {
+            return;
+        }
+        if total >= self.target {
+            // selecting more coins can only increase the excess
+            let excess = total - self.target;
+            if self.best.as_ref().map_or(true, |(best, _)| excess < *best) {
+                self.best = Some((excess, self.selected.clone()));
+            }
+            return;
+        }
+        if index == self.sorted.len() || total + self.remaining[index] < self.target {
+            return;
+        }
+
+        // branch including the coin first
+        self.selected.push(index);
+        self.explore(index + 1, total + self.sorted[index].effective_value);
+        self.selected.pop();
+        // then the branch excluding it
+        self.explore(index + 1, total);
+    }
+}
+
+/// Selects coins by decreasing effective value until `target` is covered.
+fn largest_first(sorted: &[Candidate], target: u64, cost_of_change: u64) -> Option<Selection> {
+    let mut total = 0;
+    let mut outpoints = vec![];
+    for candidate in sorted {
+        if total >= target {
+            break;
+        }
+        total += candidate.effective_value;
+        outpoints.push(candidate.outpoint);
+    }
+    (total >= target).then(|| Selection {
+        outpoints,
+        excess: total - target,
+        changeless: total - target <= cost_of_change,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::txid;
+
+    fn candidates(values: &[u64]) -> Vec<Candidate> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Candidate {
+                outpoint: OutPoint::new(txid(i as u8), 0),
+                effective_value: *value,
+            })
+            .collect()
+    }
+
+    fn values(candidates: &[Candidate], selection: &Selection) -> Vec<u64> {
+        let mut values: Vec<_> = selection
+            .outpoints
+            .iter()
+            .map(|op| {
+                candidates
+                    .iter()
+                    .find(|c| c.outpoint == *op)
+                    .unwrap()
+                    .effective_value
+            })
+            .collect();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn changeless_selection() {
+        let coins = candidates(&[50_000, 30_000, 20_000, 7_000]);
+
+        // largest first would select 50_000 + 30_000
+        let selection = select_coins(&coins, 57_000, 500).unwrap();
+        assert!(selection.changeless);
+        assert_eq!(selection.excess, 0);
+        assert_eq!(values(&coins, &selection), vec![7_000, 50_000]);
+
+        // the smallest excess within the window is preferred
+        let selection = select_coins(&coins, 49_500, 1_000).unwrap();
+        assert!(selection.changeless);
+        assert_eq!(selection.excess, 500);
+        assert_eq!(values(&coins, &selection), vec![50_000]);
+    }
+
+    #[test]
+    fn largest_first_fallback() {
+        let coins = candidates(&[60_000, 100_000]);
+
+        // no combination lies in the changeless window
+        let selection = select_coins(&coins, 70_000, 1_000).unwrap();
+        assert!(!selection.changeless);
+        assert_eq!(selection.excess, 30_000);
+        assert_eq!(values(&coins, &selection), vec![100_000]);
+
+        let selection = select_coins(&coins, 150_000, 1_000).unwrap();
+        assert!(!selection.changeless);
+        assert_eq!(values(&coins, &selection), vec![60_000, 100_000]);
+    }
+
+    #[test]
+    fn insufficient_funds() {
+        let coins = candidates(&[60_000, 100_000, 0]);
+        assert!(select_coins(&coins, 160_001, 1_000).is_none());
+        assert!(select_coins(&[], 1, 1_000).is_none());
+    }
+}
@@ -1,24 +1,40 @@
-use joinstr::miniscript::{
-    bitcoin::{self, address::NetworkUnchecked, OutPoint, ScriptBuf, Txid},
-    Descriptor, DescriptorPublicKey,
+use joinstr::{
+    miniscript::{
+        bitcoin::{self, address::NetworkUnchecked, OutPoint, ScriptBuf, Txid},
+        Descriptor, DescriptorPublicKey,
+    },
+    utils::now,
+};
+use serde::{
+    de::{DeserializeSeed, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
 };
-use serde::{Deserialize, Serialize};
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+    io::{Read, Write},
+    str::FromStr,
     sync::{mpsc, Arc, Mutex},
 };
 
 use crate::{
-    account::Notification,
+    account::{Notification, NotificationSender},
     address_store::{AddressEntry, AddressStore, AddressTip},
     coin,
-    cpp_joinstr::{AddrAccount, AddressStatus, CoinState, CoinStatus, RustAddress, RustCoin},
+    config::{Tip, DUST_ATTACK_THRESHOLD},
+    cpp_joinstr::{
+        AddrAccount, AddressStatus, CoinSort, CoinState, CoinStatus, RustAddress, RustCoin,
+        RustTransaction,
+    },
     derivator::Derivator,
     label_store::{LabelKey, LabelStore},
-    tx_store::TxStore,
+    tx_store::{TxEntry, TxStore},
     Config,
 };
 
+/// Expected delay in seconds between two blocks.
+pub const BLOCK_INTERVAL: u64 = 600;
+
 #[derive(Debug)]
 /// Represents a store for managing coins and their associated data.
 ///
@@ -35,8 +51,28 @@ pub struct CoinStore {
     spk_history: BTreeMap<ScriptBuf, SpkHistory>,
     updates: Vec<Update>,
     derivator: Derivator,
-    notification: mpsc::Sender<Notification>,
-    #[allow(unused)]
+    secondary_derivator: Option<Derivator>,
+    notification: NotificationSender,
+    /// Unix timestamp (in seconds) of the last coins change, 0 if unknown.
+    last_activity: u64,
+    /// Number of times the store has been generated, the first generation
+    /// only loads the persisted coins.
+    generations: u64,
+    /// Txids of the transactions prepared by this wallet, coins spent by
+    /// any other transaction are flagged as externally spent.
+    prepared_txs: BTreeSet<Txid>,
+    /// Script pubkeys of our coinjoin outputs and the txid of the coinjoin
+    /// paying to them, the coinjoin is registered as prepared once
+    /// received.
+    coinjoin_outputs: BTreeMap<ScriptBuf, Option<Txid>>,
+    /// Outpoints of the coins the user excluded from spending.
+    frozen: BTreeSet<OutPoint>,
+    /// Unix timestamp (in seconds) at which each unconfirmed transaction
+    /// has been seen for the first time.
+    first_seen: BTreeMap<Txid, u64>,
+    /// Whether the transaction listener waits for a subscription status,
+    /// a history or transactions from the electrum server.
+    in_flight: bool,
     config: Option<Config>,
 }
 
@@ -117,6 +153,11 @@ impl SpkHistory {
         };
         diff
     }
+
+    /// Returns the txids of the last history state.
+    pub fn txids(&self) -> impl Iterator<Item = &bitcoin::Txid> {
+        self.history.last().into_iter().flat_map(|h| h.keys())
+    }
 }
 
 impl CoinStore {
@@ -125,10 +166,16 @@ impl CoinStore {
     /// # Parameters
     /// - `network`: The Bitcoin network to use.
     /// - `mnemonic`: The mnemonic phrase for generating keys.
+    /// - `secondary_descriptor`: Optional secondary descriptor, whose coins
+    ///   are tracked alongside the main descriptor ones.
     /// - `notification`: Channel for sending notifications about updates.
     /// - `recv_tip`: Initial index for receiving address generation.
     /// - `change_tip`: Initial index for change address generation.
-    /// - `look_ahead`: Number of addresses to generate ahead of the current tip.
+    /// - `recv_look_ahead`: Number of receiving addresses to generate ahead
+    ///   of the current tip.
+    /// - `change_look_ahead`: Number of change addresses to generate ahead of
+    ///   the current tip.
+    /// - `scan_from`: Scan checkpoint to start watching addresses from.
     ///
     /// # Returns
     /// A new instance of `CoinStore`.
@@ -136,21 +183,28 @@ impl CoinStore {
     pub fn new(
         network: bitcoin::Network,
         descriptor: Descriptor<DescriptorPublicKey>,
-        notification: mpsc::Sender<Notification>,
+        secondary_descriptor: Option<Descriptor<DescriptorPublicKey>>,
+        notification: NotificationSender,
         recv_tip: u32,
         change_tip: u32,
-        look_ahead: u32,
+        recv_look_ahead: u32,
+        change_look_ahead: u32,
+        scan_from: Tip,
         tx_store: TxStore,
         label_store: Arc<Mutex<LabelStore>>,
         config: Option<Config>,
     ) -> Self {
         let derivator = Derivator::new(descriptor, network).unwrap();
+        let secondary_derivator = secondary_descriptor.map(|d| Derivator::new(d, network).unwrap());
         let address_store = AddressStore::new(
             derivator.clone(),
+            secondary_derivator.clone(),
             notification.clone(),
             recv_tip,
             change_tip,
-            look_ahead,
+            recv_look_ahead,
+            change_look_ahead,
+            scan_from,
             config.clone(),
         );
         Self {
@@ -163,6 +217,29 @@ impl CoinStore {
             spk_history: BTreeMap::new(),
             notification,
             derivator,
+            secondary_derivator,
+            last_activity: config
+                .as_ref()
+                .map(|c| c.last_activity_from_file())
+                .unwrap_or_default(),
+            generations: 0,
+            first_seen: config
+                .as_ref()
+                .map(|c| c.first_seen_from_file())
+                .unwrap_or_default(),
+            prepared_txs: config
+                .as_ref()
+                .map(|c| c.prepared_txs_from_file())
+                .unwrap_or_default(),
+            coinjoin_outputs: config
+                .as_ref()
+                .map(|c| c.coinjoin_outputs_from_file())
+                .unwrap_or_default(),
+            frozen: config
+                .as_ref()
+                .map(|c| c.frozen_from_file())
+                .unwrap_or_default(),
+            in_flight: false,
             config,
         }
     }
@@ -188,6 +265,18 @@ impl CoinStore {
     pub fn derivator_ref(&self) -> &Derivator {
         &self.derivator
     }
+    /// Returns the derivators of all the descriptors tracked by the store,
+    /// the main one first.
+    ///
+    /// # Returns
+    /// A `Vec<Derivator>` containing the main and secondary derivators.
+    pub fn derivators(&self) -> Vec<Derivator> {
+        let mut derivators = vec![self.derivator.clone()];
+        if let Some(secondary) = &self.secondary_derivator {
+            derivators.push(secondary.clone());
+        }
+        derivators
+    }
     /// Returns the current receiving watch tip index.
     ///
     /// # Returns
@@ -204,6 +293,19 @@ impl CoinStore {
         self.address_store.change_watch_tip()
     }
 
+    /// Returns the current watch tips along with the scan checkpoint floors.
+    ///
+    /// # Returns
+    /// An `AddressTip` describing the scripts to watch.
+    pub fn address_tip(&self) -> AddressTip {
+        self.address_store.address_tip()
+    }
+
+    /// Returns the number of times the coins have been generated.
+    pub fn generations(&self) -> u64 {
+        self.generations
+    }
+
     /// Generates a new receiving address.
     ///
     /// # Returns
@@ -212,6 +314,19 @@ impl CoinStore {
         self.address_store.new_recv_addr()
     }
 
+    /// Returns a receive address to hand out to the user, see
+    /// [`AddressStore::next_recv_entry`].
+    ///
+    /// # Parameters
+    /// - `reuse_unused`: Whether to return the first handed out receive
+    ///   address that is still unused instead of generating a new one.
+    ///
+    /// # Returns
+    /// The entry of the receive address.
+    pub fn next_recv_entry(&mut self, reuse_unused: bool) -> AddressEntry {
+        self.address_store.next_recv_entry(reuse_unused)
+    }
+
     /// Returns the current receiving address tip index.
     ///
     /// # Returns
@@ -220,6 +335,22 @@ impl CoinStore {
         self.address_store.recv_tip()
     }
 
+    /// Returns the current change address tip index.
+    ///
+    /// # Returns
+    /// The index of the last generated change address.
+    pub fn change_tip(&self) -> u32 {
+        self.address_store.change_tip()
+    }
+
+    /// Returns the last generated indexes of the secondary descriptor.
+    ///
+    /// # Returns
+    /// The receive and change tips of the secondary descriptor.
+    pub fn secondary_tip(&self) -> Tip {
+        self.address_store.secondary_tip()
+    }
+
     /// Generates a new change address.
     ///
     /// # Returns
@@ -228,6 +359,24 @@ impl CoinStore {
         self.address_store.new_change_addr()
     }
 
+    /// Generates a new address for a coinjoin output, see
+    /// [`AddressStore::new_coinjoin_addr`].
+    ///
+    /// # Parameters
+    /// - `keychain`: The keychain the output is derived from.
+    /// - `secondary`: Whether the contributed coin is derived from the
+    ///   secondary descriptor.
+    ///
+    /// # Returns
+    /// A new `bitcoin::Address` for the coinjoin output.
+    pub fn new_coinjoin_addr(
+        &mut self,
+        keychain: AddrAccount,
+        secondary: bool,
+    ) -> bitcoin::Address {
+        self.address_store.new_coinjoin_addr(keychain, secondary)
+    }
+
     /// Retrieves information about an address associated with the given script public key (SPK).
     ///
     /// This method queries the address store to find the entry corresponding to the provided SPK.
@@ -258,13 +407,15 @@ impl CoinStore {
     /// - `hist`: A map of script public keys to their transaction history.
     ///
     /// # Returns
-    /// A vector of `Txid` representing missing transactions.
+    /// A tuple containing whether stored transactions have been updated or
+    /// removed (e.g. replaced), in which case the coins must be generated
+    /// again, and a vector of `Txid` representing missing transactions.
     pub fn handle_history_response(
         &mut self,
         hist: BTreeMap<ScriptBuf, Vec<(bitcoin::Txid, Option<u64>)>>,
-    ) -> (bool /* height_updated */, Vec<Txid>) {
+    ) -> (bool /* updated */, Vec<Txid>) {
         let mut updates = vec![];
-        let mut height_updated = false;
+        let mut updated = false;
 
         // generate diff & drop double spent txs
         for (spk, history) in hist {
@@ -275,9 +426,9 @@ impl CoinStore {
 
         let mut updates: Vec<_> = updates
             .into_iter()
-            .map(|(height, upd)| {
-                if height {
-                    height_updated = true;
+            .map(|(upd_updated, upd)| {
+                if upd_updated {
+                    updated = true;
                 }
                 upd
             })
@@ -303,12 +454,13 @@ impl CoinStore {
 
         {
             // apply updates that are already completes
+            let history = self.history_txids();
             let store = &mut self.tx_store;
             updates = updates
                 .into_iter()
                 .filter_map(|u| {
                     if u.is_complete() {
-                        store.insert_updates(vec![u]);
+                        store.insert_history_updates(vec![u], &history);
                         None
                     } else {
                         Some(u)
@@ -318,7 +470,47 @@ impl CoinStore {
         } // <- release &mut tx_store
 
         self.updates.append(&mut updates);
-        (height_updated, txids)
+        (updated, txids)
+    }
+
+    /// Re-derives the script public keys of the stored addresses and coins
+    /// from the current descriptors.
+    ///
+    /// # Returns
+    /// A tuple containing the address entries and the outpoints of the
+    /// coins whose script public key is not derived anymore at their
+    /// derivation.
+    pub fn revalidate_addresses(&self) -> (Vec<AddressEntry>, Vec<OutPoint>) {
+        let addresses = self.address_store.mismatched_entries();
+        let derivators = self.derivators();
+        let coins = self
+            .store
+            .iter()
+            .filter(|(_, coin)| {
+                let (account, index) = coin.deriv();
+                let spk = coin.spk();
+                !derivators.iter().any(|d| d.spk_at(account, index) == spk)
+            })
+            .map(|(op, _)| *op)
+            .collect();
+        (addresses, coins)
+    }
+
+    /// Returns whether every history update has been applied, i.e. the
+    /// status of every subscribed script has been received, its history
+    /// processed and no update is waiting for transactions requested to
+    /// the server.
+    pub fn is_synced(&self) -> bool {
+        !self.in_flight && self.updates.is_empty()
+    }
+
+    /// Records whether the transaction listener waits for some responses
+    /// of the electrum server.
+    ///
+    /// # Parameters
+    /// - `in_flight`: Whether some requests are unanswered.
+    pub fn set_in_flight(&mut self, in_flight: bool) {
+        self.in_flight = in_flight;
     }
 
     /// Updates the history for a specific script public key (SPK).
@@ -331,14 +523,16 @@ impl CoinStore {
     /// - `history`: The new transaction history for the SPK.
     ///
     /// # Returns
-    /// An `Update` representing the changes made to the SPK history.
+    /// A tuple containing whether stored transactions have been updated or
+    /// removed and an `Update` representing the changes made to the SPK
+    /// history.
     ///
     /// Note: triggered on history_get response
     pub fn update_spk_history(
         &mut self,
         spk: ScriptBuf,
         history: Vec<(Txid, Option<u64> /* height */)>,
-    ) -> (bool /* height_updated */, Update) {
+    ) -> (bool /* updated */, Update) {
         // insert a blank history if no one
         if !self.spk_history.contains_key(&spk) {
             self.spk_history.insert(spk.clone(), SpkHistory::new());
@@ -362,7 +556,28 @@ impl CoinStore {
             }
         } // <- release &mut tx_store
 
-        (!diff.changed.is_empty(), Update::from_diff(spk, diff))
+        // a pending update must not bring back a tx that left every history
+        if !diff.removed.is_empty() {
+            let history = self.history_txids();
+            for Update { txs, .. } in &mut self.updates {
+                txs.retain(|(txid, ..)| !diff.removed.contains_key(txid) || history.contains(txid));
+            }
+        }
+
+        // NOTE: a removed tx has been dropped from the mempool, likely
+        // replaced, its coins must be dropped even if the replacing tx
+        // does not pay to us
+        let updated = !diff.changed.is_empty() || !diff.removed.is_empty();
+        (updated, Update::from_diff(spk, diff))
+    }
+
+    /// Returns the txids currently in the spk histories.
+    fn history_txids(&self) -> BTreeSet<Txid> {
+        self.spk_history
+            .values()
+            .flat_map(|h| h.txids())
+            .copied()
+            .collect()
     }
 
     /// Handles the response containing transactions.
@@ -387,6 +602,7 @@ impl CoinStore {
         }
         {
             // push every complete update to the tx store
+            let history = self.history_txids();
             let store = &mut self.tx_store;
             self.updates = self
                 .updates
@@ -394,7 +610,7 @@ impl CoinStore {
                 .into_iter()
                 .filter_map(|update| {
                     if update.is_complete() {
-                        store.insert_updates(vec![update]);
+                        store.insert_history_updates(vec![update], &history);
                         None
                     } else {
                         Some(update)
@@ -414,8 +630,30 @@ impl CoinStore {
     /// statuses accordingly.
     pub fn generate(&mut self) {
         self.tx_store.persist();
+        let unconfirmed: BTreeSet<_> = self
+            .tx_store
+            .inner()
+            .iter()
+            .filter(|(_, e)| e.height().is_none())
+            .map(|(txid, _)| *txid)
+            .collect();
+        let previous = self.first_seen.clone();
+        self.first_seen.retain(|txid, _| unconfirmed.contains(txid));
+        let seen = now();
+        for txid in unconfirmed {
+            self.first_seen.entry(txid).or_insert(seen);
+        }
+        // persist the first seen times so a restart do not reset the age
+        // of the stuck transactions
+        if self.first_seen != previous {
+            if let Some(config) = &self.config {
+                config.persist_first_seen(&self.first_seen);
+            }
+        }
+        self.register_coinjoin_txs();
         let addr_store = &mut self.address_store;
         let tx_store = &self.tx_store;
+        let prepared_txs = &self.prepared_txs;
 
         let mut coins = BTreeMap::<OutPoint, CoinEntry>::new();
 
@@ -455,16 +693,21 @@ impl CoinStore {
                         coin,
                         address: addr.address(),
                         label,
+                        spent_by: None,
+                        spent_externally: false,
+                        suspected_dust: false,
                     };
                     coins.insert(outpoint, coin);
                 }
             }
         }
         // list all spent coins
-        for tx_entry in tx_store.inner().values() {
+        for (txid, tx_entry) in tx_store.inner() {
             for inp in &tx_entry.tx().input {
                 coins.entry(inp.previous_output).and_modify(|e| {
                     e.status = CoinStatus::Spent;
+                    e.spent_by = Some(*txid);
+                    e.spent_externally = !prepared_txs.contains(txid);
                 });
             }
         }
@@ -490,6 +733,48 @@ impl CoinStore {
             }
         } // => release label_store lock
 
+        // flag tiny coins received on reused addresses by a transaction
+        // not spending our coins, likely a dust attack
+        let dust_threshold = self
+            .config
+            .as_ref()
+            .map(|c| c.dust_attack_threshold)
+            .unwrap_or(DUST_ATTACK_THRESHOLD);
+        let suspected: Vec<_> = coins
+            .iter()
+            .filter(|(op, coin)| {
+                let reused = spk_to_outpoint
+                    .get(&coin.spk())
+                    .is_some_and(|ops| ops.len() > 1);
+                let self_sent = tx_store.inner().get(&op.txid).is_some_and(|entry| {
+                    entry
+                        .tx()
+                        .input
+                        .iter()
+                        .any(|inp| coins.contains_key(&inp.previous_output))
+                });
+                coin.amount_sat() < dust_threshold && reused && !self_sent
+            })
+            .map(|(op, _)| *op)
+            .collect();
+        for op in suspected {
+            coins.get_mut(&op).expect("present").suspected_dust = true;
+        }
+
+        let changed = self.store.len() != coins.len()
+            || self
+                .store
+                .iter()
+                .zip(coins.iter())
+                .any(|((op, c), (new_op, new_c))| op != new_op || c.status != new_c.status);
+        if self.generations > 0 && changed {
+            self.last_activity = now();
+            if let Some(config) = &self.config {
+                config.persist_last_activity(self.last_activity);
+            }
+        }
+        self.generations += 1;
+
         self.store = coins;
         self.spk_to_outpoint = spk_to_outpoint;
 
@@ -512,6 +797,86 @@ impl CoinStore {
         }
     }
 
+    /// Returns the unix timestamp (in seconds) of the last coins change, 0
+    /// if unknown.
+    pub fn last_activity(&self) -> u64 {
+        self.last_activity
+    }
+
+    /// Registers a transaction as prepared by this wallet, the coins it
+    /// spends are then not flagged as externally spent.
+    ///
+    /// # Parameters
+    /// - `txid`: The txid of the prepared transaction.
+    pub fn register_prepared_tx(&mut self, txid: Txid) {
+        if !self.prepared_txs.insert(txid) {
+            return;
+        }
+        if let Some(config) = &self.config {
+            config.persist_prepared_txs(&self.prepared_txs);
+        }
+        self.store
+            .values_mut()
+            .filter(|c| c.spent_by == Some(txid))
+            .for_each(|c| c.spent_externally = false);
+    }
+
+    /// Registers the output of a coinjoin we take part in, the transaction
+    /// paying to it is registered as prepared by this wallet once received.
+    ///
+    /// # Parameters
+    /// - `spk`: The script pubkey of our coinjoin output.
+    pub fn register_coinjoin_output(&mut self, spk: ScriptBuf) {
+        if !self.coinjoin_outputs.contains_key(&spk) {
+            self.coinjoin_outputs.insert(spk, None);
+            if let Some(config) = &self.config {
+                config.persist_coinjoin_outputs(&self.coinjoin_outputs);
+            }
+        }
+        self.register_coinjoin_txs();
+    }
+
+    /// Returns the txid of the coinjoin paying to a registered coinjoin
+    /// output, if received.
+    ///
+    /// # Parameters
+    /// - `spk`: The script pubkey of our coinjoin output.
+    pub fn coinjoin_txid(&self, spk: &ScriptBuf) -> Option<Txid> {
+        self.coinjoin_outputs.get(spk).copied().flatten()
+    }
+
+    /// Registers as prepared the transactions of the store paying to a
+    /// registered coinjoin output whose coinjoin is not seen yet.
+    fn register_coinjoin_txs(&mut self) {
+        let pending: Vec<_> = self
+            .coinjoin_outputs
+            .iter()
+            .filter(|(_, txid)| txid.is_none())
+            .map(|(spk, _)| spk.clone())
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+        let mut found = vec![];
+        for (txid, entry) in self.tx_store.inner() {
+            for output in &entry.tx().output {
+                if pending.contains(&output.script_pubkey) {
+                    found.push((output.script_pubkey.clone(), *txid));
+                }
+            }
+        }
+        if found.is_empty() {
+            return;
+        }
+        for (spk, txid) in found {
+            self.coinjoin_outputs.insert(spk, Some(txid));
+            self.register_prepared_tx(txid);
+        }
+        if let Some(config) = &self.config {
+            config.persist_coinjoin_outputs(&self.coinjoin_outputs);
+        }
+    }
+
     /// Retrieves coins by their status.
     ///
     /// This method filters the coins in the store based on the specified
@@ -529,7 +894,8 @@ impl CoinStore {
                         .address_store
                         .get_entry(&coin.spk())
                         .expect("coins have an address");
-                    Some(rust_coin(coin, address))
+                    let frozen = self.is_frozen(&coin.coin.outpoint);
+                    Some(rust_coin(coin, address, frozen))
                 } else {
                     None
                 }
@@ -548,28 +914,252 @@ impl CoinStore {
         self.store.get(outpoint).cloned()
     }
 
+    /// Retrieves the coins paid to a script pubkey, spent ones included.
+    ///
+    /// # Parameters
+    /// - `spk`: The script pubkey the coins are paid to.
+    ///
+    /// # Returns
+    /// A vector of `RustCoin` sorted by outpoint.
+    pub fn coins_at(&self, spk: &ScriptBuf) -> Vec<RustCoin> {
+        let Some(address) = self.address_store.get_entry(spk) else {
+            return vec![];
+        };
+        let mut outpoints: Vec<_> = self
+            .spk_to_outpoint
+            .get(spk)
+            .map(|ops| ops.iter().collect())
+            .unwrap_or_default();
+        outpoints.sort();
+        outpoints
+            .into_iter()
+            .filter_map(|op| self.store.get(op).cloned())
+            .map(|coin| {
+                let frozen = self.is_frozen(&coin.coin.outpoint);
+                rust_coin(coin, address.clone(), frozen)
+            })
+            .collect()
+    }
+
+    /// Retrieves the coins created by the coinjoin paying to one of our
+    /// coinjoin outputs.
+    ///
+    /// # Parameters
+    /// - `spk`: The script pubkey of our coinjoin output.
+    ///
+    /// # Returns
+    /// A vector of `RustCoin` sorted by outpoint, empty if the coinjoin is
+    /// not seen yet.
+    pub fn coinjoin_coins(&self, spk: &ScriptBuf) -> Vec<RustCoin> {
+        let Some(txid) = self.coinjoin_txid(spk) else {
+            return vec![];
+        };
+        self.coins_at(spk)
+            .into_iter()
+            .filter(|c| OutPoint::from_str(&c.outpoint).is_ok_and(|op| op.txid == txid))
+            .collect()
+    }
+
+    /// Retrieves a stored transaction by its txid.
+    ///
+    /// # Parameters
+    /// - `txid`: The txid of the transaction.
+    ///
+    /// # Returns
+    /// An `Option<bitcoin::Transaction>` containing the transaction if known.
+    pub fn transaction(&self, txid: &bitcoin::Txid) -> Option<bitcoin::Transaction> {
+        self.tx_store.inner_get(txid)
+    }
+
+    /// Returns the unconfirmed chain a coin is part of, see
+    /// [`TxStore::unconfirmed_ancestors`].
+    ///
+    /// # Parameters
+    /// - `outpoint`: The outpoint of the coin.
+    ///
+    /// # Returns
+    /// A tuple containing the number of unconfirmed ancestors of the coin
+    /// (the transaction creating it included) and the sum of their fees in
+    /// sats, zeros if the coin is confirmed or unknown.
+    pub fn unconfirmed_chain(&self, outpoint: &bitcoin::OutPoint) -> (usize, u64) {
+        match self.store.get(outpoint) {
+            Some(coin) if coin.height.is_none() => {
+                self.tx_store.unconfirmed_ancestors(&outpoint.txid)
+            }
+            _ => (0, 0),
+        }
+    }
+
+    /// Returns the stored transactions that are not yet confirmed.
+    pub fn unconfirmed_transactions(&self) -> Vec<bitcoin::Transaction> {
+        self.tx_store.unconfirmed()
+    }
+
+    /// Returns the stored transactions that are not yet confirmed, with
+    /// the amounts they move from and to the wallet.
+    pub fn pending_transactions(&self) -> Vec<RustTransaction> {
+        self.tx_store
+            .inner()
+            .values()
+            .filter(|e| e.height().is_none())
+            .map(|e| self.rust_transaction(e))
+            .collect()
+    }
+
+    /// Returns all the stored transactions, with the amounts they move from
+    /// and to the wallet, confirmed ones first by height.
+    pub fn transactions(&self) -> Vec<RustTransaction> {
+        let mut txs: Vec<_> = self
+            .tx_store
+            .inner()
+            .values()
+            .map(|e| self.rust_transaction(e))
+            .collect();
+        txs.sort_by_key(|t| (t.height == 0, t.height, t.txid.clone()));
+        txs
+    }
+
+    /// Returns the unconfirmed transactions seen for at least
+    /// `min_age_blocks` blocks, assuming a block every [`BLOCK_INTERVAL`]
+    /// seconds.
+    ///
+    /// # Parameters
+    /// - `min_age_blocks`: The minimum age of a transaction, in blocks.
+    pub fn stuck_transactions(&self, min_age_blocks: u64) -> Vec<RustTransaction> {
+        let max_seen = now().saturating_sub(min_age_blocks.saturating_mul(BLOCK_INTERVAL));
+        self.first_seen
+            .iter()
+            .filter(|(_, seen)| **seen <= max_seen)
+            .filter_map(|(txid, _)| self.tx_store.inner().get(txid))
+            .filter(|e| e.height().is_none())
+            .map(|e| self.rust_transaction(e))
+            .collect()
+    }
+
+    /// Converts a stored transaction for the C++ side.
+    ///
+    /// # Parameters
+    /// - `entry`: The stored transaction.
+    fn rust_transaction(&self, entry: &TxEntry) -> RustTransaction {
+        let tx = entry.tx();
+        let received = tx
+            .output
+            .iter()
+            .filter(|o| self.address_store.get_entry(&o.script_pubkey).is_some())
+            .map(|o| o.value.to_sat())
+            .sum();
+        let sent = tx
+            .input
+            .iter()
+            .filter_map(|i| self.store.get(&i.previous_output))
+            .map(|c| c.amount_sat())
+            .sum();
+        // the fee is known only if all the parent transactions are stored
+        let inputs_total: Option<u64> = tx
+            .input
+            .iter()
+            .map(|i| {
+                let op = i.previous_output;
+                self.tx_store
+                    .inner()
+                    .get(&op.txid)
+                    .and_then(|p| p.tx().output.get(op.vout as usize))
+                    .map(|o| o.value.to_sat())
+            })
+            .sum();
+        let outputs_total: u64 = tx.output.iter().map(|o| o.value.to_sat()).sum();
+        RustTransaction {
+            txid: entry.txid().to_string(),
+            height: entry.height().unwrap_or(0),
+            received,
+            sent,
+            fee: inputs_total
+                .and_then(|total| total.checked_sub(outputs_total))
+                .unwrap_or(0),
+        }
+    }
+
+    /// Freezes or unfreezes a coin, a frozen coin is never spent.
+    ///
+    /// # Parameters
+    /// - `outpoint`: The outpoint of the coin.
+    /// - `frozen`: Whether the coin must be frozen.
+    ///
+    /// # Returns
+    /// `false` if the coin is not in the store.
+    pub fn freeze_coin(&mut self, outpoint: OutPoint, frozen: bool) -> bool {
+        if !self.store.contains_key(&outpoint) {
+            return false;
+        }
+        let changed = if frozen {
+            self.frozen.insert(outpoint)
+        } else {
+            self.frozen.remove(&outpoint)
+        };
+        if changed {
+            if let Some(config) = &self.config {
+                config.persist_frozen(&self.frozen);
+            }
+        }
+        true
+    }
+
+    /// Returns whether a coin is frozen.
+    ///
+    /// # Parameters
+    /// - `outpoint`: The outpoint of the coin.
+    pub fn is_frozen(&self, outpoint: &OutPoint) -> bool {
+        self.frozen.contains(outpoint)
+    }
+
+    /// Returns the outpoints of the frozen coins.
+    pub fn frozen_coins(&self) -> Vec<OutPoint> {
+        self.frozen.iter().copied().collect()
+    }
+
     /// Retrieves spendable coins from the store.
     ///
     /// This method filters the coins that are either unconfirmed or
-    /// confirmed and returns them as a `Coins` object.
+    /// confirmed and not frozen, and returns them as a `Coins` object.
     pub fn spendable_coins(&self) -> CoinState {
+        self.spendable_coins_sorted(CoinSort::Value)
+    }
+
+    /// Retrieves spendable coins from the store in the given order.
+    ///
+    /// # Parameters
+    /// - `sort`: The order of the coins:
+    ///   - `Value`: by value, the default order.
+    ///   - `Age`: oldest confirmed first, unconfirmed coins last.
+    ///   - `Outpoint`: by txid then output index.
+    ///   - `Label`: by label, unlabeled coins first.
+    ///
+    /// Ties are broken by outpoint.
+    pub fn spendable_coins_sorted(&self, sort: CoinSort) -> CoinState {
         let mut coins: Vec<_> = self
             .store
             .clone()
             .into_iter()
+            .filter(|(op, _)| !self.frozen.contains(op))
             .filter_map(|(_, coin)| match coin.status {
                 CoinStatus::Unconfirmed | CoinStatus::Confirmed | CoinStatus::BeingSpend => {
                     let address = self
                         .address_store
                         .get_entry(&coin.spk())
                         .expect("coin have a valid address");
-                    Some(rust_coin(coin, address))
+                    Some(rust_coin(coin, address, false))
                 }
                 CoinStatus::Spent => None,
                 _ => unreachable!(),
             })
             .collect();
-        coins.sort();
+        let outpoint = |c: &RustCoin| OutPoint::from_str(&c.outpoint).expect("valid outpoint");
+        match sort {
+            CoinSort::Age => coins.sort_by_key(|c| (!c.confirmed, c.height, outpoint(c))),
+            CoinSort::Outpoint => coins.sort_by_key(outpoint),
+            CoinSort::Label => coins.sort_by_key(|c| (c.label.clone(), outpoint(c))),
+            _ => coins.sort(),
+        }
         let mut state = CoinState {
             coins: vec![],
             confirmed_coins: 0,
@@ -577,7 +1167,7 @@ impl CoinStore {
             unconfirmed_coins: 0,
             unconfirmed_balance: 0,
         };
-        for coin in &state.coins {
+        for coin in &coins {
             match coin.status {
                 CoinStatus::Unconfirmed => {
                     state.unconfirmed_coins += 1;
@@ -594,6 +1184,32 @@ impl CoinStore {
         state
     }
 
+    /// Returns the balance of the wallet as of a past block height.
+    ///
+    /// Only coins confirmed at or before `height` and not spent by a
+    /// transaction confirmed at or before `height` are accounted, unconfirmed
+    /// coins are excluded.
+    ///
+    /// # Parameters
+    /// - `height`: The block height at which the balance is computed.
+    ///
+    /// # Returns
+    /// The balance in satoshis.
+    pub fn balance_at_height(&self, height: u64) -> u64 {
+        self.store
+            .values()
+            .filter(|coin| coin.height.is_some_and(|h| h <= height))
+            .filter(|coin| {
+                let spent_at = coin
+                    .spent_by
+                    .and_then(|txid| self.tx_store.inner().get(&txid))
+                    .and_then(|entry| entry.height());
+                !spent_at.is_some_and(|h| h <= height)
+            })
+            .map(|coin| coin.amount_sat())
+            .sum()
+    }
+
     /// Returns all coins in the store.
     ///
     /// # Returns
@@ -611,6 +1227,116 @@ impl CoinStore {
         serde_json::to_value(&self.store)
     }
 
+    /// Dumps the transactions of the store as a JSON value.
+    ///
+    /// # Returns
+    /// A `Result` containing the serialized JSON value of the transactions
+    /// or an error if serialization fails.
+    pub fn dump_transactions(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self.tx_store.inner())
+    }
+
+    /// Imports transactions fetched by another instance of the wallet, e.g.
+    /// a watch-only instance syncing for an offline signer, then generates
+    /// the coins. The tips are advanced as payments to the wallet are found,
+    /// so payments beyond the current look-ahead window are imported too.
+    ///
+    /// # Parameters
+    /// - `entries`: The transactions to import, indexed by their txid.
+    ///
+    /// # Returns
+    /// The number of imported transactions, the ones neither paying to nor
+    /// spending from the wallet being skipped.
+    pub fn import_transactions(&mut self, entries: BTreeMap<Txid, TxEntry>) -> usize {
+        // outpoints paying to the wallet, used to detect spends
+        let mut ours = HashSet::new();
+        let stored: Vec<_> = self
+            .tx_store
+            .inner()
+            .iter()
+            .map(|(txid, entry)| (*txid, entry.tx().clone()))
+            .collect();
+        for (txid, tx) in stored {
+            self.receive_outputs(&txid, &tx, &mut ours);
+        }
+
+        // NOTE: receiving a coin advances the tips and populates the
+        // look-ahead, a transaction skipped in a pass can then pay to a
+        // newly populated address, so the remaining transactions are checked
+        // again until a pass finds nothing new.
+        let mut imported = 0;
+        let mut pending: Vec<_> = entries
+            .into_iter()
+            .filter(|(txid, entry)| entry.txid() == *txid)
+            .collect();
+        loop {
+            let mut found = false;
+            let mut others = vec![];
+            for (txid, entry) in pending {
+                let paying = self.receive_outputs(&txid, entry.tx(), &mut ours);
+                let spending = entry
+                    .tx()
+                    .input
+                    .iter()
+                    .any(|i| ours.contains(&i.previous_output));
+                if paying || spending {
+                    self.tx_store.update(entry);
+                    imported += 1;
+                    found = true;
+                } else {
+                    others.push((txid, entry));
+                }
+            }
+            pending = others;
+            if !found {
+                break;
+            }
+        }
+        for (txid, _) in pending {
+            log::debug!("CoinStore::import_transactions() skip {txid}, not ours");
+        }
+
+        self.generate();
+        imported
+    }
+
+    /// Records the outputs of a transaction paying to the wallet and
+    /// advances the tips accordingly.
+    ///
+    /// # Parameters
+    /// - `txid`: The txid of the transaction.
+    /// - `tx`: The transaction.
+    /// - `ours`: The outpoints paying to the wallet, extended with the
+    ///   outputs of `tx` paying to the wallet.
+    ///
+    /// # Returns
+    /// Whether the transaction pays to the wallet.
+    fn receive_outputs(
+        &mut self,
+        txid: &Txid,
+        tx: &bitcoin::Transaction,
+        ours: &mut HashSet<OutPoint>,
+    ) -> bool {
+        let mut found = false;
+        for (vout, txout) in tx.output.iter().enumerate() {
+            if self.address_store.get_entry(&txout.script_pubkey).is_some() {
+                ours.insert(OutPoint::new(*txid, vout as u32));
+                self.recv_coin_at(&txout.script_pubkey);
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Drops all the transactions, histories and coins in order to fetch
+    /// them again from electrum, labels and watch tips are kept.
+    pub fn reset(&mut self) {
+        self.tx_store.clear();
+        self.spk_history.clear();
+        self.updates.clear();
+        self.generate();
+    }
+
     /// Restores the coin store from a JSON value.
     ///
     /// # Parameters
@@ -622,6 +1348,74 @@ impl CoinStore {
         self.store = serde_json::from_value(value)?;
         Ok(())
     }
+
+    /// Dumps the coin store to a writer as a JSON array of coin entries.
+    ///
+    /// Unlike [`CoinStore::dump`], coins are serialized one at a time, so
+    /// the whole store is never built in memory as a JSON tree.
+    ///
+    /// # Parameters
+    /// - `writer`: The sink to write the coins to.
+    ///
+    /// # Returns
+    /// A `Result` indicating success or failure of the dump.
+    pub fn dump_to<W: Write>(&self, mut writer: W) -> Result<(), serde_json::Error> {
+        writer.write_all(b"[").map_err(serde_json::Error::io)?;
+        for (i, entry) in self.store.values().enumerate() {
+            if i > 0 {
+                writer.write_all(b",").map_err(serde_json::Error::io)?;
+            }
+            serde_json::to_writer(&mut writer, entry)?;
+        }
+        writer.write_all(b"]").map_err(serde_json::Error::io)?;
+        writer.flush().map_err(serde_json::Error::io)
+    }
+
+    /// Restores the coin store from a reader containing a JSON array of coin
+    /// entries, as written by [`CoinStore::dump_to`].
+    ///
+    /// Coins are deserialized one at a time, the store is left untouched if
+    /// the restoration fails.
+    ///
+    /// # Parameters
+    /// - `reader`: The source to read the coins from, it should be buffered.
+    ///
+    /// # Returns
+    /// A `Result` indicating success or failure of the restoration.
+    pub fn restore_from<R: Read>(&mut self, reader: R) -> Result<(), serde_json::Error> {
+        let mut store = BTreeMap::new();
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        CoinsSeed(&mut store).deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        self.store = store;
+        Ok(())
+    }
+}
+
+/// Deserializes a sequence of coin entries directly into a coin map.
+struct CoinsSeed<'a>(&'a mut BTreeMap<OutPoint, CoinEntry>);
+
+impl<'de> DeserializeSeed<'de> for CoinsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for CoinsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of coin entries")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(entry) = seq.next_element::<CoinEntry>()? {
+            self.0.insert(entry.coin.outpoint, entry);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -689,6 +1483,12 @@ pub struct CoinEntry {
     pub coin: coin::Coin,
     address: bitcoin::Address<NetworkUnchecked>,
     label: Option<String>,
+    #[serde(default)]
+    spent_by: Option<Txid>,
+    #[serde(default)]
+    spent_externally: bool,
+    #[serde(default)]
+    suspected_dust: bool,
 }
 
 impl CoinEntry {
@@ -707,6 +1507,30 @@ impl CoinEntry {
     pub fn status(&self) -> CoinStatus {
         self.status
     }
+    /// Returns the txid of the transaction spending this coin.
+    ///
+    /// # Returns
+    /// An `Option<Txid>` containing the spending txid, or `None` if the
+    /// coin is not spent.
+    pub fn spent_by(&self) -> Option<Txid> {
+        self.spent_by
+    }
+    /// Returns whether the coin is spent by a transaction this wallet did
+    /// not prepare.
+    ///
+    /// # Returns
+    /// `true` if the spending transaction is not one built by this wallet.
+    pub fn spent_externally(&self) -> bool {
+        self.spent_externally
+    }
+    /// Returns whether the coin is a tiny amount received on a reused
+    /// address, likely a dust attack, the user should freeze it.
+    ///
+    /// # Returns
+    /// `true` if the coin is suspected to be a dust attack.
+    pub fn suspected_dust(&self) -> bool {
+        self.suspected_dust
+    }
     /// Returns a string representation of the coin's status.
     ///
     /// # Returns
@@ -791,16 +1615,25 @@ impl CoinEntry {
     }
     /// Returns the address associated with the coin as an 'RustAddress'
     ///
+    /// # Parameters
+    /// - `store`: The coin store tracking the coin, the address entry of the
+    ///   coin spk gives its status, derivation path and descriptor.
+    ///
     /// # Returns
-    /// A boxed AddressEntry representation of the coin's address.
-    pub fn rust_address(&self) -> RustAddress {
-        AddressEntry {
-            status: AddressStatus::Unknown,
-            address: self.address.clone(),
-            account: self.coin.coin_path.0,
-            index: self.coin.coin_path.1,
-        }
-        .into()
+    /// A boxed AddressEntry representation of the coin's address, its status
+    /// and derivation path are unknown if the store has no entry for it.
+    pub fn rust_address(&self, store: &CoinStore) -> RustAddress {
+        store
+            .address_info(&self.spk())
+            .unwrap_or_else(|| AddressEntry {
+                status: AddressStatus::Unknown,
+                address: self.address.clone(),
+                account: self.coin.coin_path.0,
+                index: self.coin.coin_path.1,
+                secondary: false,
+                derivation_path: String::new(),
+            })
+            .into()
     }
     /// Returns the script public key (SPK) associated with the coin.
     ///
@@ -811,7 +1644,7 @@ impl CoinEntry {
     }
 }
 
-pub fn rust_coin(coin: CoinEntry, address: AddressEntry) -> RustCoin {
+pub fn rust_coin(coin: CoinEntry, address: AddressEntry, frozen: bool) -> RustCoin {
     RustCoin {
         value: coin.coin.txout.value.to_sat(),
         height: coin.height.unwrap_or(0),
@@ -820,5 +1653,856 @@ pub fn rust_coin(coin: CoinEntry, address: AddressEntry) -> RustCoin {
         outpoint: coin.coin.outpoint.to_string(),
         address: address.into(),
         label: coin.label(),
+        spent_by: coin
+            .spent_by
+            .map(|txid| txid.to_string())
+            .unwrap_or_default(),
+        spent_externally: coin.spent_externally,
+        frozen,
+        suspected_dust: coin.suspected_dust,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use joinstr::{
+        bip39,
+        miniscript::bitcoin::{bip32::DerivationPath, Amount, Sequence, TxOut},
+    };
+
+    use crate::{
+        account::{notification_channel, NotificationReceiver},
+        signer::{tr, wpkh, HotSigner},
+        test_utils::{funding_tx, random_output, spending_tx, txid},
+    };
+
+    use super::*;
+
+    fn coin_store() -> CoinStore {
+        coin_store_with(20).0
+    }
+
+    fn coin_store_with(look_ahead: u32) -> (CoinStore, NotificationReceiver) {
+        let (sender, receiver) = notification_channel();
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let signer =
+            HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string())
+                .unwrap();
+        let descriptor = wpkh(signer.xpub(&DerivationPath::from_str("m/84'/0'/0'").unwrap()));
+        let store = CoinStore::new(
+            bitcoin::Network::Regtest,
+            descriptor,
+            None,
+            sender,
+            0,
+            0,
+            look_ahead,
+            look_ahead,
+            Tip::default(),
+            TxStore::new(Default::default(), None),
+            Arc::new(Mutex::new(LabelStore::new())),
+            None,
+        );
+        (store, receiver)
+    }
+
+    fn coin_entry(
+        address: &bitcoin::Address,
+        outpoint: OutPoint,
+        value: u64,
+        height: Option<u64>,
+    ) -> CoinEntry {
+        CoinEntry {
+            height,
+            status: if height.is_some() {
+                CoinStatus::Confirmed
+            } else {
+                CoinStatus::Unconfirmed
+            },
+            coin: coin::Coin {
+                txout: TxOut {
+                    value: Amount::from_sat(value),
+                    script_pubkey: address.script_pubkey(),
+                },
+                outpoint,
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                coin_path: (AddrAccount::Receive, 0),
+            },
+            address: address.as_unchecked().clone(),
+            label: None,
+            spent_by: None,
+            spent_externally: false,
+            suspected_dust: false,
+        }
+    }
+
+    #[test]
+    fn unconfirmed_chain() {
+        let mut store = coin_store();
+        let spks: Vec<_> = (0..3)
+            .map(|i| store.derivator_ref().receive_spk_at(i))
+            .collect();
+        let spend = |outpoint: OutPoint, spk: &ScriptBuf, value: u64| bitcoin::Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: outpoint,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: spk.clone(),
+            }],
+        };
+
+        // a confirmed coin is spent by a two-deep unconfirmed chain
+        let tx_0 = funding_tx(spks[0].clone(), 0.1);
+        let op_0 = OutPoint::new(tx_0.compute_txid(), tx_0.output.len() as u32 - 1);
+        let tx_1 = spend(op_0, &spks[1], 9_000_000);
+        let op_1 = OutPoint::new(tx_1.compute_txid(), 0);
+        let tx_2 = spend(op_1, &spks[2], 8_500_000);
+        let op_2 = OutPoint::new(tx_2.compute_txid(), 0);
+
+        let mut history = BTreeMap::new();
+        history.insert(
+            spks[0].clone(),
+            vec![(tx_0.compute_txid(), Some(1)), (op_1.txid, None)],
+        );
+        history.insert(spks[1].clone(), vec![(op_1.txid, None), (op_2.txid, None)]);
+        history.insert(spks[2].clone(), vec![(op_2.txid, None)]);
+        store.handle_history_response(history);
+        store.handle_txs_response(vec![tx_0, tx_1, tx_2]);
+
+        assert_eq!(store.unconfirmed_chain(&op_0), (0, 0));
+        assert_eq!(store.unconfirmed_chain(&op_1), (1, 1_000_000));
+        assert_eq!(store.unconfirmed_chain(&op_2), (2, 1_500_000));
+        assert_eq!(store.unconfirmed_chain(&OutPoint::new(txid(0), 0)), (0, 0));
+    }
+
+    #[test]
+    fn import_transactions_beyond_look_ahead() {
+        let (mut store, _receiver) = coin_store_with(5);
+        store.address_store.populate_maybe();
+        let entry = |tx: &bitcoin::Transaction| -> (Txid, TxEntry) {
+            let entry = serde_json::json!({ "height": 1, "tx": tx, "merkle": [] });
+            (tx.compute_txid(), serde_json::from_value(entry).unwrap())
+        };
+        // each payment is only in the look-ahead window once the previous
+        // one has been received
+        let payments: Vec<_> = [5, 10, 15]
+            .into_iter()
+            .map(|i| funding_tx(store.derivator_ref().receive_spk_at(i), 0.1))
+            .collect();
+        let last = payments.last().unwrap();
+        let spend = spending_tx(OutPoint::new(
+            last.compute_txid(),
+            last.output.len() as u32 - 1,
+        ));
+        let unrelated = funding_tx(random_output().script_pubkey, 0.1);
+        let mut entries: BTreeMap<_, _> = payments.iter().map(entry).collect();
+        entries.extend([entry(&spend), entry(&unrelated)]);
+
+        assert_eq!(store.import_transactions(entries), 4);
+        assert_eq!(store.recv_tip(), 15);
+        assert_eq!(store.recv_watch_tip(), 20);
+        assert!(store.tx_store.inner().contains_key(&spend.compute_txid()));
+        assert!(!store
+            .tx_store
+            .inner()
+            .contains_key(&unrelated.compute_txid()));
+        let coins = store.coins();
+        assert_eq!(coins.len(), 3);
+        assert_eq!(coins.values().filter(|c| c.spent_by().is_some()).count(), 1);
+    }
+
+    #[test]
+    fn incoming_rbf() {
+        let mut store = coin_store();
+        let spk = store.derivator_ref().receive_spk_at(0);
+        let payment = |value: u64| bitcoin::Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: OutPoint::new(txid(1), 0),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: spk.clone(),
+            }],
+        };
+        let history = |txid: Txid| {
+            let mut history = BTreeMap::new();
+            history.insert(spk.clone(), vec![(txid, None)]);
+            history
+        };
+
+        // an unconfirmed payment is received
+        let original = payment(100_000);
+        let (_, missing) = store.handle_history_response(history(original.compute_txid()));
+        assert_eq!(missing, vec![original.compute_txid()]);
+        store.handle_txs_response(vec![original.clone()]);
+        assert_eq!(store.coins().len(), 1);
+
+        // the sender bumps the fee, the original tx leaves the history
+        let replacement = payment(99_000);
+        let (updated, missing) = store.handle_history_response(history(replacement.compute_txid()));
+        assert!(updated);
+        assert_eq!(missing, vec![replacement.compute_txid()]);
+        store.handle_txs_response(vec![replacement.clone()]);
+
+        let coins = store.coins();
+        assert_eq!(coins.len(), 1);
+        let coin = coins.values().next().unwrap();
+        assert_eq!(coin.outpoint().txid, replacement.compute_txid());
+        assert_eq!(coin.amount_sat(), 99_000);
+        assert_eq!(coin.status(), CoinStatus::Unconfirmed);
+        assert!(store.transaction(&original.compute_txid()).is_none());
+
+        // a replacement seen before the original leaves the history
+        // also evicts the original
+        let bumped = payment(98_000);
+        let mut both = history(bumped.compute_txid());
+        both.get_mut(&spk)
+            .unwrap()
+            .push((replacement.compute_txid(), None));
+        store.handle_history_response(both);
+        store.handle_txs_response(vec![bumped.clone()]);
+        let coins = store.coins();
+        assert_eq!(coins.len(), 1);
+        assert_eq!(
+            coins.values().next().unwrap().outpoint().txid,
+            bumped.compute_txid()
+        );
+    }
+
+    #[test]
+    fn incoming_rbf_out_of_order() {
+        let mut store = coin_store();
+        let spk = store.derivator_ref().receive_spk_at(0);
+        let payment = |value: u64| bitcoin::Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: OutPoint::new(txid(1), 0),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: spk.clone(),
+            }],
+        };
+        let history = |txid: Txid| {
+            let mut history = BTreeMap::new();
+            history.insert(spk.clone(), vec![(txid, None)]);
+            history
+        };
+        let original = payment(100_000);
+        let replacement = payment(99_000);
+
+        // the original is replaced before it has been fetched
+        store.handle_history_response(history(original.compute_txid()));
+        store.handle_history_response(history(replacement.compute_txid()));
+        store.handle_txs_response(vec![original.clone()]);
+        assert!(store.coins().is_empty());
+        store.handle_txs_response(vec![replacement.clone()]);
+        assert_eq!(store.coins().len(), 1);
+
+        // a stale update completed after the replacement does not evict it
+        store.updates.push(Update {
+            spk: spk.clone(),
+            txs: vec![(original.compute_txid(), Some(original.clone()), None)],
+        });
+        store.handle_txs_response(vec![]);
+        let coins = store.coins();
+        assert_eq!(coins.len(), 1);
+        assert_eq!(
+            coins.values().next().unwrap().outpoint().txid,
+            replacement.compute_txid()
+        );
+        assert!(store.transaction(&original.compute_txid()).is_none());
+    }
+
+    #[test]
+    fn coins_at() {
+        let mut store = coin_store();
+        let spks: Vec<_> = (0..2)
+            .map(|i| store.derivator_ref().receive_spk_at(i))
+            .collect();
+        let tx_0 = funding_tx(spks[0].clone(), 0.1);
+        let tx_1 = funding_tx(spks[1].clone(), 0.2);
+        let mut history = BTreeMap::new();
+        history.insert(spks[0].clone(), vec![(tx_0.compute_txid(), Some(1))]);
+        history.insert(spks[1].clone(), vec![(tx_1.compute_txid(), Some(1))]);
+        store.handle_history_response(history);
+        store.handle_txs_response(vec![tx_0.clone(), tx_1]);
+
+        let coins = store.coins_at(&spks[0]);
+        assert_eq!(coins.len(), 1);
+        assert_eq!(coins[0].value, 10_000_000);
+        assert!(coins[0]
+            .outpoint
+            .starts_with(&tx_0.compute_txid().to_string()));
+        assert!(store
+            .coins_at(&store.derivator_ref().receive_spk_at(5))
+            .is_empty());
+        assert!(store.coins_at(&ScriptBuf::new()).is_empty());
+
+        // frozen coins are still listed, flagged as frozen
+        assert!(!coins[0].frozen);
+        let op = OutPoint::from_str(&coins[0].outpoint).unwrap();
+        assert!(store.freeze_coin(op, true));
+        assert!(store.coins_at(&spks[0])[0].frozen);
+    }
+
+    #[test]
+    fn streaming_dump_restore() {
+        let mut store = coin_store();
+        let address = store.derivator_ref().receive_at(0);
+        for i in 0..10_000u32 {
+            let outpoint = OutPoint::new(txid((i % 256) as u8), i);
+            let height = (i % 2 == 0).then_some(i as u64);
+            let entry = coin_entry(&address, outpoint, 1_000 + i as u64, height);
+            store.store.insert(outpoint, entry);
+        }
+
+        let mut buffer = vec![];
+        store.dump_to(&mut buffer).unwrap();
+
+        let mut restored = coin_store();
+        restored.restore_from(buffer.as_slice()).unwrap();
+
+        let (coins, restored_coins) = (store.coins(), restored.coins());
+        assert_eq!(restored_coins.len(), 10_000);
+        for ((op, coin), (r_op, r_coin)) in coins.iter().zip(restored_coins.iter()) {
+            assert_eq!(op, r_op);
+            assert_eq!(coin.height(), r_coin.height());
+            assert_eq!(coin.status(), r_coin.status());
+            assert_eq!(coin.amount_sat(), r_coin.amount_sat());
+            assert_eq!(coin.address(), r_coin.address());
+        }
+
+        // a truncated dump is rejected and the store left untouched
+        assert!(restored.restore_from(&buffer[..buffer.len() - 1]).is_err());
+        assert_eq!(restored.coins().len(), 10_000);
+    }
+
+    #[test]
+    fn next_recv_entry_reuse_unused() {
+        let (mut store, notif) = coin_store_with(3);
+        let gap_warnings = || {
+            std::iter::from_fn(|| notif.try_recv().ok())
+                .filter_map(|n| match n {
+                    Notification::AddressGapWarning(gap) => Some(gap),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // the unused address at the tip is returned again
+        assert_eq!(store.next_recv_entry(true).index, 0);
+        assert_eq!(store.next_recv_entry(true).index, 0);
+        assert_eq!(store.recv_tip(), 0);
+
+        // once used, a new address is generated
+        let first = store.next_recv_entry(true);
+        store
+            .address_store
+            .get_entry_mut(&first.script())
+            .unwrap()
+            .status = AddressStatus::Used;
+        let entry = store.next_recv_entry(true);
+        assert_eq!(entry.index, 1);
+        assert_eq!(entry.status, AddressStatus::NotUsed);
+        assert_eq!(entry.account, AddrAccount::Receive);
+        assert_eq!(store.next_recv_entry(true).index, 1);
+
+        // without the reuse policy a new address is always generated
+        assert_eq!(store.next_recv_entry(false).index, 2);
+        assert_eq!(store.address_store.recv_unused_gap(), 2);
+        assert!(gap_warnings().is_empty());
+
+        // the unused addresses reach the look-ahead
+        assert_eq!(store.next_recv_entry(false).index, 3);
+        assert_eq!(gap_warnings(), vec![3]);
+    }
+
+    #[test]
+    fn new_coinjoin_addr_skip_used() {
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let signer =
+            HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string())
+                .unwrap();
+        let descriptor = wpkh(signer.xpub(&DerivationPath::from_str("m/84'/0'/0'").unwrap()));
+        let secondary = tr(signer.xpub(&DerivationPath::from_str("m/86'/0'/0'").unwrap()));
+        let derivator = Derivator::new(secondary.clone(), bitcoin::Network::Regtest).unwrap();
+        let mut store = CoinStore::new(
+            bitcoin::Network::Regtest,
+            descriptor,
+            Some(secondary),
+            notification_channel().0,
+            0,
+            0,
+            20,
+            20,
+            Tip::default(),
+            TxStore::new(Default::default(), None),
+            Arc::new(Mutex::new(LabelStore::new())),
+            None,
+        );
+        store.address_store.populate_maybe();
+        let (tip, change_tip) = (store.recv_tip(), store.change_tip());
+        // the next secondary address has already been used
+        let spk = derivator.receive_spk_at(1);
+        store.address_store.get_entry_mut(&spk).unwrap().status = AddressStatus::Used;
+
+        // the secondary descriptor has its own tips, the main ones are left
+        // untouched
+        let address = store.new_coinjoin_addr(AddrAccount::Receive, true);
+        assert_eq!(address, derivator.receive_at(2));
+        let address = store.new_coinjoin_addr(AddrAccount::Change, true);
+        assert_eq!(address, derivator.change_at(1));
+        assert_eq!(
+            store.address_store.secondary_tip(),
+            Tip {
+                receive: 2,
+                change: 1
+            }
+        );
+        assert_eq!((store.recv_tip(), store.change_tip()), (tip, change_tip));
+
+        // the main descriptor skips its used addresses too
+        let spk = store.derivator_ref().receive_spk_at(tip + 1);
+        store.address_store.get_entry_mut(&spk).unwrap().status = AddressStatus::Used;
+        let address = store.new_coinjoin_addr(AddrAccount::Receive, false);
+        assert_eq!(address, store.derivator_ref().receive_at(tip + 2));
+
+        // a coin received far on the secondary descriptor moves its watch
+        // tip, not the tips of the main descriptor
+        let watch_tip = store.address_store.recv_watch_tip();
+        let spk = derivator.receive_spk_at(watch_tip - 1);
+        store.address_store.recv_coin_at(&spk);
+        assert_eq!(store.address_store.secondary_tip().receive, watch_tip - 1);
+        assert_eq!(store.recv_tip(), tip + 2);
+        assert_eq!(store.address_store.recv_watch_tip(), watch_tip - 1 + 20 + 1);
+    }
+
+    #[test]
+    fn recv_unused_gap_ignore_secondary() {
+        let mnemonic = bip39::Mnemonic::generate(12).unwrap();
+        let signer =
+            HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string())
+                .unwrap();
+        let descriptor = wpkh(signer.xpub(&DerivationPath::from_str("m/84'/0'/0'").unwrap()));
+        let secondary = tr(signer.xpub(&DerivationPath::from_str("m/86'/0'/0'").unwrap()));
+        let derivator = Derivator::new(secondary.clone(), bitcoin::Network::Regtest).unwrap();
+        let mut store = CoinStore::new(
+            bitcoin::Network::Regtest,
+            descriptor,
+            Some(secondary),
+            notification_channel().0,
+            0,
+            0,
+            20,
+            20,
+            Tip::default(),
+            TxStore::new(Default::default(), None),
+            Arc::new(Mutex::new(LabelStore::new())),
+            None,
+        );
+        store.address_store.populate_maybe();
+        for _ in 0..3 {
+            store.next_recv_entry(false);
+        }
+        let gap = store.address_store.recv_unused_gap();
+        assert_eq!(gap, store.recv_tip());
+
+        // a used secondary address do not close the gap
+        let spk = derivator.receive_spk_at(store.recv_tip());
+        store.address_store.get_entry_mut(&spk).unwrap().status = AddressStatus::Used;
+        assert_eq!(store.address_store.recv_unused_gap(), gap);
+
+        // a used main address does
+        let spk = store.derivator_ref().receive_spk_at(store.recv_tip());
+        store.address_store.get_entry_mut(&spk).unwrap().status = AddressStatus::Used;
+        assert_eq!(store.address_store.recv_unused_gap(), 0);
+    }
+
+    #[test]
+    fn new_change_addr_skip_used() {
+        let mut store = coin_store();
+        store.address_store.populate_maybe();
+        let tip = store.change_tip();
+        // the change tip lags behind used change addresses
+        for index in [tip + 1, tip + 2] {
+            let spk = store.derivator_ref().change_spk_at(index);
+            store.address_store.get_entry_mut(&spk).unwrap().status = AddressStatus::Used;
+        }
+
+        let address = store.new_change_addr();
+        assert_eq!(address, store.derivator_ref().change_at(tip + 3));
+        assert_eq!(store.change_tip(), tip + 3);
+        let entry = store.address_info(&address.script_pubkey()).unwrap();
+        assert_eq!(entry.status, AddressStatus::NotUsed);
+
+        // next one is the following index
+        let address = store.new_change_addr();
+        assert_eq!(address, store.derivator_ref().change_at(tip + 4));
+    }
+
+    #[test]
+    fn last_activity() {
+        let mut store = coin_store();
+        let spk = store.new_recv_addr().script_pubkey();
+
+        // loading the persisted coins is not an activity
+        let tx = funding_tx(spk.clone(), 0.1);
+        store.tx_store.insert_updates(vec![Update {
+            spk: spk.clone(),
+            txs: vec![(tx.compute_txid(), Some(tx), None)],
+        }]);
+        store.generate();
+        assert_eq!(store.last_activity(), 0);
+
+        // nor a generation w/o coins change
+        store.generate();
+        assert_eq!(store.last_activity(), 0);
+
+        let tx = funding_tx(spk.clone(), 0.2);
+        store.tx_store.insert_updates(vec![Update {
+            spk,
+            txs: vec![(tx.compute_txid(), Some(tx), Some(1))],
+        }]);
+        store.generate();
+        let funded = store.last_activity();
+        assert!(funded > 0);
+        assert!(funded <= now());
+    }
+
+    #[test]
+    fn balance_at_height() {
+        let mut store = coin_store();
+        let spk = store.new_recv_addr().script_pubkey();
+        let update = |tx: bitcoin::Transaction, height| Update {
+            spk: spk.clone(),
+            txs: vec![(tx.compute_txid(), Some(tx), height)],
+        };
+        let outpoint = |tx: &bitcoin::Transaction| {
+            let vout = tx.output.iter().position(|o| o.script_pubkey == spk);
+            OutPoint::new(tx.compute_txid(), vout.unwrap() as u32)
+        };
+
+        // coin a funded at 10 & spent at 20
+        let fund_a = funding_tx(spk.clone(), 0.1);
+        let spend_a = spending_tx(outpoint(&fund_a));
+        // coin b funded at 15 & spent by an unconfirmed tx
+        let fund_b = funding_tx(spk.clone(), 0.2);
+        let spend_b = spending_tx(outpoint(&fund_b));
+        // unconfirmed coin c
+        let fund_c = funding_tx(spk.clone(), 0.4);
+        store.tx_store.insert_updates(vec![
+            update(fund_a, Some(10)),
+            update(spend_a, Some(20)),
+            update(fund_b, Some(15)),
+            update(spend_b, None),
+            update(fund_c, None),
+        ]);
+        store.generate();
+
+        let (a, b) = (10_000_000, 20_000_000);
+        assert_eq!(store.balance_at_height(9), 0);
+        assert_eq!(store.balance_at_height(10), a);
+        assert_eq!(store.balance_at_height(14), a);
+        assert_eq!(store.balance_at_height(15), a + b);
+        assert_eq!(store.balance_at_height(19), a + b);
+        assert_eq!(store.balance_at_height(20), b);
+        assert_eq!(store.balance_at_height(100), b);
+    }
+
+    #[test]
+    fn suspected_dust() {
+        let mut store = coin_store();
+        let used = store.new_recv_addr().script_pubkey();
+        let fresh = store.new_recv_addr().script_pubkey();
+        let update = |spk: &ScriptBuf, tx: bitcoin::Transaction| Update {
+            spk: spk.clone(),
+            txs: vec![(tx.compute_txid(), Some(tx), Some(1))],
+        };
+        let outpoint = |tx: &bitcoin::Transaction, spk: &ScriptBuf| {
+            let vout = tx.output.iter().position(|o| o.script_pubkey == *spk);
+            OutPoint::new(tx.compute_txid(), vout.unwrap() as u32)
+        };
+
+        let funding = funding_tx(used.clone(), 0.1);
+        // tiny coins received on the used address & on a fresh one
+        let dust = funding_tx(used.clone(), 0.000005);
+        let single = funding_tx(fresh.clone(), 0.000005);
+        // a tiny change sent to ourselves on the used address
+        let mut self_sent = spending_tx(outpoint(&funding, &used));
+        self_sent.output.push(TxOut {
+            value: Amount::from_sat(500),
+            script_pubkey: used.clone(),
+        });
+        let (op_funding, op_dust) = (outpoint(&funding, &used), outpoint(&dust, &used));
+        let (op_single, op_self_sent) = (outpoint(&single, &fresh), outpoint(&self_sent, &used));
+        store.tx_store.insert_updates(vec![
+            update(&used, funding),
+            update(&used, dust),
+            update(&fresh, single),
+            update(&used, self_sent),
+        ]);
+        store.generate();
+
+        let flagged = |op: &OutPoint| store.store.get(op).unwrap().suspected_dust();
+        assert!(flagged(&op_dust));
+        assert!(!flagged(&op_funding));
+        assert!(!flagged(&op_single));
+        assert!(!flagged(&op_self_sent));
+        assert!(store
+            .spendable_coins()
+            .coins
+            .iter()
+            .any(|c| { c.outpoint == op_dust.to_string() && c.suspected_dust }));
+    }
+
+    #[test]
+    fn revalidate_addresses() {
+        let mut store = coin_store();
+        let spk = store.new_recv_addr().script_pubkey();
+        let tx = funding_tx(spk.clone(), 0.1);
+        let vout = tx.output.iter().position(|o| o.script_pubkey == spk);
+        let outpoint = OutPoint::new(tx.compute_txid(), vout.unwrap() as u32);
+        store.tx_store.insert_updates(vec![Update {
+            spk,
+            txs: vec![(tx.compute_txid(), Some(tx), Some(1))],
+        }]);
+        store.generate();
+
+        // everything is derived by the descriptor
+        let (addresses, coins) = store.revalidate_addresses();
+        assert!(addresses.is_empty());
+        assert!(coins.is_empty());
+
+        // the descriptor is swapped, the stored coin is flagged
+        let other = coin_store();
+        store.derivator = other.derivator();
+        let (_, coins) = store.revalidate_addresses();
+        assert_eq!(coins, vec![outpoint]);
+
+        // addresses restored from another descriptor are flagged
+        let mut restored = coin_store();
+        let dump = store.address_store.dump().unwrap();
+        let count = dump.as_object().unwrap().len();
+        restored.address_store.restore(dump).unwrap();
+        let (addresses, coins) = restored.revalidate_addresses();
+        assert_eq!(addresses.len(), count);
+        assert!(coins.is_empty());
+    }
+
+    #[test]
+    fn spent_externally() {
+        let mut store = coin_store();
+        let spk = store.new_recv_addr().script_pubkey();
+        let update = |tx: bitcoin::Transaction| Update {
+            spk: spk.clone(),
+            txs: vec![(tx.compute_txid(), Some(tx), Some(1))],
+        };
+        let outpoint = |tx: &bitcoin::Transaction| {
+            let vout = tx.output.iter().position(|o| o.script_pubkey == spk);
+            OutPoint::new(tx.compute_txid(), vout.unwrap() as u32)
+        };
+
+        // coin a spent by a tx the wallet prepared, coin b by an unknown one
+        let fund_a = funding_tx(spk.clone(), 0.1);
+        let spend_a = spending_tx(outpoint(&fund_a));
+        let fund_b = funding_tx(spk.clone(), 0.2);
+        let spend_b = spending_tx(outpoint(&fund_b));
+        store.register_prepared_tx(spend_a.compute_txid());
+        let (op_a, op_b) = (outpoint(&fund_a), outpoint(&fund_b));
+        store.tx_store.insert_updates(vec![
+            update(fund_a),
+            update(spend_a),
+            update(fund_b),
+            update(spend_b.clone()),
+        ]);
+        store.generate();
+
+        let a = store.store.get(&op_a).unwrap();
+        assert_eq!(a.status(), CoinStatus::Spent);
+        assert!(!a.spent_externally());
+        let b = store.store.get(&op_b).unwrap();
+        assert_eq!(b.status(), CoinStatus::Spent);
+        assert!(b.spent_externally());
+        assert!(store
+            .get_by_status(CoinStatus::Spent)
+            .iter()
+            .any(|c| { c.outpoint == op_b.to_string() && c.spent_externally }));
+
+        // registering the spending tx afterward clears the flag
+        store.register_prepared_tx(spend_b.compute_txid());
+        assert!(!store.store.get(&op_b).unwrap().spent_externally());
+
+        // a coinjoin paying to a registered output is not flagged
+        let fund_c = funding_tx(spk.clone(), 0.3);
+        let op_c = outpoint(&fund_c);
+        let mut coinjoin = spending_tx(op_c);
+        let output = store.new_recv_addr().script_pubkey();
+        coinjoin.output.push(TxOut {
+            value: Amount::from_btc(0.29).unwrap(),
+            script_pubkey: output.clone(),
+        });
+        store.register_coinjoin_output(output.clone());
+        store
+            .tx_store
+            .insert_updates(vec![update(fund_c), update(coinjoin.clone())]);
+        store.generate();
+        assert!(!store.store.get(&op_c).unwrap().spent_externally());
+        assert!(store.prepared_txs.contains(&coinjoin.compute_txid()));
+        assert_eq!(store.coinjoin_txid(&output), Some(coinjoin.compute_txid()));
+
+        // only the coin created by the coinjoin is listed, not the ones
+        // later paid to the same address
+        let reuse = funding_tx(output.clone(), 0.01);
+        store.tx_store.insert_updates(vec![Update {
+            spk: output.clone(),
+            txs: vec![(reuse.compute_txid(), Some(reuse), Some(1))],
+        }]);
+        store.generate();
+        assert_eq!(store.coins_at(&output).len(), 2);
+        let coins = store.coinjoin_coins(&output);
+        assert_eq!(coins.len(), 1);
+        assert!(coins[0]
+            .outpoint
+            .starts_with(&coinjoin.compute_txid().to_string()));
+    }
+
+    #[test]
+    fn stuck_transactions() {
+        let mut store = coin_store();
+        let spk = store.new_recv_addr().script_pubkey();
+        let update = |tx: &bitcoin::Transaction, height: Option<u64>| Update {
+            spk: spk.clone(),
+            txs: vec![(tx.compute_txid(), Some(tx.clone()), height)],
+        };
+
+        let old = funding_tx(spk.clone(), 0.1);
+        let recent = funding_tx(spk.clone(), 0.2);
+        let confirmed = funding_tx(spk.clone(), 0.3);
+        store.tx_store.insert_updates(vec![
+            update(&old, None),
+            update(&recent, None),
+            update(&confirmed, Some(1)),
+        ]);
+        store.generate();
+        assert_eq!(store.first_seen.len(), 2);
+
+        // the old tx has been first seen a day ago
+        *store.first_seen.get_mut(&old.compute_txid()).unwrap() -= 144 * BLOCK_INTERVAL;
+        let stuck = store.stuck_transactions(6);
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].txid, old.compute_txid().to_string());
+        assert_eq!(stuck[0].height, 0);
+        assert!(store.stuck_transactions(200).is_empty());
+        assert_eq!(store.stuck_transactions(0).len(), 2);
+
+        // a new generation keeps the first seen time
+        store.generate();
+        assert_eq!(store.stuck_transactions(6).len(), 1);
+
+        // once confirmed the tx is no longer stuck
+        store.tx_store.insert_updates(vec![update(&old, Some(2))]);
+        store.generate();
+        assert!(store.stuck_transactions(6).is_empty());
+        assert_eq!(store.first_seen.len(), 1);
+    }
+
+    #[test]
+    fn first_seen_persist() {
+        let config = crate::config::new_config(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+            "first_seen_persist".to_string(),
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        config.to_file().unwrap();
+        let _ = std::fs::remove_file(config.first_seen_path());
+        let config = *config;
+        let (sender, _receiver) = notification_channel();
+        let signer =
+            HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &config.mnemonic).unwrap();
+        let descriptor = wpkh(signer.xpub(&DerivationPath::from_str("m/84'/0'/0'").unwrap()));
+        let new_store = || {
+            CoinStore::new(
+                bitcoin::Network::Regtest,
+                descriptor.clone(),
+                None,
+                sender.clone(),
+                0,
+                0,
+                20,
+                20,
+                Tip::default(),
+                TxStore::new(Default::default(), None),
+                Arc::new(Mutex::new(LabelStore::new())),
+                Some(config.clone()),
+            )
+        };
+
+        let mut store = new_store();
+        let spk = store.new_recv_addr().script_pubkey();
+        let tx = funding_tx(spk.clone(), 0.1);
+        store.tx_store.insert_updates(vec![Update {
+            spk,
+            txs: vec![(tx.compute_txid(), Some(tx.clone()), None)],
+        }]);
+        store.generate();
+        let seen = store.first_seen[&tx.compute_txid()];
+
+        // a restarted store keeps the first seen time of the tx
+        let restarted = new_store();
+        assert_eq!(restarted.first_seen.get(&tx.compute_txid()), Some(&seen));
+    }
+
+    #[test]
+    fn spendable_coins_sorted() {
+        let mut store = coin_store();
+        let address = store.derivator_ref().receive_at(0);
+        // (outpoint, value, height, label)
+        let coins = [
+            (OutPoint::new(txid(3), 0), 3_000, Some(10), Some("a")),
+            (OutPoint::new(txid(1), 2), 1_000, None, Some("c")),
+            (OutPoint::new(txid(2), 1), 4_000, Some(5), None),
+            (OutPoint::new(txid(1), 1), 2_000, Some(20), Some("b")),
+        ];
+        for (outpoint, value, height, label) in coins {
+            let mut entry = coin_entry(&address, outpoint, value, height);
+            entry.label = label.map(String::from);
+            store.store.insert(outpoint, entry);
+        }
+        let order = |sort| {
+            store
+                .spendable_coins_sorted(sort)
+                .coins
+                .into_iter()
+                .map(|c| c.value)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(CoinSort::Value), vec![1_000, 2_000, 3_000, 4_000]);
+        assert_eq!(order(CoinSort::Age), vec![4_000, 3_000, 2_000, 1_000]);
+        assert_eq!(order(CoinSort::Outpoint), vec![2_000, 1_000, 4_000, 3_000]);
+        assert_eq!(order(CoinSort::Label), vec![4_000, 3_000, 2_000, 1_000]);
+
+        // the default order is by value
+        let state = store.spendable_coins();
+        assert_eq!(
+            state.coins,
+            store.spendable_coins_sorted(CoinSort::Value).coins
+        );
+        assert_eq!(state.confirmed_coins, 3);
+        assert_eq!(state.confirmed_balance, 9_000);
+        assert_eq!(state.unconfirmed_coins, 1);
+        assert_eq!(state.unconfirmed_balance, 1_000);
     }
 }
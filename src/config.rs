@@ -1,7 +1,8 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
     fs::{self, File},
-    io::{Read, Write},
+    io::{self, Read, Write},
     path::PathBuf,
     str::FromStr,
 };
@@ -9,14 +10,21 @@ use std::{
 use joinstr::{
     bip39::Mnemonic,
     miniscript::{
-        bitcoin::{self, bip32::DerivationPath, ScriptBuf},
+        bitcoin::{
+            self,
+            address::NetworkUnchecked,
+            bip32::{DerivationPath, Fingerprint},
+            Address, OutPoint, ScriptBuf, Txid,
+        },
         Descriptor, DescriptorPublicKey,
     },
+    utils::now,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    cpp_joinstr::Network,
+    cpp_joinstr::{AddrAccount, Network},
+    derivator::{merge_keychains, Derivator},
     signer::{wpkh, HotSigner},
 };
 
@@ -42,13 +50,15 @@ pub fn datadir() -> PathBuf {
         dir
     };
 
-    maybe_create_dir(&dir);
+    if let Err(e) = maybe_create_dir(&dir) {
+        log::error!("datadir() fail to create {dir:?}: {e}");
+    }
 
     dir
 }
 
 /// Creates a directory if it does not exist.
-fn maybe_create_dir(dir: &PathBuf) {
+fn maybe_create_dir(dir: &PathBuf) -> io::Result<()> {
     if !dir.exists() {
         #[cfg(unix)]
         {
@@ -56,11 +66,40 @@ fn maybe_create_dir(dir: &PathBuf) {
             use std::os::unix::fs::DirBuilderExt;
 
             let mut builder = DirBuilder::new();
-            builder.mode(0o700).recursive(true).create(dir).unwrap();
+            builder.mode(0o700).recursive(true).create(dir)?;
         }
 
         #[cfg(not(unix))]
-        std::fs::create_dir_all(dir).unwrap();
+        std::fs::create_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// Errors that can occur while persisting the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Serialize(serde_json::Error),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "Fail to write config: {e}"),
+            ConfigError::Serialize(e) => write!(f, "Fail to serialize config: {e}"),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(value: io::Error) -> Self {
+        ConfigError::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(value: serde_json::Error) -> Self {
+        ConfigError::Serialize(value)
     }
 }
 
@@ -73,14 +112,163 @@ pub struct Config {
     pub electrum_url: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub electrum_port: Option<u16>,
+    /// Delay in seconds after which an unanswered electrum request is
+    /// considered failed and the listener reconnects.
+    #[serde(default = "default_electrum_timeout")]
+    pub electrum_timeout: u64,
+    /// Maximum number of items in a single electrum history or
+    /// transactions request, larger requests are split.
+    #[serde(default = "default_electrum_batch_size")]
+    pub electrum_batch_size: u32,
+    /// Delay in milliseconds before the first reconnection attempt of the
+    /// electrum and nostr listeners.
+    #[serde(default = "default_reconnect_initial_delay_ms")]
+    pub reconnect_initial_delay_ms: u64,
+    /// Factor applied to the reconnection delay after each failed attempt.
+    #[serde(default = "default_reconnect_multiplier")]
+    pub reconnect_multiplier: f64,
+    /// Maximum delay in milliseconds between two reconnection attempts.
+    #[serde(default = "default_reconnect_max_delay_ms")]
+    pub reconnect_max_delay_ms: u64,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub nostr_relay: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub nostr_back: Option<u64>,
+    /// Hex public keys of the pool publishers to list pools from, pools of
+    /// any publisher are listed if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nostr_pool_authors: Vec<String>,
+    /// If false, the pool listener is never started and pools related
+    /// methods return empty values.
+    #[serde(default = "default_enable_coinjoin")]
+    pub enable_coinjoin: bool,
     pub network: bitcoin::Network,
     pub look_ahead: u32,
+    /// Look-ahead of the receiving keychain, `look_ahead` if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recv_look_ahead: Option<u32>,
+    /// Look-ahead of the change keychain, `look_ahead` if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_look_ahead: Option<u32>,
+    /// Minimum relay fee rate in sats/vb, transactions paying less are
+    /// rejected.
+    #[serde(default = "default_min_relay_fee")]
+    pub min_relay_fee: f64,
+    /// Earliest used indices known by the user, the scan of the address
+    /// store will start from there before lazily covering earlier indices.
+    #[serde(default)]
+    pub scan_from: Tip,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub mnemonic: String,
+    /// If false, the mnemonics are never written to the config file and
+    /// must be provided again on each launch, the account is watch-only
+    /// meanwhile.
+    #[serde(default = "default_persist_mnemonic")]
+    pub persist_mnemonic: bool,
+    /// Optional mnemonic used to sign coinjoin inputs instead of the
+    /// wallet mnemonic, e.g. for a watch-only wallet. Only the coins and
+    /// the coinjoin outputs derived from its BIP84 key can be contributed
+    /// to a pool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coinjoin_mnemonic: Option<String>,
+    /// Keychain the coinjoin output addresses are derived from, using the
+    /// change keychain keeps coinjoin outputs apart from received coins.
+    #[serde(default = "default_coinjoin_keychain")]
+    pub coinjoin_keychain: AddrAccount,
+    /// If true, a new receive address request returns the first handed
+    /// out address that is still unused instead of generating a new one.
+    #[serde(default)]
+    pub reuse_unused_addresses: bool,
+    /// Number of spendable coins above which a consolidation is suggested,
+    /// as long as it saves fees.
+    #[serde(default = "default_consolidation_max_utxos")]
+    pub consolidation_max_utxos: u32,
+    /// Minimum fee savings in sats for which a consolidation is suggested
+    /// whatever the number of coins.
+    #[serde(default = "default_consolidation_min_savings")]
+    pub consolidation_min_savings: u64,
+    /// If true, the account xpubs are added to the global map of the
+    /// prepared PSBTs, some hardware signers need them to recognize the
+    /// change outputs.
+    #[serde(default)]
+    pub psbt_global_xpubs: bool,
+    /// If true, a new receive address is generated as soon as a coin is
+    /// received on the last generated one.
+    #[serde(default)]
+    pub auto_new_recv_addr: bool,
+    /// Maximum amount in sats of a coin paid to the miners on top of the
+    /// fees when joining a pool, a coin exceeding more is refused.
+    #[serde(default = "default_max_coinjoin_excess")]
+    pub max_coinjoin_excess: u64,
+    /// Minimum amount in sats of a change output, a lower change is added
+    /// to the fees instead. The dust limit if unset or lower.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_change_sats: Option<u64>,
+    /// Coins worth less than this amount in sats received on a reused
+    /// address are flagged as a likely dust attack, 0 to disable.
+    #[serde(default = "default_dust_attack_threshold")]
+    pub dust_attack_threshold: u64,
+    /// Unix timestamp (in seconds) of the wallet creation, 0 if unknown.
+    #[serde(default)]
+    pub created_at: u64,
     pub descriptor: Descriptor<DescriptorPublicKey>,
+    /// Optional descriptor of another address type, its coins are tracked
+    /// alongside the ones of the main descriptor. The hot signer only signs
+    /// wpkh inputs, taproot coins must be signed by another signer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_descriptor: Option<Descriptor<DescriptorPublicKey>>,
+}
+
+fn default_enable_coinjoin() -> bool {
+    true
+}
+
+fn default_persist_mnemonic() -> bool {
+    true
+}
+
+fn default_electrum_timeout() -> u64 {
+    30
+}
+
+fn default_electrum_batch_size() -> u32 {
+    ELECTRUM_BATCH_SIZE
+}
+
+fn default_reconnect_initial_delay_ms() -> u64 {
+    RECONNECT_INITIAL_DELAY_MS
+}
+
+fn default_reconnect_multiplier() -> f64 {
+    RECONNECT_MULTIPLIER
+}
+
+fn default_reconnect_max_delay_ms() -> u64 {
+    RECONNECT_MAX_DELAY_MS
+}
+
+fn default_min_relay_fee() -> f64 {
+    1.0
+}
+
+fn default_coinjoin_keychain() -> AddrAccount {
+    AddrAccount::Receive
+}
+
+fn default_consolidation_max_utxos() -> u32 {
+    50
+}
+
+fn default_consolidation_min_savings() -> u64 {
+    10_000
+}
+
+fn default_max_coinjoin_excess() -> u64 {
+    MAX_COINJOIN_EXCESS
+}
+
+fn default_dust_attack_threshold() -> u64 {
+    DUST_ATTACK_THRESHOLD
 }
 
 /// Lists all configuration directories in the data directory.
@@ -149,6 +337,17 @@ impl Config {
         Box::new(self.clone())
     }
 
+    /// Returns the mnemonic used to sign coinjoin inputs.
+    ///
+    /// # Returns
+    ///
+    /// The coinjoin mnemonic if any, the wallet mnemonic otherwise.
+    pub fn coinjoin_signer_mnemonic(&self) -> String {
+        self.coinjoin_mnemonic
+            .clone()
+            .unwrap_or_else(|| self.mnemonic.clone())
+    }
+
     /// Creates a `Config` instance from a configuration file.
     ///
     /// # Arguments
@@ -162,8 +361,18 @@ impl Config {
         let mut content = String::new();
         let _ = file.read_to_string(&mut content);
         let mut conf: Config = serde_json::from_str(&content).unwrap();
+        // NOTE: the config file may have been edited by hand, an out of range
+        // lookback is clamped rather than sent to the relay
+        if let Some(back) = conf
+            .nostr_back
+            .filter(|b| !(1..=MAX_NOSTR_BACK).contains(b))
+        {
+            log::warn!("Config::from_file() nostr lookback out of range: {back}");
+            conf.nostr_back = Some(back.clamp(1, MAX_NOSTR_BACK));
+        }
         let mnemo = Mnemonic::from_str(&conf.mnemonic);
-        if mnemo.is_ok() {
+        // NOTE: a config without persisted mnemonic is loaded watch-only
+        if mnemo.is_ok() || !conf.persist_mnemonic {
             conf.account = account;
         }
         conf
@@ -190,6 +399,59 @@ impl Config {
         path
     }
 
+    /// Returns the path to the secondary descriptor tip file for the
+    /// current account.
+    pub fn secondary_tip_path(&self) -> PathBuf {
+        let mut path = Self::path(self.account.clone());
+        path.push("secondary_tip.json");
+        path
+    }
+
+    /// Returns the path to the activity file for the current account.
+    pub fn activity_path(&self) -> PathBuf {
+        let mut path = Self::path(self.account.clone());
+        path.push("activity.json");
+        path
+    }
+
+    /// Returns the path to the pool outputs file for the current account.
+    pub fn pool_outputs_path(&self) -> PathBuf {
+        let mut path = Self::path(self.account.clone());
+        path.push("pool_outputs.json");
+        path
+    }
+
+    /// Returns the path to the prepared transactions file for the current
+    /// account.
+    pub fn prepared_txs_path(&self) -> PathBuf {
+        let mut path = Self::path(self.account.clone());
+        path.push("prepared_txs.json");
+        path
+    }
+
+    /// Returns the path to the file recording when the unconfirmed
+    /// transactions were first seen, for the current account.
+    pub fn first_seen_path(&self) -> PathBuf {
+        let mut path = Self::path(self.account.clone());
+        path.push("first_seen.json");
+        path
+    }
+
+    /// Returns the path to the coinjoin outputs file for the current
+    /// account.
+    pub fn coinjoin_outputs_path(&self) -> PathBuf {
+        let mut path = Self::path(self.account.clone());
+        path.push("coinjoin_outputs.json");
+        path
+    }
+
+    /// Returns the path to the frozen coins file for the current account.
+    pub fn frozen_path(&self) -> PathBuf {
+        let mut path = Self::path(self.account.clone());
+        path.push("frozen.json");
+        path
+    }
+
     /// Returns the path to the labels file for the current account.
     pub fn labels_path(&self) -> PathBuf {
         let mut path = Self::path(self.account.clone());
@@ -232,6 +494,250 @@ impl Config {
         }
     }
 
+    /// Persists the tips of the secondary descriptor for the current
+    /// account.
+    ///
+    /// # Arguments
+    ///
+    /// * `tip` - The last generated receive and change indexes.
+    pub fn persist_secondary_tip(&self, tip: Tip) {
+        let file = File::create(self.secondary_tip_path());
+        match file {
+            Ok(mut file) => {
+                let content = serde_json::to_string_pretty(&tip).expect("cannot fail");
+                let _ = file.write(content.as_bytes());
+            }
+            Err(e) => {
+                log::error!("Config::persist_secondary_tip() fail to open file: {e}");
+            }
+        }
+    }
+
+    /// Retrieves the tips of the secondary descriptor for the current
+    /// account.
+    ///
+    /// # Returns
+    ///
+    /// A `Tip` instance, zeros if none have been persisted.
+    pub fn secondary_tip_from_file(&self) -> Tip {
+        if let Ok(mut file) = File::open(self.secondary_tip_path()) {
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Default::default()
+        }
+    }
+
+    /// Persists the timestamp of the last coins change for the current
+    /// account.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - The unix timestamp (in seconds) of the last activity.
+    pub fn persist_last_activity(&self, timestamp: u64) {
+        let file = File::create(self.activity_path());
+        match file {
+            Ok(mut file) => {
+                let content = serde_json::to_string(&timestamp).expect("cannot fail");
+                let _ = file.write(content.as_bytes());
+            }
+            Err(e) => {
+                log::error!("Config::persist_last_activity() fail to open file: {e}");
+            }
+        }
+    }
+
+    /// Retrieves the timestamp of the last coins change from the activity
+    /// file for the current account.
+    ///
+    /// # Returns
+    ///
+    /// The unix timestamp (in seconds) of the last activity, 0 if unknown.
+    pub fn last_activity_from_file(&self) -> u64 {
+        if let Ok(mut file) = File::open(self.activity_path()) {
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Default::default()
+        }
+    }
+
+    /// Persists the output addresses registered in the pools we take part
+    /// in to a file for the current account.
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs` - The output addresses by pool ID.
+    pub fn persist_pool_outputs(&self, outputs: &BTreeMap<String, Address<NetworkUnchecked>>) {
+        let file = File::create(self.pool_outputs_path());
+        match file {
+            Ok(mut file) => {
+                let content = serde_json::to_string_pretty(outputs).expect("cannot fail");
+                let _ = file.write(content.as_bytes());
+            }
+            Err(e) => {
+                log::error!("Config::persist_pool_outputs() fail to open file: {e}");
+            }
+        }
+    }
+
+    /// Retrieves the output addresses registered in the pools we take part
+    /// in from the pool outputs file for the current account.
+    ///
+    /// # Returns
+    ///
+    /// The output addresses by pool ID.
+    pub fn pool_outputs_from_file(&self) -> BTreeMap<String, Address<NetworkUnchecked>> {
+        if let Ok(mut file) = File::open(self.pool_outputs_path()) {
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Default::default()
+        }
+    }
+
+    /// Persists the transactions prepared by this wallet to a file for the
+    /// current account.
+    ///
+    /// # Arguments
+    ///
+    /// * `txids` - The txids of the prepared transactions.
+    pub fn persist_prepared_txs(&self, txids: &BTreeSet<Txid>) {
+        let file = File::create(self.prepared_txs_path());
+        match file {
+            Ok(mut file) => {
+                let content = serde_json::to_string_pretty(txids).expect("cannot fail");
+                let _ = file.write(content.as_bytes());
+            }
+            Err(e) => {
+                log::error!("Config::persist_prepared_txs() fail to open file: {e}");
+            }
+        }
+    }
+
+    /// Retrieves the transactions prepared by this wallet from the prepared
+    /// transactions file for the current account.
+    ///
+    /// # Returns
+    ///
+    /// The txids of the prepared transactions.
+    pub fn prepared_txs_from_file(&self) -> BTreeSet<Txid> {
+        if let Ok(mut file) = File::open(self.prepared_txs_path()) {
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Default::default()
+        }
+    }
+
+    /// Persists the time the unconfirmed transactions were first seen to a
+    /// file for the current account.
+    ///
+    /// # Arguments
+    ///
+    /// * `first_seen` - The first seen timestamps (in seconds) by txid.
+    pub fn persist_first_seen(&self, first_seen: &BTreeMap<Txid, u64>) {
+        let file = File::create(self.first_seen_path());
+        match file {
+            Ok(mut file) => {
+                let content = serde_json::to_string_pretty(first_seen).expect("cannot fail");
+                let _ = file.write(content.as_bytes());
+            }
+            Err(e) => {
+                log::error!("Config::persist_first_seen() fail to open file: {e}");
+            }
+        }
+    }
+
+    /// Retrieves the time the unconfirmed transactions were first seen from
+    /// the first seen file for the current account.
+    ///
+    /// # Returns
+    ///
+    /// The first seen timestamps (in seconds) by txid.
+    pub fn first_seen_from_file(&self) -> BTreeMap<Txid, u64> {
+        if let Ok(mut file) = File::open(self.first_seen_path()) {
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Default::default()
+        }
+    }
+
+    /// Persists the coinjoin outputs and the txid of the coinjoin paying to
+    /// them, once seen, to a file for the current account.
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs` - The coinjoin txids by output script pubkey.
+    pub fn persist_coinjoin_outputs(&self, outputs: &BTreeMap<ScriptBuf, Option<Txid>>) {
+        let file = File::create(self.coinjoin_outputs_path());
+        match file {
+            Ok(mut file) => {
+                let content = serde_json::to_string_pretty(outputs).expect("cannot fail");
+                let _ = file.write(content.as_bytes());
+            }
+            Err(e) => {
+                log::error!("Config::persist_coinjoin_outputs() fail to open file: {e}");
+            }
+        }
+    }
+
+    /// Retrieves the coinjoin outputs from the coinjoin outputs file for the
+    /// current account.
+    ///
+    /// # Returns
+    ///
+    /// The coinjoin txids (if seen) by output script pubkey.
+    pub fn coinjoin_outputs_from_file(&self) -> BTreeMap<ScriptBuf, Option<Txid>> {
+        if let Ok(mut file) = File::open(self.coinjoin_outputs_path()) {
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Default::default()
+        }
+    }
+
+    /// Persists the frozen coins to a file for the current account.
+    ///
+    /// # Arguments
+    ///
+    /// * `outpoints` - The outpoints of the frozen coins.
+    pub fn persist_frozen(&self, outpoints: &BTreeSet<OutPoint>) {
+        let file = File::create(self.frozen_path());
+        match file {
+            Ok(mut file) => {
+                let content = serde_json::to_string_pretty(outpoints).expect("cannot fail");
+                let _ = file.write(content.as_bytes());
+            }
+            Err(e) => {
+                log::error!("Config::persist_frozen() fail to open file: {e}");
+            }
+        }
+    }
+
+    /// Retrieves the frozen coins from the frozen coins file for the current
+    /// account.
+    ///
+    /// # Returns
+    ///
+    /// The outpoints of the frozen coins.
+    pub fn frozen_from_file(&self) -> BTreeSet<OutPoint> {
+        if let Ok(mut file) = File::open(self.frozen_path()) {
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            serde_json::from_str(&content).unwrap_or_default()
+        } else {
+            Default::default()
+        }
+    }
+
     /// Persists the statuses information to a file for the current account.
     ///
     /// # Arguments
@@ -267,7 +773,7 @@ impl Config {
 }
 
 /// Represents the tip information for the current account.
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct Tip {
     pub receive: u32,
     pub change: u32,
@@ -286,6 +792,147 @@ pub fn config_from_file(account: String) -> Box<Config> {
     Config::from_file(account).boxed()
 }
 
+/// Checks if the provided electrum url is valid.
+///
+/// The url must be a non-empty host, optionally prefixed by a `tcp://` or
+/// `ssl://` scheme. The port is not part of the url and is checked separately.
+///
+/// # Arguments
+///
+/// * `url` - A string representing the electrum url to validate.
+pub fn is_electrum_url_valid(url: &str) -> bool {
+    let host = url
+        .strip_prefix("tcp://")
+        .or_else(|| url.strip_prefix("ssl://"))
+        .unwrap_or(url);
+    !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+/// Parses an electrum url and port.
+///
+/// # Arguments
+///
+/// * `url` - A string representing the electrum url.
+/// * `port` - A string representing the electrum port.
+///
+/// # Returns
+///
+/// `Some((url, port))` if both the url and the port are valid, `None` otherwise.
+pub fn parse_electrum_config(url: &str, port: &str) -> Option<(String, u16)> {
+    if !is_electrum_url_valid(url) {
+        return None;
+    }
+    match port.parse::<u16>() {
+        Ok(port) if port != 0 => Some((url.to_string(), port)),
+        _ => None,
+    }
+}
+
+/// Parses a minimum relay fee rate.
+///
+/// # Arguments
+///
+/// * `fee` - A string representing the fee rate in sats/vb.
+///
+/// # Returns
+///
+/// `Some(fee)` if the fee rate is a strictly positive number, `None` otherwise.
+pub fn parse_min_relay_fee(fee: &str) -> Option<f64> {
+    fee.parse::<f64>()
+        .ok()
+        .filter(|f| f.is_finite() && *f > 0.0)
+}
+
+/// Default amount in sats below which a coin received on a reused address
+/// is flagged as a likely dust attack.
+pub const DUST_ATTACK_THRESHOLD: u64 = 1_000;
+
+/// Default maximum number of items in a single electrum history or
+/// transactions request.
+pub const ELECTRUM_BATCH_SIZE: u32 = 100;
+
+/// Default delay in milliseconds before the first reconnection attempt.
+pub const RECONNECT_INITIAL_DELAY_MS: u64 = 100;
+
+/// Default factor applied to the reconnection delay after a failed attempt.
+pub const RECONNECT_MULTIPLIER: f64 = 2.0;
+
+/// Default maximum delay in milliseconds between two reconnection attempts.
+pub const RECONNECT_MAX_DELAY_MS: u64 = 30_000;
+
+/// Maximum lookback in seconds when fetching nostr pool events (30 days).
+pub const MAX_NOSTR_BACK: u64 = 30 * 24 * 60 * 60;
+
+/// Default maximum amount in sats of a coin paid to the miners on top of
+/// the fees when joining a pool.
+pub const MAX_COINJOIN_EXCESS: u64 = 1_000;
+
+/// Parses the lookback used when fetching nostr pool events.
+///
+/// # Arguments
+///
+/// * `back` - A string representing the lookback in seconds.
+///
+/// # Returns
+///
+/// `Some(back)` if the lookback is between 1 and [`MAX_NOSTR_BACK`] seconds,
+/// `None` otherwise.
+pub fn parse_nostr_back(back: &str) -> Option<u64> {
+    back.parse::<u64>()
+        .ok()
+        .filter(|b| (1..=MAX_NOSTR_BACK).contains(b))
+}
+
+/// Parses the lookback used when fetching nostr pool events, given in
+/// hours.
+///
+/// # Arguments
+///
+/// * `hours` - A string representing the lookback in hours, can be
+///   fractional.
+///
+/// # Returns
+///
+/// `Some(back)` the lookback in seconds if it is between 1 and
+/// [`MAX_NOSTR_BACK`] seconds, `None` otherwise.
+pub fn parse_nostr_back_hours(hours: &str) -> Option<u64> {
+    let hours = hours.parse::<f64>().ok().filter(|h| h.is_finite())?;
+    let back = (hours * 3600.0).round();
+    if back < 1.0 || back > MAX_NOSTR_BACK as f64 {
+        return None;
+    }
+    Some(back as u64)
+}
+
+/// Parses the hex public key of a pool publisher.
+///
+/// # Arguments
+///
+/// * `author` - A string representing a 32 bytes x-only public key in hex.
+///
+/// # Returns
+///
+/// The lowercase public key, or `None` if the key is not 64 hex characters.
+pub fn parse_pool_author(author: &str) -> Option<String> {
+    let author = author.trim();
+    (author.len() == 64 && author.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| author.to_ascii_lowercase())
+}
+
+/// Checks whether a pool publisher is allowed by the configured authors.
+///
+/// # Arguments
+///
+/// * `authors` - The allowed hex public keys, any publisher is allowed if
+///   empty.
+/// * `author` - The hex public key of the pool publisher.
+pub fn is_pool_author_allowed(authors: &[String], author: &str) -> bool {
+    authors.is_empty() || authors.iter().any(|a| a.eq_ignore_ascii_case(author))
+}
+
 /// Checks if the provided descriptor string is valid.
 ///
 /// # Arguments
@@ -295,6 +942,54 @@ pub fn is_descriptor_valid(descriptor: String) -> bool {
     Descriptor::<DescriptorPublicKey>::from_str(&descriptor).is_ok()
 }
 
+/// Merges separate receive and change descriptors, as exported by wallets
+/// that do not support multipath descriptors, into a multipath descriptor.
+///
+/// # Arguments
+///
+/// * `receive` - The single path descriptor of the receiving keychain,
+///   e.g. `wpkh([fg/84'/0'/0']xpub/0/*)`.
+/// * `change` - The single path descriptor of the change keychain,
+///   e.g. `wpkh([fg/84'/0'/0']xpub/1/*)`.
+///
+/// # Returns
+///
+/// The multipath descriptor, or an empty string if a descriptor cannot be
+/// parsed or if they differ by more than their keychain.
+pub fn multipath_descriptor(receive: String, change: String) -> String {
+    let (Ok(receive), Ok(change)) = (
+        Descriptor::<DescriptorPublicKey>::from_str(&receive),
+        Descriptor::<DescriptorPublicKey>::from_str(&change),
+    ) else {
+        return String::new();
+    };
+    merge_keychains(&receive, &change)
+        .map(|d| d.to_string())
+        .unwrap_or_default()
+}
+
+/// Checks if a descriptor contains a key of the given master fingerprint,
+/// e.g. the one of a hardware signer.
+///
+/// # Arguments
+///
+/// * `descriptor` - A string representing the descriptor.
+/// * `fingerprint` - The hex master fingerprint.
+///
+/// # Returns
+///
+/// `false` if the descriptor or the fingerprint cannot be parsed or if no
+/// key origin matches the fingerprint.
+pub fn descriptor_contains_fingerprint(descriptor: String, fingerprint: String) -> bool {
+    let (Ok(descriptor), Ok(fingerprint)) = (
+        Descriptor::<DescriptorPublicKey>::from_str(&descriptor),
+        Fingerprint::from_str(&fingerprint),
+    ) else {
+        return false;
+    };
+    !descriptor.for_each_key(|k| k.master_fingerprint() != fingerprint)
+}
+
 /// Creates a new `Config` instance with the specified descriptor.
 ///
 /// # Arguments
@@ -320,12 +1015,36 @@ pub fn new_config(mnemonic: String, account: String, network: Network) -> Box<Co
         account,
         electrum_url: None,
         electrum_port: None,
+        electrum_timeout: default_electrum_timeout(),
+        electrum_batch_size: ELECTRUM_BATCH_SIZE,
+        reconnect_initial_delay_ms: RECONNECT_INITIAL_DELAY_MS,
+        reconnect_multiplier: RECONNECT_MULTIPLIER,
+        reconnect_max_delay_ms: RECONNECT_MAX_DELAY_MS,
         nostr_relay: None,
         nostr_back: None,
+        nostr_pool_authors: vec![],
+        enable_coinjoin: true,
         network: network.into(),
         look_ahead: 20,
+        recv_look_ahead: None,
+        change_look_ahead: None,
+        min_relay_fee: default_min_relay_fee(),
+        scan_from: Tip::default(),
         mnemonic,
+        persist_mnemonic: true,
+        coinjoin_mnemonic: None,
+        coinjoin_keychain: default_coinjoin_keychain(),
+        reuse_unused_addresses: false,
+        consolidation_max_utxos: default_consolidation_max_utxos(),
+        consolidation_min_savings: default_consolidation_min_savings(),
+        psbt_global_xpubs: false,
+        auto_new_recv_addr: false,
+        max_coinjoin_excess: MAX_COINJOIN_EXCESS,
+        min_change_sats: None,
+        dust_attack_threshold: DUST_ATTACK_THRESHOLD,
+        created_at: now(),
         descriptor,
+        secondary_descriptor: None,
     }
     .boxed()
 }
@@ -342,6 +1061,10 @@ impl Config {
             .map(|v| format!("{v}"))
             .unwrap_or_default()
     }
+    /// Returns the electrum request timeout (seconds) as a string.
+    pub fn electrum_timeout(&self) -> String {
+        self.electrum_timeout.to_string()
+    }
     /// Returns the Nostr relay URL as a string.
     pub fn nostr_url(&self) -> String {
         self.nostr_relay.clone().unwrap_or_default()
@@ -350,10 +1073,41 @@ impl Config {
     pub fn nostr_back(&self) -> String {
         self.nostr_back.map(|v| format!("{v}")).unwrap_or_default()
     }
+    /// Returns the Nostr back value in hours as a string.
+    pub fn nostr_back_hours(&self) -> String {
+        self.nostr_back
+            .map(|v| format!("{}", v as f64 / 3600.0))
+            .unwrap_or_default()
+    }
+    /// Returns whether coinjoin features are enabled.
+    pub fn enable_coinjoin(&self) -> bool {
+        self.enable_coinjoin
+    }
     /// Returns the look-ahead value as a string.
     pub fn look_ahead(&self) -> String {
         self.look_ahead.to_string()
     }
+    /// Returns the look-ahead of the receiving keychain as a string.
+    pub fn recv_look_ahead(&self) -> String {
+        self.recv_look_ahead.unwrap_or(self.look_ahead).to_string()
+    }
+    /// Returns the look-ahead of the change keychain as a string.
+    pub fn change_look_ahead(&self) -> String {
+        self.change_look_ahead
+            .unwrap_or(self.look_ahead)
+            .to_string()
+    }
+    /// Returns the minimum relay fee rate (sats/vb) as a string.
+    pub fn min_relay_fee(&self) -> String {
+        self.min_relay_fee.to_string()
+    }
+    /// Returns the secondary descriptor as a string.
+    pub fn secondary_descriptor(&self) -> String {
+        self.secondary_descriptor
+            .as_ref()
+            .map(|d| d.to_string())
+            .unwrap_or_default()
+    }
     /// Returns the network as a `Network` instance.
     pub fn network(&self) -> Network {
         self.network.into()
@@ -366,13 +1120,53 @@ impl Config {
     pub fn set_electrum_port(&mut self, port: String) {
         self.electrum_port = port.parse::<u16>().ok();
     }
+    /// Sets the electrum request timeout (seconds) from a string, a null
+    /// timeout is ignored.
+    pub fn set_electrum_timeout(&mut self, timeout: String) {
+        if let Ok(timeout) = timeout.parse::<u64>() {
+            if timeout > 0 {
+                self.electrum_timeout = timeout;
+            }
+        }
+    }
     /// Sets the Nostr relay URL.
     pub fn set_nostr_relay(&mut self, relay: String) {
         self.nostr_relay = Some(relay);
     }
-    /// Sets the Nostr back value from a string.
+    /// Sets the Nostr back value from a string, an empty value clears it
+    /// and an invalid value is ignored.
     pub fn set_nostr_back(&mut self, back: String) {
-        self.nostr_back = back.parse::<u64>().ok();
+        if back.is_empty() {
+            self.nostr_back = None;
+        } else if let Some(back) = parse_nostr_back(&back) {
+            self.nostr_back = Some(back);
+        }
+    }
+    /// Returns the hex public keys of the pool publishers pools are listed
+    /// from, empty if pools of any publisher are listed.
+    pub fn nostr_pool_authors(&self) -> Vec<String> {
+        self.nostr_pool_authors.clone()
+    }
+    /// Sets the public keys of the pool publishers to list pools from,
+    /// invalid keys are ignored.
+    pub fn set_nostr_pool_authors(&mut self, authors: Vec<String>) {
+        self.nostr_pool_authors = authors
+            .iter()
+            .filter_map(|a| parse_pool_author(a))
+            .collect();
+    }
+    /// Sets the Nostr back value from a string in hours, an empty value
+    /// clears it and an invalid value is ignored.
+    pub fn set_nostr_back_hours(&mut self, hours: String) {
+        if hours.is_empty() {
+            self.nostr_back = None;
+        } else if let Some(back) = parse_nostr_back_hours(&hours) {
+            self.nostr_back = Some(back);
+        }
+    }
+    /// Enables or disables coinjoin features.
+    pub fn set_enable_coinjoin(&mut self, enable: bool) {
+        self.enable_coinjoin = enable;
     }
     /// Sets the look-ahead value from a string.
     pub fn set_look_ahead(&mut self, look_ahead: String) {
@@ -380,6 +1174,46 @@ impl Config {
             self.look_ahead = la;
         }
     }
+    /// Sets the look-ahead of the receiving keychain from a string, an
+    /// empty value fallback to the look-ahead.
+    pub fn set_recv_look_ahead(&mut self, look_ahead: String) {
+        if look_ahead.is_empty() {
+            self.recv_look_ahead = None;
+        } else if let Ok(la) = look_ahead.parse::<u32>() {
+            self.recv_look_ahead = Some(la);
+        }
+    }
+    /// Sets the look-ahead of the change keychain from a string, an empty
+    /// value fallback to the look-ahead.
+    pub fn set_change_look_ahead(&mut self, look_ahead: String) {
+        if look_ahead.is_empty() {
+            self.change_look_ahead = None;
+        } else if let Ok(la) = look_ahead.parse::<u32>() {
+            self.change_look_ahead = Some(la);
+        }
+    }
+    /// Sets the minimum relay fee rate (sats/vb) from a string.
+    pub fn set_min_relay_fee(&mut self, fee: String) {
+        if let Some(fee) = parse_min_relay_fee(&fee) {
+            self.min_relay_fee = fee;
+        }
+    }
+    /// Sets the scan checkpoint (earliest used receive and change indices).
+    pub fn set_scan_from(&mut self, receive: u32, change: u32) {
+        self.scan_from = Tip { receive, change };
+    }
+    /// Sets the secondary descriptor from a string, an empty descriptor
+    /// removes it. A descriptor that cannot be derived for the network of
+    /// the config is ignored.
+    pub fn set_secondary_descriptor(&mut self, descriptor: String) {
+        if descriptor.is_empty() {
+            self.secondary_descriptor = None;
+        } else if let Ok(descriptor) = Descriptor::<DescriptorPublicKey>::from_str(&descriptor) {
+            if Derivator::new(descriptor.clone(), self.network).is_ok() {
+                self.secondary_descriptor = Some(descriptor);
+            }
+        }
+    }
     /// Sets the network.
     pub fn set_network(&mut self, network: Network) {
         self.network = network.into();
@@ -388,20 +1222,483 @@ impl Config {
     pub fn set_mnemonic(&mut self, mnemonic: String) {
         self.mnemonic = mnemonic;
     }
+    /// Returns the coinjoin mnemonic, empty if coinjoin inputs are signed
+    /// with the wallet mnemonic.
+    pub fn coinjoin_mnemonic(&self) -> String {
+        self.coinjoin_mnemonic.clone().unwrap_or_default()
+    }
+    /// Sets the mnemonic used to sign coinjoin inputs, an empty mnemonic
+    /// fallback to the wallet mnemonic.
+    pub fn set_coinjoin_mnemonic(&mut self, mnemonic: String) {
+        self.coinjoin_mnemonic = (!mnemonic.is_empty()).then_some(mnemonic);
+    }
+    /// Returns the keychain the coinjoin output addresses are derived from.
+    pub fn coinjoin_keychain(&self) -> AddrAccount {
+        self.coinjoin_keychain
+    }
+    /// Sets the keychain the coinjoin output addresses are derived from.
+    pub fn set_coinjoin_keychain(&mut self, keychain: AddrAccount) {
+        self.coinjoin_keychain = keychain;
+    }
+    /// Returns the unix timestamp (in seconds) of the wallet creation, 0 if
+    /// unknown.
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+    /// Returns whether new receive address requests reuse unused
+    /// addresses first.
+    pub fn reuse_unused_addresses(&self) -> bool {
+        self.reuse_unused_addresses
+    }
+    /// Sets whether new receive address requests reuse unused addresses
+    /// first.
+    pub fn set_reuse_unused_addresses(&mut self, reuse: bool) {
+        self.reuse_unused_addresses = reuse;
+    }
+    /// Returns the number of spendable coins above which a consolidation
+    /// is suggested.
+    pub fn consolidation_max_utxos(&self) -> u32 {
+        self.consolidation_max_utxos
+    }
+    /// Sets the number of spendable coins above which a consolidation is
+    /// suggested.
+    pub fn set_consolidation_max_utxos(&mut self, max: u32) {
+        self.consolidation_max_utxos = max;
+    }
+    /// Returns the minimum fee savings (sats) for which a consolidation is
+    /// suggested.
+    pub fn consolidation_min_savings(&self) -> u64 {
+        self.consolidation_min_savings
+    }
+    /// Sets the minimum fee savings (sats) for which a consolidation is
+    /// suggested.
+    pub fn set_consolidation_min_savings(&mut self, savings: u64) {
+        self.consolidation_min_savings = savings;
+    }
+    /// Returns whether the account xpubs are added to the prepared PSBTs.
+    pub fn psbt_global_xpubs(&self) -> bool {
+        self.psbt_global_xpubs
+    }
+    /// Sets whether the account xpubs are added to the prepared PSBTs.
+    pub fn set_psbt_global_xpubs(&mut self, enable: bool) {
+        self.psbt_global_xpubs = enable;
+    }
+    /// Returns whether a new receive address is generated when the last
+    /// one receives a coin.
+    pub fn auto_new_recv_addr(&self) -> bool {
+        self.auto_new_recv_addr
+    }
+    /// Sets whether a new receive address is generated when the last one
+    /// receives a coin, applied on the next account load.
+    pub fn set_auto_new_recv_addr(&mut self, enable: bool) {
+        self.auto_new_recv_addr = enable;
+    }
+    /// Returns the maximum amount (sats) of a coin paid to the miners on top
+    /// of the fees when joining a pool.
+    pub fn max_coinjoin_excess(&self) -> u64 {
+        self.max_coinjoin_excess
+    }
+    /// Sets the maximum amount (sats) of a coin paid to the miners on top of
+    /// the fees when joining a pool, 0 only accepts coins matching the
+    /// denomination plus the fees.
+    pub fn set_max_coinjoin_excess(&mut self, sats: u64) {
+        self.max_coinjoin_excess = sats;
+    }
+    /// Returns the minimum amount (sats) of a change output, 0 if unset.
+    pub fn min_change_sats(&self) -> u64 {
+        self.min_change_sats.unwrap_or_default()
+    }
+    /// Sets the minimum amount (sats) of a change output, 0 unsets it.
+    pub fn set_min_change_sats(&mut self, sats: u64) {
+        self.min_change_sats = (sats > 0).then_some(sats);
+    }
+    /// Returns the amount (sats) below which a coin received on a reused
+    /// address is flagged as a likely dust attack.
+    pub fn dust_attack_threshold(&self) -> u64 {
+        self.dust_attack_threshold
+    }
+    /// Sets the amount (sats) below which a coin received on a reused
+    /// address is flagged as a likely dust attack, 0 disables the flag.
+    pub fn set_dust_attack_threshold(&mut self, sats: u64) {
+        self.dust_attack_threshold = sats;
+    }
+    /// Returns the maximum number of items in a single electrum history or
+    /// transactions request.
+    pub fn electrum_batch_size(&self) -> u32 {
+        self.electrum_batch_size
+    }
+    /// Sets the maximum number of items in a single electrum history or
+    /// transactions request, 0 is treated as 1. Applied on the next
+    /// electrum connection.
+    pub fn set_electrum_batch_size(&mut self, size: u32) {
+        self.electrum_batch_size = size.max(1);
+    }
+    /// Returns the delay (ms) before the first reconnection attempt.
+    pub fn reconnect_initial_delay_ms(&self) -> u64 {
+        self.reconnect_initial_delay_ms
+    }
+    /// Sets the delay (ms) before the first reconnection attempt, at least
+    /// 1 ms so a zero delay never grows.
+    pub fn set_reconnect_initial_delay_ms(&mut self, delay: u64) {
+        self.reconnect_initial_delay_ms = delay.max(1);
+    }
+    /// Returns the factor applied to the reconnection delay after a failed
+    /// attempt, as a string.
+    pub fn reconnect_multiplier(&self) -> String {
+        self.reconnect_multiplier.to_string()
+    }
+    /// Sets the factor applied to the reconnection delay after a failed
+    /// attempt from a string, values lower than 1 are ignored.
+    pub fn set_reconnect_multiplier(&mut self, multiplier: String) {
+        if let Some(multiplier) = multiplier
+            .parse::<f64>()
+            .ok()
+            .filter(|m| m.is_finite() && *m >= 1.0)
+        {
+            self.reconnect_multiplier = multiplier;
+        }
+    }
+    /// Returns the maximum delay (ms) between two reconnection attempts.
+    pub fn reconnect_max_delay_ms(&self) -> u64 {
+        self.reconnect_max_delay_ms
+    }
+    /// Sets the maximum delay (ms) between two reconnection attempts.
+    pub fn set_reconnect_max_delay_ms(&mut self, delay: u64) {
+        self.reconnect_max_delay_ms = delay;
+    }
+    /// Returns whether the mnemonics are written to the config file.
+    pub fn persist_mnemonic(&self) -> bool {
+        self.persist_mnemonic
+    }
+    /// Sets whether the mnemonics are written to the config file, if not
+    /// they must be provided again on each launch.
+    pub fn set_persist_mnemonic(&mut self, persist: bool) {
+        self.persist_mnemonic = persist;
+    }
     /// Sets the account name.
     pub fn set_account(&mut self, name: String) {
         self.account = name;
     }
     /// Saves the configuration to a file.
-    pub fn to_file(&self) {
-        let mut path = Self::path(self.account.clone());
-        maybe_create_dir(&path);
-        path.push(CONFIG_FILENAME);
+    ///
+    /// # Returns
+    ///
+    /// A `ConfigError` if the configuration cannot be written.
+    pub fn to_file(&self) -> Result<(), ConfigError> {
+        self.write_to(Self::path(self.account.clone()))
+    }
+
+    /// Saves the configuration in the directory `dir`, creating it if
+    /// needed.
+    fn write_to(&self, mut dir: PathBuf) -> Result<(), ConfigError> {
+        maybe_create_dir(&dir)?;
+        dir.push(CONFIG_FILENAME);
+
+        log::warn!("Config::to_file() {:?}", dir);
+
+        let content = if self.persist_mnemonic {
+            serde_json::to_string_pretty(&self)?
+        } else {
+            let mut config = self.clone();
+            config.mnemonic = String::new();
+            config.coinjoin_mnemonic = None;
+            serde_json::to_string_pretty(&config)?
+        };
+        let mut file = File::create(dir)?;
+        file.write_all(content.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use joinstr::miniscript::bitcoin::{
+        bip32::Xpriv,
+        secp256k1::{Message, Secp256k1},
+    };
+
+    use super::*;
+
+    #[test]
+    fn set_secondary_descriptor() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mut config = new_config(mnemonic.to_string(), "test".to_string(), Network::Regtest);
+        let signer = HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, mnemonic).unwrap();
+        let descriptor =
+            wpkh(signer.xpub(&DerivationPath::from_str("m/84'/1'/1'").unwrap())).to_string();
+        config.set_secondary_descriptor(descriptor.clone());
+        assert_eq!(config.secondary_descriptor(), descriptor);
+
+        // descriptors that cannot be derived are rejected
+        let mainnet = HotSigner::new_from_mnemonics(bitcoin::Network::Bitcoin, mnemonic).unwrap();
+        let wrong_network =
+            wpkh(mainnet.xpub(&DerivationPath::from_str("m/84'/0'/1'").unwrap())).to_string();
+        for invalid in [wrong_network, "wpkh(key)".to_string()] {
+            config.set_secondary_descriptor(invalid);
+            assert_eq!(config.secondary_descriptor(), descriptor);
+        }
+
+        config.set_secondary_descriptor(String::new());
+        assert_eq!(config.secondary_descriptor(), "");
+    }
+
+    #[test]
+    fn descriptor_fingerprint() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let signer = HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, mnemonic).unwrap();
+        let descriptor =
+            wpkh(signer.xpub(&DerivationPath::from_str("m/84'/1'/0'").unwrap())).to_string();
+
+        assert!(descriptor_contains_fingerprint(
+            descriptor.clone(),
+            "73c5da0a".to_string()
+        ));
+        assert!(descriptor_contains_fingerprint(
+            descriptor.clone(),
+            "73C5DA0A".to_string()
+        ));
+        assert!(!descriptor_contains_fingerprint(
+            descriptor.clone(),
+            "deadbeef".to_string()
+        ));
+        assert!(!descriptor_contains_fingerprint(
+            descriptor,
+            "fingerprint".to_string()
+        ));
+        assert!(!descriptor_contains_fingerprint(
+            "wpkh(key)".to_string(),
+            "73c5da0a".to_string()
+        ));
+    }
+
+    #[test]
+    fn to_file_unwritable() {
+        let config = new_config(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+            "unwritable".to_string(),
+            Network::Regtest,
+        );
+        // procfs does not allow creating directories, even as root
+        let dir = PathBuf::from("/proc/qoinstr/unwritable");
+        let err = config.write_to(dir).unwrap_err();
+        assert!(matches!(err, ConfigError::Io(_)));
+        assert!(err.to_string().starts_with("Fail to write config"));
+    }
+
+    #[test]
+    fn coinjoin_signer_mnemonic() {
+        let wallet = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let coinjoin =
+            "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let mut config = new_config(wallet.to_string(), "test".to_string(), Network::Regtest);
+        assert_eq!(config.coinjoin_signer_mnemonic(), wallet);
+        assert_eq!(config.coinjoin_mnemonic(), "");
+
+        config.set_coinjoin_mnemonic(coinjoin.to_string());
+        assert_eq!(config.coinjoin_signer_mnemonic(), coinjoin);
+        assert_eq!(config.coinjoin_mnemonic(), coinjoin);
+
+        // the coinjoin signer derives keys distinct from the wallet ones
+        let path = DerivationPath::from_str("m/84'/1'/0'").unwrap();
+        let wallet_signer = HotSigner::new_from_mnemonics(config.network, wallet).unwrap();
+        let coinjoin_signer =
+            HotSigner::new_from_mnemonics(config.network, &config.coinjoin_signer_mnemonic())
+                .unwrap();
+        assert_ne!(
+            wallet_signer.xpub(&path).xkey,
+            coinjoin_signer.xpub(&path).xkey
+        );
+
+        // a signature of the coinjoin signer verifies against the key of
+        // the coinjoin mnemonic, not against the wallet one
+        let secp = Secp256k1::new();
+        let msg = Message::from_digest([7; 32]);
+        let signature = secp.sign_ecdsa(&msg, &coinjoin_signer.xpriv(&path).xkey.private_key);
+        let seed = Mnemonic::parse(coinjoin).unwrap().to_seed("");
+        let expected = Xpriv::new_master(config.network, &seed)
+            .unwrap()
+            .derive_priv(&secp, &path)
+            .unwrap()
+            .private_key
+            .public_key(&secp);
+        assert!(secp.verify_ecdsa(&msg, &signature, &expected).is_ok());
+        assert!(secp
+            .verify_ecdsa(&msg, &signature, &wallet_signer.public_key_at(&path))
+            .is_err());
+
+        config.set_coinjoin_mnemonic(String::new());
+        assert_eq!(config.coinjoin_signer_mnemonic(), wallet);
+    }
+
+    #[test]
+    fn min_relay_fee() {
+        assert_eq!(parse_min_relay_fee("1"), Some(1.0));
+        assert_eq!(parse_min_relay_fee("0.1"), Some(0.1));
+        assert!(parse_min_relay_fee("0").is_none());
+        assert!(parse_min_relay_fee("-1").is_none());
+        assert!(parse_min_relay_fee("NaN").is_none());
+        assert!(parse_min_relay_fee("inf").is_none());
+        assert!(parse_min_relay_fee("").is_none());
+    }
 
-        log::warn!("Config::to_file() {:?}", path);
+    #[test]
+    fn created_at() {
+        let config = new_config(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+            "created_at".to_string(),
+            Network::Regtest,
+        );
+        assert!(config.created_at() > 0);
+
+        // the creation timestamp is persisted as is
+        let content = serde_json::to_string(&config).unwrap();
+        let parsed: Config = serde_json::from_str(&content).unwrap();
+        assert_eq!(parsed.created_at(), config.created_at());
+
+        // unknown for configs created before it was tracked
+        let mut value = serde_json::to_value(&*config).unwrap();
+        value.as_object_mut().unwrap().remove("created_at");
+        let parsed: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.created_at(), 0);
+    }
+
+    #[test]
+    fn nostr_back_bounds() {
+        assert_eq!(parse_nostr_back("3600"), Some(3600));
+        assert_eq!(parse_nostr_back("1"), Some(1));
+        assert_eq!(
+            parse_nostr_back(&MAX_NOSTR_BACK.to_string()),
+            Some(MAX_NOSTR_BACK)
+        );
+        assert_eq!(parse_nostr_back("0"), None);
+        assert_eq!(parse_nostr_back(&(MAX_NOSTR_BACK + 1).to_string()), None);
+        assert_eq!(parse_nostr_back("-1"), None);
+        assert_eq!(parse_nostr_back(""), None);
+    }
+
+    #[test]
+    fn nostr_back_from_file() {
+        let mut config = new_config(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+            "nostr_back_from_file".to_string(),
+            Network::Regtest,
+        );
+        for (back, loaded) in [
+            (3600, 3600),
+            (0, 1),
+            (MAX_NOSTR_BACK + 1, MAX_NOSTR_BACK),
+            (u64::MAX, MAX_NOSTR_BACK),
+        ] {
+            config.nostr_back = Some(back);
+            config.to_file().unwrap();
+            let config = Config::from_file(config.account.clone());
+            assert_eq!(config.nostr_back, Some(loaded));
+        }
+    }
+
+    #[test]
+    fn nostr_back_hours() {
+        let mut config = new_config(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+            "nostr_back_hours".to_string(),
+            Network::Regtest,
+        );
+        config.set_nostr_back_hours("24".to_string());
+        assert_eq!(config.nostr_back, Some(86400));
+        assert_eq!(config.nostr_back_hours(), "24");
+
+        config.set_nostr_back_hours("1.5".to_string());
+        assert_eq!(config.nostr_back(), "5400");
+        assert_eq!(config.nostr_back_hours(), "1.5");
+
+        // invalid values are ignored
+        for hours in ["0", "-1", "NaN", "inf", "721", "hours"] {
+            config.set_nostr_back_hours(hours.to_string());
+            assert_eq!(config.nostr_back, Some(5400));
+        }
+        assert_eq!(parse_nostr_back_hours("720"), Some(MAX_NOSTR_BACK));
+
+        // the seconds setter ignores invalid values too
+        let too_far = (MAX_NOSTR_BACK + 1).to_string();
+        for back in ["0", "-1", "back", too_far.as_str()] {
+            config.set_nostr_back(back.to_string());
+            assert_eq!(config.nostr_back, Some(5400));
+        }
+        config.set_nostr_back("3600".to_string());
+        assert_eq!(config.nostr_back_hours(), "1");
+
+        // an empty value clears the lookback
+        config.set_nostr_back(String::new());
+        assert_eq!(config.nostr_back, None);
+        config.set_nostr_back_hours("1".to_string());
+        config.set_nostr_back_hours(String::new());
+        assert_eq!(config.nostr_back, None);
+    }
+
+    #[test]
+    fn nostr_pool_authors() {
+        let mut config = new_config(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+            "nostr_pool_authors".to_string(),
+            Network::Regtest,
+        );
+        let author = "79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+        let other = "c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+
+        // any publisher is allowed by default
+        assert!(config.nostr_pool_authors().is_empty());
+        assert!(is_pool_author_allowed(&config.nostr_pool_authors, other));
+
+        // invalid keys are ignored
+        config.set_nostr_pool_authors(vec![
+            author.to_string(),
+            "npub".to_string(),
+            author[..62].to_string(),
+        ]);
+        assert_eq!(config.nostr_pool_authors(), vec![author.to_lowercase()]);
+
+        // the filter is applied and persisted
+        let json = serde_json::to_string(&*config).unwrap();
+        let loaded: Config = serde_json::from_str(&json).unwrap();
+        assert!(is_pool_author_allowed(&loaded.nostr_pool_authors, author));
+        assert!(is_pool_author_allowed(
+            &loaded.nostr_pool_authors,
+            &author.to_lowercase()
+        ));
+        assert!(!is_pool_author_allowed(&loaded.nostr_pool_authors, other));
+    }
+
+    #[test]
+    fn electrum_config_empty_host() {
+        assert!(parse_electrum_config("", "50001").is_none());
+        assert!(parse_electrum_config("tcp://", "50001").is_none());
+        assert!(parse_electrum_config("ssl://", "50001").is_none());
+    }
+
+    #[test]
+    fn electrum_config_wrong_port() {
+        assert!(parse_electrum_config("127.0.0.1", "").is_none());
+        assert!(parse_electrum_config("127.0.0.1", "port").is_none());
+        assert!(parse_electrum_config("127.0.0.1", "0").is_none());
+        assert!(parse_electrum_config("127.0.0.1", "65536").is_none());
+    }
+
+    #[test]
+    fn electrum_config_malformed_host() {
+        assert!(parse_electrum_config("127.0.0.1:50001", "50001").is_none());
+        assert!(parse_electrum_config("my host", "50001").is_none());
+        assert!(parse_electrum_config("http://host", "50001").is_none());
+    }
 
-        let mut file = File::create(path).unwrap();
-        let content = serde_json::to_string_pretty(&self).unwrap();
-        file.write_all(content.as_bytes()).unwrap();
+    #[test]
+    fn electrum_config_valid() {
+        assert_eq!(
+            parse_electrum_config("127.0.0.1", "50001"),
+            Some(("127.0.0.1".to_string(), 50001))
+        );
+        assert_eq!(
+            parse_electrum_config("ssl://electrum.blockstream.info", "50002"),
+            Some(("ssl://electrum.blockstream.info".to_string(), 50002))
+        );
     }
 }
@@ -1,9 +1,17 @@
+use std::{collections::BTreeMap, str::FromStr};
+
 use joinstr::miniscript::{
-    bitcoin::{self, ScriptBuf},
+    bitcoin::{
+        self,
+        bip32::{ChildNumber, DerivationPath, Fingerprint, Xpub},
+        ScriptBuf,
+    },
     descriptor::Wildcard,
     Descriptor, DescriptorPublicKey, ForEachKey,
 };
 
+use crate::cpp_joinstr::AddrAccount;
+
 #[derive(Debug)]
 pub enum Error {
     NotMultiXpub,
@@ -11,6 +19,7 @@ pub enum Error {
     MultiPathCount,
     MultiPath,
     Wildcard,
+    KeychainMismatch,
 }
 
 /// A struct that manages Bitcoin address derivation from a descriptor.
@@ -40,8 +49,9 @@ impl Derivator {
     /// # Note: the descriptor is expected to have this properties:
     /// - It must be of type [`DescriptorPublicKey::MultiXpub`]
     /// - All keys must have a multipath of size 2, the first element being the receive index,
-    ///   the second being the change index.
-    /// - Multipath elements must be of unhardened type.
+    ///   the second being the change index. The multipath can be at any position in the
+    ///   derivation path.
+    /// - All derivation steps (including multipath elements) must be of unhardened type.
     /// - It must have an Unhardened wildcard.
     /// - All key must be for the given network.
     pub fn new(
@@ -64,12 +74,9 @@ impl Derivator {
                 }
                 let paths = key.derivation_paths.paths();
                 for p in paths {
-                    let v = p.to_u32_vec();
-                    // expected 1 multipath + 1 wildcard
-                    if v.len() != 1 {
-                        wrong_multipath = true;
-                    }
-                    for child in v {
+                    // NOTE: the multipath can sit anywhere in the path and
+                    // be surrounded by other unhardened steps
+                    for child in p.to_u32_vec() {
                         // if hardened derivation path
                         if child >= 0x80000000 {
                             wrong_multipath = true;
@@ -104,6 +111,9 @@ impl Derivator {
         let mut single_descriptors = single_descriptors.into_iter();
         let recv = single_descriptors.next().expect("length checked");
         let change = single_descriptors.next().expect("length checked");
+        if recv == change {
+            return Err(Error::MultiPath);
+        }
 
         Ok(Self {
             descriptor,
@@ -113,6 +123,26 @@ impl Derivator {
         })
     }
 
+    /// Creates a new `Derivator` instance from separate receive and change
+    /// descriptors, as exported by wallets that do not support multipath
+    /// descriptors.
+    ///
+    /// # Parameters
+    /// - `recv`: The single path descriptor of the receiving keychain.
+    /// - `change`: The single path descriptor of the change keychain.
+    /// - `network`: The Bitcoin network type.
+    ///
+    /// # Returns
+    /// - `Result<Self, Error>`: Returns an instance of `Derivator` built on the
+    ///   merged multipath descriptor, see [`merge_keychains`].
+    pub fn from_keychains(
+        recv: &Descriptor<DescriptorPublicKey>,
+        change: &Descriptor<DescriptorPublicKey>,
+        network: bitcoin::Network,
+    ) -> Result<Self, Error> {
+        Self::new(merge_keychains(recv, change)?, network)
+    }
+
     /// Returns the main descriptor of the `Derivator`.
     ///
     /// # Returns
@@ -122,6 +152,85 @@ impl Derivator {
         self.descriptor.clone()
     }
 
+    /// Returns the single descriptor of the receiving keychain.
+    ///
+    /// # Returns
+    /// - `Descriptor<DescriptorPublicKey>`: The descriptor deriving receiving
+    ///   addresses, the first element of the multipath.
+    pub fn receive_descriptor(&self) -> Descriptor<DescriptorPublicKey> {
+        self.recv.clone()
+    }
+
+    /// Returns the single descriptor of the change keychain.
+    ///
+    /// # Returns
+    /// - `Descriptor<DescriptorPublicKey>`: The descriptor deriving change
+    ///   addresses, the second element of the multipath.
+    pub fn change_descriptor(&self) -> Descriptor<DescriptorPublicKey> {
+        self.change.clone()
+    }
+
+    /// Returns the key expression of the account key, as expected by a
+    /// multisig coordinator: the key origin followed by the xpub, without
+    /// multipath nor wildcard.
+    ///
+    /// The unhardened steps before the multipath are kept in the expression,
+    /// the coordinator appends `/<0;1>/*` to it.
+    ///
+    /// # Returns
+    /// - `String`: The key expression, e.g. `[fingerprint/84'/0'/0']xpub...`,
+    ///   empty if the key is not followed by `/<0;1>/*` once these steps
+    ///   are kept.
+    pub fn key_expression(&self) -> String {
+        let mut expression = String::new();
+        self.descriptor.for_each_key(|k| {
+            if let DescriptorPublicKey::MultiXPub(key) = k {
+                let paths = key.derivation_paths.paths();
+                let (recv, change) = (paths[0].to_u32_vec(), paths[1].to_u32_vec());
+                let steps = match (recv.split_last(), change.split_last()) {
+                    (Some((&0, r)), Some((&1, c))) if r == c => r,
+                    _ => return false,
+                };
+                let steps = steps.iter().fold(String::new(), |p, s| format!("{p}/{s}"));
+                expression = match &key.origin {
+                    Some((fg, path)) => format!("[{fg}/{path}]{}{steps}", key.xkey),
+                    None => format!("{}{steps}", key.xkey),
+                };
+            }
+            // NOTE: only the first key is returned
+            false
+        });
+        expression
+    }
+
+    /// Returns the account keys with their origin, as expected in the
+    /// `PSBT_GLOBAL_XPUB` map of a PSBT.
+    ///
+    /// # Returns
+    /// - `BTreeMap<Xpub, (Fingerprint, DerivationPath)>`: The xpubs of the
+    ///   descriptor, a key without origin is its own master key if at depth
+    ///   0, otherwise its origin is unknown and it is skipped.
+    pub fn global_xpubs(&self) -> BTreeMap<Xpub, (Fingerprint, DerivationPath)> {
+        let mut xpubs = BTreeMap::new();
+        self.descriptor.for_each_key(|k| {
+            if let DescriptorPublicKey::MultiXPub(key) = k {
+                let origin = match &key.origin {
+                    Some(origin) => Some(origin.clone()),
+                    None if key.xkey.depth == 0 => {
+                        Some((key.xkey.fingerprint(), DerivationPath::master()))
+                    }
+                    // NOTE: a made up origin would mislead the signers
+                    None => None,
+                };
+                if let Some(origin) = origin {
+                    xpubs.insert(key.xkey, origin);
+                }
+            }
+            true
+        });
+        xpubs
+    }
+
     /// Derives a receiving address at the specified index.
     ///
     /// # Parameters
@@ -137,6 +246,107 @@ impl Derivator {
             .expect("valid")
     }
 
+    /// Returns the full derivation path of an address, from the master key
+    /// of the descriptor key origin.
+    ///
+    /// # Parameters
+    /// - `account`: The keychain of the address.
+    /// - `index`: The index of the address.
+    ///
+    /// # Returns
+    /// - `String`: The derivation path, e.g. `m/84'/0'/0'/0/5`, of the first
+    ///   key of the descriptor.
+    pub fn derivation_path(&self, account: AddrAccount, index: u32) -> String {
+        let mut path = None;
+        self.keychain(account)
+            .at_derivation_index(index)
+            .expect("wildcard checked")
+            .for_each_key(|k| {
+                path = k.full_derivation_path();
+                // NOTE: only the first key is returned
+                false
+            });
+        let path = path.unwrap_or_default();
+        path.as_ref()
+            .iter()
+            .fold("m".to_string(), |path, child| format!("{path}/{child}"))
+    }
+
+    /// Returns the full derivation path of the key of a signer in an address.
+    ///
+    /// # Parameters
+    /// - `account`: The keychain of the address.
+    /// - `index`: The index of the address.
+    /// - `fingerprint`: The master fingerprint of the signer.
+    ///
+    /// # Returns
+    /// - `Option<DerivationPath>`: The derivation path from the master key,
+    ///   `None` if the descriptor has no key of this signer.
+    pub fn key_path(
+        &self,
+        account: AddrAccount,
+        index: u32,
+        fingerprint: Fingerprint,
+    ) -> Option<DerivationPath> {
+        let mut path = None;
+        self.keychain(account)
+            .at_derivation_index(index)
+            .expect("wildcard checked")
+            .for_each_key(|k| {
+                if k.master_fingerprint() == fingerprint {
+                    path = k.full_derivation_path();
+                }
+                path.is_none()
+            });
+        path
+    }
+
+    /// Returns the single descriptor of a keychain.
+    fn keychain(&self, account: AddrAccount) -> &Descriptor<DescriptorPublicKey> {
+        match account {
+            AddrAccount::Receive => &self.recv,
+            AddrAccount::Change => &self.change,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the keychain and the index of the address a key belongs to.
+    ///
+    /// # Parameters
+    /// - `fingerprint`: The master fingerprint of the key.
+    /// - `path`: The full derivation path of the key, from its master key,
+    ///   as recorded in the `bip32_derivation` of a PSBT.
+    ///
+    /// # Returns
+    /// - `Option<(AddrAccount, u32)>`: The keychain and the index of the
+    ///   address, `None` if the key is not derived from the descriptor.
+    pub fn address_index(
+        &self,
+        fingerprint: Fingerprint,
+        path: &DerivationPath,
+    ) -> Option<(AddrAccount, u32)> {
+        // NOTE: the wildcard is always the last step
+        let index = match path.as_ref().last()? {
+            ChildNumber::Normal { index } => *index,
+            _ => return None,
+        };
+        [
+            (AddrAccount::Receive, &self.recv),
+            (AddrAccount::Change, &self.change),
+        ]
+        .into_iter()
+        .find(|(_, descriptor)| {
+            descriptor
+                .at_derivation_index(index)
+                .expect("wildcard checked")
+                .for_any_key(|k| {
+                    k.master_fingerprint() == fingerprint
+                        && k.full_derivation_path().as_ref() == Some(path)
+                })
+        })
+        .map(|(account, _)| (account, index))
+    }
+
     /// Derives a change address at the specified index.
     ///
     /// # Parameters
@@ -173,4 +383,419 @@ impl Derivator {
     pub fn change_spk_at(&self, index: u32) -> ScriptBuf {
         self.change_at(index).script_pubkey()
     }
+
+    /// Returns the script public key of the address of a keychain at the
+    /// specified index.
+    ///
+    /// # Parameters
+    /// - `account`: The keychain of the address.
+    /// - `index`: The index at which to derive the address.
+    ///
+    /// # Returns
+    /// - `ScriptBuf`: The script public key of the derived address.
+    pub fn spk_at(&self, account: AddrAccount, index: u32) -> ScriptBuf {
+        match account {
+            AddrAccount::Receive => self.receive_spk_at(index),
+            AddrAccount::Change => self.change_spk_at(index),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Merges a receive and a change single path descriptors into a multipath
+/// descriptor.
+///
+/// # Parameters
+/// - `recv`: The single path descriptor of the receiving keychain.
+/// - `change`: The single path descriptor of the change keychain.
+///
+/// # Returns
+/// - `Result<Descriptor<DescriptorPublicKey>, Error>`: The descriptor with a
+///   `<recv;change>` multipath step on each key, or
+///   [`Error::KeychainMismatch`] if the descriptors do not share the same
+///   script and keys, or if the derivation paths of a key differ by more
+///   than one step.
+pub fn merge_keychains(
+    recv: &Descriptor<DescriptorPublicKey>,
+    change: &Descriptor<DescriptorPublicKey>,
+) -> Result<Descriptor<DescriptorPublicKey>, Error> {
+    let mut recv_keys = vec![];
+    recv.for_each_key(|k| {
+        recv_keys.push(k.clone());
+        true
+    });
+    let mut change_keys = vec![];
+    change.for_each_key(|k| {
+        change_keys.push(k.clone());
+        true
+    });
+    if recv_keys.len() != change_keys.len() {
+        return Err(Error::KeychainMismatch);
+    }
+
+    // NOTE: the descriptor string has a checksum suffix
+    let recv_str = recv.to_string();
+    let mut merged = recv_str
+        .split('#')
+        .next()
+        .expect("at least one element")
+        .to_string();
+    for (r, c) in recv_keys.iter().zip(&change_keys) {
+        let (DescriptorPublicKey::XPub(r_key), DescriptorPublicKey::XPub(c_key)) = (r, c) else {
+            return Err(Error::KeychainMismatch);
+        };
+        if r_key.origin != c_key.origin
+            || r_key.xkey != c_key.xkey
+            || r_key.wildcard != c_key.wildcard
+        {
+            return Err(Error::KeychainMismatch);
+        }
+        let r_path = r_key.derivation_path.as_ref();
+        let c_path = c_key.derivation_path.as_ref();
+        if r_path.len() != c_path.len()
+            || r_path.iter().zip(c_path).filter(|(a, b)| a != b).count() != 1
+        {
+            return Err(Error::KeychainMismatch);
+        }
+
+        let mut key = match &r_key.origin {
+            Some((fg, path)) => format!("[{fg}/{path}]{}", r_key.xkey),
+            None => r_key.xkey.to_string(),
+        };
+        for (a, b) in r_path.iter().zip(c_path) {
+            if a == b {
+                key.push_str(&format!("/{a}"));
+            } else {
+                key.push_str(&format!("/<{a};{b}>"));
+            }
+        }
+        key.push_str(match r_key.wildcard {
+            Wildcard::None => "",
+            Wildcard::Unhardened => "/*",
+            Wildcard::Hardened => "/*'",
+        });
+        merged = merged.replacen(&r.to_string(), &key, 1);
+    }
+
+    let merged = Descriptor::<DescriptorPublicKey>::from_str(&merged)
+        .map_err(|_| Error::KeychainMismatch)?;
+    // the keychains must be the only difference between the descriptors
+    match merged.clone().into_single_descriptors() {
+        Ok(singles) if singles == [recv.clone(), change.clone()] => Ok(merged),
+        _ => Err(Error::KeychainMismatch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::signer::HotSigner;
+
+    const MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn xpub(path: &str) -> String {
+        let signer = HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, MNEMONIC).unwrap();
+        signer
+            .xpub(&DerivationPath::from_str(path).unwrap())
+            .xkey
+            .to_string()
+    }
+
+    fn descriptor(descr: &str) -> Descriptor<DescriptorPublicKey> {
+        Descriptor::from_str(descr).unwrap()
+    }
+
+    fn address(descr: &str, index: u32) -> bitcoin::Address {
+        descriptor(descr)
+            .at_derivation_index(index)
+            .unwrap()
+            .address(bitcoin::Network::Regtest)
+            .unwrap()
+    }
+
+    #[test]
+    fn multipath_layouts() {
+        let key = xpub("m/84'/1'/0'");
+        let other = xpub("m/84'/1'/1'");
+
+        // the standard layout
+        let derivator = Derivator::new(
+            descriptor(&format!("wpkh({key}/<0;1>/*)")),
+            bitcoin::Network::Regtest,
+        )
+        .unwrap();
+        assert_eq!(
+            derivator.receive_at(2),
+            address(&format!("wpkh({key}/0/*)"), 2)
+        );
+        assert_eq!(
+            derivator.change_at(2),
+            address(&format!("wpkh({key}/1/*)"), 2)
+        );
+
+        // an unhardened step before the multipath
+        let derivator = Derivator::new(
+            descriptor(&format!("wpkh({key}/7/<0;1>/*)")),
+            bitcoin::Network::Regtest,
+        )
+        .unwrap();
+        assert_eq!(
+            derivator.receive_at(2),
+            address(&format!("wpkh({key}/7/0/*)"), 2)
+        );
+        assert_eq!(
+            derivator.change_at(2),
+            address(&format!("wpkh({key}/7/1/*)"), 2)
+        );
+
+        // an unhardened step after the multipath
+        let derivator = Derivator::new(
+            descriptor(&format!("wpkh({key}/<0;1>/3/*)")),
+            bitcoin::Network::Regtest,
+        )
+        .unwrap();
+        assert_eq!(
+            derivator.receive_at(2),
+            address(&format!("wpkh({key}/0/3/*)"), 2)
+        );
+        assert_eq!(
+            derivator.change_at(2),
+            address(&format!("wpkh({key}/1/3/*)"), 2)
+        );
+
+        // keys with different layouts
+        let derivator = Derivator::new(
+            descriptor(&format!("wsh(multi(1,{key}/<0;1>/*,{other}/5/<2;3>/*))")),
+            bitcoin::Network::Regtest,
+        )
+        .unwrap();
+        assert_eq!(
+            derivator.change_at(4),
+            address(&format!("wsh(multi(1,{key}/1/*,{other}/5/3/*))"), 4)
+        );
+    }
+
+    #[test]
+    fn key_expression_layouts() {
+        let signer = HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, MNEMONIC).unwrap();
+        let key = signer.xpub(&DerivationPath::from_str("m/84'/1'/0'").unwrap());
+        let origin = format!("[{}/84'/1'/0']{}", key.origin.0, key.xkey);
+        let derivator = |layout: &str| {
+            Derivator::new(
+                descriptor(&format!("wpkh({origin}{layout})")),
+                bitcoin::Network::Regtest,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(derivator("/<0;1>/*").key_expression(), origin);
+        // steps before the multipath are part of the key
+        assert_eq!(
+            derivator("/7/<0;1>/*").key_expression(),
+            format!("{origin}/7")
+        );
+        // layouts a coordinator cannot rebuild with `/<0;1>/*`
+        assert!(derivator("/<0;1>/3/*").key_expression().is_empty());
+        assert!(derivator("/<1;0>/*").key_expression().is_empty());
+
+        // the key path follows the layout
+        let path = derivator("/<0;1>/3/*")
+            .key_path(AddrAccount::Change, 5, signer.fingerprint())
+            .unwrap();
+        assert_eq!(path, DerivationPath::from_str("m/84'/1'/0'/1/3/5").unwrap());
+        assert!(derivator("/<0;1>/*")
+            .key_path(AddrAccount::Change, 5, Fingerprint::default())
+            .is_none());
+    }
+
+    #[test]
+    fn global_xpubs_origin() {
+        let signer = HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, MNEMONIC).unwrap();
+        let key = signer.xpub(&DerivationPath::from_str("m/84'/1'/0'").unwrap());
+        let master = signer.xpub(&DerivationPath::master()).xkey;
+        let derivator =
+            |descr: String| Derivator::new(descriptor(&descr), bitcoin::Network::Regtest).unwrap();
+
+        // a key with its origin
+        let origin = format!("[{}/84'/1'/0']{}", key.origin.0, key.xkey);
+        let xpubs = derivator(format!("wpkh({origin}/<0;1>/*)")).global_xpubs();
+        assert_eq!(xpubs.len(), 1);
+        assert_eq!(
+            xpubs[&key.xkey],
+            (
+                signer.fingerprint(),
+                DerivationPath::from_str("m/84'/1'/0'").unwrap()
+            )
+        );
+
+        // a master key without origin is its own origin
+        let xpubs = derivator(format!("wpkh({master}/<0;1>/*)")).global_xpubs();
+        assert_eq!(
+            xpubs[&master],
+            (master.fingerprint(), DerivationPath::master())
+        );
+
+        // the origin of a derived key without origin is unknown
+        let xpubs = derivator(format!("wpkh({}/<0;1>/*)", key.xkey)).global_xpubs();
+        assert!(xpubs.is_empty());
+    }
+
+    #[test]
+    fn address_index() {
+        let signer = HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, MNEMONIC).unwrap();
+        let key = signer.xpub(&DerivationPath::from_str("m/84'/1'/0'").unwrap());
+        let origin = format!("[{}/84'/1'/0']{}", key.origin.0, key.xkey);
+        let fg = signer.fingerprint();
+        let path = |p: &str| DerivationPath::from_str(p).unwrap();
+
+        let derivator = Derivator::new(
+            descriptor(&format!("wpkh({origin}/<0;1>/*)")),
+            bitcoin::Network::Regtest,
+        )
+        .unwrap();
+        assert_eq!(
+            derivator.address_index(fg, &path("m/84'/1'/0'/0/5")),
+            Some((AddrAccount::Receive, 5))
+        );
+        assert_eq!(
+            derivator.address_index(fg, &path("m/84'/1'/0'/1/2")),
+            Some((AddrAccount::Change, 2))
+        );
+        // another account, another fingerprint or a relative path
+        assert_eq!(derivator.address_index(fg, &path("m/84'/1'/1'/0/5")), None);
+        assert_eq!(
+            derivator.address_index(Fingerprint::default(), &path("m/84'/1'/0'/0/5")),
+            None
+        );
+        assert_eq!(derivator.address_index(fg, &path("m/0/5")), None);
+
+        // the keychain is not the second to last step
+        let derivator = Derivator::new(
+            descriptor(&format!("wpkh({origin}/<0;1>/3/*)")),
+            bitcoin::Network::Regtest,
+        )
+        .unwrap();
+        assert_eq!(
+            derivator.address_index(fg, &path("m/84'/1'/0'/1/3/7")),
+            Some((AddrAccount::Change, 7))
+        );
+        assert_eq!(derivator.address_index(fg, &path("m/84'/1'/0'/0/7")), None);
+    }
+
+    #[test]
+    fn invalid_multipath_layouts() {
+        let key = xpub("m/84'/1'/0'");
+
+        // not a multipath descriptor
+        assert!(matches!(
+            Derivator::new(
+                descriptor(&format!("wpkh({key}/0/*)")),
+                bitcoin::Network::Regtest
+            ),
+            Err(Error::NotMultiXpub)
+        ));
+        // more than two keychains
+        assert!(matches!(
+            Derivator::new(
+                descriptor(&format!("wpkh({key}/<0;1;2>/*)")),
+                bitcoin::Network::Regtest
+            ),
+            Err(Error::MultiPathCount)
+        ));
+        // not ranged
+        assert!(matches!(
+            Derivator::new(
+                descriptor(&format!("wpkh({key}/<0;1>)")),
+                bitcoin::Network::Regtest
+            ),
+            Err(Error::Wildcard)
+        ));
+        // wrong network
+        assert!(matches!(
+            Derivator::new(
+                descriptor(&format!("wpkh({key}/<0;1>/*)")),
+                bitcoin::Network::Bitcoin
+            ),
+            Err(Error::WrongNetwork)
+        ));
+    }
+
+    #[test]
+    fn merge_single_path_keychains() {
+        let key = format!("[73c5da0a/84'/1'/0']{}", xpub("m/84'/1'/0'"));
+        let other = xpub("m/84'/1'/1'");
+
+        let merged = merge_keychains(
+            &descriptor(&format!("wpkh({key}/0/*)")),
+            &descriptor(&format!("wpkh({key}/1/*)")),
+        )
+        .unwrap();
+        assert_eq!(merged, descriptor(&format!("wpkh({key}/<0;1>/*)")));
+
+        // the keychain step can sit anywhere in the path of each key
+        let merged = merge_keychains(
+            &descriptor(&format!("wsh(multi(1,{key}/0/*,{other}/5/2/*))")),
+            &descriptor(&format!("wsh(multi(1,{key}/1/*,{other}/5/3/*))")),
+        )
+        .unwrap();
+        assert_eq!(
+            merged,
+            descriptor(&format!("wsh(multi(1,{key}/<0;1>/*,{other}/5/<2;3>/*))"))
+        );
+
+        let derivator = Derivator::from_keychains(
+            &descriptor(&format!("wpkh({key}/0/*)")),
+            &descriptor(&format!("wpkh({key}/1/*)")),
+            bitcoin::Network::Regtest,
+        )
+        .unwrap();
+        assert_eq!(
+            derivator.receive_at(3),
+            address(&format!("wpkh({key}/0/*)"), 3)
+        );
+        assert_eq!(
+            derivator.change_at(3),
+            address(&format!("wpkh({key}/1/*)"), 3)
+        );
+    }
+
+    #[test]
+    fn merge_mismatched_keychains() {
+        let key = xpub("m/84'/1'/0'");
+        let other = xpub("m/84'/1'/1'");
+        let merge =
+            |recv: &str, change: &str| merge_keychains(&descriptor(recv), &descriptor(change));
+
+        // different keys
+        assert!(matches!(
+            merge(&format!("wpkh({key}/0/*)"), &format!("wpkh({other}/1/*)")),
+            Err(Error::KeychainMismatch)
+        ));
+        // different scripts
+        assert!(matches!(
+            merge(&format!("wpkh({key}/0/*)"), &format!("tr({key}/1/*)")),
+            Err(Error::KeychainMismatch)
+        ));
+        // paths differing by more than the keychain
+        assert!(matches!(
+            merge(&format!("wpkh({key}/0/0/*)"), &format!("wpkh({key}/1/1/*)")),
+            Err(Error::KeychainMismatch)
+        ));
+        // same keychain
+        assert!(matches!(
+            merge(&format!("wpkh({key}/0/*)"), &format!("wpkh({key}/0/*)")),
+            Err(Error::KeychainMismatch)
+        ));
+        // already multipath
+        assert!(matches!(
+            merge(
+                &format!("wpkh({key}/<0;1>/*)"),
+                &format!("wpkh({key}/<0;1>/*)")
+            ),
+            Err(Error::KeychainMismatch)
+        ));
+    }
 }
@@ -1,7 +1,9 @@
 use std::{
     collections::BTreeMap,
+    fmt::Display,
     fs::File,
     io::{Read, Write},
+    str::FromStr,
 };
 
 use joinstr::miniscript::bitcoin::{self, address::NetworkUnchecked, OutPoint};
@@ -16,6 +18,53 @@ pub enum LabelKey {
     Address(bitcoin::Address<NetworkUnchecked>),
 }
 
+impl Display for LabelKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelKey::OutPoint(op) => write!(f, "outpoint:{op}"),
+            LabelKey::Transaction(txid) => write!(f, "tx:{txid}"),
+            LabelKey::Address(addr) => write!(f, "addr:{}", addr.clone().assume_checked()),
+        }
+    }
+}
+
+/// The type of a BIP-329 label record.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Bip329Type {
+    Tx,
+    Addr,
+    Output,
+}
+
+/// A label record as defined by BIP-329.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Bip329Label {
+    #[serde(rename = "type")]
+    pub kind: Bip329Type,
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub label: String,
+}
+
+impl Bip329Label {
+    /// Returns the label key the record refers to, or `None` if the
+    /// reference cannot be parsed.
+    pub fn key(&self) -> Option<LabelKey> {
+        match self.kind {
+            Bip329Type::Tx => bitcoin::Txid::from_str(&self.reference)
+                .ok()
+                .map(LabelKey::Transaction),
+            Bip329Type::Addr => bitcoin::Address::from_str(&self.reference)
+                .ok()
+                .map(LabelKey::Address),
+            Bip329Type::Output => OutPoint::from_str(&self.reference)
+                .ok()
+                .map(LabelKey::OutPoint),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 /// A store for managing labels associated with Bitcoin addresses, transactions, and outpoints.
 pub struct LabelStore {
@@ -109,6 +158,61 @@ impl LabelStore {
         self.store.remove(&key);
     }
 
+    /// Returns all the labels.
+    ///
+    /// # Returns
+    /// A vector of `(key, label)` pairs, the key is prefixed by its kind:
+    /// `outpoint:`, `tx:` or `addr:`.
+    pub fn labels(&self) -> Vec<(String, String)> {
+        self.store
+            .iter()
+            .map(|(key, label)| (key.to_string(), label.clone()))
+            .collect()
+    }
+
+    /// Exports the labels as BIP-329 records.
+    ///
+    /// # Returns
+    /// A vector of `Bip329Label` records.
+    pub fn to_bip329(&self) -> Vec<Bip329Label> {
+        self.store
+            .iter()
+            .map(|(key, label)| {
+                let (kind, reference) = match key {
+                    LabelKey::OutPoint(op) => (Bip329Type::Output, op.to_string()),
+                    LabelKey::Transaction(txid) => (Bip329Type::Tx, txid.to_string()),
+                    LabelKey::Address(addr) => {
+                        (Bip329Type::Addr, addr.clone().assume_checked().to_string())
+                    }
+                };
+                Bip329Label {
+                    kind,
+                    reference,
+                    label: label.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Imports BIP-329 records, records with a reference that cannot be
+    /// parsed are skipped.
+    ///
+    /// # Parameters
+    /// - `labels`: The records to import.
+    ///
+    /// # Returns
+    /// The number of imported labels.
+    pub fn import_bip329(&mut self, labels: Vec<Bip329Label>) -> usize {
+        let mut imported = 0;
+        for record in labels {
+            if let Some(key) = record.key() {
+                self.edit(key, Some(record.label));
+                imported += 1;
+            }
+        }
+        imported
+    }
+
     /// Retrieves the label associated with the given Bitcoin address.
     ///
     /// # Parameters
@@ -142,3 +246,37 @@ impl LabelStore {
         self.get(&LabelKey::Transaction(txid))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::txid;
+
+    #[test]
+    fn all_labels() {
+        let mut store = LabelStore::new();
+        assert!(store.labels().is_empty());
+
+        let address =
+            bitcoin::Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080").unwrap();
+        store.edit(LabelKey::Address(address), Some("addr".to_string()));
+        store.edit(LabelKey::Transaction(txid(1)), Some("tx".to_string()));
+        store.edit(
+            LabelKey::OutPoint(OutPoint::new(txid(2), 3)),
+            Some("output".to_string()),
+        );
+
+        let labels = store.labels();
+        assert_eq!(labels.len(), 3);
+        assert!(labels.contains(&(format!("outpoint:{}:3", txid(2)), "output".to_string())));
+        assert!(labels.contains(&(format!("tx:{}", txid(1)), "tx".to_string())));
+        assert!(labels.contains(&(
+            "addr:bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080".to_string(),
+            "addr".to_string()
+        )));
+
+        // removed labels are not enumerated
+        store.remove(LabelKey::Transaction(txid(1)));
+        assert_eq!(store.labels().len(), 2);
+    }
+}
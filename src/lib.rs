@@ -1,6 +1,9 @@
 pub mod account;
+pub mod account_manager;
 pub mod address_store;
+pub mod backup;
 pub mod coin;
+pub mod coin_selection;
 pub mod coin_store;
 pub mod config;
 pub mod derivator;
@@ -16,19 +19,31 @@ pub mod tx_store;
 
 use std::{collections::HashMap, fmt::Display};
 
-use account::{new_account, Account, Poll, Signal};
+use account::{new_account, new_offline_account, Account, Poll, Signal};
+use account_manager::{new_account_manager, AccountManager};
 use address_store::AddressEntry;
+use backup::import_backup;
 pub use config::{
-    config_exists, config_from_file, is_descriptor_valid, list_configs, new_config, Config,
+    config_exists, config_from_file, descriptor_contains_fingerprint, is_descriptor_valid,
+    list_configs, multipath_descriptor, new_config, Config,
 };
 use joinstr::miniscript::bitcoin;
 pub use mnemonic::{generate_mnemonic, mnemonic_from_string, Mnemonic};
+use signing_manager::{new_signing_manager, SigningManager};
 
 #[cxx::bridge]
 pub mod cpp_joinstr {
 
     pub struct TransactionTemplate {
         inputs: Vec<RustCoin>,
+        // if `inputs` is empty, all the spendable coins received at one of
+        // these addresses are spent
+        inputs_by_address: Vec<String>,
+        // if `inputs` is empty, all the spendable coins of at least this
+        // value (in sats) are spent, 0 to disable
+        min_input_value: u64,
+        // if true, unconfirmed coins are never spent
+        confirmed_only: bool,
         outputs: Vec<Output>,
         fee_sats: u64,
         fee_sats_vb: f64,
@@ -37,6 +52,15 @@ pub mod cpp_joinstr {
     pub struct TransactionSimulation {
         spendable: bool,
         has_change: bool,
+        // value in sats of the change output, 0 if no change
+        change_amount: u64,
+        // index of the change output, -1 if no change
+        change_index: i64,
+        // the change is larger than every payment, it likely reveals which
+        // output is the change
+        change_larger_than_payments: bool,
+        // at least one of the spent coins is unconfirmed
+        has_unconfirmed_inputs: bool,
         estimated_weight: u64,
         error: String,
     }
@@ -48,6 +72,63 @@ pub mod cpp_joinstr {
         max: bool, // if max == true, amount is not taken in account,
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PsbtOutputKind {
+        External,
+        Receive,
+        Change,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct PsbtOutput {
+        address: String,
+        amount: u64, // amount in sats
+        kind: PsbtOutputKind,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct PsbtVerification {
+        inputs_ours: bool,
+        outputs: Vec<PsbtOutput>,
+        fee: u64, // fee in sats
+        unexpected_spks: Vec<String>,
+        error: String,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct TxInputDetails {
+        outpoint: String,
+        address: String, // empty if the spent output is unknown
+        amount: u64,     // amount in sats, 0 if the spent output is unknown
+        ours: bool,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct TransactionDetails {
+        txid: String,
+        inputs: Vec<TxInputDetails>,
+        outputs: Vec<PsbtOutput>,
+        fee: u64, // fee in sats, 0 if some spent outputs are unknown
+        error: String,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct RustTransaction {
+        txid: String,
+        height: u64,   // 0 if unconfirmed
+        received: u64, // sats paid to the wallet
+        sent: u64,     // sats spent from the wallet coins
+        fee: u64,      // fee in sats, 0 if some spent outputs are unknown
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum CoinSort {
+        Value,
+        Age,
+        Outpoint,
+        Label,
+    }
+
     #[derive(Debug, Clone, Copy)]
     pub enum LogLevel {
         Off,
@@ -68,17 +149,43 @@ pub mod cpp_joinstr {
         PoolUpdate,
         PoolListenerStopped,
         PoolListenerError,
+        PoolStepChanged,
+        PoolMined,
+        PoolFailed,
         AddressTipChanged,
         CoinUpdate,
         AccountError,
         Stopped,
         Error,
+        AddressGapWarning,
+        PeerJoined,
+        AddressMismatch,
     }
 
     extern "Rust" {
         fn signal_flag_to_string(signal: SignalFlag) -> String;
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SignerEvent {
+        None,
+        Info,
+        Xpub,
+        Descriptor,
+        DescriptorRegistered,
+        Signed,
+        Error,
+        ManagerError,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct SignerPoll {
+        fingerprint: String, // empty for manager errors
+        event: SignerEvent,
+        value: String, // the signed PSBT, the xpub or the descriptor
+        error: String,
+    }
+
     pub enum Network {
         Regtest,
         Signet,
@@ -86,25 +193,29 @@ pub mod cpp_joinstr {
         Bitcoin,
     }
 
+    // NOTE: this enum is persisted by its discriminant, variants must never
+    // be reordered and new variants must be given a new discriminant.
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord)]
     pub enum CoinStatus {
-        Unconfirmed,
-        Confirmed,
-        BeingSpend,
-        Spent,
+        Unconfirmed = 0,
+        Confirmed = 1,
+        BeingSpend = 2,
+        Spent = 3,
     }
 
+    // NOTE: this enum is persisted by its discriminant, variants must never
+    // be reordered and new variants must be given a new discriminant.
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub enum PoolStatus {
-        Posting,
-        Connecting,
-        Available,
-        RegisterOutputs,
-        RegisterInputs,
-        Broadcast,
-        Mined,
-        Closed,
-        Error,
+        Posting = 0,
+        Connecting = 1,
+        Available = 2,
+        RegisterOutputs = 3,
+        RegisterInputs = 4,
+        Broadcast = 5,
+        Mined = 6,
+        Closed = 7,
+        Error = 8,
     }
 
     extern "Rust" {
@@ -112,29 +223,36 @@ pub mod cpp_joinstr {
     }
 
     /// Represents the role of a pool participant
+    ///
+    /// NOTE: this enum is persisted by its discriminant, variants must never
+    /// be reordered and new variants must be given a new discriminant.
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
     pub enum PoolRole {
-        Coordinator,
-        Initiator,
-        Peer,
-        None,
+        Coordinator = 0,
+        Initiator = 1,
+        Peer = 2,
+        None = 3,
     }
     extern "Rust" {
         fn pool_role_to_string(status: PoolRole) -> String;
     }
 
+    // NOTE: this enum is persisted by its discriminant, variants must never
+    // be reordered and new variants must be given a new discriminant.
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord)]
     pub enum AddrAccount {
-        Receive,
-        Change,
+        Receive = 0,
+        Change = 1,
     }
 
+    // NOTE: this enum is persisted by its discriminant, variants must never
+    // be reordered and new variants must be given a new discriminant.
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord)]
     pub enum AddressStatus {
-        NotUsed,
-        Used,
-        Reused,
-        Unknown,
+        NotUsed = 0,
+        Used = 1,
+        Reused = 2,
+        Unknown = 3,
     }
 
     extern "Rust" {
@@ -142,23 +260,74 @@ pub mod cpp_joinstr {
         type RustConfig;
         fn electrum_url(&self) -> String;
         fn electrum_port(&self) -> String;
+        fn electrum_timeout(&self) -> String;
         fn nostr_url(&self) -> String;
         fn nostr_back(&self) -> String;
+        fn nostr_back_hours(&self) -> String;
         fn look_ahead(&self) -> String;
+        fn recv_look_ahead(&self) -> String;
+        fn change_look_ahead(&self) -> String;
+        fn min_relay_fee(&self) -> String;
+        fn enable_coinjoin(&self) -> bool;
+        fn secondary_descriptor(&self) -> String;
         fn network(&self) -> Network;
         fn set_electrum_url(&mut self, url: String);
         fn set_electrum_port(&mut self, port: String);
+        fn set_electrum_timeout(&mut self, timeout: String);
         fn set_nostr_relay(&mut self, relay: String);
         fn set_nostr_back(&mut self, back: String);
+        fn set_nostr_back_hours(&mut self, hours: String);
+        fn nostr_pool_authors(&self) -> Vec<String>;
+        fn set_nostr_pool_authors(&mut self, authors: Vec<String>);
         fn set_look_ahead(&mut self, look_ahead: String);
+        fn set_recv_look_ahead(&mut self, look_ahead: String);
+        fn set_change_look_ahead(&mut self, look_ahead: String);
+        fn set_min_relay_fee(&mut self, fee: String);
+        fn set_enable_coinjoin(&mut self, enable: bool);
+        fn set_scan_from(&mut self, receive: u32, change: u32);
+        fn set_secondary_descriptor(&mut self, descriptor: String);
         fn set_network(&mut self, network: Network);
         fn set_mnemonic(&mut self, mnemonic: String);
-        fn to_file(&self);
+        fn coinjoin_mnemonic(&self) -> String;
+        fn set_coinjoin_mnemonic(&mut self, mnemonic: String);
+        fn coinjoin_keychain(&self) -> AddrAccount;
+        fn set_coinjoin_keychain(&mut self, keychain: AddrAccount);
+        fn reuse_unused_addresses(&self) -> bool;
+        fn created_at(&self) -> u64;
+        fn set_reuse_unused_addresses(&mut self, reuse: bool);
+        fn persist_mnemonic(&self) -> bool;
+        fn set_persist_mnemonic(&mut self, persist: bool);
+        fn consolidation_max_utxos(&self) -> u32;
+        fn set_consolidation_max_utxos(&mut self, max: u32);
+        fn consolidation_min_savings(&self) -> u64;
+        fn set_consolidation_min_savings(&mut self, savings: u64);
+        fn psbt_global_xpubs(&self) -> bool;
+        fn set_psbt_global_xpubs(&mut self, enable: bool);
+        fn auto_new_recv_addr(&self) -> bool;
+        fn set_auto_new_recv_addr(&mut self, enable: bool);
+        fn max_coinjoin_excess(&self) -> u64;
+        fn set_max_coinjoin_excess(&mut self, sats: u64);
+        fn min_change_sats(&self) -> u64;
+        fn set_min_change_sats(&mut self, sats: u64);
+        fn dust_attack_threshold(&self) -> u64;
+        fn set_dust_attack_threshold(&mut self, sats: u64);
+        fn electrum_batch_size(&self) -> u32;
+        fn set_electrum_batch_size(&mut self, size: u32);
+        fn reconnect_initial_delay_ms(&self) -> u64;
+        fn set_reconnect_initial_delay_ms(&mut self, delay: u64);
+        fn reconnect_multiplier(&self) -> String;
+        fn set_reconnect_multiplier(&mut self, multiplier: String);
+        fn reconnect_max_delay_ms(&self) -> u64;
+        fn set_reconnect_max_delay_ms(&mut self, delay: u64);
+        fn to_file(&self) -> Result<()>;
         fn config_from_file(account: String) -> Box<Config>;
         fn config_exists(account: String) -> bool;
         fn set_account(&mut self, name: String);
         fn is_descriptor_valid(descriptor: String) -> bool;
+        fn descriptor_contains_fingerprint(descriptor: String, fingerprint: String) -> bool;
+        fn multipath_descriptor(receive: String, change: String) -> String;
         fn new_config(mnemonic: String, account: String, network: Network) -> Box<Config>;
+        fn import_backup(backup: String, account: String, mnemonic: String) -> Result<Box<Config>>;
     }
 
     extern "Rust" {
@@ -175,6 +344,8 @@ pub mod cpp_joinstr {
         fn is_err(&self) -> bool;
         fn error(&self) -> String;
         fn unwrap(&self) -> SignalFlag;
+        fn payload(&self) -> String;
+        fn account(&self) -> String;
     }
 
     extern "Rust" {
@@ -195,6 +366,33 @@ pub mod cpp_joinstr {
         unconfirmed_balance: u64,
     }
 
+    /// Stored addresses and coins not derived anymore by the descriptors.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct AddressValidation {
+        addresses: Vec<String>,
+        coins: Vec<String>, // outpoints
+    }
+
+    /// Wallet state for the dashboard, gathered in a single call.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct WalletSummary {
+        confirmed_balance: u64,
+        unconfirmed_balance: u64,
+        coins: usize,           // spendable coins
+        available_pools: usize, // pools in `Available` status
+        active_coinjoins: usize,
+        electrum_running: bool,
+        nostr_running: bool,
+        synced: bool, // every subscribed status & its history is processed
+    }
+
+    /// Number of spendable coins in a value bucket.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct UtxoBucket {
+        lower_bound: u64, // in sats, the bucket spans up to the next bound
+        coins: usize,
+    }
+
     #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
     pub struct RustCoin {
         value: u64,
@@ -204,6 +402,16 @@ pub mod cpp_joinstr {
         outpoint: String,
         address: RustAddress,
         label: String,
+        spent_by: String,
+        spent_externally: bool, // spent by a tx this wallet did not prepare
+        frozen: bool,           // excluded from coin selection and pools
+        suspected_dust: bool,   // tiny coin received on a reused address
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct RustLabel {
+        key: String, // prefixed by its kind: `outpoint:`, `tx:` or `addr:`
+        label: String,
     }
 
     #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -212,6 +420,8 @@ pub mod cpp_joinstr {
         status: AddressStatus,
         account: AddrAccount,
         index: u32,
+        secondary: bool,
+        derivation_path: String, // e.g. m/84'/0'/0'/0/5
     }
 
     extern "Rust" {
@@ -252,16 +462,104 @@ pub mod cpp_joinstr {
         fn error(&self) -> String;
     }
 
+    extern "Rust" {
+        type SatsAmount;
+        fn is_ok(&self) -> bool;
+        fn is_err(&self) -> bool;
+        fn value(&self) -> u64;
+        fn error(&self) -> String;
+    }
+
+    extern "Rust" {
+        type ChangeAddresses;
+        fn is_ok(&self) -> bool;
+        fn is_err(&self) -> bool;
+        fn value(&self) -> Vec<RustAddress>;
+        fn error(&self) -> String;
+    }
+
+    extern "Rust" {
+        type SignedMessage;
+        fn is_ok(&self) -> bool;
+        fn is_err(&self) -> bool;
+        fn value(&self) -> String;
+        fn error(&self) -> String;
+    }
+
+    extern "Rust" {
+        type ImportedTxs;
+        fn is_ok(&self) -> bool;
+        fn is_err(&self) -> bool;
+        fn value(&self) -> u32;
+        fn error(&self) -> String;
+    }
+
+    extern "Rust" {
+        type CoinjoinExcess;
+        fn is_ok(&self) -> bool;
+        fn is_err(&self) -> bool;
+        fn value(&self) -> u64;
+        fn error(&self) -> String;
+    }
+
+    extern "Rust" {
+        type ConfirmationTarget;
+        fn is_ok(&self) -> bool;
+        fn is_err(&self) -> bool;
+        fn value(&self) -> u16;
+        fn error(&self) -> String;
+    }
+
     extern "Rust" {
         type Account;
         fn spendable_coins(&self) -> CoinState;
+        fn spendable_coins_sorted(&self, sort: CoinSort) -> CoinState;
+        fn summary(&self) -> WalletSummary;
+        fn freeze_coin(&mut self, outpoint: String, frozen: bool) -> bool;
+        fn frozen_coins(&self) -> Vec<String>;
+        fn register_prepared_tx(&mut self, txid: String) -> bool;
+        fn revalidate_addresses(&self) -> AddressValidation;
+        fn uneconomical_coins(&self, fee_rate: f64) -> Vec<RustCoin>;
+        fn should_consolidate(&self, fee_rate: f64) -> bool;
+        fn utxo_histogram(&self, buckets: Vec<u64>) -> Vec<UtxoBucket>;
+        fn balance_at_height(&self, height: u64) -> u64;
+        fn last_activity(&self) -> u64;
         fn generate_coins(&mut self);
         fn edit_coin_label(&self, outpoint: String, label: String);
+        fn all_labels(&self) -> Vec<RustLabel>;
         fn recv_addr_at(&self, index: u32) -> String;
         fn change_addr_at(&self, index: u32) -> String;
+        fn recv_addrs(&self, start: u32, count: u32) -> Vec<String>;
+        fn change_addrs(&self, start: u32, count: u32) -> Vec<String>;
+        fn receive_uri(&self, index: u32, amount_sats: u64, label: String) -> String;
+        fn receive_descriptor(&self) -> String;
+        fn change_descriptor(&self) -> String;
+        fn cosigner_info(&self) -> String;
         fn simulate_transaction(&self, tx_template: TransactionTemplate) -> TransactionSimulation;
         fn prepare_transaction(&mut self, tx_template: TransactionTemplate) -> Box<PsbtResult>;
+        #[cxx_name = "max_spendable"]
+        fn max_spendable_result(&self, address: String, fee_rate: f64) -> Box<SatsAmount>;
+        #[cxx_name = "fee_headroom"]
+        fn fee_headroom_result(&self, tx_template: TransactionTemplate) -> Box<SatsAmount>;
+        fn sweep_coin(
+            &mut self,
+            outpoint: String,
+            address: String,
+            fee_rate: f64,
+        ) -> Box<PsbtResult>;
+        fn verify_psbt(&self, psbt: String) -> Box<PsbtVerification>;
+        fn verify_change_address(&self, psbt: String) -> Box<ChangeAddresses>;
+        fn set_fee_estimate(&mut self, target: u16, feerate: f64);
+        fn estimate_confirmation_target(&self, feerate: f64) -> Box<ConfirmationTarget>;
+        fn rebroadcast_unconfirmed(&self) -> u32;
+        fn test_accept(&self, tx_hex: String) -> Result<()>;
+        fn matches_prepared(&self, original_psbt: String, signed_tx_hex: String) -> bool;
+        fn raw_transaction(&self, txid: String) -> String;
+        fn transaction_details(&self, txid: String) -> Box<TransactionDetails>;
+        fn pending_transactions(&self) -> Vec<RustTransaction>;
+        fn stuck_transactions(&self, min_age_blocks: u64) -> Vec<RustTransaction>;
         fn pools(&self) -> Box<PoolsResult>;
+        fn joinable_pools(&self) -> Box<PoolsResult>;
         fn create_pool(
             &mut self,
             outpoint: String,
@@ -271,20 +569,65 @@ pub mod cpp_joinstr {
             peers: usize,
         );
         fn join_pool(&mut self, outpoint: String, pool_id: String);
+        #[cxx_name = "estimate_coinjoin_excess"]
+        fn coinjoin_excess_result(&self, pool_id: String, outpoint: String) -> Box<CoinjoinExcess>;
         fn pool(&mut self, pool_id: String) -> Box<RustPool>;
+        fn coins_from_pool(&self, pool_id: String) -> Vec<RustCoin>;
         fn try_recv(&mut self) -> Box<Poll>;
         fn relay(&self) -> String;
         fn new_addr(&mut self) -> RustAddress;
+        fn recv_watch_tip(&self) -> u32;
+        fn change_watch_tip(&self) -> u32;
+        fn recv_tip(&self) -> u32;
+        fn change_tip(&self) -> u32;
         fn set_electrum(&mut self, url: String, port: String);
         fn start_electrum(&mut self);
         fn stop_electrum(&mut self);
+        fn refresh(&mut self);
+        fn resync(&mut self);
         fn set_nostr(&mut self, url: String, back: String);
         fn start_nostr(&mut self);
         fn stop_nostr(&mut self);
         fn set_look_ahead(&mut self, look_ahead: String);
+        fn set_min_relay_fee(&mut self, fee: String);
+        fn set_psbt_global_xpubs(&mut self, enable: bool);
+        fn set_min_change_sats(&mut self, sats: u64);
         fn get_config(&self) -> Box<Config>;
         fn new_account(account: String) -> Box<Account>;
+        fn new_offline_account(account: String) -> Box<Account>;
         fn stop(&mut self);
+        fn export_backup(&self, include_mnemonic: bool) -> Result<String>;
+        fn unlock(&mut self, mnemonic: String) -> Result<()>;
+        fn export_transactions(&self) -> String;
+        fn export_csv(&self) -> String;
+        fn import_transactions(&mut self, txs_json: String) -> Box<ImportedTxs>;
+        fn is_stopped(&self) -> bool;
+        fn sign_message(&self, address: String, message: String) -> Box<SignedMessage>;
+    }
+
+    extern "Rust" {
+        type SigningManager;
+        fn new_signing_manager() -> Box<SigningManager>;
+        fn new_hot_signer(&mut self, network: Network);
+        fn new_hot_signer_from_mnemonic(&mut self, network: Network, mnemonic: String);
+        fn register_descriptor(&mut self, descriptor: String);
+        fn sign(&self, network: Network, psbt: String);
+        #[cxx_name = "sign_batch"]
+        fn sign_batch_poll(&self, network: Network, psbts: Vec<String>) -> Vec<SignerPoll>;
+        #[cxx_name = "poll"]
+        fn poll_event(&self) -> SignerPoll;
+    }
+
+    extern "Rust" {
+        type AccountManager;
+        fn new_account_manager() -> Box<AccountManager>;
+        fn open_account(&mut self, name: String) -> bool;
+        fn remove_account(&mut self, name: String) -> bool;
+        #[cxx_name = "account"]
+        fn managed_account(&mut self, name: String) -> Result<&mut Account>;
+        fn accounts(&self) -> Vec<String>;
+        fn try_recv(&mut self) -> Box<Poll>;
+        fn total_balance(&self) -> u64;
     }
 
     extern "Rust" {
@@ -304,9 +647,15 @@ pub mod cpp_joinstr {
     extern "Rust" {
         pub fn estimate_weight(peer: usize) -> usize;
     }
+
+    extern "Rust" {
+        fn pool_fee_from_rate(fee_rate: f64) -> u32;
+    }
 }
 
-use cpp_joinstr::{AddrAccount, LogLevel, Network, PoolRole, PoolStatus, RustPool, SignalFlag};
+use cpp_joinstr::{
+    AddrAccount, LogLevel, Network, PoolRole, PoolStatus, RustAddress, RustPool, SignalFlag,
+};
 
 impl Network {
     pub fn boxed(&self) -> Box<Network> {
@@ -342,6 +691,18 @@ result!(AddressesResult, Vec<AddressEntry>);
 
 result!(Txid, String);
 
+result!(SignedMessage, String);
+
+result!(SatsAmount, u64);
+
+result!(ImportedTxs, u32);
+
+result!(CoinjoinExcess, u64);
+
+result!(ConfirmationTarget, u16);
+
+result!(ChangeAddresses, Vec<RustAddress>);
+
 impl Display for SignalFlag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
@@ -351,10 +712,16 @@ impl Display for SignalFlag {
             SignalFlag::PoolListenerStarted => write!(f, "PoolListenerStarted"),
             SignalFlag::PoolListenerStopped => write!(f, "PoolListenerStopped"),
             SignalFlag::PoolListenerError => write!(f, "PoolListenerError"),
+            SignalFlag::PoolStepChanged => write!(f, "PoolStepChanged"),
+            SignalFlag::PoolMined => write!(f, "PoolMined"),
+            SignalFlag::PoolFailed => write!(f, "PoolFailed"),
             SignalFlag::AddressTipChanged => write!(f, "AddressTipChanged"),
             SignalFlag::CoinUpdate => write!(f, "CoinUpdate"),
             SignalFlag::AccountError => write!(f, "AccountError"),
             SignalFlag::PoolUpdate => write!(f, "PoolUpdate"),
+            SignalFlag::AddressGapWarning => write!(f, "AddressGapWarning"),
+            SignalFlag::PeerJoined => write!(f, "PeerJoined"),
+            SignalFlag::AddressMismatch => write!(f, "AddressMismatch"),
             _ => write!(f, "unexpected SignalFlag"),
         }
     }
@@ -524,6 +891,12 @@ pub fn estimate_weight(peer: usize) -> usize {
     (400 * peer) + 50
 }
 
+/// Converts a fee rate in sats/vB into a pool fee rate, returns 0 if the
+/// fee rate is out of the accepted range.
+pub fn pool_fee_from_rate(fee_rate: f64) -> u32 {
+    pool_store::pool_fee_rate(fee_rate).unwrap_or(0)
+}
+
 pub fn pool_role_to_string(status: PoolRole) -> String {
     status.to_string()
 }
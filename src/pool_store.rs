@@ -1,7 +1,8 @@
 use crate::{
-    account::{Error, JoinstrNotif, Notification},
+    account::{Error, JoinstrNotif, Notification, NotificationSender},
     coin::Coin,
     cpp_joinstr::{PoolRole, PoolStatus, RustPool},
+    Config,
 };
 use joinstr::{
     electrum::short_string,
@@ -14,36 +15,138 @@ use joinstr::{
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
-    sync::{mpsc, Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, RwLock,
+    },
     thread::{self, JoinHandle},
     time::Duration,
 };
 
+/// A snapshot of the available pools, shared with the readers so they
+/// never wait for the store lock.
+pub type PoolsSnapshot = Arc<RwLock<Vec<RustPool>>>;
+
 /// A structure to manage a collection of pools.
 #[derive(Debug, Default)]
 pub struct PoolStore {
     store: BTreeMap<String, PoolEntry>,
+    workers: Vec<(Arc<AtomicBool>, JoinHandle<()>)>,
+    snapshot: PoolsSnapshot,
+    /// Our output addresses by pool ID, persisted as the pools are not.
+    outputs: BTreeMap<String, Address<NetworkUnchecked>>,
+    config: Option<Config>,
 }
 
 impl PoolStore {
     /// Creates a new instance of `PoolStore`.
-    pub fn new() -> Self {
+    ///
+    /// # Arguments
+    /// * `config` - The config of the account, the output addresses of
+    ///   the pools are persisted if provided.
+    pub fn new(config: Option<Config>) -> Self {
         Self {
             store: BTreeMap::default(),
+            workers: Vec::new(),
+            snapshot: PoolsSnapshot::default(),
+            outputs: config
+                .as_ref()
+                .map(|c| c.pool_outputs_from_file())
+                .unwrap_or_default(),
+            config,
         }
     }
 
+    /// Returns a handle to the snapshot of the available pools, it's
+    /// refreshed on every update of the store.
+    pub fn snapshot(&self) -> PoolsSnapshot {
+        self.snapshot.clone()
+    }
+
+    /// Refreshes the snapshot of the available pools.
+    fn refresh_snapshot(&self) {
+        *self.snapshot.write().expect("poisoned") = self.available_pools();
+    }
+
+    /// Inserts a pool entry, replacing the previous entry of the pool.
+    fn insert(&mut self, pool_id: String, entry: PoolEntry) {
+        if let Some(output) = &entry.output {
+            if self.outputs.get(&pool_id) != Some(output) {
+                self.outputs.insert(pool_id.clone(), output.clone());
+                if let Some(config) = &self.config {
+                    config.persist_pool_outputs(&self.outputs);
+                }
+            }
+        }
+        self.store.insert(pool_id, entry);
+        self.refresh_snapshot();
+    }
+
+    /// Returns our output address in a pool, if we took part in it.
+    ///
+    /// # Arguments
+    /// * `pool_id` - The ID of the pool.
+    pub fn output(&self, pool_id: &str) -> Option<Address<NetworkUnchecked>> {
+        self.outputs.get(pool_id).cloned()
+    }
+
+    /// Updates the step of a pool we are taking part in.
+    ///
+    /// # Returns
+    /// The number of peers before and after the update.
+    fn update_step(
+        &mut self,
+        pool_id: &str,
+        step: Step,
+        inputs: usize,
+        outputs: usize,
+    ) -> (usize, usize) {
+        let entry = self.store.get_mut(pool_id).expect("present");
+        let previous_peers = entry.current_peers;
+        entry.update_status(step, inputs, outputs);
+        let peers = entry.current_peers;
+        self.refresh_snapshot();
+        (previous_peers, peers)
+    }
+
+    /// Registers a pool worker thread and its stop flag, finished workers
+    /// are pruned.
+    fn register_worker(&mut self, stop: Arc<AtomicBool>, handle: JoinHandle<()>) {
+        self.workers.retain(|(_, h)| !h.is_finished());
+        self.workers.push((stop, handle));
+    }
+
+    /// Signals all the pool workers to stop, they are not joined here as
+    /// a worker can take a while to notice the stop request.
+    ///
+    /// # Arguments
+    /// * `store` - The pool store.
+    ///
+    /// # Returns
+    /// The handles of the workers, to be joined (or detached) by the caller.
+    pub fn shutdown(store: &Arc<Mutex<PoolStore>>) -> Vec<JoinHandle<()>> {
+        let workers = std::mem::take(&mut store.lock().expect("poisoned").workers);
+        workers
+            .into_iter()
+            .map(|(stop, handle)| {
+                stop.store(true, Ordering::Relaxed);
+                handle
+            })
+            .collect()
+    }
+
     /// Updates the status of a pool in the store.
     ///
     /// Returns `true` if the status was changed, `false` otherwise.
     pub fn update(&mut self, pool: nostr::Pool, status: PoolStatus) -> bool /* updated */ {
+        let inserted = !self.store.contains_key(&pool.id);
         let mut updated = false;
         self.store
             .entry(pool.id.clone())
             .and_modify(|e| {
-                // NOTE: if `role` is assigned or there is an handle we do not update here
+                // NOTE: if `role` is assigned or there is a worker we do not update here
                 // as we can overwrite more relevant updates done by the peer/initiator thread
-                if !(e.role != PoolRole::None || e.handle.is_some()) && e.status != status {
+                if !(e.role != PoolRole::None || e.stop.is_some()) && e.status != status {
                     e.status = status;
                     updated = true;
                 }
@@ -53,9 +156,13 @@ impl PoolStore {
                 status,
                 role: PoolRole::None,
                 step: None,
-                handle: None,
+                stop: None,
                 current_peers: 0,
+                output: None,
             });
+        if inserted || updated {
+            self.refresh_snapshot();
+        }
         updated
     }
 
@@ -112,12 +219,14 @@ impl PoolStore {
         electrum: (String, u16),
         network: Network,
         store: Arc<Mutex<PoolStore>>,
-        sender: mpsc::Sender<Notification>,
+        sender: NotificationSender,
     ) {
         log::debug!("PoolStore::create_pool()");
         let cloned_store = store.clone();
         let (id_sender, id_recv) = mpsc::channel::<Option<String>>();
         let cloned_sender = sender.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let cloned_stop = stop.clone();
         let signer = match joinstr::signer::WpkhHotSigner::new_from_mnemonics(network, &mnemonic) {
             Ok(s) => s,
             Err(e) => {
@@ -127,6 +236,7 @@ impl PoolStore {
                 return;
             }
         };
+        let output = address.clone();
         let handle = thread::spawn(move || {
             let mut j = match initiator(
                 denomination,
@@ -150,6 +260,11 @@ impl PoolStore {
             log::info!("PoolStore::create_pool() start coinjoin...");
             j.start_coinjoin(None, Some(signer));
             let pool = loop {
+                if stop.load(Ordering::Relaxed) {
+                    log::info!("PoolStore::create_pool() stopped");
+                    let _ = id_sender.send(None);
+                    return;
+                }
                 match j.state() {
                     Some(s) => break s.pool.clone(),
                     None => {
@@ -168,14 +283,14 @@ impl PoolStore {
                 pool,
                 role: PoolRole::Initiator,
                 step: None,
-                handle: None,
+                stop: Some(stop.clone()),
                 current_peers: 0,
+                output: Some(output),
             };
 
             store
                 .lock()
                 .expect("poisoned")
-                .store
                 .insert(pool_id.clone(), pool_entry);
             let _ = id_sender.send(Some(pool_id.clone()));
 
@@ -185,6 +300,10 @@ impl PoolStore {
 
             loop {
                 thread::sleep(Duration::from_millis(300));
+                if stop.load(Ordering::Relaxed) {
+                    log::info!("PoolStore::create_pool() worker for pool {short_id} stopped");
+                    break;
+                }
                 let state = j.state().expect("must have a state");
                 let step = state.step;
                 let inputs = state.inputs.len();
@@ -197,33 +316,36 @@ impl PoolStore {
                 let update = update || last_inputs != inputs || last_outputs != outputs;
                 if update {
                     log::info!("PoolStore::create_pool() step for pool {short_id} changed: {last_step:?} => {step:?} ");
+                    let previous_step = last_step;
                     last_step = Some(step);
                     last_outputs = outputs;
                     last_inputs = inputs;
-                    store
+                    let (previous_peers, peers) = store
                         .lock()
                         .expect("poisoned")
-                        .store
-                        .get_mut(&pool_id)
-                        .expect("present")
-                        .update_status(step, inputs, outputs);
-                    let _ = sender.send(JoinstrNotif::PoolUpdate.into());
+                        .update_step(&pool_id, step, inputs, outputs);
+                    let mut notifs = step_notifications(&pool_id, step);
+                    notifs.extend(peer_joined_notification(
+                        &pool_id,
+                        previous_step,
+                        step,
+                        previous_peers,
+                        peers,
+                    ));
+                    for notif in notifs {
+                        let _ = sender.send(notif.into());
+                    }
                 }
-                if matches!(step, Step::Mined) {
+                if matches!(step, Step::Mined | Step::Failed) {
                     break;
                 }
             }
         });
-        let handle = Arc::new(Mutex::new(handle));
-        if let Ok(Some(pool_id)) = id_recv.recv() {
-            cloned_store
-                .lock()
-                .expect("poisoned")
-                .store
-                .get_mut(&pool_id)
-                .expect("pool must exists")
-                .handle = Some(handle);
-        } else {
+        cloned_store
+            .lock()
+            .expect("poisoned")
+            .register_worker(cloned_stop, handle);
+        if id_recv.recv().ok().flatten().is_none() {
             let _ = cloned_sender.send(Notification::Joinstr(JoinstrNotif::Error(
                 Error::CreatePool,
             )));
@@ -239,7 +361,7 @@ impl PoolStore {
         mnemonic: String,
         network: Network,
         store: Arc<Mutex<PoolStore>>,
-        sender: mpsc::Sender<Notification>,
+        sender: NotificationSender,
         coin: Coin,
         address: Address<NetworkUnchecked>,
     ) {
@@ -253,8 +375,10 @@ impl PoolStore {
                 return;
             }
         };
-        let cloned_pool = pool.clone();
         let short_id = short_string(pool.clone().id);
+        let stop = Arc::new(AtomicBool::new(false));
+        let cloned_stop = stop.clone();
+        let output = address.clone();
         let handle = thread::spawn(move || {
             let pool_id = pool.id.clone();
             let mut j = match peer(pool.clone(), relay, coin, electrum, network, address) {
@@ -262,6 +386,7 @@ impl PoolStore {
                 Err(e) => {
                     log::error!("PoolStore::join_pool() fail to create peer: {e:?}");
                     let _ = sender.send(e.into());
+                    let _ = sender.send(JoinstrNotif::PoolFailed(pool_id).into());
                     return;
                 }
             };
@@ -272,14 +397,14 @@ impl PoolStore {
                 pool,
                 role: PoolRole::Peer,
                 step: None,
-                handle: None,
+                stop: Some(stop.clone()),
                 current_peers: 0,
+                output: Some(output),
             };
 
             store
                 .lock()
                 .expect("poisoned")
-                .store
                 .insert(pool_id.clone(), pool_entry);
 
             let mut last_step: Option<Step> = None;
@@ -288,6 +413,10 @@ impl PoolStore {
 
             loop {
                 thread::sleep(Duration::from_millis(300));
+                if stop.load(Ordering::Relaxed) {
+                    log::info!("PoolStore::join_pool() worker for pool {short_id} stopped");
+                    break;
+                }
                 let state = j.state().expect("must have a state");
                 let step = state.step;
                 let inputs = state.inputs.len();
@@ -306,27 +435,204 @@ impl PoolStore {
                     store
                         .lock()
                         .expect("poisoned")
-                        .store
-                        .get_mut(&pool_id)
-                        .expect("present")
-                        .update_status(step, inputs, outputs);
-                    let _ = sender.send(JoinstrNotif::PoolUpdate.into());
+                        .update_step(&pool_id, step, inputs, outputs);
+                    for notif in step_notifications(&pool_id, step) {
+                        let _ = sender.send(notif.into());
+                    }
                 }
-                if matches!(step, Step::Mined) {
+                if matches!(step, Step::Mined | Step::Failed) {
                     break;
                 }
             }
         });
-        let pool_id = cloned_pool.id.clone();
-        let handle = Arc::new(Mutex::new(handle));
         cloned_store
             .lock()
             .expect("poisoned")
-            .store
-            .get_mut(&pool_id)
-            .expect("pool must exists")
-            .handle = Some(handle);
+            .register_worker(cloned_stop, handle);
+    }
+}
+
+/// Minimum fee rate of a pool in sats/vB.
+pub const MIN_POOL_FEE_RATE: u32 = 1;
+
+/// Maximum fee rate of a pool in sats/vB, a higher fee rate is considered
+/// absurd.
+pub const MAX_POOL_FEE_RATE: u32 = 1_000;
+
+/// Checks if a pool fee rate is within the accepted range.
+///
+/// NOTE: the fee passed to `Joinstr::fee()` is the fee rate of the coinjoin
+/// transaction in sats/vB.
+///
+/// # Parameters
+/// - `fee_rate`: The pool fee rate in sats/vB.
+pub fn is_pool_fee_valid(fee_rate: u32) -> bool {
+    (MIN_POOL_FEE_RATE..=MAX_POOL_FEE_RATE).contains(&fee_rate)
+}
+
+/// Converts a fee rate in sats/vB, as collected by the UI, into a pool fee
+/// rate, rounding up to the next whole sat/vB.
+///
+/// # Parameters
+/// - `fee_rate`: The fee rate in sats/vB.
+///
+/// # Returns
+/// The pool fee rate, or `None` if the fee rate is not a number or is out
+/// of the accepted range.
+pub fn pool_fee_rate(fee_rate: f64) -> Option<u32> {
+    if !fee_rate.is_finite() || fee_rate <= 0.0 || fee_rate > MAX_POOL_FEE_RATE as f64 {
+        return None;
+    }
+    Some((fee_rate.ceil() as u32).max(MIN_POOL_FEE_RATE))
+}
+
+/// Estimates the share of the coinjoin transaction fees paid by a single
+/// peer contributing one input and one output.
+///
+/// The transaction overhead (version, locktime, inputs/outputs counts and
+/// segwit marker) is split evenly between the peers.
+///
+/// # Parameters
+/// - `fee_rate`: The pool fee rate in sats/vB.
+/// - `peers`: The number of peers of the pool.
+/// - `satisfaction_weight`: The max satisfaction weight of the input in WU.
+/// - `output_spk_len`: The length of the output script pubkey.
+///
+/// # Returns
+/// The fee in sats paid by the peer.
+pub fn contribution_fee(
+    fee_rate: u32,
+    peers: usize,
+    satisfaction_weight: u64,
+    output_spk_len: usize,
+) -> u64 {
+    // version + locktime + inputs/outputs counts, segwit marker & flag
+    let overhead = (4 + 4 + 1 + 1) * 4 + 2;
+    // outpoint + sequence + empty script_sig
+    let input = (32 + 4 + 4 + 1) * 4 + satisfaction_weight;
+    // amount + script_pubkey
+    let output = (8 + 1 + output_spk_len as u64) * 4;
+    let weight = input + output + (overhead as u64).div_ceil(peers.max(1) as u64);
+    weight.div_ceil(4) * fee_rate as u64
+}
+
+/// Computes the part of a coin value exceeding the denomination of a pool
+/// plus the peer's share of the fees.
+///
+/// NOTE: a joinstr transaction has no change output, each peer registers a
+/// single output of the denomination, the excess of the input is paid to
+/// the miners on top of the fees.
+///
+/// # Parameters
+/// - `coin_value`: The value in sats of the contributed coin.
+/// - `denomination`: The pool denomination in sats.
+/// - `fee`: The peer's share of the fees in sats, see [`contribution_fee`].
+///
+/// # Returns
+/// The excess in sats, or `None` if the coin does not cover the
+/// denomination plus the fee.
+pub fn coinjoin_excess(coin_value: u64, denomination: u64, fee: u64) -> Option<u64> {
+    coin_value.checked_sub(denomination)?.checked_sub(fee)
+}
+
+/// Checks that the excess of a coin contributed to a pool, see
+/// [`coinjoin_excess`], is not above the maximum accepted.
+///
+/// # Parameters
+/// - `excess`: The excess in sats.
+/// - `max_excess`: The maximum excess in sats.
+///
+/// # Errors
+/// `Error::CoinjoinExcess` if the excess is above `max_excess`.
+pub fn check_coinjoin_excess(excess: u64, max_excess: u64) -> Result<(), Error> {
+    if excess > max_excess {
+        return Err(Error::CoinjoinExcess(excess));
+    }
+    Ok(())
+}
+
+/// Filters the pools that can be joined with one of the coins `coins`.
+///
+/// # Parameters
+/// - `pools`: The pools to filter.
+/// - `coins`: The values in sats of the spendable coins.
+/// - `satisfaction_weight`: The max satisfaction weight of an input in WU.
+/// - `output_spk_len`: The length of the coinjoin output script pubkey.
+///
+/// # Returns
+/// The `Available` pools for which one of the coins covers the denomination
+/// plus the contribution fee.
+pub fn joinable_pools(
+    pools: Vec<RustPool>,
+    coins: &[u64],
+    satisfaction_weight: u64,
+    output_spk_len: usize,
+) -> Vec<RustPool> {
+    pools
+        .into_iter()
+        .filter(|pool| pool.status == PoolStatus::Available)
+        .filter(|pool| {
+            let fee = contribution_fee(
+                pool.fees,
+                pool.total_peers,
+                satisfaction_weight,
+                output_spk_len,
+            );
+            coins.iter().any(|value| *value >= pool.denomination + fee)
+        })
+        .collect()
+}
+
+/// Returns the notifications to send when the step of the pool `pool_id`
+/// changed to `step`.
+fn step_notifications(pool_id: &str, step: Step) -> Vec<JoinstrNotif> {
+    let mut notifs = vec![
+        JoinstrNotif::PoolUpdate,
+        JoinstrNotif::PoolStepChanged(pool_id.to_string()),
+    ];
+    match step {
+        Step::Mined => notifs.push(JoinstrNotif::PoolMined(pool_id.to_string())),
+        Step::Failed => notifs.push(JoinstrNotif::PoolFailed(pool_id.to_string())),
+        _ => {}
     }
+    notifs
+}
+
+/// Returns the number of peers registered to a pool at `step`, or `None`
+/// if the step is not a registration step.
+fn registered_peers(step: Step, inputs: usize, outputs: usize) -> Option<usize> {
+    match step {
+        Step::OutputRegistration => Some(outputs),
+        Step::InputRegistration => Some(inputs),
+        _ => None,
+    }
+}
+
+/// Returns the notification to send when peers registered their input to
+/// the pool `pool_id`, called by the workers of the pools initiated by the
+/// wallet.
+///
+/// # Parameters
+/// - `pool_id`: The id of the pool.
+/// - `previous_step`: The step of the pool before the update, if any.
+/// - `step`: The step of the pool.
+/// - `previous_peers`: The number of registered peers before the update.
+/// - `peers`: The number of registered peers.
+fn peer_joined_notification(
+    pool_id: &str,
+    previous_step: Option<Step>,
+    step: Step,
+    previous_peers: usize,
+    peers: usize,
+) -> Option<JoinstrNotif> {
+    // NOTE: the peers counted before the input registration are the
+    // registered outputs, the first input is a join on its own
+    let previous_peers = match previous_step {
+        Some(Step::InputRegistration) => previous_peers,
+        _ => 0,
+    };
+    (step == Step::InputRegistration && peers > previous_peers)
+        .then(|| JoinstrNotif::PeerJoined(pool_id.to_string()))
 }
 
 #[allow(clippy::complexity)]
@@ -376,8 +682,12 @@ pub struct PoolEntry {
     role: PoolRole,
     step: Option<Step>,
     current_peers: usize,
+    /// Our output address, if we take part in the pool.
+    #[serde(default)]
+    output: Option<Address<NetworkUnchecked>>,
+    /// Stop flag of the worker thread handling this pool, if any.
     #[serde(skip)]
-    handle: Option<Arc<Mutex<JoinHandle<()>>>>,
+    stop: Option<Arc<AtomicBool>>,
 }
 
 impl PoolEntry {
@@ -393,6 +703,11 @@ impl PoolEntry {
     pub fn pool(&self) -> nostr::Pool {
         self.pool.clone()
     }
+    /// Returns the address our coinjoin output is paid to, if we take part
+    /// in the pool.
+    pub fn output(&self) -> Option<Address<NetworkUnchecked>> {
+        self.output.clone()
+    }
     /// Update the PoolEntry given the
     pub fn update_status(&mut self, step: Step, inputs: usize, outputs: usize) {
         self.step = Some(step);
@@ -409,14 +724,8 @@ impl PoolEntry {
         if let Some(status) = status {
             self.status = status;
         }
-        match step {
-            Step::OutputRegistration => {
-                self.current_peers = outputs;
-            }
-            Step::InputRegistration => {
-                self.current_peers = inputs;
-            }
-            _ => {}
+        if let Some(peers) = registered_peers(step, inputs, outputs) {
+            self.current_peers = peers;
         }
     }
 }
@@ -448,3 +757,217 @@ impl From<PoolEntry> for RustPool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::new_config, cpp_joinstr::SignalFlag};
+
+    #[test]
+    fn step_notifications_carry_pool_id() {
+        let id = "pool_id";
+
+        let notifs = step_notifications(id, Step::OutputRegistration);
+        assert_eq!(notifs.len(), 2);
+        assert!(matches!(notifs[0], JoinstrNotif::PoolUpdate));
+        let signal = Notification::from(notifs.into_iter().nth(1).unwrap()).to_signal();
+        assert!(matches!(signal.unwrap(), SignalFlag::PoolStepChanged));
+        assert_eq!(signal.payload(), id);
+
+        let notifs = step_notifications(id, Step::Mined);
+        assert_eq!(notifs.len(), 3);
+        let signal = Notification::from(notifs.into_iter().last().unwrap()).to_signal();
+        assert!(matches!(signal.unwrap(), SignalFlag::PoolMined));
+        assert_eq!(signal.payload(), id);
+
+        let notifs = step_notifications(id, Step::Failed);
+        assert_eq!(notifs.len(), 3);
+        let signal = Notification::from(notifs.into_iter().last().unwrap()).to_signal();
+        assert!(matches!(signal.unwrap(), SignalFlag::PoolFailed));
+        assert_eq!(signal.payload(), id);
+    }
+
+    #[test]
+    fn peer_joined_notifications() {
+        let id = "pool_id";
+        let mut current_peers = 0;
+        // (step, inputs, outputs) as seen by the initiator worker
+        let steps = [
+            (Step::OutputRegistration, 0, 1),
+            (Step::OutputRegistration, 0, 3),
+            (Step::InputRegistration, 1, 3),
+            (Step::InputRegistration, 1, 3),
+            (Step::InputRegistration, 2, 3),
+            (Step::InputRegistration, 3, 3),
+            (Step::Broadcast, 3, 3),
+        ];
+        let mut joined = vec![];
+        let mut previous_step = None;
+        for (step, inputs, outputs) in steps {
+            let previous_peers = current_peers;
+            current_peers = registered_peers(step, inputs, outputs).unwrap_or(current_peers);
+            let notif =
+                peer_joined_notification(id, previous_step, step, previous_peers, current_peers);
+            previous_step = Some(step);
+            if let Some(notif) = notif {
+                let signal = Notification::from(notif).to_signal();
+                assert!(matches!(signal.unwrap(), SignalFlag::PeerJoined));
+                assert_eq!(signal.payload(), id);
+                joined.push(current_peers);
+            }
+        }
+        // output registrations are not reported, the first input is
+        assert_eq!(joined, vec![1, 2, 3]);
+
+        // the first input is reported even if the worker missed the output
+        // registration
+        assert!(peer_joined_notification(id, None, Step::InputRegistration, 0, 1).is_some());
+        assert!(peer_joined_notification(
+            id,
+            Some(Step::InputRegistration),
+            Step::InputRegistration,
+            1,
+            1
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn pool_fee_range() {
+        assert!(!is_pool_fee_valid(0));
+        assert!(is_pool_fee_valid(MIN_POOL_FEE_RATE));
+        assert!(is_pool_fee_valid(MAX_POOL_FEE_RATE));
+        assert!(!is_pool_fee_valid(MAX_POOL_FEE_RATE + 1));
+
+        assert_eq!(pool_fee_rate(0.5), Some(1));
+        assert_eq!(pool_fee_rate(2.0), Some(2));
+        assert_eq!(pool_fee_rate(2.1), Some(3));
+        assert_eq!(pool_fee_rate(1_000.0), Some(1_000));
+        assert_eq!(pool_fee_rate(1_000.5), None);
+        assert_eq!(pool_fee_rate(0.0), None);
+        assert_eq!(pool_fee_rate(-1.0), None);
+        assert_eq!(pool_fee_rate(f64::NAN), None);
+        assert_eq!(pool_fee_rate(f64::INFINITY), None);
+    }
+
+    #[test]
+    fn contribution_fee_share() {
+        // p2wpkh input (max 108 WU witness) & p2wpkh output
+        let fee = contribution_fee(1, 5, 108, 22);
+        // 164 + 108 + 124 + ceil(42 / 5) = 405 WU => 102 vB
+        assert_eq!(fee, 102);
+        assert_eq!(contribution_fee(10, 5, 108, 22), 1_020);
+
+        // the overhead share decreases with the number of peers
+        assert!(contribution_fee(1, 2, 108, 22) > contribution_fee(1, 20, 108, 22));
+        // a larger satisfaction costs more
+        assert!(contribution_fee(1, 5, 300, 22) > fee);
+    }
+
+    #[test]
+    fn coinjoin_excess_share() {
+        let fee = contribution_fee(10, 5, 108, 22);
+        assert_eq!(coinjoin_excess(100_000 + fee, 100_000, fee), Some(0));
+        // a larger coin leaves its excess to the miners
+        assert_eq!(coinjoin_excess(150_000 + fee, 100_000, fee), Some(50_000));
+        assert_eq!(coinjoin_excess(100_000 + fee - 1, 100_000, fee), None);
+        assert_eq!(coinjoin_excess(50_000, 100_000, fee), None);
+
+        // joining with a larger coin is refused above the maximum excess
+        assert!(check_coinjoin_excess(0, 0).is_ok());
+        assert!(check_coinjoin_excess(1_000, 1_000).is_ok());
+        assert!(matches!(
+            check_coinjoin_excess(50_000, 1_000),
+            Err(Error::CoinjoinExcess(50_000))
+        ));
+    }
+
+    #[test]
+    fn joinable_pools_filter() {
+        let pool = |id: &str, denomination, status| RustPool {
+            denomination,
+            total_peers: 5,
+            current_peers: 0,
+            relay: "ws://127.0.0.1".to_string(),
+            fees: 10,
+            id: id.to_string(),
+            status,
+            role: PoolRole::None,
+            timeout: 0,
+        };
+        let pools = vec![
+            pool("small", 100_000, PoolStatus::Available),
+            pool("large", 1_000_000, PoolStatus::Available),
+            pool("closed", 100_000, PoolStatus::Closed),
+        ];
+        let fee = contribution_fee(10, 5, 108, 22);
+
+        // a coin covering the small pool denomination & fee only
+        let coins = [50_000, 500_000];
+        let joinable = joinable_pools(pools.clone(), &coins, 108, 22);
+        assert_eq!(joinable.len(), 1);
+        assert_eq!(joinable[0].id, "small");
+
+        // the fee headroom is required
+        let joinable = joinable_pools(pools.clone(), &[100_000 + fee - 1], 108, 22);
+        assert!(joinable.is_empty());
+        let joinable = joinable_pools(pools.clone(), &[100_000 + fee], 108, 22);
+        assert_eq!(joinable.len(), 1);
+
+        assert!(joinable_pools(pools, &[], 108, 22).is_empty());
+    }
+
+    #[test]
+    fn shutdown_stops_workers() {
+        let store = Arc::new(Mutex::new(PoolStore::new(None)));
+        let mut finished = vec![];
+        for _ in 0..3 {
+            let stop = Arc::new(AtomicBool::new(false));
+            let cloned_stop = stop.clone();
+            let done = Arc::new(AtomicBool::new(false));
+            let cloned_done = done.clone();
+            let handle = thread::spawn(move || {
+                while !cloned_stop.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                cloned_done.store(true, Ordering::Relaxed);
+            });
+            store
+                .lock()
+                .expect("poisoned")
+                .register_worker(stop, handle);
+            finished.push(done);
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert!(finished.iter().all(|d| !d.load(Ordering::Relaxed)));
+
+        let handles = PoolStore::shutdown(&store);
+        assert_eq!(handles.len(), 3);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(finished.iter().all(|d| d.load(Ordering::Relaxed)));
+        assert!(store.lock().expect("poisoned").workers.is_empty());
+    }
+
+    #[test]
+    fn pool_outputs_persist() {
+        let config = new_config(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_string(),
+            "pool_outputs_persist".to_string(),
+            crate::cpp_joinstr::Network::Regtest,
+        );
+        config.to_file().unwrap();
+        let address: Address<NetworkUnchecked> = "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"
+            .parse()
+            .unwrap();
+        let mut outputs = BTreeMap::new();
+        outputs.insert("pool_id".to_string(), address.clone());
+        config.persist_pool_outputs(&outputs);
+
+        let store = PoolStore::new(Some(*config));
+        assert_eq!(store.output("pool_id"), Some(address));
+        assert_eq!(store.output("other"), None);
+        assert_eq!(PoolStore::new(None).output("pool_id"), None);
+    }
+}
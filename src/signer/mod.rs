@@ -11,12 +11,17 @@ use joinstr::{
     bip39,
     miniscript::{
         bitcoin::{
-            self,
+            self, absolute,
+            base64::{prelude::BASE64_STANDARD, Engine},
             bip32::{self, DerivationPath},
-            ecdsa,
+            consensus, ecdsa,
+            hashes::{sha256, Hash, HashEngine},
+            opcodes::{all::OP_RETURN, OP_0},
             psbt::Input,
+            script::Builder,
             secp256k1::{self, All, Message},
-            sighash, EcdsaSighashType, NetworkKind, Psbt,
+            sighash, transaction, Amount, CompressedPublicKey, EcdsaSighashType, NetworkKind,
+            OutPoint, Psbt, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
         },
         descriptor::DescriptorMultiXKey,
         Descriptor, DescriptorPublicKey, ForEachKey,
@@ -140,16 +145,8 @@ impl Signer for HotSigner {
         }
     }
 
-    fn sign(&self, mut psbt: Psbt, descriptor: Descriptor<DescriptorPublicKey>) {
-        let response = if self.descriptors.contains(&descriptor) {
-            if let Err(e) = self.inner_sign(&mut psbt, &descriptor) {
-                SignerNotif::Error(self.fingerprint(), e)
-            } else {
-                SignerNotif::Signed(self.fingerprint(), psbt)
-            }
-        } else {
-            SignerNotif::Error(self.fingerprint(), Error::UnregisteredDescriptor)
-        };
+    fn sign(&self, psbt: Psbt, descriptor: Descriptor<DescriptorPublicKey>) {
+        let response = self.sign_registered(psbt, descriptor);
         if let Some(sender) = &self.sender {
             let _ = sender.send(response);
         }
@@ -194,6 +191,22 @@ pub fn wpkh(xpub: OXpub) -> Descriptor<DescriptorPublicKey> {
     Descriptor::<DescriptorPublicKey>::from_str(&descr_str).expect("hardcoded descriptor")
 }
 
+/// Creates a key-path only TR descriptor from the given extended public
+/// key (OXpub).
+///
+/// # Arguments
+/// * `xpub` - An instance of `OXpub` representing the extended public key.
+///
+/// # Returns
+/// A `Descriptor<DescriptorPublicKey>` that represents the tr descriptor.
+pub fn tr(xpub: OXpub) -> Descriptor<DescriptorPublicKey> {
+    let descr_str = format!(
+        "tr([{}/{}]{}/<0;1>/*)",
+        xpub.origin.0, xpub.origin.1, xpub.xkey
+    );
+    Descriptor::<DescriptorPublicKey>::from_str(&descr_str).expect("hardcoded descriptor")
+}
+
 /// A struct that represents an extended private key.
 ///
 /// This struct contains the origin fingerprint and derivation path
@@ -313,6 +326,30 @@ impl HotSigner {
         }
     }
 
+    /// Signs a PSBT if the given descriptor is registered.
+    ///
+    /// # Arguments
+    /// * `psbt` - The PSBT to sign.
+    /// * `descriptor` - The descriptor the inputs are derived from.
+    ///
+    /// # Returns
+    /// A [`SignerNotif::Signed`] notification with the signed PSBT, or a
+    /// [`SignerNotif::Error`] if the descriptor is not registered or the
+    /// signing fails.
+    pub fn sign_registered(
+        &self,
+        mut psbt: Psbt,
+        descriptor: Descriptor<DescriptorPublicKey>,
+    ) -> SignerNotif {
+        if !self.descriptors.contains(&descriptor) {
+            return SignerNotif::Error(self.fingerprint(), Error::UnregisteredDescriptor);
+        }
+        match self.inner_sign(&mut psbt, &descriptor) {
+            Ok(()) => SignerNotif::Signed(self.fingerprint(), psbt),
+            Err(e) => SignerNotif::Error(self.fingerprint(), e),
+        }
+    }
+
     /// Retrieves the extended private key at the specified derivation path.
     ///
     /// # Arguments
@@ -463,7 +500,12 @@ impl HotSigner {
             }
 
             if let Some(wit) = &input.witness_utxo {
-                let ap = account_path(d)?;
+                // NOTE: a path relative to the descriptor key is also
+                // accepted
+                let ap = match derivator.address_index(self.fingerprint(), d) {
+                    Some(ap) => ap,
+                    None => account_path(d)?,
+                };
                 let expected_spk = match ap.0 {
                     AddrAccount::Receive => derivator.receive_at(ap.1),
                     AddrAccount::Change => derivator.change_at(ap.1),
@@ -496,6 +538,70 @@ impl HotSigner {
         Ok(())
     }
 
+    /// Sign a message with the key of the address at the given derivation,
+    /// following the BIP322 "simple" format.
+    ///
+    /// The key is resolved from the registered wpkh descriptor that has a
+    /// key derived from this signer.
+    ///
+    /// # Arguments
+    /// * `address_index` - The keychain and the index of the address.
+    /// * `message` - The message to sign.
+    ///
+    /// # Returns
+    /// The base64 encoded signature. Returns an error if:
+    /// * There is no registered wpkh descriptor for this signer.
+    /// * The derived key do not match the address of the descriptor.
+    pub fn sign_message(
+        &self,
+        address_index: (AddrAccount, u32),
+        message: &str,
+    ) -> Result<String, Error> {
+        let (account, index) = address_index;
+        if !matches!(account, AddrAccount::Receive | AddrAccount::Change) {
+            return Err(Error::DerivationPath);
+        }
+        let (path, derivator) = self
+            .descriptors
+            .iter()
+            .filter(|d| matches!(d, Descriptor::Wpkh(_)))
+            .find_map(|d| {
+                let derivator = Derivator::new(d.clone(), self.network).ok()?;
+                // NOTE: the path is read from the descriptor, the keychain
+                // is not always the second to last step
+                let path = derivator.key_path(account, index, self.fingerprint)?;
+                Some((path, derivator))
+            })
+            .ok_or(Error::UnregisteredDescriptor)?;
+
+        let spk = match account {
+            AddrAccount::Receive => derivator.receive_spk_at(index),
+            _ => derivator.change_spk_at(index),
+        };
+        let signing_key = self.private_key_at(&path);
+        let pubkey = signing_key.public_key(self.secp());
+        if ScriptBuf::new_p2wpkh(&CompressedPublicKey(pubkey).wpubkey_hash()) != spk {
+            return Err(Error::SpkNotMatch);
+        }
+
+        let to_sign = bip322_to_sign(&bip322_to_spend(&spk, message));
+        let hash = sighash::SighashCache::new(&to_sign)
+            .p2wpkh_signature_hash(0, &spk, Amount::ZERO, EcdsaSighashType::All)
+            .map_err(|_| Error::SighashFail)?;
+        let hash = Message::from_digest(hash.to_byte_array());
+        let signature = self.secp.sign_ecdsa_low_r(&hash, &signing_key);
+        self.secp()
+            .verify_ecdsa(&hash, &signature, &pubkey)
+            .map_err(|_| Error::InvalidSignature)?;
+
+        let signature = ecdsa::Signature {
+            signature,
+            sighash_type: EcdsaSighashType::All,
+        };
+        let witness = Witness::p2wpkh(&signature, &pubkey);
+        Ok(BASE64_STANDARD.encode(consensus::serialize(&witness)))
+    }
+
     /// Returns the [`Fingerprint`] of this [`HotSigner`].
     pub fn fingerprint(&self) -> bip32::Fingerprint {
         self.fingerprint
@@ -513,6 +619,63 @@ impl HotSigner {
     }
 }
 
+/// Computes the BIP322 tagged hash of `message`.
+fn bip322_message_hash(message: &str) -> sha256::Hash {
+    let tag = sha256::Hash::hash(b"BIP0322-signed-message");
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(message.as_bytes());
+    sha256::Hash::from_engine(engine)
+}
+
+/// Builds the BIP322 virtual `to_spend` transaction committing to `message`
+/// and locked to `spk`.
+fn bip322_to_spend(spk: &ScriptBuf, message: &str) -> Transaction {
+    let script_sig = Builder::new()
+        .push_opcode(OP_0)
+        .push_slice(bip322_message_hash(message).to_byte_array())
+        .into_script();
+    Transaction {
+        version: transaction::Version(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0xFFFFFFFF,
+            },
+            script_sig,
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: spk.clone(),
+        }],
+    }
+}
+
+/// Builds the BIP322 virtual `to_sign` transaction spending `to_spend`.
+fn bip322_to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: transaction::Version(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: to_spend.compute_txid(),
+                vout: 0,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
 /// Converts a tuple containing an account type and an index into a derivation path.
 ///
 /// # Arguments
@@ -880,4 +1043,122 @@ mod tests {
             _ => panic!("Expected DescriptorRegistered notification"),
         }
     }
+
+    #[test]
+    fn bip322_vectors() {
+        assert_eq!(
+            bip322_message_hash("").to_string(),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            bip322_message_hash("Hello World").to_string(),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let spk = bitcoin::Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l")
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+        let to_spend = bip322_to_spend(&spk, "");
+        assert_eq!(
+            to_spend.compute_txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        assert_eq!(
+            bip322_to_sign(&to_spend).compute_txid().to_string(),
+            "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+        );
+        let to_spend = bip322_to_spend(&spk, "Hello World");
+        assert_eq!(
+            to_spend.compute_txid().to_string(),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+        assert_eq!(
+            bip322_to_sign(&to_spend).compute_txid().to_string(),
+            "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"
+        );
+    }
+
+    #[test]
+    fn sign_message() {
+        let network = Network::Regtest;
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mut signer = HotSigner::new_from_mnemonics(network, mnemonic).unwrap();
+        let deriv = (AddrAccount::Receive, 3);
+        let message = "Hello World";
+
+        // there is no registered descriptor to resolve the key
+        assert_eq!(
+            signer.sign_message(deriv, message),
+            Err(Error::UnregisteredDescriptor)
+        );
+
+        let descriptor = wpkh(signer.xpub(&DerivationPath::from_str("m/84'/1'/0'").unwrap()));
+        signer.inner_register_descriptor(descriptor.clone());
+        let signature = signer.sign_message(deriv, message).unwrap();
+
+        // the witness contains the signature and the key of the address
+        let derivator = Derivator::new(descriptor, network).unwrap();
+        let spk = derivator.receive_spk_at(deriv.1);
+        let witness: Witness =
+            consensus::deserialize(&BASE64_STANDARD.decode(signature).unwrap()).unwrap();
+        assert_eq!(witness.len(), 2);
+        let pubkey = CompressedPublicKey::from_slice(&witness[1]).unwrap();
+        assert_eq!(ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash()), spk);
+
+        // the signature commits to the message
+        let signature = ecdsa::Signature::from_slice(&witness[0]).unwrap();
+        let sighash = |msg: &str| {
+            let to_sign = bip322_to_sign(&bip322_to_spend(&spk, msg));
+            let hash = sighash::SighashCache::new(&to_sign)
+                .p2wpkh_signature_hash(0, &spk, Amount::ZERO, EcdsaSighashType::All)
+                .unwrap();
+            Message::from_digest(hash.to_byte_array())
+        };
+        assert!(signer
+            .secp()
+            .verify_ecdsa(&sighash(message), &signature.signature, &pubkey.0)
+            .is_ok());
+        assert!(signer
+            .secp()
+            .verify_ecdsa(&sighash("Hello"), &signature.signature, &pubkey.0)
+            .is_err());
+
+        // the change keychain is signed with a different key
+        let change = signer
+            .sign_message((AddrAccount::Change, 3), message)
+            .unwrap();
+        let witness: Witness =
+            consensus::deserialize(&BASE64_STANDARD.decode(change).unwrap()).unwrap();
+        assert_eq!(
+            ScriptBuf::new_p2wpkh(
+                &CompressedPublicKey::from_slice(&witness[1])
+                    .unwrap()
+                    .wpubkey_hash()
+            ),
+            derivator.change_spk_at(3)
+        );
+
+        // the keychain is not the second to last step of the path
+        let xpub = signer.xpub(&DerivationPath::from_str("m/84'/1'/1'").unwrap());
+        let descriptor = Descriptor::<DescriptorPublicKey>::from_str(&format!(
+            "wpkh([{}/{}]{}/<0;1>/3/*)",
+            xpub.origin.0, xpub.origin.1, xpub.xkey
+        ))
+        .unwrap();
+        let mut signer = HotSigner::new_from_mnemonics(network, mnemonic).unwrap();
+        signer.inner_register_descriptor(descriptor.clone());
+        let derivator = Derivator::new(descriptor, network).unwrap();
+        let signature = signer.sign_message(deriv, message).unwrap();
+        let witness: Witness =
+            consensus::deserialize(&BASE64_STANDARD.decode(signature).unwrap()).unwrap();
+        assert_eq!(
+            ScriptBuf::new_p2wpkh(
+                &CompressedPublicKey::from_slice(&witness[1])
+                    .unwrap()
+                    .wpubkey_hash()
+            ),
+            derivator.receive_spk_at(deriv.1)
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
     fs::File,
     io::{Read, Write},
     path::PathBuf,
@@ -9,21 +10,95 @@ use std::{
 
 use joinstr::{
     bip39::{self},
-    miniscript::bitcoin::{
-        bip32::{self, DerivationPath},
-        Psbt,
+    miniscript::{
+        bitcoin::{
+            self,
+            bip32::{self, ChildNumber, DerivationPath},
+            Psbt,
+        },
+        Descriptor, DescriptorPublicKey, ForEachKey,
     },
 };
 
 use crate::{
     config,
-    cpp_joinstr::Network,
+    cpp_joinstr::{Network, SignerEvent, SignerPoll},
     signer::{wpkh, HotSigner, JsonSigner, Signer, SignerNotif},
 };
 
 #[derive(Debug, Clone)]
 pub enum Error {
     ParsePsbt,
+    ParseDescriptor,
+    /// None of the keys of the descriptor belong to a managed signer.
+    UnknownDescriptor,
+    /// The inputs of the PSBT are derived from several accounts.
+    MixedAccounts,
+    /// The derivation paths of the PSBT are not for the signer network.
+    WrongNetwork,
+    /// The PSBT spends taproot inputs of the signer, e.g. coins of the
+    /// secondary descriptor, the hot signer only signs wpkh inputs.
+    TaprootInput,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::ParsePsbt => write!(f, "Fail to parse PSBT"),
+            Error::ParseDescriptor => write!(f, "Fail to parse descriptor"),
+            Error::UnknownDescriptor => write!(f, "Descriptor do not match any signer"),
+            Error::MixedAccounts => write!(f, "PSBT inputs are derived from several accounts"),
+            Error::WrongNetwork => write!(f, "PSBT do not match the signer network"),
+            Error::TaprootInput => write!(f, "Taproot inputs cannot be signed by the hot signer"),
+        }
+    }
+}
+
+impl From<SignerNotif> for SignerPoll {
+    fn from(value: SignerNotif) -> Self {
+        let (fingerprint, event, value, error) = match value {
+            SignerNotif::Info(fg, info) => (Some(fg), SignerEvent::Info, info.to_string(), None),
+            SignerNotif::Xpub(fg, xpub) => (
+                Some(fg),
+                SignerEvent::Xpub,
+                format!("[{}/{}]{}", xpub.origin.0, xpub.origin.1, xpub.xkey),
+                None,
+            ),
+            SignerNotif::Descriptor(fg, key) => (
+                Some(fg),
+                SignerEvent::Descriptor,
+                DescriptorPublicKey::MultiXPub(key).to_string(),
+                None,
+            ),
+            SignerNotif::DescriptorRegistered(fg, descriptor, registered) => (
+                Some(fg),
+                SignerEvent::DescriptorRegistered,
+                descriptor.to_string(),
+                (!registered).then(|| "Descriptor not registered".to_string()),
+            ),
+            SignerNotif::Signed(fg, psbt) => {
+                (Some(fg), SignerEvent::Signed, psbt.to_string(), None)
+            }
+            SignerNotif::Error(fg, e) => (
+                Some(fg),
+                SignerEvent::Error,
+                String::new(),
+                Some(e.to_string()),
+            ),
+            SignerNotif::Manager(e) => (
+                None,
+                SignerEvent::ManagerError,
+                String::new(),
+                Some(e.to_string()),
+            ),
+        };
+        SignerPoll {
+            fingerprint: fingerprint.map(|fg| fg.to_string()).unwrap_or_default(),
+            event,
+            value,
+            error: error.unwrap_or_default(),
+        }
+    }
 }
 
 /// A manager for handling hot signers and their notifications.
@@ -114,6 +189,20 @@ impl SigningManager {
         self.receiver.try_recv().ok()
     }
 
+    /// Polls for a new signer notification, converted for the C++ side.
+    ///
+    /// # Returns
+    /// A `SignerPoll` with a `SignerEvent::None` event if there are no new
+    /// notifications.
+    pub fn poll_event(&self) -> SignerPoll {
+        self.poll().map(SignerPoll::from).unwrap_or(SignerPoll {
+            fingerprint: String::new(),
+            event: SignerEvent::None,
+            value: String::new(),
+            error: String::new(),
+        })
+    }
+
     /// Creates a new hot signer with a generated mnemonic.
     ///
     /// # Parameters
@@ -134,21 +223,27 @@ impl SigningManager {
         self.hot_signers.insert(signer.fingerprint(), signer);
     }
 
-    pub fn sign(&self, network: Network, psbt: String) {
-        let psbt = match Psbt::from_str(&psbt) {
-            Ok(p) => p,
-            Err(_) => {
-                if self
-                    .sender
-                    .send(SignerNotif::Manager(Error::ParsePsbt))
-                    .is_err()
-                {
-                    log::error!("SigningManager::sign() fails to send notif")
-                }
-                return;
-            }
-        };
+    /// Sends a manager error notification.
+    fn send_error(&self, error: Error) {
+        if self.sender.send(SignerNotif::Manager(error)).is_err() {
+            log::error!("SigningManager::sign() fails to send notif")
+        }
+    }
 
+    /// Returns the hot signer and the descriptor matching the inputs of a PSBT.
+    ///
+    /// The account of the descriptor is read from the `m/84'/coin'/account'`
+    /// origins of the inputs belonging to the signer, account 0 is used if
+    /// there is none.
+    ///
+    /// # Parameters
+    /// - `network`: The network of the PSBT.
+    /// - `psbt`: The PSBT to sign.
+    fn signing_descriptor(
+        &self,
+        network: Network,
+        psbt: &Psbt,
+    ) -> Result<(&HotSigner, Descriptor<DescriptorPublicKey>), Error> {
         let signer = self
             .hot_signers
             .iter()
@@ -156,25 +251,463 @@ impl SigningManager {
             .expect("at least one signer")
             .1;
 
-        let n_path = match network {
-            Network::Bitcoin => 0,
-            _ => 1,
-        };
-        let deriv_path = DerivationPath::from_str(&format!("m/84'/{}'/0'", n_path)).unwrap();
+        if !matches_network(psbt, network.into()) {
+            return Err(Error::WrongNetwork);
+        }
+
+        // NOTE: the inputs would be left unsigned and the transaction
+        // could not be broadcast
+        if psbt.inputs.iter().any(|input| {
+            input
+                .tap_key_origins
+                .values()
+                .any(|(_, (fg, _))| *fg == signer.fingerprint())
+        }) {
+            return Err(Error::TaprootInput);
+        }
+
+        let accounts: BTreeSet<_> = psbt
+            .inputs
+            .iter()
+            .flat_map(|input| input.bip32_derivation.values())
+            .filter(|(fg, _)| *fg == signer.fingerprint())
+            .filter_map(|(_, path)| account_index(path))
+            .collect();
+        if accounts.len() > 1 {
+            return Err(Error::MixedAccounts);
+        }
+        let account = accounts
+            .into_iter()
+            .next()
+            .unwrap_or(ChildNumber::Hardened { index: 0 });
+
+        let deriv_path = DerivationPath::from(vec![
+            ChildNumber::Hardened { index: 84 },
+            coin_type(network.into()),
+            account,
+        ]);
         let xpub = signer.xpub(&deriv_path);
-        let descriptor = wpkh(xpub);
+        Ok((signer, wpkh(xpub)))
+    }
+
+    /// Registers the descriptor of an account on the hot signers owning
+    /// one of its keys, a [`SignerNotif`] is sent by each of them.
+    ///
+    /// Only the descriptors registered this way are signed for.
+    ///
+    /// # Parameters
+    /// - `descriptor`: The account descriptor.
+    pub fn register_descriptor(&mut self, descriptor: String) {
+        let descriptor = match Descriptor::<DescriptorPublicKey>::from_str(&descriptor) {
+            Ok(d) => d,
+            Err(_) => {
+                self.send_error(Error::ParseDescriptor);
+                return;
+            }
+        };
+        let mut registered = false;
+        for (fg, signer) in self.hot_signers.iter_mut() {
+            if descriptor.for_any_key(|k| k.master_fingerprint() == *fg) {
+                signer.register_descriptor(descriptor.clone());
+                registered = true;
+            }
+        }
+        if !registered {
+            self.send_error(Error::UnknownDescriptor);
+        }
+    }
+
+    /// Signs a PSBT with the hot signer, the result is sent as a
+    /// [`SignerNotif`].
+    ///
+    /// The account of the descriptor is read from the `m/84'/coin'/account'`
+    /// origins of the inputs belonging to the signer, account 0 is used if
+    /// there is none. The PSBT is left unsigned with an
+    /// [`crate::signer::Error::UnregisteredDescriptor`] error if this
+    /// descriptor has not been registered with
+    /// [`SigningManager::register_descriptor`], and refused with
+    /// [`Error::TaprootInput`] if it spends taproot inputs of the signer.
+    ///
+    /// # Parameters
+    /// - `network`: The network of the PSBT.
+    /// - `psbt`: The PSBT to sign, as a base64 string.
+    pub fn sign(&self, network: Network, psbt: String) {
+        let psbt = match Psbt::from_str(&psbt) {
+            Ok(p) => p,
+            Err(_) => {
+                self.send_error(Error::ParsePsbt);
+                return;
+            }
+        };
+
+        let (signer, descriptor) = match self.signing_descriptor(network, &psbt) {
+            Ok(s) => s,
+            Err(e) => {
+                self.send_error(e);
+                return;
+            }
+        };
 
         signer.sign(psbt, descriptor);
     }
+
+    /// Signs several PSBTs with the hot signer.
+    ///
+    /// Each PSBT is processed independently: an error on one of them does
+    /// not abort the others. Results are returned instead of being sent
+    /// through the notification channel.
+    ///
+    /// # Parameters
+    /// - `network`: The network of the PSBTs.
+    /// - `psbts`: The PSBTs to sign, as base64 strings.
+    ///
+    /// # Returns
+    /// One [`SignerNotif`] per PSBT, in the same order: `Signed` on success,
+    /// `Error` if the signer fails or the descriptor of the PSBT is not
+    /// registered and `Manager` if the PSBT cannot be handled by the manager.
+    pub fn sign_batch(&self, network: Network, psbts: Vec<String>) -> Vec<SignerNotif> {
+        psbts
+            .into_iter()
+            .map(|psbt| {
+                let psbt = match Psbt::from_str(&psbt) {
+                    Ok(p) => p,
+                    Err(_) => return SignerNotif::Manager(Error::ParsePsbt),
+                };
+                let (signer, descriptor) = match self.signing_descriptor(network, &psbt) {
+                    Ok(s) => s,
+                    Err(e) => return SignerNotif::Manager(e),
+                };
+                signer.sign_registered(psbt, descriptor)
+            })
+            .collect()
+    }
+
+    /// Signs several PSBTs with the hot signer, see
+    /// [`SigningManager::sign_batch`].
+    ///
+    /// # Returns
+    /// One [`SignerPoll`] per PSBT, in the same order.
+    pub fn sign_batch_poll(&self, network: Network, psbts: Vec<String>) -> Vec<SignerPoll> {
+        self.sign_batch(network, psbts)
+            .into_iter()
+            .map(SignerPoll::from)
+            .collect()
+    }
+}
+
+/// Creates a new signing manager without signers.
+///
+/// # Returns
+/// A boxed `SigningManager` instance.
+pub fn new_signing_manager() -> Box<SigningManager> {
+    Box::new(SigningManager::default())
+}
+
+/// Returns the account index of a BIP84 derivation path.
+///
+/// # Parameters
+/// - `path`: A derivation path of the form `m/84'/coin'/account'/...`.
+///
+/// # Returns
+/// The hardened account index, or `None` if the path is not a BIP84 path.
+fn account_index(path: &DerivationPath) -> Option<ChildNumber> {
+    match path.as_ref() {
+        [ChildNumber::Hardened { index: 84 }, ChildNumber::Hardened { .. }, account @ ChildNumber::Hardened { .. }, ..] => {
+            Some(*account)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the BIP44 coin type of a network.
+fn coin_type(network: bitcoin::Network) -> ChildNumber {
+    match network {
+        bitcoin::Network::Bitcoin => ChildNumber::Hardened { index: 0 },
+        _ => ChildNumber::Hardened { index: 1 },
+    }
+}
+
+/// Checks that the derivation paths of a PSBT are for a given network.
+///
+/// NOTE: segwit script pubkeys do not commit to a network, the coin type
+/// of the `m/purpose'/coin'/...` origins of the inputs & outputs is the
+/// only hint a PSBT carries.
+///
+/// # Parameters
+/// - `psbt`: The PSBT to check.
+/// - `network`: The expected network.
+///
+/// # Returns
+/// `false` if any origin has the coin type of another network.
+pub fn matches_network(psbt: &Psbt, network: bitcoin::Network) -> bool {
+    let expected = coin_type(network);
+    psbt.inputs
+        .iter()
+        .flat_map(|input| input.bip32_derivation.values())
+        .chain(
+            psbt.outputs
+                .iter()
+                .flat_map(|output| output.bip32_derivation.values()),
+        )
+        .all(|(_, path)| match path.as_ref() {
+            [ChildNumber::Hardened { .. }, coin @ ChildNumber::Hardened { .. }, ..] => {
+                *coin == expected
+            }
+            _ => true,
+        })
 }
 
 #[cfg(test)]
 mod tests {
     use bip32::Fingerprint;
+    use joinstr::miniscript::bitcoin;
 
     use super::*;
+    use crate::signer;
     use std::str::FromStr;
 
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    /// Returns a PSBT spending a coin received at `m/84'/coin'/account'/0/0`
+    /// of the signer, and the descriptor of this account.
+    fn account_psbt(
+        signer: &HotSigner,
+        coin: u32,
+        account: u32,
+    ) -> (Psbt, Descriptor<DescriptorPublicKey>) {
+        let path = DerivationPath::from_str(&format!("m/84'/{coin}'/{account}'/0/0")).unwrap();
+        let descriptor = wpkh(
+            signer.xpub(&DerivationPath::from_str(&format!("m/84'/{coin}'/{account}'")).unwrap()),
+        );
+        let spk = descriptor
+            .at_derivation_index(0)
+            .unwrap()
+            .into_single_descriptors()
+            .unwrap()[0]
+            .script_pubkey();
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(90_000),
+                script_pubkey: spk.clone(),
+            }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(bitcoin::TxOut {
+            value: bitcoin::Amount::from_sat(100_000),
+            script_pubkey: spk,
+        });
+        psbt.inputs[0]
+            .bip32_derivation
+            .insert(signer.public_key_at(&path), (signer.fingerprint(), path));
+        (psbt, descriptor)
+    }
+
+    #[test]
+    fn register_descriptor() {
+        let mut manager = SigningManager::default();
+        manager.new_hot_signer_from_mnemonic(Network::Regtest, MNEMONIC.to_string());
+        assert!(matches!(manager.poll(), Some(SignerNotif::Info(..))));
+
+        manager.register_descriptor("descriptor".to_string());
+        assert!(matches!(
+            manager.poll(),
+            Some(SignerNotif::Manager(Error::ParseDescriptor))
+        ));
+
+        // a descriptor of another signer
+        let other = HotSigner::new(Network::Regtest.into()).unwrap();
+        let (_, descriptor) = account_psbt(&other, 1, 0);
+        manager.register_descriptor(descriptor.to_string());
+        assert!(matches!(
+            manager.poll(),
+            Some(SignerNotif::Manager(Error::UnknownDescriptor))
+        ));
+        assert!(manager.poll().is_none());
+    }
+
+    #[test]
+    fn sign_account() {
+        let mut manager = SigningManager::default();
+        manager.new_hot_signer_from_mnemonic(Network::Regtest, MNEMONIC.to_string());
+        assert!(matches!(manager.poll(), Some(SignerNotif::Info(..))));
+
+        // an input of the account 1
+        let signer = HotSigner::new_from_mnemonics(Network::Regtest.into(), MNEMONIC).unwrap();
+        let (psbt, descriptor) = account_psbt(&signer, 1, 1);
+
+        // the descriptor of the account is not registered yet
+        manager.sign(Network::Regtest, psbt.to_string());
+        assert!(matches!(
+            manager.poll(),
+            Some(SignerNotif::Error(_, signer::Error::UnregisteredDescriptor))
+        ));
+
+        manager.register_descriptor(descriptor.to_string());
+        assert!(matches!(
+            manager.poll(),
+            Some(SignerNotif::DescriptorRegistered(_, _, true))
+        ));
+        manager.sign(Network::Regtest, psbt.to_string());
+        match manager.poll() {
+            Some(SignerNotif::Signed(fg, psbt)) => {
+                assert_eq!(fg, signer.fingerprint());
+                assert!(!psbt.inputs[0].partial_sigs.is_empty());
+            }
+            n => panic!("expect signed psbt, got {n:?}"),
+        }
+
+        // inputs of several accounts are rejected
+        let mut mixed = psbt.clone();
+        mixed.unsigned_tx.input.push(bitcoin::TxIn::default());
+        mixed
+            .inputs
+            .push(account_psbt(&signer, 1, 0).0.inputs[0].clone());
+        manager.sign(Network::Regtest, mixed.to_string());
+        assert!(matches!(
+            manager.poll(),
+            Some(SignerNotif::Manager(Error::MixedAccounts))
+        ));
+    }
+
+    #[test]
+    fn sign_wrong_network() {
+        let mut manager = SigningManager::default();
+        manager.new_hot_signer_from_mnemonic(Network::Regtest, MNEMONIC.to_string());
+        assert!(matches!(manager.poll(), Some(SignerNotif::Info(..))));
+
+        // an input derived for mainnet
+        let signer = HotSigner::new_from_mnemonics(Network::Regtest.into(), MNEMONIC).unwrap();
+        let (psbt, _) = account_psbt(&signer, 0, 0);
+        assert!(matches_network(&psbt, bitcoin::Network::Bitcoin));
+        assert!(!matches_network(&psbt, bitcoin::Network::Regtest));
+
+        manager.sign(Network::Regtest, psbt.to_string());
+        assert!(matches!(
+            manager.poll(),
+            Some(SignerNotif::Manager(Error::WrongNetwork))
+        ));
+    }
+
+    #[test]
+    fn sign_taproot_input() {
+        let mut manager = SigningManager::default();
+        manager.new_hot_signer_from_mnemonic(Network::Regtest, MNEMONIC.to_string());
+        assert!(matches!(manager.poll(), Some(SignerNotif::Info(..))));
+
+        let signer = HotSigner::new_from_mnemonics(Network::Regtest.into(), MNEMONIC).unwrap();
+        let (psbt, descriptor) = account_psbt(&signer, 1, 0);
+        manager.register_descriptor(descriptor.to_string());
+        assert!(matches!(
+            manager.poll(),
+            Some(SignerNotif::DescriptorRegistered(_, _, true))
+        ));
+
+        // an additional input of a taproot descriptor of the signer
+        let path = DerivationPath::from_str("m/86'/1'/0'/0/0").unwrap();
+        let key = signer.public_key_at(&path).x_only_public_key().0;
+        let mut taproot = psbt.clone();
+        taproot.unsigned_tx.input.push(bitcoin::TxIn::default());
+        let mut input = bitcoin::psbt::Input::default();
+        input
+            .tap_key_origins
+            .insert(key, (vec![], (signer.fingerprint(), path)));
+        taproot.inputs.push(input);
+        manager.sign(Network::Regtest, taproot.to_string());
+        assert!(matches!(
+            manager.poll(),
+            Some(SignerNotif::Manager(Error::TaprootInput))
+        ));
+
+        // the wpkh inputs alone are signed
+        manager.sign(Network::Regtest, psbt.to_string());
+        assert!(matches!(manager.poll(), Some(SignerNotif::Signed(..))));
+    }
+
+    #[test]
+    fn sign_batch() {
+        let mut manager = SigningManager::default();
+        manager.new_hot_signer_from_mnemonic(Network::Regtest, MNEMONIC.to_string());
+        assert!(matches!(manager.poll(), Some(SignerNotif::Info(..))));
+
+        let signer = HotSigner::new_from_mnemonics(Network::Regtest.into(), MNEMONIC).unwrap();
+        let (psbt_0, descriptor_0) = account_psbt(&signer, 1, 0);
+        let (psbt_1, _) = account_psbt(&signer, 1, 1);
+        let (psbt_2, descriptor_2) = account_psbt(&signer, 1, 2);
+        for descriptor in [descriptor_0, descriptor_2] {
+            manager.register_descriptor(descriptor.to_string());
+            assert!(matches!(
+                manager.poll(),
+                Some(SignerNotif::DescriptorRegistered(_, _, true))
+            ));
+        }
+
+        let results = manager.sign_batch(
+            Network::Regtest,
+            vec![
+                psbt_0.to_string(),
+                "psbt".to_string(),
+                psbt_1.to_string(),
+                psbt_2.to_string(),
+            ],
+        );
+        assert_eq!(results.len(), 4);
+        for i in [0, 3] {
+            match &results[i] {
+                SignerNotif::Signed(fg, psbt) => {
+                    assert_eq!(*fg, signer.fingerprint());
+                    assert!(!psbt.inputs[0].partial_sigs.is_empty());
+                }
+                n => panic!("expect signed psbt, got {n:?}"),
+            }
+        }
+        assert!(matches!(results[1], SignerNotif::Manager(Error::ParsePsbt)));
+        // the descriptor of the account 1 is not registered
+        assert!(matches!(
+            results[2],
+            SignerNotif::Error(_, signer::Error::UnregisteredDescriptor)
+        ));
+
+        // results are returned, nothing is sent through the channel
+        assert!(manager.poll().is_none());
+    }
+
+    #[test]
+    fn poll_event() {
+        let mut manager = new_signing_manager();
+        assert_eq!(manager.poll_event().event, SignerEvent::None);
+
+        manager.new_hot_signer_from_mnemonic(Network::Regtest, MNEMONIC.to_string());
+        let event = manager.poll_event();
+        assert_eq!(event.event, SignerEvent::Info);
+        assert_eq!(event.fingerprint, "73c5da0a");
+        assert!(event.error.is_empty());
+
+        let signer = HotSigner::new_from_mnemonics(Network::Regtest.into(), MNEMONIC).unwrap();
+        let (psbt, descriptor) = account_psbt(&signer, 1, 0);
+
+        manager.register_descriptor(descriptor.to_string());
+        let event = manager.poll_event();
+        assert_eq!(event.event, SignerEvent::DescriptorRegistered);
+        assert_eq!(event.value, descriptor.to_string());
+
+        manager.sign(Network::Regtest, psbt.to_string());
+        let event = manager.poll_event();
+        assert_eq!(event.event, SignerEvent::Signed);
+        assert_eq!(event.fingerprint, "73c5da0a");
+        let signed = Psbt::from_str(&event.value).unwrap();
+        assert!(!signed.inputs[0].partial_sigs.is_empty());
+
+        manager.sign(Network::Regtest, "psbt".to_string());
+        let event = manager.poll_event();
+        assert_eq!(event.event, SignerEvent::ManagerError);
+        assert!(event.fingerprint.is_empty());
+        assert_eq!(event.error, Error::ParsePsbt.to_string());
+        assert_eq!(manager.poll_event().event, SignerEvent::None);
+    }
+
     #[test]
     fn test_manager_hot_signer() {
         let mut manager = SigningManager::default();
@@ -1,7 +1,7 @@
 use joinstr::miniscript::bitcoin::{self, Txid};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fs::File,
     io::{Read, Write},
     path::PathBuf,
@@ -42,6 +42,20 @@ impl TxStore {
     /// # Parameters
     /// - `updates`: A vector of `Update` instances containing transactions to insert.
     pub fn insert_updates(&mut self, updates: Vec<Update>) {
+        self.insert_history_updates(updates, &BTreeSet::new());
+    }
+
+    /// Inserts a vector of updates into the transaction store, resolving
+    /// the conflicts against the current spk histories.
+    ///
+    /// An unconfirmed transaction replaced by an inserted one is dropped,
+    /// unless it is still in the histories while the inserted one is not:
+    /// the inserted one is then stale and dropped instead.
+    ///
+    /// # Parameters
+    /// - `updates`: A vector of `Update` instances containing transactions to insert.
+    /// - `history`: The txids currently in the spk histories.
+    pub fn insert_history_updates(&mut self, updates: Vec<Update>, history: &BTreeSet<Txid>) {
         // sanitize, all Txs must Some(_)
         updates.iter().for_each(|u| {
             assert!(u.is_complete());
@@ -54,11 +68,101 @@ impl TxStore {
                     tx: tx.expect("all txs populated"),
                     merkle: Default::default(),
                 };
-                self.store.insert(txid, entry);
+                if self.remove_conflicts(&entry.tx, history) {
+                    self.store.insert(txid, entry);
+                }
             }
         }
     }
 
+    /// Removes the unconfirmed transactions that spend an input of a given
+    /// transaction, i.e. the ones it replaces.
+    ///
+    /// # Parameters
+    /// - `tx`: The replacing transaction.
+    /// - `history`: The txids currently in the spk histories.
+    ///
+    /// # Returns
+    /// `false` if a conflicting transaction is in the histories while `tx`
+    /// is not, nothing is removed and `tx` must not be inserted.
+    fn remove_conflicts(&mut self, tx: &bitcoin::Transaction, history: &BTreeSet<Txid>) -> bool {
+        let txid = tx.compute_txid();
+        let spent: BTreeSet<_> = tx.input.iter().map(|i| i.previous_output).collect();
+        let conflicts: Vec<_> = self
+            .store
+            .iter()
+            .filter(|(id, entry)| {
+                **id != txid
+                    && entry.height.is_none()
+                    && entry
+                        .tx
+                        .input
+                        .iter()
+                        .any(|i| spent.contains(&i.previous_output))
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        if !history.contains(&txid) && conflicts.iter().any(|id| history.contains(id)) {
+            return false;
+        }
+        for id in conflicts {
+            self.store.remove(&id);
+        }
+        true
+    }
+
+    /// Returns the transactions that are not yet confirmed.
+    pub fn unconfirmed(&self) -> Vec<bitcoin::Transaction> {
+        self.store
+            .values()
+            .filter(|e| e.height.is_none())
+            .map(|e| e.tx.clone())
+            .collect()
+    }
+
+    /// Walks the unconfirmed ancestors of a transaction.
+    ///
+    /// The transaction itself is counted if unconfirmed, then every parent
+    /// transaction that is in the store and unconfirmed, recursively.
+    ///
+    /// # Parameters
+    /// - `txid`: The transaction ID of the transaction to start from.
+    ///
+    /// # Returns
+    /// A tuple containing the number of unconfirmed ancestors and the sum
+    /// of their fees in sats. The fee of an ancestor spending outputs that
+    /// are not in the store is unknown and not accounted.
+    pub fn unconfirmed_ancestors(&self, txid: &Txid) -> (usize, u64) {
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![*txid];
+        let mut fees = 0;
+        while let Some(txid) = pending.pop() {
+            let entry = match self.store.get(&txid) {
+                Some(e) if e.height.is_none() => e,
+                _ => continue,
+            };
+            if !visited.insert(txid) {
+                continue;
+            }
+            let mut inputs_total = Some(0);
+            for txin in &entry.tx.input {
+                let op = txin.previous_output;
+                let value = self
+                    .store
+                    .get(&op.txid)
+                    .and_then(|parent| parent.tx.output.get(op.vout as usize))
+                    .map(|txout| txout.value.to_sat());
+                inputs_total = inputs_total.zip(value).map(|(total, v)| total + v);
+                pending.push(op.txid);
+            }
+            let outputs_total: u64 = entry.tx.output.iter().map(|o| o.value.to_sat()).sum();
+            if let Some(inputs_total) = inputs_total {
+                fees += inputs_total.saturating_sub(outputs_total);
+            }
+        }
+        (visited.len(), fees)
+    }
+
     /// Updates an existing transaction entry in the store.
     ///
     /// # Parameters
@@ -79,6 +183,11 @@ impl TxStore {
         self.store.get(txid).map(|e| e.tx.clone())
     }
 
+    /// Removes all the transactions from the store.
+    pub fn clear(&mut self) {
+        self.store.clear();
+    }
+
     /// Removes a transaction from the store by its transaction ID.
     ///
     /// # Parameters
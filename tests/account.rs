@@ -3,14 +3,32 @@ use std::{collections::BTreeMap, str::FromStr, sync::Once, thread::sleep, time::
 
 use crate::utils::bootstrap_electrs;
 use cpp_joinstr::{
-    account::Account,
-    signer::{wpkh, HotSigner},
+    account::{Account, Error},
+    account_manager::AccountManager,
+    config::{
+        new_config, Tip, DUST_ATTACK_THRESHOLD, ELECTRUM_BATCH_SIZE, MAX_COINJOIN_EXCESS,
+        MAX_NOSTR_BACK, RECONNECT_INITIAL_DELAY_MS, RECONNECT_MAX_DELAY_MS, RECONNECT_MULTIPLIER,
+    },
+    cpp_joinstr::{
+        AddrAccount, CoinStatus, Network as CppNetwork, Output, PsbtOutputKind, RustCoin,
+        SignalFlag, TransactionTemplate,
+    },
+    derivator::Derivator,
+    pool_store::{MAX_POOL_FEE_RATE, MIN_POOL_FEE_RATE},
+    signer::{tr, wpkh, HotSigner},
     Config,
 };
-use electrsd::bitcoind::bitcoincore_rpc::RpcApi;
+use electrsd::{
+    bitcoind::{bitcoincore_rpc::RpcApi, BitcoinD},
+    ElectrsD,
+};
 use joinstr::{
     bip39::Mnemonic,
-    miniscript::bitcoin::{self, bip32::DerivationPath, Amount, Network},
+    miniscript::{
+        bitcoin::{self, bip32::DerivationPath, Amount, Network},
+        psbt::PsbtExt,
+        Descriptor, DescriptorPublicKey,
+    },
 };
 use utils::{dump_logs, generate, get_block_hash, get_block_height, reorg_chain, send_to_address};
 
@@ -50,6 +68,112 @@ where
     panic!("Timeout elapsed while waiting for condition.");
 }
 
+/// Drains the notifications of `account` until the electrum listener
+/// reports it started, panics after `timeout` seconds.
+pub fn wait_listener_started(account: &mut Account, timeout: u64) {
+    let start_time = std::time::Instant::now();
+    while start_time.elapsed().as_secs() < timeout {
+        let poll = account.try_recv();
+        if !poll.is_ok() {
+            sleep(Duration::from_millis(100));
+            continue;
+        }
+        let signal = poll.value();
+        if signal.is_ok() && signal.unwrap() == SignalFlag::TxListenerStarted {
+            return;
+        }
+    }
+    panic!("Timeout elapsed while waiting for the listener to start.");
+}
+
+/// Creates an account connected to a fresh regtest electrum server and
+/// funded with one confirmed coin per amount (in BTC).
+pub fn funded_account(name: &str, amounts: &[f64]) -> (Account, ElectrsD, BitcoinD) {
+    let (url, port, electrsd, bitcoind) = bootstrap_electrs();
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let mut config = new_config(mnemonic.to_string(), name.to_string(), CppNetwork::Regtest);
+    config.set_electrum_url(url);
+    config.set_electrum_port(port.to_string());
+    config.to_file().unwrap();
+    let mut account = Account::new(*config);
+    account.start_electrum();
+    sleep(Duration::from_millis(300));
+
+    for amount in amounts {
+        let addr = account.new_recv_addr();
+        send_to_address(&bitcoind, &addr, Amount::from_btc(*amount).unwrap());
+    }
+    generate(&bitcoind, 1);
+    wait_until_timeout(
+        || {
+            let coins = account.spendable_coins();
+            coins.confirmed_coins == amounts.len()
+        },
+        5,
+    );
+
+    (account, electrsd, bitcoind)
+}
+
+/// Creates an offline account w/o electrum server from a fresh mnemonic,
+/// its config is persisted under `name`.
+pub fn offline_account(name: &str) -> Account {
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let config = new_config(mnemonic.to_string(), name.to_string(), CppNetwork::Regtest);
+    config.to_file().unwrap();
+    Account::new_offline(*config)
+}
+
+/// Returns a new address of the bitcoind wallet, external to the tested
+/// accounts.
+pub fn external_address(bitcoind: &BitcoinD) -> bitcoin::Address {
+    bitcoind
+        .client
+        .get_new_address(None, None)
+        .unwrap()
+        .assume_checked()
+}
+
+/// Returns an unlabeled output paying `amount` sats to `address`.
+pub fn output(address: &bitcoin::Address, amount: u64) -> Output {
+    Output {
+        address: address.to_string(),
+        amount,
+        label: String::new(),
+        max: false,
+    }
+}
+
+/// Returns a template spending `coins` to `outputs` at `fee_sats_vb`, the
+/// coins are selected from the wallet if `coins` is empty.
+pub fn template(
+    coins: Vec<RustCoin>,
+    outputs: Vec<Output>,
+    fee_sats_vb: f64,
+) -> TransactionTemplate {
+    TransactionTemplate {
+        inputs: coins,
+        inputs_by_address: vec![],
+        min_input_value: 0,
+        confirmed_only: false,
+        outputs,
+        fee_sats: 0,
+        fee_sats_vb,
+    }
+}
+
+/// Prepares a PSBT spending all the spendable coins of `account` to
+/// `address`, the remaining is sent back to a change output.
+pub fn prepare_payment(account: &mut Account, address: &bitcoin::Address, amount: u64) -> String {
+    let psbt = account.prepare_transaction(template(
+        account.spendable_coins().coins,
+        vec![output(address, amount)],
+        2.0,
+    ));
+    assert!(psbt.is_ok());
+    psbt.value()
+}
+
 #[test]
 fn test_reorg() {
     setup_logger();
@@ -78,13 +202,37 @@ fn simple_wallet() {
     let mut config = Config {
         network: Network::Regtest,
         look_ahead,
+        recv_look_ahead: None,
+        change_look_ahead: None,
+        min_relay_fee: 1.0,
+        scan_from: Tip::default(),
         account: "account".into(),
         electrum_url: None,
         electrum_port: None,
+        electrum_timeout: 30,
+        electrum_batch_size: ELECTRUM_BATCH_SIZE,
+        reconnect_initial_delay_ms: RECONNECT_INITIAL_DELAY_MS,
+        reconnect_multiplier: RECONNECT_MULTIPLIER,
+        reconnect_max_delay_ms: RECONNECT_MAX_DELAY_MS,
         nostr_relay: None,
         nostr_back: None,
+        nostr_pool_authors: vec![],
+        enable_coinjoin: true,
         mnemonic: mnemonic.to_string(),
+        persist_mnemonic: true,
+        coinjoin_mnemonic: None,
+        coinjoin_keychain: AddrAccount::Receive,
+        reuse_unused_addresses: false,
+        consolidation_max_utxos: 50,
+        consolidation_min_savings: 10_000,
+        psbt_global_xpubs: false,
+        auto_new_recv_addr: false,
+        max_coinjoin_excess: MAX_COINJOIN_EXCESS,
+        min_change_sats: None,
+        dust_attack_threshold: DUST_ATTACK_THRESHOLD,
+        created_at: 0,
         descriptor,
+        secondary_descriptor: None,
     };
     config.network = Network::Regtest;
     config.look_ahead = look_ahead;
@@ -201,13 +349,37 @@ fn simple_reorg() {
     let mut config = Config {
         network: Network::Regtest,
         look_ahead,
+        recv_look_ahead: None,
+        change_look_ahead: None,
+        min_relay_fee: 1.0,
+        scan_from: Tip::default(),
         account: "account".into(),
         electrum_url: None,
         electrum_port: None,
+        electrum_timeout: 30,
+        electrum_batch_size: ELECTRUM_BATCH_SIZE,
+        reconnect_initial_delay_ms: RECONNECT_INITIAL_DELAY_MS,
+        reconnect_multiplier: RECONNECT_MULTIPLIER,
+        reconnect_max_delay_ms: RECONNECT_MAX_DELAY_MS,
         nostr_relay: None,
         nostr_back: None,
+        nostr_pool_authors: vec![],
+        enable_coinjoin: true,
         mnemonic: mnemonic.to_string(),
+        persist_mnemonic: true,
+        coinjoin_mnemonic: None,
+        coinjoin_keychain: AddrAccount::Receive,
+        reuse_unused_addresses: false,
+        consolidation_max_utxos: 50,
+        consolidation_min_savings: 10_000,
+        psbt_global_xpubs: false,
+        auto_new_recv_addr: false,
+        max_coinjoin_excess: MAX_COINJOIN_EXCESS,
+        min_change_sats: None,
+        dust_attack_threshold: DUST_ATTACK_THRESHOLD,
+        created_at: 0,
         descriptor,
+        secondary_descriptor: None,
     };
     config.network = Network::Regtest;
     config.look_ahead = look_ahead;
@@ -303,6 +475,1573 @@ fn simple_reorg() {
     // assert!(diff_heights);
 }
 
+#[test]
+fn coinjoin_disabled() {
+    setup_logger();
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let config = Config {
+        nostr_relay: Some("ws://127.0.0.1:1".into()),
+        nostr_back: Some(3600),
+        enable_coinjoin: false,
+        ..*new_config(
+            mnemonic.to_string(),
+            "coinjoin_disabled".into(),
+            CppNetwork::Regtest,
+        )
+    };
+    config.to_file().unwrap();
+    let mut account = Account::new(config);
+    account.start_nostr();
+    sleep(Duration::from_millis(300));
+
+    // the pool listener is never started
+    assert!(!account.is_pool_listener_running());
+
+    // pools related methods return empty values
+    assert_eq!(account.relay(), "");
+    let pools = account.pools();
+    assert!(pools.is_ok());
+    assert!(pools.value().is_empty());
+}
+
+#[test]
+fn verify_psbt() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("verify_psbt", &[0.1]);
+    let external = external_address(&bitcoind);
+    let psbt = prepare_payment(&mut account, &external, 1_000_000);
+
+    let verification = account.verify_psbt(psbt.clone());
+    assert!(verification.error.is_empty());
+    assert!(verification.inputs_ours);
+    assert!(verification.unexpected_spks.is_empty());
+    assert_eq!(verification.outputs.len(), 2);
+    assert!(verification.outputs.iter().any(|o| {
+        o.kind == PsbtOutputKind::External
+            && o.address == external.to_string()
+            && o.amount == 1_000_000
+    }));
+    assert!(verification
+        .outputs
+        .iter()
+        .any(|o| o.kind == PsbtOutputKind::Change));
+    let outputs_total: u64 = verification.outputs.iter().map(|o| o.amount).sum();
+    assert_eq!(verification.fee + outputs_total, 10_000_000);
+
+    // tamper the spent output
+    let mut tampered = bitcoin::Psbt::from_str(&psbt).unwrap();
+    tampered.inputs[0]
+        .witness_utxo
+        .as_mut()
+        .unwrap()
+        .script_pubkey = external.script_pubkey();
+    let verification = account.verify_psbt(tampered.to_string());
+    assert!(!verification.inputs_ours);
+    assert_eq!(verification.unexpected_spks, vec![external.to_string()]);
+
+    // a PSBT derived for mainnet is rejected
+    let mut mainnet = bitcoin::Psbt::from_str(&psbt).unwrap();
+    for (_, (_, path)) in mainnet.inputs[0].bip32_derivation.iter_mut() {
+        let mut children: Vec<_> = path.into_iter().cloned().collect();
+        children[1] = bitcoin::bip32::ChildNumber::Hardened { index: 0 };
+        *path = children.into();
+    }
+    let verification = account.verify_psbt(mainnet.to_string());
+    assert!(!verification.error.is_empty());
+}
+
+#[test]
+fn psbt_global_xpubs() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("psbt_global_xpubs", &[0.1]);
+    let external = external_address(&bitcoind);
+
+    // disabled by default
+    let psbt = prepare_payment(&mut account, &external, 1_000_000);
+    assert!(bitcoin::Psbt::from_str(&psbt).unwrap().xpub.is_empty());
+
+    account.set_psbt_global_xpubs(true);
+    assert!(account.get_config().psbt_global_xpubs());
+    let psbt = prepare_payment(&mut account, &external, 1_000_000);
+    let psbt = bitcoin::Psbt::from_str(&psbt).unwrap();
+    assert_eq!(psbt.xpub.len(), 1);
+    let (xpub, (fingerprint, path)) = psbt.xpub.iter().next().unwrap();
+    assert_eq!(path.to_string(), "84'/1'/0'");
+    assert_eq!(
+        format!("[{fingerprint}/{path}]{xpub}"),
+        account.cosigner_info()
+    );
+}
+
+#[test]
+fn resync() {
+    setup_logger();
+    let (mut account, _electrsd, _bitcoind) = funded_account("resync", &[0.1, 0.2]);
+    let coin = account.spendable_coins().coins[0].clone();
+    account.edit_coin_label(coin.outpoint.clone(), "kept".to_string());
+    let recv_tip = account.recv_tip();
+    // drop the notifications of the first listener
+    while account.try_recv().is_ok() {}
+
+    account.resync();
+    assert_eq!(account.recv_tip(), recv_tip);
+    wait_listener_started(&mut account, 5);
+
+    // the coins are fetched again from electrum
+    wait_until_timeout(|| account.spendable_coins().confirmed_coins == 2, 5);
+    let coins = account.spendable_coins().coins;
+    let relabeled = coins.iter().find(|c| c.outpoint == coin.outpoint).unwrap();
+    assert_eq!(relabeled.label, "kept");
+}
+
+#[test]
+fn coin_derivation_path() {
+    setup_logger();
+    let (account, _electrsd, _bitcoind) = funded_account("coin_derivation_path", &[0.1]);
+    let coin = account.spendable_coins().coins[0].clone();
+    assert_eq!(coin.address.account, AddrAccount::Receive);
+
+    // the key origin of the descriptor followed by the keychain & index
+    let info = account.cosigner_info();
+    let origin = info[1..info.find(']').unwrap()].split_once('/').unwrap().1;
+    assert_eq!(origin, "84'/1'/0'");
+    assert_eq!(
+        coin.address.derivation_path,
+        format!("m/{origin}/0/{}", coin.address.index)
+    );
+}
+
+#[test]
+fn verify_change_address() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("verify_change_address", &[0.1]);
+    let external = external_address(&bitcoind);
+    let psbt = prepare_payment(&mut account, &external, 1_000_000);
+
+    // the change output resolves to an owned change index
+    let change = account.verify_change_address(psbt.clone());
+    assert!(change.is_ok());
+    let change = change.value();
+    assert_eq!(change.len(), 1);
+    assert_eq!(change[0].account, AddrAccount::Change);
+    assert_eq!(change[0].address, account.change_addr_at(change[0].index));
+
+    // the derivation recorded in the PSBT must match
+    let mut tampered = bitcoin::Psbt::from_str(&psbt).unwrap();
+    let spk = bitcoin::Address::from_str(&change[0].address)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey();
+    let index = tampered
+        .unsigned_tx
+        .output
+        .iter()
+        .position(|o| o.script_pubkey == spk)
+        .unwrap();
+    for (_, (_, path)) in tampered.outputs[index].bip32_derivation.iter_mut() {
+        *path = path.extend([bitcoin::bip32::ChildNumber::from_normal_idx(1_000).unwrap()]);
+    }
+    assert!(account.verify_change_address(tampered.to_string()).is_err());
+
+    // a change output w/o any recorded derivation is rejected
+    tampered.outputs[index].bip32_derivation.clear();
+    assert!(account.verify_change_address(tampered.to_string()).is_err());
+
+    assert!(account.verify_change_address("psbt".to_string()).is_err());
+}
+
+#[test]
+fn simulated_change() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("simulated_change", &[0.01]);
+    let external = external_address(&bitcoind);
+    let payment = |amount| template(vec![], vec![output(&external, amount)], 2.0);
+
+    // the simulated change matches the change of the prepared PSBT
+    let simulation = account.simulate_transaction(payment(300_000));
+    assert!(simulation.has_change);
+    assert!(simulation.change_index >= 0);
+    let psbt = account.prepare_transaction(payment(300_000));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    let psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+    let change = &psbt.unsigned_tx.output[simulation.change_index as usize];
+    assert_ne!(change.script_pubkey, external.script_pubkey());
+    assert_eq!(change.value.to_sat(), simulation.change_amount);
+
+    // no change when the whole coin is spent
+    let mut no_change = payment(0);
+    no_change.inputs = account.spendable_coins().coins;
+    no_change.outputs[0].max = true;
+    let simulation = account.simulate_transaction(no_change);
+    assert!(simulation.spendable);
+    assert!(!simulation.has_change);
+    assert_eq!(simulation.change_amount, 0);
+    assert_eq!(simulation.change_index, -1);
+}
+
+#[test]
+fn min_change_sats() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("min_change_sats", &[0.01]);
+    let external = external_address(&bitcoind);
+    let coins = account.spendable_coins().coins;
+    let payment = || template(coins.clone(), vec![output(&external, 980_000)], 2.0);
+
+    // a change between the dust limit and the default threshold is kept
+    let simulation = account.simulate_transaction(payment());
+    assert!(simulation.spendable, "{}", simulation.error);
+    assert!(simulation.has_change);
+    assert!(simulation.change_amount > 5_000);
+    assert!(simulation.change_amount < 20_000);
+
+    // it is added to the fees once below the threshold
+    account.set_min_change_sats(50_000);
+    assert_eq!(account.get_config().min_change_sats(), 50_000);
+    let simulation = account.simulate_transaction(payment());
+    assert!(simulation.spendable, "{}", simulation.error);
+    assert!(!simulation.has_change);
+    let psbt = account.prepare_transaction(payment());
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    let psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+    assert_eq!(psbt.unsigned_tx.output.len(), 1);
+    assert_eq!(psbt.fee().unwrap().to_sat(), 20_000);
+
+    // a threshold below the dust limit fallback to the dust limit
+    account.set_min_change_sats(1);
+    let simulation = account.simulate_transaction(payment());
+    assert!(simulation.has_change);
+}
+
+#[test]
+fn change_larger_than_payments() {
+    setup_logger();
+    let (account, _electrsd, bitcoind) = funded_account("change_larger_than_payments", &[0.01]);
+    let external = external_address(&bitcoind);
+    let payment = |amount| template(vec![], vec![output(&external, amount)], 2.0);
+
+    // the change is larger than the payment
+    let simulation = account.simulate_transaction(payment(300_000));
+    assert!(simulation.has_change);
+    assert!(simulation.change_larger_than_payments);
+
+    // the payment is larger than the change
+    let simulation = account.simulate_transaction(payment(800_000));
+    assert!(simulation.has_change);
+    assert!(!simulation.change_larger_than_payments);
+}
+
+#[test]
+fn simulated_fee() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("simulated_fee", &[0.01]);
+    let external = external_address(&bitcoind);
+    let coins = account.spendable_coins().coins;
+    let payment = || template(coins.clone(), vec![output(&external, 300_000)], 3.0);
+
+    let simulation = account.simulate_transaction(payment());
+    assert!(simulation.has_change);
+    let simulated_fee = 1_000_000 - 300_000 - simulation.change_amount;
+    assert_eq!(
+        simulated_fee,
+        (3.0 * simulation.estimated_weight as f64).ceil() as u64
+    );
+
+    let psbt = account.prepare_transaction(payment());
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    let psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+
+    // the dummy change spk weighs as much as the real change spk, so the
+    // simulated fee is the fee of the prepared transaction
+    let change = &psbt.unsigned_tx.output[simulation.change_index as usize];
+    assert_eq!(account.dummy_spk().len(), change.script_pubkey.len());
+    assert_eq!(psbt.fee().unwrap().to_sat(), simulated_fee);
+}
+
+#[test]
+fn max_spendable() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("max_spendable", &[0.01, 0.02]);
+    let external = external_address(&bitcoind);
+    let max = account.max_spendable(external.to_string(), 2.0).unwrap();
+
+    // the max amount is the balance minus the fee of the sweep
+    let sweep = Output {
+        max: true,
+        ..output(&external, 0)
+    };
+    let psbt =
+        account.prepare_transaction(template(account.spendable_coins().coins, vec![sweep], 2.0));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    let psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+    let fee = psbt.fee().unwrap().to_sat();
+    assert!(fee > 0);
+    assert_eq!(max, 3_000_000 - fee);
+    assert_eq!(psbt.unsigned_tx.output.len(), 1);
+    assert_eq!(psbt.unsigned_tx.output[0].value.to_sat(), max);
+
+    // a higher fee rate delivers less
+    assert!(account.max_spendable(external.to_string(), 10.0).unwrap() < max);
+
+    assert!(matches!(
+        account.max_spendable("address".to_string(), 2.0),
+        Err(Error::InvalidAddress)
+    ));
+    // the fee exceeds the balance
+    assert!(matches!(
+        account.max_spendable(external.to_string(), 100_000.0),
+        Err(Error::Transaction(_))
+    ));
+    // the bridged result carries the same amount or error
+    let result = account.max_spendable_result(external.to_string(), 2.0);
+    assert!(result.is_ok());
+    assert_eq!(result.value(), max);
+    assert!(account
+        .max_spendable_result("address".to_string(), 2.0)
+        .is_err());
+}
+
+#[test]
+fn fee_headroom() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("fee_headroom", &[0.01, 0.02]);
+    let external = external_address(&bitcoind);
+    let coins = account.spendable_coins().coins;
+    let payment = |amount| template(coins.clone(), vec![output(&external, amount)], 2.0);
+
+    let headroom = account.fee_headroom(&payment(1_000_000)).unwrap();
+    assert_eq!(headroom, 2_000_000);
+
+    // the reserve is split between the fee and the change
+    let psbt = account.prepare_transaction(payment(1_000_000));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    let psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+    let fee = psbt.fee().unwrap().to_sat();
+    let change: u64 = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .filter(|o| o.script_pubkey != external.script_pubkey())
+        .map(|o| o.value.to_sat())
+        .sum();
+    assert_eq!(fee + change, headroom);
+
+    // the inputs do not cover the outputs
+    assert!(matches!(
+        account.fee_headroom(&payment(4_000_000)),
+        Err(Error::Transaction(_))
+    ));
+    let result = account.fee_headroom_result(payment(1_000_000));
+    assert!(result.is_ok());
+    assert_eq!(result.value(), headroom);
+    assert!(account.fee_headroom_result(payment(4_000_000)).is_err());
+}
+
+#[test]
+fn uneconomical_coins() {
+    setup_logger();
+    let (account, _electrsd, _bitcoind) = funded_account("uneconomical_coins", &[0.00001, 0.01]);
+
+    // at a low fee rate every coin is worth spending
+    assert!(account.uneconomical_coins(1.0).is_empty());
+
+    // at a high fee rate the small coin costs more than its value
+    let coins = account.uneconomical_coins(100.0);
+    assert_eq!(coins.len(), 1);
+    assert_eq!(coins[0].value, 1_000);
+}
+
+#[test]
+fn should_consolidate() {
+    setup_logger();
+    let (account, _electrsd, _bitcoind) = funded_account("should_consolidate", &[0.0001; 20]);
+
+    // many small coins at a low fee rate are cheaper to spend now
+    assert!(account.should_consolidate(1.0));
+
+    // no savings above the long term fee rate
+    assert!(!account.should_consolidate(10.0));
+    assert!(!account.should_consolidate(0.0));
+}
+
+#[test]
+fn min_relay_fee() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("min_relay_fee", &[0.1]);
+    account.set_min_relay_fee("5".to_string());
+    let external = external_address(&bitcoind);
+    let coins = account.spendable_coins().coins;
+    let payment = |fee_sats, fee_sats_vb| TransactionTemplate {
+        fee_sats,
+        ..template(
+            coins.clone(),
+            vec![output(&external, 1_000_000)],
+            fee_sats_vb,
+        )
+    };
+
+    // fee rate below the relay floor
+    let simulation = account.simulate_transaction(payment(0, 2.0));
+    assert!(!simulation.spendable);
+    assert!(simulation.error.contains("minimum relay fee"));
+
+    // absolute fee below the relay floor
+    let simulation = account.simulate_transaction(payment(200, 0.0));
+    assert!(!simulation.spendable);
+    assert!(simulation.error.contains("minimum relay fee"));
+
+    // above the relay floor
+    assert!(account.simulate_transaction(payment(0, 6.0)).spendable);
+    assert!(account.simulate_transaction(payment(5_000, 0.0)).spendable);
+}
+
+#[test]
+fn confirmed_only() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("confirmed_only", &[0.01]);
+    let addr = account.new_recv_addr();
+    send_to_address(&bitcoind, &addr, Amount::from_btc(0.005).unwrap());
+    wait_until_timeout(|| account.spendable_coins().unconfirmed_coins == 1, 5);
+    let external = external_address(&bitcoind);
+    let payment = |inputs, amount, confirmed_only| TransactionTemplate {
+        confirmed_only,
+        ..template(inputs, vec![output(&external, amount)], 2.0)
+    };
+    let coins = account.spendable_coins().coins;
+    let confirmed = coins.iter().find(|c| c.confirmed).unwrap().clone();
+
+    // an unconfirmed input is rejected
+    let psbt = account.prepare_transaction(payment(coins.clone(), 1_200_000, true));
+    assert!(psbt.is_err());
+    assert!(psbt.error().contains("not confirmed"));
+    let psbt = account.prepare_transaction(payment(coins, 1_200_000, false));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+
+    // unconfirmed coins are excluded from the auto selection
+    let simulation = account.simulate_transaction(payment(vec![], 1_200_000, true));
+    assert!(!simulation.spendable);
+    assert!(simulation.error.contains("Not enough funds"));
+    let psbt = account.prepare_transaction(payment(vec![], 500_000, true));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    let psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+    assert_eq!(psbt.unsigned_tx.input.len(), 1);
+    assert_eq!(
+        psbt.unsigned_tx.input[0].previous_output.to_string(),
+        confirmed.outpoint
+    );
+}
+
+#[test]
+fn coin_selection() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) =
+        funded_account("coin_selection", &[0.01, 0.002, 0.0005]);
+    let external = external_address(&bitcoind);
+    let payment = |amount, max| {
+        let outputs = vec![Output {
+            max,
+            ..output(&external, amount)
+        }];
+        template(vec![], outputs, 2.0)
+    };
+
+    // 0.01 + 0.0005 covers the payment w/o change
+    let simulation = account.simulate_transaction(payment(1_048_000, false));
+    assert!(simulation.spendable, "{}", simulation.error);
+    assert!(!simulation.has_change);
+
+    // no changeless combination
+    let simulation = account.simulate_transaction(payment(500_000, false));
+    assert!(simulation.spendable, "{}", simulation.error);
+    assert!(simulation.has_change);
+
+    let simulation = account.simulate_transaction(payment(2_000_000, false));
+    assert!(!simulation.spendable);
+    assert!(simulation.error.contains("Not enough funds"));
+
+    // coins are not selected for a maxed output
+    let simulation = account.simulate_transaction(payment(0, true));
+    assert!(!simulation.spendable);
+    assert_eq!(simulation.error, "No inputs!");
+}
+
+#[test]
+fn duplicate_inputs() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("duplicate_inputs", &[0.01]);
+    let external = external_address(&bitcoind);
+    let coin = account.spendable_coins().coins.remove(0);
+    let sweep = || Output {
+        max: true,
+        ..output(&external, 0)
+    };
+    let payment = || template(vec![coin.clone(), coin.clone()], vec![sweep()], 2.0);
+
+    // the same coin listed twice is rejected
+    let simulation = account.simulate_transaction(payment());
+    assert!(!simulation.spendable);
+    assert!(simulation.error.contains("Duplicate input"));
+    let psbt = account.prepare_transaction(payment());
+    assert!(psbt.is_err());
+    assert!(psbt.error().contains(&coin.outpoint));
+}
+
+#[test]
+fn inputs_by_address() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("inputs_by_address", &[0.01, 0.002]);
+
+    // a second coin is received at the address of the first one
+    let coins = account.spendable_coins().coins;
+    let first = coins.iter().find(|c| c.value == 1_000_000).unwrap();
+    let address = bitcoin::Address::from_str(&first.address.address)
+        .unwrap()
+        .assume_checked();
+    send_to_address(&bitcoind, &address, Amount::from_btc(0.003).unwrap());
+    generate(&bitcoind, 1);
+    wait_until_timeout(|| account.spendable_coins().confirmed_coins == 3, 5);
+
+    let external = external_address(&bitcoind);
+    let sweep = || Output {
+        max: true,
+        ..output(&external, 0)
+    };
+    let payment = |inputs_by_address, min_input_value| TransactionTemplate {
+        inputs_by_address,
+        min_input_value,
+        ..template(vec![], vec![sweep()], 2.0)
+    };
+    let spent = |psbt: &str| {
+        let psbt = bitcoin::Psbt::from_str(psbt).unwrap();
+        let mut spent: Vec<_> = psbt
+            .inputs
+            .iter()
+            .map(|i| i.witness_utxo.as_ref().unwrap().value.to_sat())
+            .collect();
+        spent.sort();
+        spent
+    };
+
+    // all the coins at the address are spent
+    let psbt = account.prepare_transaction(payment(vec![address.to_string()], 0));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    assert_eq!(spent(&psbt.value()), vec![300_000, 1_000_000]);
+
+    // the coins over a value
+    let psbt = account.prepare_transaction(payment(vec![], 250_000));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    assert_eq!(spent(&psbt.value()), vec![300_000, 1_000_000]);
+
+    // both criteria apply
+    let psbt = account.prepare_transaction(payment(vec![address.to_string()], 500_000));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    assert_eq!(spent(&psbt.value()), vec![1_000_000]);
+
+    // no coin matches
+    let psbt = account.prepare_transaction(payment(vec![external.to_string()], 0));
+    assert!(psbt.is_err());
+}
+
+#[test]
+fn unconfirmed_inputs() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("unconfirmed_inputs", &[0.01]);
+    let confirmed = account.spendable_coins().coins;
+
+    // receive a coin that stays unconfirmed
+    let addr = account.new_recv_addr();
+    send_to_address(&bitcoind, &addr, Amount::from_btc(0.02).unwrap());
+    wait_until_timeout(|| account.spendable_coins().unconfirmed_coins == 1, 5);
+    let coins = account.spendable_coins().coins;
+    let unconfirmed: Vec<_> = coins.into_iter().filter(|c| !c.confirmed).collect();
+
+    let external = external_address(&bitcoind);
+    let payment = |inputs| template(inputs, vec![output(&external, 500_000)], 2.0);
+
+    let simulation = account.simulate_transaction(payment(confirmed));
+    assert!(simulation.spendable, "{}", simulation.error);
+    assert!(!simulation.has_unconfirmed_inputs);
+
+    let simulation = account.simulate_transaction(payment(unconfirmed));
+    assert!(simulation.spendable, "{}", simulation.error);
+    assert!(simulation.has_unconfirmed_inputs);
+}
+
+#[test]
+fn stop_progress() {
+    setup_logger();
+    let (mut account, _electrsd, _bitcoind) = funded_account("stop_progress", &[]);
+    // the electrum listener is running
+    assert!(!account.is_stopped());
+
+    account.stop();
+    wait_until_timeout(|| account.is_stopped(), 5);
+    assert!(account.is_stopped());
+}
+
+#[test]
+fn offline_mode() {
+    setup_logger();
+    let name = "offline_account";
+    let (mut account, _electrsd, bitcoind) = funded_account(name, &[0.01, 0.02]);
+    let coins = account.spendable_coins();
+    account.stop();
+    wait_until_timeout(|| account.is_stopped(), 5);
+    drop(account);
+
+    let mut offline = Account::new_offline(Config::from_file(name.to_string()));
+    // no listener is started, even on request
+    offline.start_electrum();
+    offline.start_nostr();
+    assert!(offline.is_stopped());
+
+    // the wallet is loaded from the persisted state
+    let offline_coins = offline.spendable_coins();
+    assert_eq!(offline_coins.confirmed_coins, 2);
+    assert_eq!(offline_coins.confirmed_balance, coins.confirmed_balance);
+
+    let external = external_address(&bitcoind);
+    let psbt = prepare_payment(&mut offline, &external, 1_000_000);
+    let psbt = bitcoin::Psbt::from_str(&psbt).unwrap();
+    assert_eq!(psbt.inputs.len(), 2);
+    let signed = offline.sign_message(offline.recv_addr_at(0), "offline".to_string());
+    assert!(signed.is_ok());
+    assert!(offline.is_stopped());
+}
+
+#[test]
+fn import_transactions() {
+    setup_logger();
+    let (account, _electrsd, bitcoind) = funded_account("import_transactions", &[0.01, 0.02]);
+    let mut config = account.get_config();
+    config.account = "import_transactions_offline".to_string();
+    let _ = std::fs::remove_dir_all(Config::path(config.account.clone()));
+    config.to_file().unwrap();
+    let mut offline = Account::new_offline(*config);
+    assert_eq!(offline.spendable_coins().coins.len(), 0);
+
+    let exported = account.export_transactions();
+    let mut txs: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&exported).unwrap();
+    assert_eq!(txs.len(), 2);
+
+    // a transaction not paying to the wallet is skipped
+    let external = external_address(&bitcoind);
+    let mut entry = txs.values().next().unwrap().clone();
+    let mut tx: bitcoin::Transaction = serde_json::from_value(entry["tx"].clone()).unwrap();
+    tx.output
+        .iter_mut()
+        .for_each(|o| o.script_pubkey = external.script_pubkey());
+    entry["tx"] = serde_json::to_value(&tx).unwrap();
+    txs.insert(tx.compute_txid().to_string(), entry);
+
+    let imported = offline.import_transactions(serde_json::Value::Object(txs).to_string());
+    assert!(imported.is_ok());
+    assert_eq!(imported.value(), 2);
+
+    let coins = offline.spendable_coins();
+    assert_eq!(coins.confirmed_coins, 2);
+    assert_eq!(
+        coins.confirmed_balance,
+        account.spendable_coins().confirmed_balance
+    );
+    assert_eq!(offline.export_transactions(), exported);
+
+    assert!(offline.import_transactions("not json".to_string()).is_err());
+}
+
+#[test]
+fn auto_new_recv_addr() {
+    setup_logger();
+    let (url, port, _electrsd, bitcoind) = bootstrap_electrs();
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let mut config = new_config(
+        mnemonic.to_string(),
+        "auto_new_recv_addr".to_string(),
+        CppNetwork::Regtest,
+    );
+    config.set_electrum_url(url);
+    config.set_electrum_port(port.to_string());
+    config.set_auto_new_recv_addr(true);
+    config.to_file().unwrap();
+    let mut account = Account::new(*config);
+    sleep(Duration::from_millis(300));
+
+    let addr = account.new_recv_addr();
+    let tip = account.recv_tip();
+    assert_eq!(addr, account.recv_at(tip));
+
+    // a payment to the last generated address advances the tip
+    send_to_address(&bitcoind, &addr, Amount::from_btc(0.01).unwrap());
+    wait_until_timeout(|| account.spendable_coins().coins.len() == 1, 5);
+    assert_eq!(account.recv_tip(), tip + 1);
+
+    // a payment to an older address does not
+    let older = account.recv_at(tip - 1);
+    send_to_address(&bitcoind, &older, Amount::from_btc(0.01).unwrap());
+    wait_until_timeout(|| account.spendable_coins().coins.len() == 2, 5);
+    assert_eq!(account.recv_tip(), tip + 1);
+}
+
+#[test]
+fn missing_statuses_resubscribe() {
+    setup_logger();
+    let name = "missing_statuses_resubscribe";
+    let (mut account, _electrsd, _bitcoind) = funded_account(name, &[0.1, 0.2]);
+    let (recv_watch_tip, change_watch_tip) = (account.recv_watch_tip(), account.change_watch_tip());
+    account.stop();
+    drop(account);
+    sleep(Duration::from_millis(500));
+
+    // keep tip.json but lose statuses.json
+    let config = Config::from_file(name.to_string());
+    assert!(config.tip_path().exists());
+    std::fs::remove_file(config.statuses_path()).unwrap();
+
+    let account = Account::new(config.clone());
+    wait_until_timeout(|| account.spendable_coins().confirmed_coins == 2, 5);
+    assert_eq!(account.recv_watch_tip(), recv_watch_tip);
+    assert_eq!(account.change_watch_tip(), change_watch_tip);
+    assert_eq!(
+        config.statuses_from_file().len() as u32,
+        recv_watch_tip + change_watch_tip
+    );
+}
+
+#[test]
+fn persist_mnemonic() {
+    setup_logger();
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let name = "persist_mnemonic".to_string();
+    let mut config = new_config(mnemonic.to_string(), name.clone(), CppNetwork::Regtest);
+    assert!(config.persist_mnemonic());
+    config.set_persist_mnemonic(false);
+    config.to_file().unwrap();
+
+    // the mnemonic is not written to disk
+    let mut path = Config::path(name.clone());
+    path.push("config.json");
+    let content = std::fs::read_to_string(path).unwrap();
+    assert!(!content.contains(&mnemonic.to_string()));
+
+    // the config still loads as watch-only
+    let loaded = Config::from_file(name.clone());
+    assert_eq!(loaded.account, name);
+    assert!(loaded.mnemonic.is_empty());
+    assert!(!loaded.persist_mnemonic());
+    assert_eq!(loaded.descriptor, config.descriptor);
+    let derivator = Derivator::new(loaded.descriptor.clone(), Network::Regtest).unwrap();
+    let mut account = Account::new(loaded);
+    let address = account.new_recv_addr();
+    assert_eq!(address, derivator.receive_at(account.recv_tip()));
+
+    // a locked account can neither sign nor export its mnemonic
+    let address = address.to_string();
+    assert!(account
+        .sign_message(address.clone(), "locked".to_string())
+        .is_err());
+    assert!(account.export_backup(true).is_err());
+    assert!(account.export_backup(false).is_ok());
+
+    // only the mnemonic of the account unlocks it
+    let other = Mnemonic::generate(12).unwrap();
+    assert!(account.unlock(other.to_string()).is_err());
+    assert!(account.unlock("not a mnemonic".to_string()).is_err());
+    account.unlock(mnemonic.to_string()).unwrap();
+    assert!(account
+        .sign_message(address, "unlocked".to_string())
+        .is_ok());
+    assert!(account
+        .export_backup(true)
+        .unwrap()
+        .contains(&mnemonic.to_string()));
+
+    // the mnemonic is still not written to disk
+    assert!(Config::from_file(name).mnemonic.is_empty());
+}
+
+#[test]
+fn coinjoin_output_keychain() {
+    setup_logger();
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let mut config = new_config(
+        mnemonic.to_string(),
+        "coinjoin_output_keychain".to_string(),
+        CppNetwork::Regtest,
+    );
+    assert_eq!(config.coinjoin_keychain(), AddrAccount::Receive);
+    config.set_coinjoin_keychain(AddrAccount::Change);
+    config.to_file().unwrap();
+    let derivator = Derivator::new(config.descriptor.clone(), Network::Regtest).unwrap();
+    let mut account = Account::new(*config);
+
+    let (recv_tip, change_tip) = (account.recv_tip(), account.change_tip());
+    let address = account.coinjoin_output_addr(false);
+
+    // the output address is derived from the change keychain and the
+    // receive keychain is left untouched
+    assert_eq!(account.recv_tip(), recv_tip);
+    assert_eq!(account.change_tip(), change_tip + 1);
+    assert_eq!(address, derivator.change_at(account.change_tip()));
+}
+
+#[test]
+fn coinjoin_output_type() {
+    setup_logger();
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let signer =
+        HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string()).unwrap();
+    let tr_descriptor = tr(signer.xpub(&DerivationPath::from_str("m/86'/0'/0'/1'").unwrap()));
+
+    // a tr account contributes a tr output
+    let mut config = new_config(
+        mnemonic.to_string(),
+        "coinjoin_output_type_tr".to_string(),
+        CppNetwork::Regtest,
+    );
+    config.descriptor = tr_descriptor.clone();
+    config.to_file().unwrap();
+    let mut account = Account::new(*config);
+    assert!(account
+        .coinjoin_output_addr(false)
+        .script_pubkey()
+        .is_p2tr());
+
+    // a coin of the secondary descriptor is mixed into an output of the
+    // secondary descriptor, at the next index of its own tip
+    let mut config = new_config(
+        mnemonic.to_string(),
+        "coinjoin_output_type_secondary".to_string(),
+        CppNetwork::Regtest,
+    );
+    config.secondary_descriptor = Some(tr_descriptor.clone());
+    config.to_file().unwrap();
+    let derivator = Derivator::new(tr_descriptor, Network::Regtest).unwrap();
+    let mut account = Account::new(*config);
+    assert!(account
+        .coinjoin_output_addr(false)
+        .script_pubkey()
+        .is_p2wpkh());
+    let recv_tip = account.recv_tip();
+    let address = account.coinjoin_output_addr(true);
+    assert!(address.script_pubkey().is_p2tr());
+    assert_eq!(address, derivator.receive_at(1));
+    assert_eq!(account.coinjoin_output_addr(true), derivator.receive_at(2));
+    assert_eq!(account.recv_tip(), recv_tip);
+}
+
+#[test]
+fn join_pool_secondary_coin() {
+    setup_logger();
+    let (url, port, _electrsd, bitcoind) = bootstrap_electrs();
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let signer =
+        HotSigner::new_from_mnemonics(bitcoin::Network::Regtest, &mnemonic.to_string()).unwrap();
+    let tr_descriptor = tr(signer.xpub(&DerivationPath::from_str("m/86'/1'/0'").unwrap()));
+    let mut config = new_config(
+        mnemonic.to_string(),
+        "join_pool_secondary_coin".to_string(),
+        CppNetwork::Regtest,
+    );
+    config.set_electrum_url(url);
+    config.set_electrum_port(port.to_string());
+    config.secondary_descriptor = Some(tr_descriptor.clone());
+    config.to_file().unwrap();
+    let mut account = Account::new(*config);
+    account.start_electrum();
+    sleep(Duration::from_millis(300));
+
+    // a coin received on the secondary descriptor
+    let derivator = Derivator::new(tr_descriptor, Network::Regtest).unwrap();
+    send_to_address(
+        &bitcoind,
+        &derivator.receive_at(0),
+        Amount::from_btc(0.01).unwrap(),
+    );
+    generate(&bitcoind, 1);
+    wait_until_timeout(|| account.spendable_coins().confirmed_coins == 1, 5);
+    let coin = account.spendable_coins().coins.pop().unwrap();
+
+    // the coinjoin signer only signs wpkh inputs, the coin is refused
+    // before any coinjoin output is generated
+    let recv_tip = account.recv_tip();
+    assert!(matches!(
+        account.rust_join_pool(coin.outpoint.clone(), "pool".to_string()),
+        Err(Error::CoinjoinSigner)
+    ));
+    assert!(matches!(
+        account.rust_create_pool(coin.outpoint, 100_000, MIN_POOL_FEE_RATE, 60, 2),
+        Err(Error::CoinjoinSigner)
+    ));
+    assert_eq!(account.recv_tip(), recv_tip);
+    assert_eq!(account.coinjoin_output_addr(true), derivator.receive_at(1));
+}
+
+#[test]
+fn create_pool_fee_range() {
+    setup_logger();
+    let mut account = offline_account("create_pool_fee_range");
+    let mut create_pool = |fee| account.rust_create_pool("".into(), 100_000, fee, 60, 2);
+
+    // out of range fees are rejected before anything else is checked
+    assert!(matches!(create_pool(0), Err(Error::InvalidPoolFee)));
+    assert!(matches!(
+        create_pool(MAX_POOL_FEE_RATE + 1),
+        Err(Error::InvalidPoolFee)
+    ));
+    assert!(matches!(
+        create_pool(MIN_POOL_FEE_RATE),
+        Err(Error::InvalidOutPoint)
+    ));
+    assert!(matches!(
+        create_pool(MAX_POOL_FEE_RATE),
+        Err(Error::InvalidOutPoint)
+    ));
+}
+
+#[test]
+fn account_manager() {
+    setup_logger();
+    let (url, port, _electrsd, bitcoind) = bootstrap_electrs();
+    let names = ["account_manager_a", "account_manager_b"];
+    let mut manager = AccountManager::default();
+    for name in names {
+        let mnemonic = Mnemonic::generate(12).unwrap();
+        let mut config = new_config(mnemonic.to_string(), name.to_string(), CppNetwork::Regtest);
+        config.set_electrum_url(url.clone());
+        config.set_electrum_port(port.to_string());
+        config.to_file().unwrap();
+        assert!(manager.add_account(Account::new(*config.clone())));
+        // an account is managed only once
+        assert!(!manager.add_account(Account::new(*config)));
+    }
+    assert_eq!(manager.accounts(), names.to_vec());
+
+    for (name, amount) in names.into_iter().zip([0.01, 0.02]) {
+        let account = manager.account_mut(name).unwrap();
+        account.start_electrum();
+        let addr = account.new_recv_addr();
+        send_to_address(&bitcoind, &addr, Amount::from_btc(amount).unwrap());
+    }
+    generate(&bitcoind, 1);
+
+    // notifications of both accounts are received tagged with the account
+    let mut started = vec![];
+    let start = std::time::Instant::now();
+    while started.len() < names.len() && start.elapsed().as_secs() < 5 {
+        let poll = manager.try_recv();
+        if poll.is_ok() {
+            let signal = poll.value();
+            if signal.is_ok() && signal.unwrap() == SignalFlag::TxListenerStarted {
+                started.push(signal.account());
+            }
+        } else {
+            sleep(Duration::from_millis(100));
+        }
+    }
+    started.sort();
+    assert_eq!(started, names.to_vec());
+
+    wait_until_timeout(|| manager.total_balance() == 3_000_000, 5);
+    assert_eq!(manager.total_balance(), 3_000_000);
+
+    // the bridged accessor reaches the same account
+    let account = manager.managed_account(names[1].to_string()).unwrap();
+    assert_eq!(account.get_config().account, names[1]);
+    assert!(manager.managed_account("unknown".to_string()).is_err());
+
+    assert!(manager.remove_account(names[0].to_string()));
+    assert!(!manager.remove_account(names[0].to_string()));
+    assert_eq!(manager.total_balance(), 2_000_000);
+    assert!(manager.managed_account(names[0].to_string()).is_err());
+}
+
+#[test]
+fn nostr_back_bounds() {
+    setup_logger();
+    let name = "nostr_back_bounds";
+    let mut account = offline_account(name);
+    let relay = "ws://127.0.0.1:1".to_string();
+    let errors = |account: &mut Account| {
+        std::iter::from_fn(|| {
+            let poll = account.try_recv();
+            poll.is_ok().then(|| poll.value())
+        })
+        .map(|signal| signal.error())
+        .filter(|e| !e.is_empty())
+        .collect::<Vec<_>>()
+    };
+    errors(&mut account);
+
+    // out of range lookbacks are rejected
+    for back in ["0".to_string(), (MAX_NOSTR_BACK + 1).to_string()] {
+        account.set_nostr(relay.clone(), back);
+        let errors = errors(&mut account);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Invalid nostr lookback"));
+        assert_eq!(account.get_config().nostr_back(), "");
+    }
+
+    // a valid lookback is persisted
+    account.set_nostr(relay.clone(), "3600".to_string());
+    assert!(errors(&mut account).is_empty());
+    assert_eq!(account.get_config().nostr_back(), "3600");
+    assert_eq!(account.get_config().nostr_url(), relay);
+    assert_eq!(Config::from_file(name.to_string()).nostr_back, Some(3600));
+}
+
+#[test]
+fn single_descriptors() {
+    setup_logger();
+    let account = offline_account("single_descriptors");
+
+    let recv = account.receive_descriptor();
+    let change = account.change_descriptor();
+    assert_ne!(recv, change);
+
+    // only the keychain index differs
+    let strip = |d: &str| d.split('#').next().unwrap().to_string();
+    assert!(strip(&recv).contains("/0/*"));
+    assert_eq!(strip(&recv).replace("/0/*", "/1/*"), strip(&change));
+
+    // both re-parse and derive the account addresses
+    let recv = Descriptor::<DescriptorPublicKey>::from_str(&recv).unwrap();
+    let change = Descriptor::<DescriptorPublicKey>::from_str(&change).unwrap();
+    let derive = |d: &Descriptor<DescriptorPublicKey>, index| {
+        d.at_derivation_index(index)
+            .unwrap()
+            .address(Network::Regtest)
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(derive(&recv, 3), account.recv_addr_at(3));
+    assert_eq!(derive(&change, 3), account.change_addr_at(3));
+}
+
+#[test]
+fn cosigner_info() {
+    setup_logger();
+    let account = offline_account("cosigner_info");
+
+    // the key expression is a single xpub with its origin
+    let info = account.cosigner_info();
+    let key = DescriptorPublicKey::from_str(&info).unwrap();
+    assert!(matches!(key, DescriptorPublicKey::XPub(_)));
+    let mnemonic = account.get_config().mnemonic;
+    let signer = HotSigner::new_from_mnemonics(Network::Regtest, &mnemonic).unwrap();
+    assert_eq!(key.master_fingerprint(), signer.fingerprint());
+    assert!(account.receive_descriptor().contains(&info));
+
+    // it derives the account keys
+    let recv = Descriptor::<DescriptorPublicKey>::from_str(&format!("wpkh({info}/0/*)")).unwrap();
+    let address = recv
+        .at_derivation_index(4)
+        .unwrap()
+        .address(Network::Regtest)
+        .unwrap();
+    assert_eq!(address.to_string(), account.recv_addr_at(4));
+}
+
+#[test]
+fn final_transactions() {
+    setup_logger();
+    let name = "final_transactions";
+    let txid = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".to_string();
+
+    {
+        let mut account = offline_account(name);
+        assert!(!account.is_tx_final(txid.clone()));
+        account.set_tx_final(txid.clone(), true);
+        assert!(account.is_tx_final(txid.clone()));
+        // an invalid txid is ignored
+        account.set_tx_final("not a txid".to_string(), true);
+        assert!(!account.is_tx_final("not a txid".to_string()));
+    }
+
+    // the flag is persisted
+    let mut account = Account::new_offline(Config::from_file(name.to_string()));
+    assert!(account.is_tx_final(txid.clone()));
+    account.set_tx_final(txid.clone(), false);
+    assert!(!account.is_tx_final(txid.clone()));
+
+    let account = Account::new_offline(Config::from_file(name.to_string()));
+    assert!(!account.is_tx_final(txid));
+}
+
+#[test]
+fn sign_message() {
+    setup_logger();
+    let account = offline_account("sign_message");
+
+    let signed = account.sign_message(account.recv_addr_at(2), "Hello World".to_string());
+    assert!(signed.is_ok());
+    assert!(!signed.value().is_empty());
+    let signed = account.sign_message(account.change_addr_at(1), "Hello World".to_string());
+    assert!(signed.is_ok());
+
+    // an address that is not part of the wallet
+    let signed = account.sign_message(
+        "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080".to_string(),
+        "Hello World".to_string(),
+    );
+    assert!(signed.is_err());
+
+    // an invalid address
+    let signed = account.sign_message("not an address".to_string(), "Hello World".to_string());
+    assert!(signed.is_err());
+}
+
+#[test]
+fn transaction_details() {
+    setup_logger();
+    let (account, _electrsd, _bitcoind) = funded_account("transaction_details", &[0.1]);
+    let coin = account.spendable_coins().coins[0].clone();
+    let (txid, vout) = coin.outpoint.split_once(':').unwrap();
+    let vout: usize = vout.parse().unwrap();
+
+    let raw = account.raw_transaction(txid.to_string());
+    let tx: bitcoin::Transaction = bitcoin::consensus::encode::deserialize_hex(&raw).unwrap();
+    assert_eq!(tx.compute_txid().to_string(), txid);
+
+    let details = account.transaction_details(txid.to_string());
+    assert!(details.error.is_empty());
+    assert_eq!(details.txid, txid);
+    assert_eq!(details.inputs.len(), tx.input.len());
+    // the funding tx spends coins of the bitcoind wallet
+    assert!(details.inputs.iter().all(|i| !i.ours));
+    assert_eq!(details.outputs.len(), tx.output.len());
+    let output = &details.outputs[vout];
+    assert_eq!(output.kind, PsbtOutputKind::Receive);
+    assert_eq!(output.amount, 10_000_000);
+    assert_eq!(output.address, coin.address.address);
+    assert!(details
+        .outputs
+        .iter()
+        .enumerate()
+        .all(|(i, o)| i == vout || o.kind == PsbtOutputKind::External));
+
+    // unknown transaction
+    let unknown = "0000000000000000000000000000000000000000000000000000000000000000";
+    assert!(account.raw_transaction(unknown.to_string()).is_empty());
+    assert!(!account
+        .transaction_details(unknown.to_string())
+        .error
+        .is_empty());
+}
+
+#[test]
+fn rebroadcast_unconfirmed() {
+    setup_logger();
+    let (url, port, _electrsd, bitcoind) = bootstrap_electrs();
+    let mnemonic = Mnemonic::generate(12).unwrap();
+    let mut config = new_config(
+        mnemonic.to_string(),
+        "rebroadcast_unconfirmed".to_string(),
+        CppNetwork::Regtest,
+    );
+    config.set_electrum_url(url);
+    config.set_electrum_port(port.to_string());
+    config.to_file().unwrap();
+    let mut account = Account::new(*config);
+    sleep(Duration::from_millis(300));
+
+    // nothing to rebroadcast
+    assert_eq!(account.rebroadcast_unconfirmed(), 0);
+
+    let addr = account.new_recv_addr();
+    send_to_address(&bitcoind, &addr, Amount::from_btc(0.1).unwrap());
+    wait_until_timeout(|| account.spendable_coins().unconfirmed_coins == 1, 5);
+    assert_eq!(account.rebroadcast_unconfirmed(), 1);
+    assert_eq!(bitcoind.client.get_raw_mempool().unwrap().len(), 1);
+
+    // confirmed transactions are skipped
+    generate(&bitcoind, 1);
+    wait_until_timeout(|| account.spendable_coins().confirmed_coins == 1, 5);
+    assert_eq!(account.rebroadcast_unconfirmed(), 0);
+}
+
+#[test]
+fn pending_transactions() {
+    setup_logger();
+    let (account, _electrsd, bitcoind) = funded_account("pending_transactions", &[0.01]);
+    // confirmed transactions are not pending
+    assert!(account.pending_transactions().is_empty());
+
+    let addr = account.recv_at(5);
+    let txid = send_to_address(&bitcoind, &addr, Amount::from_btc(0.02).unwrap());
+    wait_until_timeout(|| account.spendable_coins().unconfirmed_coins == 1, 5);
+    let pending = account.pending_transactions();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].txid, txid.to_string());
+    assert_eq!(pending[0].height, 0);
+    assert_eq!(pending[0].received, 2_000_000);
+    assert_eq!(pending[0].sent, 0);
+
+    // the transaction leaves the list once confirmed
+    generate(&bitcoind, 1);
+    wait_until_timeout(|| account.spendable_coins().confirmed_coins == 2, 5);
+    assert!(account.pending_transactions().is_empty());
+}
+
+#[test]
+fn test_accept() {
+    setup_logger();
+    let (account, _electrsd, bitcoind) = funded_account("test_accept", &[0.01]);
+    let coin = account.spendable_coins().coins[0].clone();
+    let external = external_address(&bitcoind);
+
+    let tx = |value: u64| {
+        let tx = bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::from_str(&coin.outpoint).unwrap(),
+                script_sig: bitcoin::ScriptBuf::new(),
+                sequence: bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME,
+                // NOTE: a dummy p2wpkh witness, signatures are not checked
+                witness: bitcoin::Witness::from_slice(&[vec![0u8; 72], vec![2u8; 33]]),
+            }],
+            output: vec![bitcoin::TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: external.script_pubkey(),
+            }],
+        };
+        bitcoin::consensus::encode::serialize_hex(&tx)
+    };
+
+    // below the minimum relay fee
+    let res = account.test_accept(tx(coin.value - 10));
+    assert!(res.unwrap_err().contains("minimum relay fee"));
+
+    // dust output
+    let res = account.test_accept(tx(100));
+    assert!(res.unwrap_err().contains("dust"));
+
+    // outputs exceeding inputs
+    assert!(account.test_accept(tx(coin.value + 1)).is_err());
+
+    assert!(account.test_accept(tx(coin.value - 1_000)).is_ok());
+    assert!(account.test_accept("not a tx".to_string()).is_err());
+}
+
+#[test]
+fn matches_prepared() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("matches_prepared", &[0.01]);
+    let external = external_address(&bitcoind);
+    let psbt = prepare_payment(&mut account, &external, 500_000);
+
+    // NOTE: a dummy p2wpkh witness stands for the external signature
+    let mut signed = bitcoin::Psbt::from_str(&psbt).unwrap().unsigned_tx;
+    for txin in &mut signed.input {
+        txin.witness = bitcoin::Witness::from_slice(&[vec![0u8; 72], vec![2u8; 33]]);
+    }
+    let signed_hex = bitcoin::consensus::encode::serialize_hex(&signed);
+    assert!(account.matches_prepared(psbt.clone(), signed_hex));
+
+    // an output has been swapped
+    let mut tampered = signed.clone();
+    tampered.output[0].script_pubkey = account.new_recv_addr().script_pubkey();
+    let tampered_hex = bitcoin::consensus::encode::serialize_hex(&tampered);
+    assert!(!account.matches_prepared(psbt.clone(), tampered_hex));
+
+    // an amount has been modified
+    let mut tampered = signed;
+    tampered.output[0].value -= Amount::from_sat(1);
+    let tampered_hex = bitcoin::consensus::encode::serialize_hex(&tampered);
+    assert!(!account.matches_prepared(psbt.clone(), tampered_hex));
+
+    assert!(!account.matches_prepared(psbt, "not a tx".to_string()));
+}
+
+/// Spends `coin` to `address` with a transaction signed outside of the
+/// account, as a hardware signer would.
+fn spend_with_external_signer(
+    account: &mut Account,
+    coin: RustCoin,
+    address: &bitcoin::Address,
+) -> bitcoin::Transaction {
+    let config = account.get_config();
+    let mut signer = HotSigner::new_from_mnemonics(Network::Regtest, &config.mnemonic).unwrap();
+    signer.inner_register_descriptor(config.descriptor.clone());
+    let amount = coin.value / 2;
+    let psbt =
+        account.prepare_transaction(template(vec![coin], vec![output(address, amount)], 2.0));
+    let mut psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+    signer.inner_sign(&mut psbt, &config.descriptor).unwrap();
+    psbt.finalize_mut(&bitcoin::secp256k1::Secp256k1::verification_only())
+        .unwrap();
+    psbt.extract_tx().unwrap()
+}
+
+#[test]
+fn register_prepared_tx() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("register_prepared_tx", &[0.01, 0.02]);
+    assert!(!account.register_prepared_tx("not a txid".to_string()));
+    let external = external_address(&bitcoind);
+    let coins = account.spendable_coins().coins;
+
+    // the txid of the first transaction is registered before broadcast,
+    // the second one is not
+    let registered = spend_with_external_signer(&mut account, coins[0].clone(), &external);
+    assert!(account.register_prepared_tx(registered.compute_txid().to_string()));
+    let unregistered = spend_with_external_signer(&mut account, coins[1].clone(), &external);
+    for tx in [&registered, &unregistered] {
+        bitcoind.client.send_raw_transaction(tx).unwrap();
+    }
+    generate(&bitcoind, 1);
+    let spent = |account: &Account, outpoint: &str| {
+        account
+            .coins()
+            .into_iter()
+            .find(|(op, _)| op.to_string() == outpoint)
+            .map(|(_, c)| c)
+            .filter(|c| c.status() == CoinStatus::Spent)
+    };
+    wait_until_timeout(
+        || {
+            spent(&account, &coins[0].outpoint).is_some()
+                && spent(&account, &coins[1].outpoint).is_some()
+        },
+        5,
+    );
+    assert!(!spent(&account, &coins[0].outpoint)
+        .unwrap()
+        .spent_externally());
+    assert!(spent(&account, &coins[1].outpoint)
+        .unwrap()
+        .spent_externally());
+
+    // registering it afterward clears the flag
+    assert!(account.register_prepared_tx(unregistered.compute_txid().to_string()));
+    assert!(!spent(&account, &coins[1].outpoint)
+        .unwrap()
+        .spent_externally());
+}
+
+#[test]
+fn frozen_coins_persist() {
+    setup_logger();
+    let name = "frozen_coins_persist";
+    let (mut account, _electrsd, bitcoind) = funded_account(name, &[0.01, 0.02]);
+    let coins = account.spendable_coins().coins;
+    assert_eq!(coins.len(), 2);
+    let frozen = coins.iter().find(|c| c.value == 1_000_000).unwrap().clone();
+    assert!(account.freeze_coin(frozen.outpoint.clone(), true));
+    assert!(!account.freeze_coin("not an outpoint".to_string(), true));
+    account.stop();
+    wait_until_timeout(|| account.is_stopped(), 5);
+    drop(account);
+
+    // the coin is still frozen once the account is reloaded from disk
+    let mut account = Account::new_offline(Config::from_file(name.to_string()));
+    assert_eq!(account.frozen_coins(), vec![frozen.outpoint.clone()]);
+    let spendable = account.spendable_coins();
+    assert_eq!(spendable.coins.len(), 1);
+    assert_eq!(spendable.confirmed_balance, 2_000_000);
+
+    // and it is neither selected nor accepted as an explicit input
+    let external = external_address(&bitcoind);
+    let psbt = prepare_payment(&mut account, &external, 500_000);
+    let psbt = bitcoin::Psbt::from_str(&psbt).unwrap();
+    assert_eq!(psbt.inputs.len(), 1);
+    assert_ne!(
+        psbt.unsigned_tx.input[0].previous_output.to_string(),
+        frozen.outpoint
+    );
+    let payment = template(vec![frozen.clone()], vec![output(&external, 500_000)], 2.0);
+    assert!(account.prepare_transaction(payment).is_err());
+
+    // nor contributed to a pool
+    assert!(matches!(
+        account.rust_create_pool(frozen.outpoint.clone(), 100_000, MIN_POOL_FEE_RATE, 60, 2),
+        Err(Error::CoinFrozen)
+    ));
+    assert!(matches!(
+        account.rust_join_pool(frozen.outpoint.clone(), "pool".to_string()),
+        Err(Error::CoinFrozen)
+    ));
+
+    // unfreezing makes it spendable again
+    assert!(account.freeze_coin(frozen.outpoint.clone(), false));
+    assert!(account.frozen_coins().is_empty());
+    assert_eq!(account.spendable_coins().coins.len(), 2);
+}
+
+#[test]
+fn sweep_coin() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("sweep_coin", &[0.01, 0.02]);
+    let coin = account
+        .spendable_coins()
+        .coins
+        .into_iter()
+        .find(|c| c.value == 1_000_000)
+        .unwrap();
+    let external = external_address(&bitcoind);
+
+    let psbt = account.sweep_coin(coin.outpoint.clone(), external.to_string(), 2.0);
+    assert!(psbt.is_ok());
+    let psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+    let tx = &psbt.unsigned_tx;
+    assert_eq!(tx.input.len(), 1);
+    assert_eq!(tx.input[0].previous_output.to_string(), coin.outpoint);
+    assert_eq!(tx.output.len(), 1);
+    assert_eq!(tx.output[0].script_pubkey, external.script_pubkey());
+    let fee = coin.value - tx.output[0].value.to_sat();
+    let min_fee = (2.0 * tx.vsize() as f64).ceil() as u64;
+    assert!(fee >= min_fee);
+    assert!(fee < 2 * min_fee);
+
+    // unknown coin
+    let unknown = account.sweep_coin(
+        format!("{}:0", tx.compute_txid()),
+        external.to_string(),
+        2.0,
+    );
+    assert!(unknown.is_err());
+
+    // the coin do not cover the fee
+    let too_expensive = account.sweep_coin(coin.outpoint, external.to_string(), 10_000.0);
+    assert!(too_expensive.is_err());
+}
+
+#[test]
+fn wallet_summary() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("wallet_summary", &[0.01, 0.02]);
+    let addr = account.new_recv_addr();
+    send_to_address(&bitcoind, &addr, Amount::from_btc(0.005).unwrap());
+    wait_until_timeout(|| account.spendable_coins().unconfirmed_coins == 1, 5);
+    wait_until_timeout(|| account.summary().synced, 5);
+
+    let summary = account.summary();
+    let coins = account.spendable_coins();
+    assert_eq!(summary.confirmed_balance, coins.confirmed_balance);
+    assert_eq!(summary.confirmed_balance, 3_000_000);
+    assert_eq!(summary.unconfirmed_balance, coins.unconfirmed_balance);
+    assert_eq!(summary.unconfirmed_balance, 500_000);
+    assert_eq!(summary.coins, coins.coins.len());
+    assert_eq!(summary.coins, 3);
+    let pools = account.pools().value();
+    assert_eq!(summary.available_pools, pools.len());
+    assert_eq!(summary.active_coinjoins, 0);
+    assert!(summary.electrum_running);
+    assert!(summary.synced);
+
+    account.stop();
+    wait_until_timeout(|| account.is_stopped(), 5);
+    assert!(!account.summary().electrum_running);
+}
+
+#[test]
+fn export_csv() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("export_csv", &[0.01, 0.02]);
+    let addr = account.new_recv_addr();
+    let pending = send_to_address(&bitcoind, &addr, Amount::from_btc(0.005).unwrap());
+    wait_until_timeout(|| account.spendable_coins().unconfirmed_coins == 1, 5);
+
+    let csv = account.export_csv();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("height,txid,type,amount,fee,label"));
+    let rows: Vec<Vec<&str>> = lines.map(|l| l.split(',').collect()).collect();
+    assert_eq!(rows.len(), 3);
+    let amount = |row: &Vec<&str>| {
+        bitcoin::SignedAmount::from_str_in(row[3], bitcoin::Denomination::Bitcoin)
+            .unwrap()
+            .to_sat()
+    };
+    let mut confirmed: Vec<_> = rows[..2].iter().map(amount).collect();
+    confirmed.sort();
+    assert_eq!(confirmed, vec![1_000_000, 2_000_000]);
+    for row in &rows[..2] {
+        assert!(!row[0].is_empty());
+        assert_eq!(row[2], "receive");
+    }
+
+    // pending transactions come last
+    let row = &rows[2];
+    assert_eq!(row[0], "");
+    assert_eq!(row[1], pending.to_string());
+    assert_eq!(row[2], "receive");
+    assert_eq!(amount(row), 500_000);
+}
+
+#[test]
+fn change_tracked_by_index() {
+    setup_logger();
+    let (mut account, _electrsd, bitcoind) = funded_account("change_tracked_by_index", &[0.1]);
+    // recipients scripts have the length of the change placeholder
+    let externals: Vec<_> = (0..2).map(|_| external_address(&bitcoind)).collect();
+    assert!(externals
+        .iter()
+        .all(|a| a.script_pubkey().len() == account.dummy_spk().len()));
+    let coins = account.spendable_coins().coins;
+    let payments = || {
+        vec![
+            output(&externals[0], 1_000_000),
+            output(&externals[1], 2_000_000),
+        ]
+    };
+
+    let simulation = account.simulate_transaction(template(coins.clone(), payments(), 2.0));
+    assert!(simulation.has_change);
+    assert_eq!(simulation.change_index, 2);
+
+    let psbt = account.prepare_transaction(template(coins.clone(), payments(), 2.0));
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    let verification = account.verify_psbt(psbt.value());
+    assert!(verification.error.is_empty());
+    assert_eq!(verification.outputs.len(), 3);
+    for (i, external) in externals.iter().enumerate() {
+        assert_eq!(verification.outputs[i].kind, PsbtOutputKind::External);
+        assert_eq!(verification.outputs[i].address, external.to_string());
+    }
+    assert_eq!(verification.outputs[0].amount, 1_000_000);
+    assert_eq!(verification.outputs[1].amount, 2_000_000);
+    let change = &verification.outputs[simulation.change_index as usize];
+    assert_eq!(change.kind, PsbtOutputKind::Change);
+    assert_eq!(change.amount, simulation.change_amount);
+
+    // a maxed output receives the remaining value wherever it sits
+    let sweep = Output {
+        max: true,
+        ..output(&externals[1], 0)
+    };
+    let maxed = template(coins, vec![output(&externals[0], 1_000_000), sweep], 2.0);
+    let psbt = account.prepare_transaction(maxed);
+    assert!(psbt.is_ok(), "{}", psbt.error());
+    let psbt = bitcoin::Psbt::from_str(&psbt.value()).unwrap();
+    let fee = psbt.fee().unwrap().to_sat();
+    assert_eq!(psbt.unsigned_tx.output.len(), 2);
+    assert_eq!(psbt.unsigned_tx.output[0].value.to_sat(), 1_000_000);
+    assert_eq!(
+        psbt.unsigned_tx.output[1].value.to_sat(),
+        10_000_000 - 1_000_000 - fee
+    );
+    assert!(fee < 10_000);
+}
+
+#[test]
+fn estimate_confirmation_target() {
+    setup_logger();
+    let mut account = offline_account("estimate_confirmation_target");
+
+    // no estimate known
+    assert!(account.estimate_confirmation_target(10.0).is_err());
+
+    account.set_fee_estimate(1, 20.0);
+    account.set_fee_estimate(6, 5.0);
+    let target = account.estimate_confirmation_target(10.0);
+    assert!(target.is_ok());
+    assert_eq!(target.value(), 6);
+    assert_eq!(account.estimate_confirmation_target(20.0).value(), 1);
+
+    // too low for all the known targets
+    assert!(account.estimate_confirmation_target(1.0).is_err());
+}
+
 fn test_conf_unconf() {
     // TODO: verify that coins status (confirmed/unconfirmed) are good
 }
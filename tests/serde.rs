@@ -0,0 +1,184 @@
+//! Golden fixtures of the persisted types, if one of these tests fails the
+//! on-disk format changed and existing wallets data would be misparsed.
+
+use std::str::FromStr;
+
+use cpp_joinstr::{
+    address_store::AddressEntry,
+    coin_store::CoinEntry,
+    cpp_joinstr::{AddrAccount, AddressStatus, CoinStatus, PoolRole, PoolStatus, RustPool},
+    pool_store::PoolEntry,
+    tx_store::TxEntry,
+};
+use joinstr::miniscript::bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, Txid};
+use serde::Deserialize;
+
+const TXID_1: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+const TXID_2: &str = "0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098";
+const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+const SPK: &str = "0014751e76e8199196d454941c45d1b3a323f1433bd6";
+
+#[derive(Deserialize)]
+struct Enums {
+    coin_status: Vec<CoinStatus>,
+    address_status: Vec<AddressStatus>,
+    addr_account: Vec<AddrAccount>,
+    pool_status: Vec<PoolStatus>,
+    pool_role: Vec<PoolRole>,
+}
+
+#[test]
+fn enums_fixture() {
+    let enums: Enums = serde_json::from_str(include_str!("fixtures/enums.json")).unwrap();
+    assert_eq!(
+        enums.coin_status,
+        vec![
+            CoinStatus::Unconfirmed,
+            CoinStatus::Confirmed,
+            CoinStatus::BeingSpend,
+            CoinStatus::Spent,
+        ]
+    );
+    assert_eq!(
+        enums.address_status,
+        vec![
+            AddressStatus::NotUsed,
+            AddressStatus::Used,
+            AddressStatus::Reused,
+            AddressStatus::Unknown,
+        ]
+    );
+    assert_eq!(
+        enums.addr_account,
+        vec![AddrAccount::Receive, AddrAccount::Change]
+    );
+    assert_eq!(
+        enums.pool_status,
+        vec![
+            PoolStatus::Posting,
+            PoolStatus::Connecting,
+            PoolStatus::Available,
+            PoolStatus::RegisterOutputs,
+            PoolStatus::RegisterInputs,
+            PoolStatus::Broadcast,
+            PoolStatus::Mined,
+            PoolStatus::Closed,
+            PoolStatus::Error,
+        ]
+    );
+    assert_eq!(
+        enums.pool_role,
+        vec![
+            PoolRole::Coordinator,
+            PoolRole::Initiator,
+            PoolRole::Peer,
+            PoolRole::None,
+        ]
+    );
+}
+
+#[test]
+fn coin_entry_fixture() {
+    let entries: Vec<CoinEntry> =
+        serde_json::from_str(include_str!("fixtures/coin_entries.json")).unwrap();
+    assert_eq!(entries.len(), 2);
+
+    let spent = &entries[0];
+    assert_eq!(spent.height(), Some(120));
+    assert_eq!(spent.status(), CoinStatus::Spent);
+    assert_eq!(spent.label(), "change");
+    assert_eq!(spent.address(), ADDRESS);
+    assert_eq!(spent.spent_by(), Some(Txid::from_str(TXID_2).unwrap()));
+    assert_eq!(
+        spent.coin.outpoint,
+        OutPoint::from_str(&format!("{TXID_1}:1")).unwrap()
+    );
+    assert_eq!(spent.coin.txout.value, Amount::from_sat(100_000));
+    assert_eq!(
+        spent.coin.txout.script_pubkey,
+        ScriptBuf::from_hex(SPK).unwrap()
+    );
+    assert_eq!(spent.coin.sequence, Sequence::ENABLE_RBF_NO_LOCKTIME);
+    assert_eq!(spent.coin.coin_path, (AddrAccount::Change, 3));
+
+    // entry persisted before `spent_by` was introduced
+    let unconfirmed = &entries[1];
+    assert_eq!(unconfirmed.height(), None);
+    assert_eq!(unconfirmed.status(), CoinStatus::Unconfirmed);
+    assert_eq!(unconfirmed.spent_by(), None);
+    assert_eq!(unconfirmed.coin.coin_path, (AddrAccount::Receive, 0));
+}
+
+#[test]
+fn address_entry_fixture() {
+    let entries: Vec<AddressEntry> =
+        serde_json::from_str(include_str!("fixtures/address_entries.json")).unwrap();
+    assert_eq!(entries.len(), 2);
+
+    let reused = &entries[0];
+    assert_eq!(reused.status(), AddressStatus::Reused);
+    assert_eq!(reused.account(), AddrAccount::Change);
+    assert_eq!(reused.index(), 7);
+    assert_eq!(reused.value(), ADDRESS);
+    assert!(reused.is_secondary());
+
+    // entry persisted before `secondary` was introduced
+    let unused = &entries[1];
+    assert_eq!(unused.status(), AddressStatus::NotUsed);
+    assert_eq!(unused.account(), AddrAccount::Receive);
+    assert_eq!(unused.index(), 0);
+    assert!(!unused.is_secondary());
+}
+
+#[test]
+fn tx_entry_fixture() {
+    let entry: TxEntry = serde_json::from_str(include_str!("fixtures/tx_entry.json")).unwrap();
+    assert_eq!(entry.height(), Some(120));
+    assert_eq!(entry.merkle(), vec![vec![1, 2, 3]]);
+
+    let tx = entry.tx();
+    assert_eq!(tx.version.0, 2);
+    assert_eq!(tx.lock_time.to_consensus_u32(), 0);
+    assert_eq!(tx.input.len(), 1);
+    assert_eq!(
+        tx.input[0].previous_output,
+        OutPoint::from_str(&format!("{TXID_1}:0")).unwrap()
+    );
+    assert_eq!(tx.input[0].witness.len(), 2);
+    assert_eq!(tx.output.len(), 1);
+    assert_eq!(tx.output[0].value, Amount::from_sat(100_000));
+    assert_eq!(
+        tx.output[0].script_pubkey,
+        ScriptBuf::from_hex(SPK).unwrap()
+    );
+}
+
+#[test]
+fn pool_entry_fixture() {
+    let entry: PoolEntry = serde_json::from_str(include_str!("fixtures/pool_entry.json")).unwrap();
+    assert_eq!(
+        entry.pool_id(),
+        "b5f7a8c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9"
+    );
+    assert_eq!(entry.status(), PoolStatus::RegisterOutputs);
+    assert_eq!(
+        entry.output().map(|a| a.assume_checked().to_string()),
+        Some(ADDRESS.to_string())
+    );
+
+    let pool = RustPool::from(entry);
+    assert_eq!(pool.role, PoolRole::Peer);
+    assert_eq!(pool.current_peers, 3);
+    assert_eq!(pool.total_peers, 5);
+    assert_eq!(pool.denomination, 100_000);
+    assert_eq!(pool.fees, 10);
+    assert_eq!(pool.timeout, 1_735_689_600);
+    assert_eq!(pool.relay, "wss://relay.example");
+
+    // entry persisted before `output` was introduced
+    let mut value: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/pool_entry.json")).unwrap();
+    value.as_object_mut().unwrap().remove("output");
+    let entry: PoolEntry = serde_json::from_value(value).unwrap();
+    assert_eq!(entry.output(), None);
+}